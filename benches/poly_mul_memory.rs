@@ -0,0 +1,97 @@
+//! Compares the peak heap usage of multiplying two polynomials of degree `N - 1` in a circuit, either by
+//! materializing every partial product before summing them, or with `poly_mul_assigned`, which accumulates every
+//! output coefficient with a single inner product over slices of the inputs.
+//!
+//! Run with `cargo bench --bench poly_mul_memory`.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use halo2_base::gates::{GateChip, GateInstructions};
+use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+use halo2_base::{AssignedValue, Context};
+use halo2_scaffold::poly::operations::{load_poly, poly_mul_assigned};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const N: usize = 512;
+
+// keeps track of the current and peak number of bytes allocated on the heap
+struct PeakAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(current, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAlloc = PeakAlloc;
+
+// returns the peak number of bytes allocated while running f, on top of what was allocated before
+fn peak_bytes(f: impl FnOnce()) -> usize {
+    let baseline = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    f();
+    PEAK.load(Ordering::SeqCst) - baseline
+}
+
+// the convolution as it was written in the examples: every partial product of a coefficient is collected in a
+// vector, and then summed up starting from zero
+fn poly_mul_materialized(
+    ctx: &mut Context<Fr>,
+    gate: &GateChip<Fr>,
+    a: &[AssignedValue<Fr>],
+    b: &[AssignedValue<Fr>],
+) -> Vec<AssignedValue<Fr>> {
+    let mut prod_val = vec![];
+    for i in 0..(a.len() + b.len() - 1) {
+        let mut coefficient_accumulator = vec![];
+        for j in i.saturating_sub(b.len() - 1)..=i.min(a.len() - 1) {
+            coefficient_accumulator.push(gate.mul(ctx, a[j], b[i - j]));
+        }
+        let zero = ctx.load_zero();
+        prod_val.push(coefficient_accumulator.iter().fold(zero, |acc, x| gate.add(ctx, acc, *x)));
+    }
+    prod_val
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let a = (0..N).map(|_| rng.gen::<u32>() as u64).collect::<Vec<_>>();
+    let b = (0..N).map(|_| rng.gen::<u32>() as u64).collect::<Vec<_>>();
+    let gate = GateChip::<Fr>::default();
+
+    let (mut expected, mut out) = (vec![], vec![]);
+    let materialized = peak_bytes(|| {
+        let mut ctx = Context::new(false, 0);
+        let (a, b) = (load_poly(&mut ctx, &a), load_poly(&mut ctx, &b));
+        expected =
+            poly_mul_materialized(&mut ctx, &gate, &a, &b).iter().map(|x| *x.value()).collect();
+    });
+    let inner_product = peak_bytes(|| {
+        let mut ctx = Context::new(false, 0);
+        let (a, b) = (load_poly(&mut ctx, &a), load_poly(&mut ctx, &b));
+        out = poly_mul_assigned(&mut ctx, &gate, &a, &b)
+            .unwrap()
+            .iter()
+            .map(|x| *x.value())
+            .collect();
+    });
+    assert_eq!(out, expected);
+
+    println!("peak heap usage of a degree {} polynomial multiplication", N - 1);
+    println!("  materialized partial products: {:>12} bytes", materialized);
+    println!("  inner product over slices:     {:>12} bytes", inner_product);
+}
@@ -0,0 +1,39 @@
+//! Measures the witness generation time of multiplying two polynomials of degree `N - 1` in a circuit.
+//!
+//! Run with `cargo bench --bench poly_mul_time` and `cargo bench --bench poly_mul_time --features parallel` to compare
+//! the serial and parallel witness generation.
+use std::time::{Duration, Instant};
+
+use halo2_base::gates::GateChip;
+use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+use halo2_base::Context;
+use halo2_scaffold::poly::operations::{load_poly, poly_mul_assigned};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const N: usize = 512;
+const RUNS: u32 = 10;
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let a = (0..N).map(|_| rng.gen::<u32>() as u64).collect::<Vec<_>>();
+    let b = (0..N).map(|_| rng.gen::<u32>() as u64).collect::<Vec<_>>();
+    let gate = GateChip::<Fr>::default();
+
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let mut ctx = Context::new(false, 0);
+        let (a, b) = (load_poly(&mut ctx, &a), load_poly(&mut ctx, &b));
+        let start = Instant::now();
+        poly_mul_assigned(&mut ctx, &gate, &a, &b).unwrap();
+        total += start.elapsed();
+    }
+
+    let mode = if cfg!(feature = "parallel") { "parallel" } else { "serial" };
+    println!(
+        "{} witness generation of a degree {} polynomial multiplication: {:?} on average over {} runs",
+        mode,
+        N - 1,
+        total / RUNS,
+        RUNS
+    );
+}
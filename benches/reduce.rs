@@ -0,0 +1,162 @@
+//! Compares the strategies to reduce the `N` coefficients of an unreduced polynomial product mod `Q` in a circuit:
+//! - `div_mod`: the range chip computes the quotient and remainder of every coefficient, as in `poly_reduce_mod_assigned`
+//! - Barrett: `barrett_reduce_assigned` computes them outside the circuit, and only constrains `x = quot * q + rem`
+//! - Montgomery: `montgomery_reduce_assigned` constrains `x + m * q = t * 2^r` and subtracts `q` from `t` once, which gives `x * 2^(-r) mod q`
+//!
+//! For every strategy it prints the number of advice and lookup cells of the reduction, then criterion measures the time to create a proof with the real prover.
+//! The strategies skip the range check of the input that `poly_reduce_mod_assigned` adds on top of `div_mod`, so that only the reduction itself is compared.
+//!
+//! `poly_reduce_mod_assigned` keeps `div_mod` as its default:
+//! - Barrett only saves the `check_less_than` of the quotient, and its host-side `u128` arithmetic limits the coefficients to `(127 + bit_length(q)) / 2` bits,
+//!   while `poly_reduce_mod_assigned` accepts any width up to the field capacity. It is used where the width is known to be small, as in `poly_reduce_ring_assigned`, and `batch_reduce` has the same constraints without this limit.
+//! - Montgomery needs a range check of `m`, a comparison and a selection on top of the range check of `t`, and its output is only the residue of a product of values in
+//!   Montgomery form, which none of the circuits of this crate use.
+//!
+//! Run with `cargo bench --bench reduce`.
+use std::env::set_var;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use halo2_base::gates::builder::{GateThreadBuilder, RangeCircuitBuilder};
+use halo2_base::gates::{RangeChip, RangeInstructions};
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{create_proof, keygen_pk, keygen_vk},
+    poly::{
+        commitment::ParamsProver,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::ProverSHPLONK,
+        },
+    },
+    transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+};
+use halo2_base::utils::ScalarField;
+use halo2_base::{AssignedValue, Context};
+use halo2_scaffold::poly::operations::{barrett_reduce_assigned, montgomery_reduce_assigned};
+use halo2_scaffold::poly::utils::{bit_length, inv_mod, montgomery_bits, pow_mod};
+use rand::{
+    rngs::{OsRng, StdRng},
+    Rng, SeedableRng,
+};
+
+const N: usize = 256;
+const Q: u64 = 1073479681;
+const K: u32 = 16;
+const LOOKUP_BITS: usize = 15;
+
+type Reduce =
+    fn(&mut Context<Fr>, &RangeChip<Fr>, &[AssignedValue<Fr>], usize) -> Vec<AssignedValue<Fr>>;
+
+fn div_mod(
+    ctx: &mut Context<Fr>,
+    range: &RangeChip<Fr>,
+    values: &[AssignedValue<Fr>],
+    num_bits: usize,
+) -> Vec<AssignedValue<Fr>> {
+    values.iter().map(|x| range.div_mod(ctx, *x, Q, num_bits).1).collect()
+}
+
+fn barrett(
+    ctx: &mut Context<Fr>,
+    range: &RangeChip<Fr>,
+    values: &[AssignedValue<Fr>],
+    num_bits: usize,
+) -> Vec<AssignedValue<Fr>> {
+    values.iter().map(|x| barrett_reduce_assigned(ctx, range, *x, Q, num_bits).unwrap().1).collect()
+}
+
+fn montgomery(
+    ctx: &mut Context<Fr>,
+    range: &RangeChip<Fr>,
+    values: &[AssignedValue<Fr>],
+    num_bits: usize,
+) -> Vec<AssignedValue<Fr>> {
+    values
+        .iter()
+        .map(|x| montgomery_reduce_assigned(ctx, range, *x, Q, num_bits).unwrap())
+        .collect()
+}
+
+// loads the coefficients and reduces all of them mod Q, then returns the reduced values and the number of
+// advice and lookup cells used by the reduction
+fn synthesize(
+    builder: &mut GateThreadBuilder<Fr>,
+    reduce: Reduce,
+    coeffs: &[u128],
+) -> (Vec<Fr>, usize, usize) {
+    let range = RangeChip::default(LOOKUP_BITS);
+    let num_bits = bit_length(coeffs.iter().copied().max().unwrap());
+    let ctx = builder.main(0);
+    let coeffs = coeffs.iter().map(|x| ctx.load_witness(Fr::from_u128(*x))).collect::<Vec<_>>();
+    let (advice, lookup) = (ctx.advice.len(), ctx.cells_to_lookup.len());
+    let out = reduce(ctx, &range, &coeffs, num_bits).iter().map(|x| *x.value()).collect();
+    (out, ctx.advice.len() - advice, ctx.cells_to_lookup.len() - lookup)
+}
+
+fn bench_reduce(c: &mut Criterion) {
+    set_var("LOOKUP_BITS", LOOKUP_BITS.to_string());
+    // the coefficients of the product of two polynomials of degree N - 1 with coefficients in [0, Q)
+    let mut rng = StdRng::seed_from_u64(0);
+    let coeffs =
+        (0..N).map(|_| rng.gen_range(0..N as u128 * (Q as u128).pow(2))).collect::<Vec<_>>();
+    let num_bits = bit_length(N as u128 * (Q as u128).pow(2));
+    let params = ParamsKZG::<Bn256>::setup(K, OsRng);
+
+    // Montgomery reduction returns x * 2^(-r) mod Q instead of x mod Q
+    let r_inv = inv_mod(pow_mod(2, montgomery_bits(Q, num_bits) as u64, Q), Q) as u128;
+    let residues = |factor: u128| {
+        coeffs
+            .iter()
+            .map(|x| Fr::from((x % Q as u128 * factor % Q as u128) as u64))
+            .collect::<Vec<_>>()
+    };
+    let strategies: [(&str, Reduce, Vec<Fr>); 3] = [
+        ("div_mod", div_mod, residues(1)),
+        ("barrett", barrett, residues(1)),
+        ("montgomery", montgomery, residues(r_inv)),
+    ];
+
+    println!("reduction of {N} coefficients of {num_bits} bits mod {Q} (k = {K})");
+    println!("  {:<10} {:>12} {:>12}", "strategy", "advice cells", "lookup cells");
+    let mut group = c.benchmark_group("reduce");
+    group.sample_size(10);
+    for (name, reduce, expected) in strategies {
+        let mut builder = GateThreadBuilder::keygen();
+        let (out, advice, lookup) = synthesize(&mut builder, reduce, &coeffs);
+        assert_eq!(out, expected, "{name} reduction is wrong");
+        println!("  {:<10} {:>12} {:>12}", name, advice, lookup);
+
+        builder.config(K as usize, Some(9));
+        let circuit = RangeCircuitBuilder::keygen(builder);
+        let vk = keygen_vk(&params, &circuit).expect("vk should not fail");
+        let pk = keygen_pk(&params, vk, &circuit).expect("pk should not fail");
+        let break_points = circuit.0.break_points.take();
+
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || {
+                    let mut builder = GateThreadBuilder::prover();
+                    synthesize(&mut builder, reduce, &coeffs);
+                    RangeCircuitBuilder::prover(builder, break_points.clone())
+                },
+                |circuit| {
+                    let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+                    create_proof::<
+                        KZGCommitmentScheme<Bn256>,
+                        ProverSHPLONK<'_, Bn256>,
+                        Challenge255<G1Affine>,
+                        _,
+                        Blake2bWrite<Vec<u8>, G1Affine, Challenge255<_>>,
+                        _,
+                    >(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript)
+                    .expect("prover should not fail");
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_reduce);
+criterion_main!(benches);
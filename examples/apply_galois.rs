@@ -0,0 +1,80 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{log_coeffs, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::{apply_automorphism, load_poly};
+use halo2_scaffold::poly::ring::CyclotomicRing;
+use halo2_scaffold::poly::utils::{check_coeffs_bound, check_degree};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The input polynomial and the claimed rotated polynomial are made public
+// - The exponent k is fixed in the circuit, since it decides how the coefficients are wired, so the proving and verifying keys depend on it
+// - Q is a public constant of the circuit
+// - N is the number of coefficients of the inputs, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // modulus of the ring R_q = Z_q[x]/(x^N + 1)
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub a: Vec<u64>,   // polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub k: usize,      // exponent of the automorphism x -> x^k, coprime to 2N
+    pub out: Vec<u64>, // claimed coefficients of a(x^k) in R_q, little endian of degree N - 1 in [0, Q)
+}
+
+// this algorithm takes a polynomial a in R_q and a claimed polynomial out, and proves that out = a(x^k) in R_q,
+// which is the automorphism applied by the rotations of BFV and CKKS.
+// The coefficient of x^i moves to x^(i*k mod 2N), and is negated when i*k mod 2N >= N since x^N = -1
+fn apply_galois<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that both polynomials are elements of R_q
+    let n = ring_n(input.a.len())?;
+    for poly in [&input.a, &input.out] {
+        check_degree(poly, n - 1)?;
+        check_coeffs_bound(poly, Q)?;
+    }
+
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+    let ring = CyclotomicRing::new(n, Q)?;
+
+    // Assign the polynomials to the circuit and make them public. The claimed rotation is constrained below to equal a reduced polynomial
+    let a = ring.load(ctx, &range, &input.a)?;
+    let out = load_poly(ctx, &input.out);
+    make_public.extend(a.iter().chain(out.iter()));
+
+    // Permute and negate the coefficients. The negated coefficients are in (-Q, 0], so reducing them mod Q gives the rotation in R_q
+    let rotated = apply_automorphism(ctx, &range.gate, &a, input.k, n)?;
+    let rotated = ring.reduce(ctx, &range, &rotated, Q as u128 - 1)?;
+    log_coeffs("rotated", &rotated);
+
+    // Constrain the claimed polynomial to be the rotation
+    for (x, y) in rotated.iter().zip(out.iter()) {
+        ctx.constrain_equal(x, y);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| apply_galois(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
@@ -0,0 +1,74 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::lookup_bits;
+use halo2_scaffold::poly::operations::{assert_base_decomposition, load_poly};
+use halo2_scaffold::poly::utils::{check_coeffs_bound, check_degree};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The digits are made public, the coefficient is not
+// - W and NUM_DIGITS are public constants of the circuit. W^NUM_DIGITS >= Q, so every coefficient in [0, Q) has a decomposition
+
+const Q: u64 = 65521; // modulus of the coefficients
+const W: u64 = 16; // base of the decomposition, which does not need to be a power of two
+const NUM_DIGITS: usize = 4; // number of digits of the decomposition
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub coeff: u64,       // coefficient in [0, Q)
+    pub digits: Vec<u64>, // claimed digits of coeff in base W, little endian, NUM_DIGITS of them in [0, W)
+}
+
+// this algorithm takes a coefficient and its claimed digits in base W, and proves that coeff = sum_i digits_i * W^i with every digit in [0, W).
+// This is the gadget decomposition used by relinearization and key switching, which multiply the digits with the key instead of the coefficient
+// to keep the noise small
+fn base_decompose<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that the coefficient is reduced and that there are NUM_DIGITS digits in [0, W)
+    check_coeffs_bound(&[input.coeff], Q)?;
+    check_degree(&input.digits, NUM_DIGITS - 1)?;
+    check_coeffs_bound(&input.digits, W)?;
+
+    // Assign the coefficient and the digits to the circuit, and make the digits public
+    let coeff = ctx.load_witness(F::from(input.coeff));
+    let digits = load_poly(ctx, &input.digits);
+    make_public.extend(digits.iter());
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Constrain every digit to [0, W) and the digits to recompose to the coefficient
+    assert_base_decomposition(ctx, &range, coeff, &digits, W);
+
+    // TEST
+    // Recompose the digits outside the circuit to see if they match the coefficient
+    let recomposed = input.digits.iter().rev().fold(0, |acc, digit| acc * W + digit);
+    assert_eq!(recomposed, input.coeff);
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| base_decompose(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
@@ -0,0 +1,93 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::bfv::mul_plain;
+use halo2_scaffold::fhe::gadgets::bfv_mul_plain;
+use halo2_scaffold::params::{apply_auto_params, input_ring_n, log_coeffs, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::{check_coeffs_bound, check_degree};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The input ciphertext and the product are made public
+// - The plaintext pt is fixed in the circuit as constants, so the proving and verifying keys depend on it
+// - Q is a public constant of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the ciphertext, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+const T: u64 = 8; // plaintext modulus
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub ct: [Vec<u64>; 2], // ciphertext (c0, c1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub pt: Vec<u64>, // public plaintext polynomial coefficients little endian of degree N - 1 in [0, T)
+}
+
+// this algorithm takes a BFV ciphertext and a public plaintext polynomial,
+// and computes the ciphertext (ct0 * pt, ct1 * pt) in R_Q, which encrypts the product of the plaintexts
+fn bfv_mul_plain_circuit<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that every polynomial has N coefficients, and that the plaintext is in R_T
+    let n = ring_n(input.ct[0].len())?;
+    for poly in input.ct.iter().chain([&input.pt]) {
+        check_degree(poly, n - 1)?;
+    }
+    check_coeffs_bound(&input.pt, T)?;
+
+    // Assign the ciphertext to the circuit and make it public
+    let [c0, c1] = [&input.ct[0], &input.ct[1]].map(|c| load_poly(ctx, c));
+    for poly in [&c0, &c1] {
+        make_public.extend(poly.iter());
+    }
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Multiply both components by the constant plaintext, reduce mod x^N + 1 and Q, and make the result public
+    let prod = bfv_mul_plain(ctx, &range, [&c0, &c1], &input.pt, Q)?;
+    for (label, poly) in ["ct0 * pt", "ct1 * pt"].into_iter().zip(prod.iter()) {
+        log_coeffs(label, poly);
+        make_public.extend(poly.iter());
+    }
+
+    // TEST
+    // Multiply the ciphertext outside the circuit to see if this matches the result of the circuit
+    let expected = mul_plain(&input.ct, &input.pt, Q);
+    for (poly, expected) in prod.iter().zip(expected.iter()) {
+        for (coeff, expected) in poly.iter().zip(expected.iter()) {
+            assert_eq!(*coeff.value(), F::from(*expected));
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse_with_inline_inputs();
+    // with --auto-params, derive the circuit degree and LOOKUP_BITS from Q and the N of the input
+    let n =
+        input_ring_n(&mut args, |input: &CircuitInput| input.ct[0].len()).expect("invalid input");
+    apply_auto_params(&mut args, Q, n);
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| {
+            bfv_mul_plain_circuit(ctx, input, make_public).expect("invalid input")
+        },
+        args,
+    );
+}
@@ -1,5 +1,4 @@
 //! Example of scaffolding where function uses full `GateThreaderBuilder` instead of single `Context`
-use clap::Parser;
 use halo2_base::gates::builder::GateThreadBuilder;
 use halo2_base::gates::{GateChip, GateInstructions};
 use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
@@ -70,7 +69,7 @@ fn some_algorithm_in_zk<F: ScalarField>(
 fn main() {
     env_logger::init();
 
-    let args = Cli::parse();
+    let args = Cli::parse_with_inline_inputs();
 
     // let's say we don't want to run prover with inputs from file
     // instead we generate inputs here:
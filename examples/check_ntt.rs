@@ -0,0 +1,83 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{lookup_bits, ring_n};
+use halo2_scaffold::poly::ntt::{
+    negacyclic_ntt, negacyclic_ntt_butterfly_assigned, primitive_root_of_unity,
+};
+use halo2_scaffold::poly::operations::{assert_reduced, load_poly};
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The NTT output is made public
+// - The input polynomial is not made public
+// - Q and the root of unity psi are public constants of the circuit
+// - As the NTT is a bijection, this also proves that the input polynomial is the INTT of the public evaluations
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the polynomial, or the one given with --n, so the same binary runs for every N
+// - The NTT needs a primitive 2N-th root of unity mod Q, so N must be a power of two with 2N dividing Q - 1 = 2^9 * 15, i.e. N <= 256
+
+const Q: u64 = 7681; // NTT-friendly modulus, i.e. 2N divides Q - 1
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub coeffs: Vec<u64>, // polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub evals: Vec<u64>, // claimed evaluations at psi^(2i+1) for i = 0, ..., N - 1, in natural order, in [0, Q)
+}
+
+// this algorithm takes a polynomial and its claimed negacyclic NTT, and checks that the evaluations are the NTT of the polynomial
+// by computing the NTT with the butterfly network in the circuit, against twiddle factors that are checked to be the powers of a primitive 2N-th root of unity
+fn check_ntt<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that both polynomials have N coefficients
+    let n = ring_n(input.coeffs.len())?;
+    check_degree(&input.coeffs, n - 1)?;
+    check_degree(&input.evals, n - 1)?;
+
+    // Assign the claimed NTT output to the circuit and make it public
+    let evals = load_poly(ctx, &input.evals);
+    make_public.extend(evals.iter());
+
+    // Assign the input polynomial to the circuit as a private witness
+    let coeffs = load_poly(ctx, &input.coeffs);
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Check that the coefficients are in [0, Q)
+    assert_reduced(ctx, &range, &coeffs, Q);
+
+    // Compute the NTT with the butterfly network and check that it matches the claimed output
+    let psi = primitive_root_of_unity(2 * n as u64, Q)?;
+    let computed = negacyclic_ntt_butterfly_assigned(ctx, &range, &coeffs, psi, Q)?;
+    for (x, y) in computed.iter().zip(evals.iter()) {
+        ctx.constrain_equal(x, y);
+    }
+
+    // TEST
+    // Compute the NTT outside the circuit to see if this matches the claimed output
+    assert_eq!(negacyclic_ntt(&input.coeffs, psi, Q), input.evals);
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(|ctx, input, make_public| check_ntt(ctx, input, make_public).expect("invalid input"), args);
+}
@@ -1,8 +1,4 @@
-use std::env::var;
-
-use clap::Parser;
 use halo2_base::safe_types::RangeChip;
-use halo2_base::safe_types::{GateInstructions, RangeInstructions};
 use halo2_base::utils::ScalarField;
 use halo2_base::AssignedValue;
 #[allow(unused_imports)]
@@ -10,20 +6,24 @@ use halo2_base::{
     Context,
     QuantumCell::{Constant, Existing, Witness},
 };
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{circuit_n, lookup_bits};
+use halo2_scaffold::poly::distribution;
+use halo2_scaffold::poly::utils::check_degree;
 use halo2_scaffold::scaffold::cmd::Cli;
 use halo2_scaffold::scaffold::run;
 use serde::{Deserialize, Serialize};
 
-const N: usize = 3; // degree of the polynomial
 const Q: u64 = 2u64.pow(8) + 1; // modulus of the field F_q
 const B: u64 = 30; // upper bound of the distribution [-b, b]
 
 // Notes:
 // - Q and B are public constants of the circuit
 // - The input polynomial is not made public
+// - The degree N is the degree of the input polynomial, or the one given with --n, so the same binary runs for every degree
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct CircuitInput<const N: usize> {
+pub struct CircuitInput {
     pub a: Vec<u64>, // polynomial coefficients little endian of degree N
 }
 
@@ -31,9 +31,12 @@ pub struct CircuitInput<const N: usize> {
 // if the coefficients are in the range, it means that the polynomial was sampled from the distribution
 fn check_poly_from_distribution_chi_error<F: ScalarField>(
     ctx: &mut Context<F>,
-    input: CircuitInput<N>,
+    input: CircuitInput,
     make_public: &mut Vec<AssignedValue<F>>,
-) {
+) -> Result<(), CircuitError> {
+    // check that the degree of the input polynomial is equal to N
+    check_degree(&input.a, circuit_n(input.a.len().saturating_sub(1)))?;
+
     // // Since we cannot represent negative numbers in the circuit, the value - 1 is represented as the field element q - 1.
     // // Therefore we split the range [-b, b] into two ranges [0, b] and [q-b, q-1]
 
@@ -48,47 +51,25 @@ fn check_poly_from_distribution_chi_error<F: ScalarField>(
         .collect();
 
     // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
-    let lookup_bits =
-        var("LOOKUP_BITS").unwrap_or_else(|_| panic!("LOOKUP_BITS not set")).parse().unwrap();
+    let lookup_bits = lookup_bits();
 
     // The goal is to check that a_assigned[i] is in the range [0, b] or in the range [q-b, q-1]
-    // We split this check into two checks:
-    // - Check that a_assigned[i] is in the range [0, b] and store the boolean result in in_partial_range_1_vec
-    // - Check that a_assigned[i] is in the range [q-b, q-1] and store the boolean result in in_partial_range_2_vec
-    // We then perform (`in_partial_range_1_vec` OR `in_partial_range_2_vec`) to check that a_assigned[i] is in the range [0, b] [q-b, q-1]
-    // The result of this check is stored in the `in_range` vector. The bool value of `in_range` is then enforced to be true
-
     let range = RangeChip::default(lookup_bits);
+    distribution::check_poly_from_distribution_chi_error(ctx, &range, &a_assigned, Q, B);
 
-    let mut in_range_vec = Vec::with_capacity(N + 1);
-
-    for coeff in &a_assigned {
-        // Check for the range [0, b]
-        let in_partial_range_1 = range.is_less_than_safe(ctx, *coeff, B + 1);
-
-        // Check for the range [q-b, q-1]
-        let not_in_range_lower_bound = range.is_less_than_safe(ctx, *coeff, Q - B);
-        let in_range_lower_bound = range.gate.not(ctx, not_in_range_lower_bound);
-        let in_range_upper_bound = range.is_less_than_safe(ctx, *coeff, Q);
-        let in_partial_range_2 = range.gate.and(ctx, in_range_lower_bound, in_range_upper_bound);
-
-        // Combined check for [0, b] OR [q-b, q-1]
-        let in_range = range.gate.or(ctx, in_partial_range_1, in_partial_range_2);
-        in_range_vec.push(in_range);
-    }
-
-    // Enforce that in_range_vec[i] = true
-    for in_range in in_range_vec {
-        let bool = range.gate.is_equal(ctx, in_range, Constant(F::from(1)));
-        range.gate.assert_is_const(ctx, &bool, &F::from(1));
-    }
+    Ok(())
 }
 
 fn main() {
     env_logger::init();
 
-    let args = Cli::parse();
+    let args = Cli::parse_with_inline_inputs();
 
     // run different zk commands based on the command line arguments
-    run(check_poly_from_distribution_chi_error, args);
+    run(
+        |ctx, input, make_public| {
+            check_poly_from_distribution_chi_error(ctx, input, make_public).expect("invalid input")
+        },
+        args,
+    );
 }
@@ -0,0 +1,95 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::bfv::{public_key, Ciphertext};
+use halo2_scaffold::params::{apply_auto_params, input_ring_n, lookup_bits, ring_n};
+use halo2_scaffold::poly::distribution::{
+    check_poly_from_distribution_chi_error, check_poly_from_distribution_chi_key,
+};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::ring::CyclotomicRing;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::{constrain_equals_public, run};
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The public key (p0, p1 = a) is made public
+// - The secret key s and the error e are not made public
+// - Q and B are public constants of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the secret key, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+const B: u64 = 2; // upper bound of the error distribution [-B, B]
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub pk: Ciphertext, // public key (p0, a), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub s: Vec<u64>, // secret key polynomial coefficients little endian of degree N - 1 in {0, 1, Q - 1}
+    pub e: Vec<u64>, // error polynomial coefficients little endian of degree N - 1 in [0, B] or [Q - B, Q - 1]
+}
+
+// this algorithm takes a public key (p0, a), the secret key s and the error e, and recomputes p0 = [-(a * s + e)]_Q
+// step by step with the operations of the ring R_Q: the product a * s, the sum with the error and its negation.
+// `verify_pubkey` proves the same statement with a single zero check of p0 + a * s + e in R_Q
+fn check_public_key<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that every polynomial has N coefficients
+    let n = ring_n(input.s.len())?;
+    for poly in input.pk.iter().chain([&input.s, &input.e]) {
+        check_degree(poly, n - 1)?;
+    }
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+    let ring = CyclotomicRing::new(n, Q)?;
+
+    // Assign a to the circuit, check that it is in R_Q and make it public
+    let a = ring.load(ctx, &range, &input.pk[1])?;
+    make_public.extend(a.iter());
+
+    // Assign the secret key and the error to the circuit as private witnesses, and check that s is ternary and e is in [-B, B]
+    let [s, e] = [&input.s, &input.e].map(|c| load_poly(ctx, c));
+    check_poly_from_distribution_chi_key(ctx, &range.gate, &s, Q);
+    check_poly_from_distribution_chi_error(ctx, &range, &e, Q, B);
+
+    // Compute a * s + e in R_Q. The coefficients of s and e are in [0, Q) after the distribution checks, so they are elements of R_Q
+    let prod = ring.mul(ctx, &range, &a, &s)?;
+    let sum = ring.add(ctx, &range, &prod, &e)?;
+
+    // Negate the sum in R_Q, and constrain it to equal p0, which is made public
+    let p0 = ring.neg(ctx, &range, &sum)?;
+    constrain_equals_public(ctx, &p0, &input.pk[0], make_public)?;
+
+    // TEST
+    // Compute the public key outside the circuit to see if this matches the input public key
+    let expected = public_key(&input.s, &input.pk[1], &input.e, Q);
+    assert_eq!(expected[0], input.pk[0]);
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse_with_inline_inputs();
+    // with --auto-params, derive the circuit degree and LOOKUP_BITS from Q and the N of the input
+    let n = input_ring_n(&mut args, |input: &CircuitInput| input.s.len()).expect("invalid input");
+    apply_auto_params(&mut args, Q, n);
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| check_public_key(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
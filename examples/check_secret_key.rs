@@ -0,0 +1,71 @@
+use halo2_base::gates::GateChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::circuit_n;
+use halo2_scaffold::poly::distribution::{check_poly_from_distribution_chi_key, hamming_weight};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+const Q: u64 = 2u64.pow(8) + 1; // modulus of the field F_q
+
+// Notes:
+// - The secret key is not made public
+// - The Hamming weight h is made public
+// - Q is a public constant of the circuit
+// - The degree N is the degree of the secret key, or the one given with --n, so the same binary runs for every degree
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub sk: Vec<u64>, // secret key polynomial coefficients little endian of degree N
+    pub h: u64,       // number of nonzero coefficients of the secret key
+}
+
+// this algorithm takes a secret key polynomial sk and a Hamming weight h and checks that the coefficients of sk are in [-1, 0, +1]
+// and that exactly h of them are nonzero, i.e. that sk is a valid sparse ternary secret key
+fn check_secret_key<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that the degree of the secret key is equal to N
+    check_degree(&input.sk, circuit_n(input.sk.len().saturating_sub(1)))?;
+
+    // Assign the secret key to the circuit
+    let sk = load_poly(ctx, &input.sk);
+
+    // Assign the Hamming weight to the circuit and make it public
+    let h = ctx.load_witness(F::from(input.h));
+    make_public.push(h);
+
+    let gate = GateChip::<F>::default();
+
+    // Check that the secret key is ternary, i.e. that every coefficient is 0, 1 or q-1
+    check_poly_from_distribution_chi_key(ctx, &gate, &sk, Q);
+
+    // Check that the number of nonzero coefficients is equal to the public h
+    let weight = hamming_weight(ctx, &gate, &sk);
+    ctx.constrain_equal(&weight, &h);
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| check_secret_key(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
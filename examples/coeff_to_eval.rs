@@ -0,0 +1,116 @@
+use halo2_base::gates::{GateChip, RangeChip};
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{lookup_bits, ring_n};
+use halo2_scaffold::poly::ntt::{
+    assert_ntt_at_challenge, negacyclic_intt, negacyclic_ntt, ntt_quotients,
+    primitive_root_of_unity,
+};
+use halo2_scaffold::poly::operations::{assert_reduced, load_poly};
+use halo2_scaffold::poly::utils::{check_degree, field_to_i64, from_centered};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use poseidon::PoseidonChip;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The input polynomial is not made public, the converted one is made public
+// - Q and the root of unity psi are public constants of the circuit
+// - The conversion is computed outside the circuit, and the circuit checks the n equations evals[i] = coeffs(psi^(2i+1)) mod Q
+//   at a single random point (Schwartz-Zippel). The random point is the Poseidon hash of both forms and of the quotients of the
+//   reductions mod Q, so that it is fixed only after the prover has committed to them
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the polynomial, or the one given with --n, so the same binary runs for every N
+// - The NTT needs a primitive 2N-th root of unity mod Q, so N must be a power of two with 2N dividing Q - 1 = 2^9 * 15, i.e. N <= 256
+
+const Q: u64 = 7681; // NTT-friendly modulus, i.e. 2N divides Q - 1
+
+// Poseidon parameters used to derive the challenge of the check
+const T: usize = 3;
+const RATE: usize = 2;
+const R_F: usize = 8;
+const R_P: usize = 57;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub poly: Vec<u64>, // polynomial of degree N - 1 in [0, Q), either its coefficients little endian or its evaluations at psi^(2i+1) in natural order
+    #[serde(default)]
+    pub from_evals: bool, // `poly` is in evaluation form and is converted to coefficient form (eval_to_coeff), instead of the other way around (coeff_to_eval)
+}
+
+// this algorithm takes a polynomial in coefficient form and converts it to evaluation form at the roots psi^(2i+1) of x^N + 1, or the reverse with
+// `from_evals`. Both forms are related by the negacyclic NTT, which is checked at a random point instead of being computed in the circuit
+fn coeff_to_eval<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that the polynomial has N coefficients
+    let n = ring_n(input.poly.len())?;
+    check_degree(&input.poly, n - 1)?;
+
+    // Convert the polynomial outside the circuit
+    let psi = primitive_root_of_unity(2 * n as u64, Q)?;
+    let (coeffs, evals) = if input.from_evals {
+        (negacyclic_intt(&input.poly, psi, Q), input.poly.clone())
+    } else {
+        (input.poly.clone(), negacyclic_ntt(&input.poly, psi, Q))
+    };
+
+    // Assign both forms to the circuit, and make the converted one public
+    let [coeffs, evals] = [&coeffs, &evals].map(|p| load_poly(ctx, p));
+    make_public.extend(if input.from_evals { &coeffs } else { &evals }.iter());
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Check that the coefficients are in [0, Q), the evaluations are checked by `assert_ntt_at_challenge`
+    assert_reduced(ctx, &range, &coeffs, Q);
+
+    // Assign the quotients of the evaluations by Q, computed outside the circuit
+    let coeff_values = coeffs.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>();
+    let quotients = load_poly(ctx, &ntt_quotients(&coeff_values, psi, Q));
+
+    // derive the challenge from both forms and the quotients
+    let gate = GateChip::<F>::default();
+    let mut poseidon = PoseidonChip::<F, T, RATE>::new(ctx, R_F, R_P).unwrap();
+    for poly in [&coeffs, &evals, &quotients] {
+        poseidon.update(poly);
+    }
+    let r = poseidon.squeeze(ctx, &gate).unwrap();
+
+    // Check that the evaluations are the negacyclic NTT of the coefficients at the challenge
+    assert_ntt_at_challenge(ctx, &range, &coeffs, &evals, &quotients, psi, Q, r)?;
+
+    // TEST
+    // Convert the output back outside the circuit to see if it round-trips to the input
+    if input.from_evals {
+        assert_eq!(negacyclic_ntt(&coeff_values, psi, Q), input.poly);
+    } else {
+        let eval_values = evals
+            .iter()
+            .map(|x| field_to_i64(x.value(), Q).map(|x| from_centered(x, Q)))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(negacyclic_intt(&eval_values, psi, Q), input.poly);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| coeff_to_eval(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
@@ -0,0 +1,77 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{log_coeffs, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::conjugate;
+use halo2_scaffold::poly::ring::CyclotomicRing;
+use halo2_scaffold::poly::utils::{
+    self, check_coeffs_bound, check_degree, field_to_i64, to_centered,
+};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The input polynomial is private, and its conjugate is made public
+// - Q is a public constant of the circuit
+// - N is the number of coefficients of the input, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // modulus of the ring R_q = Z_q[x]/(x^N + 1)
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub a: Vec<u64>, // polynomial coefficients little endian of degree N - 1 in [0, Q)
+}
+
+// this algorithm takes a polynomial a in R_q and outputs its conjugate a(x^(-1)) = a(x^(2N-1)) in R_q to the public,
+// which is the complex conjugation of the slots of CKKS.
+// The constant coefficient stays in place, and the coefficient of x^i moves to x^(N-i) negated since x^(-i) = -x^(N-i)
+fn conjugate_poly<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that the polynomial is an element of R_q
+    let n = ring_n(input.a.len())?;
+    check_degree(&input.a, n - 1)?;
+    check_coeffs_bound(&input.a, Q)?;
+
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+    let ring = CyclotomicRing::new(n, Q)?;
+
+    // Assign the polynomial to the circuit as a private witness, and range check it
+    let a = ring.load(ctx, &range, &input.a)?;
+
+    // Reverse and negate the coefficients. The negated coefficients are in (-Q, 0], so reducing them mod Q gives the conjugate in R_q
+    let conj = conjugate(ctx, &range.gate, &a, n)?;
+    let conj = ring.reduce(ctx, &range, &conj, Q as u128 - 1)?;
+    log_coeffs("conjugate", &conj);
+    make_public.extend(conj.iter());
+
+    // TEST
+    // Compute the conjugate outside the circuit to see if this matches the output of the circuit
+    let expected = utils::conjugate(&input.a, Q);
+    let values = conj.iter().map(|x| field_to_i64(x.value(), Q)).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(values, expected.iter().map(|x| to_centered(*x, Q)).collect::<Vec<_>>());
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| conjugate_poly(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
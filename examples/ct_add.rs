@@ -0,0 +1,95 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::bfv::add;
+use halo2_scaffold::fhe::gadgets::bfv_add;
+use halo2_scaffold::params::{apply_auto_params, input_ring_n, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - All three ciphertexts are made public
+// - Q is a public constant of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the ciphertexts, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub ct_a: [Vec<u64>; 2], // first ciphertext (c0, c1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub ct_b: [Vec<u64>; 2], // second ciphertext (c0, c1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub ct_out: [Vec<u64>; 2], // claimed sum of the ciphertexts, polynomial coefficients little endian of degree N - 1 in [0, Q)
+}
+
+// this algorithm takes two BFV ciphertexts and a claimed output ciphertext,
+// and checks that the output is the homomorphic sum of the two ciphertexts in R_Q
+fn ct_add<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that every ciphertext component has N coefficients
+    let n = ring_n(input.ct_a[0].len())?;
+    for poly in input.ct_a.iter().chain(input.ct_b.iter()).chain(input.ct_out.iter()) {
+        check_degree(poly, n - 1)?;
+    }
+
+    // Assign the three ciphertexts to the circuit and make them public
+    let [a0, a1, b0, b1, out0, out1] = [
+        &input.ct_a[0],
+        &input.ct_a[1],
+        &input.ct_b[0],
+        &input.ct_b[1],
+        &input.ct_out[0],
+        &input.ct_out[1],
+    ]
+    .map(|c| load_poly(ctx, c));
+    for poly in [&a0, &a1, &b0, &b1, &out0, &out1] {
+        make_public.extend(poly.iter());
+    }
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Add the ciphertexts component-wise mod Q and check that the result is the claimed output
+    let sum = bfv_add(ctx, &range, [&a0, &a1], [&b0, &b1], Q)?;
+    for (poly, claimed) in sum.iter().zip([&out0, &out1]) {
+        for (x, y) in poly.iter().zip(claimed.iter()) {
+            ctx.constrain_equal(x, y);
+        }
+    }
+
+    // TEST
+    // Add the ciphertexts outside the circuit to see if this matches the result of the circuit
+    let expected = add(&input.ct_a, &input.ct_b, Q);
+    for (poly, expected) in sum.iter().zip(expected.iter()) {
+        for (coeff, expected) in poly.iter().zip(expected.iter()) {
+            assert_eq!(*coeff.value(), F::from(*expected));
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse_with_inline_inputs();
+    // with --auto-params, derive the circuit degree and LOOKUP_BITS from Q and the N of the input
+    let n =
+        input_ring_n(&mut args, |input: &CircuitInput| input.ct_a[0].len()).expect("invalid input");
+    apply_auto_params(&mut args, Q, n);
+
+    // run different zk commands based on the command line arguments
+    run(|ctx, input, make_public| ct_add(ctx, input, make_public).expect("invalid input"), args);
+}
@@ -0,0 +1,99 @@
+use halo2_base::gates::{GateInstructions, RangeChip};
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::bfv::decrypt;
+use halo2_scaffold::fhe::gadgets::bfv_decrypt;
+use halo2_scaffold::params::{apply_auto_params, input_ring_n, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The secret key and the decrypted messages are not made public
+// - The two ciphertexts and the result of the equality check are made public
+// - Q, T and NOISE_BOUND are public constants of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the secret key, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+const T: u64 = 8; // plaintext modulus
+const NOISE_BOUND: u64 = Q / (2 * T) - T; // largest noise for which decryption is guaranteed to be correct
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub sk: Vec<u64>, // secret key polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub ct1: [Vec<u64>; 2], // first ciphertext (c0, c1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub ct2: [Vec<u64>; 2], // second ciphertext (c0, c1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+}
+
+// this algorithm takes two BFV ciphertexts and the secret key, decrypts both ciphertexts and checks that they encrypt the same message,
+// without revealing the message itself
+fn ct_equality<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that the secret key and every ciphertext component have N coefficients
+    let n = ring_n(input.sk.len())?;
+    for poly in input.ct1.iter().chain(input.ct2.iter()).chain([&input.sk]) {
+        check_degree(poly, n - 1)?;
+    }
+
+    // Assign the secret key to the circuit
+    let sk = load_poly(ctx, &input.sk);
+
+    // Assign the input ciphertexts to the circuit and make them public
+    let [a0, a1, b0, b1] =
+        [&input.ct1[0], &input.ct1[1], &input.ct2[0], &input.ct2[1]].map(|c| load_poly(ctx, c));
+    for poly in [&a0, &a1, &b0, &b1] {
+        make_public.extend(poly.iter());
+    }
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Decrypt both ciphertexts. This also checks that the noise of both ciphertexts is at most NOISE_BOUND,
+    // so that the recovered messages are the ones that were actually encrypted
+    let m1 = bfv_decrypt(ctx, &range, &sk, [&a0, &a1], Q, T, NOISE_BOUND)?;
+    let m2 = bfv_decrypt(ctx, &range, &sk, [&b0, &b1], Q, T, NOISE_BOUND)?;
+
+    // Check that the messages are equal coefficient-wise and make the result public
+    let eq =
+        m1.iter().zip(m2.iter()).map(|(&x, &y)| range.gate.is_equal(ctx, x, y)).collect::<Vec<_>>();
+    let eq = eq.into_iter().reduce(|acc, x| range.gate.and(ctx, acc, x)).unwrap();
+    range.gate.assert_is_const(ctx, &eq, &F::one());
+    make_public.push(eq);
+
+    // TEST
+    // Decrypt the ciphertexts outside the circuit to see if this matches the result of the circuit
+    let expected = decrypt(&input.sk, &input.ct1, Q, T);
+    assert_eq!(expected, decrypt(&input.sk, &input.ct2, Q, T));
+    for (coeff, expected) in m1.iter().zip(expected.iter()) {
+        assert_eq!(*coeff.value(), F::from(*expected));
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse_with_inline_inputs();
+    // with --auto-params, derive the circuit degree and LOOKUP_BITS from Q and the N of the input
+    let n = input_ring_n(&mut args, |input: &CircuitInput| input.sk.len()).expect("invalid input");
+    apply_auto_params(&mut args, Q, n);
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| ct_equality(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
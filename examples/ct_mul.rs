@@ -0,0 +1,129 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+#[cfg(feature = "relin")]
+use halo2_scaffold::fhe::bfv::{num_digits, relinearize};
+use halo2_scaffold::fhe::bfv::{tensor, Ciphertext};
+#[cfg(feature = "relin")]
+use halo2_scaffold::fhe::gadgets::bfv_relinearize;
+use halo2_scaffold::fhe::gadgets::bfv_tensor;
+use halo2_scaffold::params::{apply_auto_params, input_ring_n, log_coeffs, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The input ciphertexts, the relinearization key and the output ciphertext are all made public
+// - Without the `relin` feature, the output is the degree 2 ciphertext (c0, c1, c2) and the relinearization key is ignored
+// - With the `relin` feature, (c0, c1, c2) is relinearized back into a regular ciphertext inside the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the ciphertexts, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+const T: u64 = 8; // plaintext modulus
+#[cfg(feature = "relin")]
+const W: u64 = 16; // base of the decomposition used by the relinearization key
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub ct1: Ciphertext, // first ciphertext (c0, c1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub ct2: Ciphertext, // second ciphertext (c0, c1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub rlk: Vec<Ciphertext>, // relinearization key, one pair of polynomials of degree N - 1 in [0, Q) per base W digit of Q
+}
+
+// this algorithm takes two BFV ciphertexts and outputs their homomorphic product to the public
+fn ct_mul<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that every ciphertext component has N coefficients
+    let n = ring_n(input.ct1[0].len())?;
+    for poly in input.ct1.iter().chain(input.ct2.iter()) {
+        check_degree(poly, n - 1)?;
+    }
+
+    // Assign the input ciphertexts to the circuit and make them public
+    let [a0, a1, b0, b1] =
+        [&input.ct1[0], &input.ct1[1], &input.ct2[0], &input.ct2[1]].map(|c| load_poly(ctx, c));
+    for poly in [&a0, &a1, &b0, &b1] {
+        make_public.extend(poly.iter());
+    }
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Compute the tensor product, including the scaling by T/Q with rounding and the reduction mod Q
+    let tensored = bfv_tensor(ctx, &range, [&a0, &a1], [&b0, &b1], Q, T)?;
+    for (i, poly) in tensored.iter().enumerate() {
+        log_coeffs(&format!("tensor c{i}"), poly);
+    }
+    let expected = tensor(&input.ct1, &input.ct2, Q, T);
+
+    #[cfg(not(feature = "relin"))]
+    let (out, expected) = (tensored.to_vec(), expected.to_vec());
+
+    #[cfg(feature = "relin")]
+    let (out, expected) = {
+        // check that there is one key per digit and that every key component has N coefficients
+        if input.rlk.len() != num_digits(Q, W) {
+            return Err(CircuitError::LengthMismatch {
+                left: num_digits(Q, W),
+                right: input.rlk.len(),
+            });
+        }
+        for poly in input.rlk.iter().flatten() {
+            check_degree(poly, n - 1)?;
+        }
+
+        // Assign the relinearization key to the circuit and make it public
+        let rlk = input
+            .rlk
+            .iter()
+            .map(|key| [&key[0], &key[1]].map(|c| load_poly(ctx, c)))
+            .collect::<Vec<_>>();
+        for poly in rlk.iter().flatten() {
+            make_public.extend(poly.iter());
+        }
+
+        let [c0, c1, c2] = &tensored;
+        let out = bfv_relinearize(ctx, &range, [c0, c1, c2], &rlk, Q, W)?;
+        (out.to_vec(), relinearize(&expected, &input.rlk, Q, W).to_vec())
+    };
+
+    // Make the coefficients of the output ciphertext public
+    for poly in out.iter() {
+        make_public.extend(poly.iter());
+    }
+
+    // TEST
+    // Perform the multiplication outside the circuit to see if this matches the result of the circuit
+    for (poly, expected) in out.iter().zip(expected.iter()) {
+        for (coeff, expected) in poly.iter().zip(expected.iter()) {
+            assert_eq!(*coeff.value(), F::from(*expected));
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse_with_inline_inputs();
+    // with --auto-params, derive the circuit degree and LOOKUP_BITS from Q and the N of the input
+    let n =
+        input_ring_n(&mut args, |input: &CircuitInput| input.ct1[0].len()).expect("invalid input");
+    apply_auto_params(&mut args, Q, n);
+
+    // run different zk commands based on the command line arguments
+    run(|ctx, input, make_public| ct_mul(ctx, input, make_public).expect("invalid input"), args);
+}
@@ -0,0 +1,102 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::bfv::{decrypt, Ciphertext};
+use halo2_scaffold::fhe::gadgets::{bfv_decrypt, bfv_verify_public_key, commit_poly};
+use halo2_scaffold::params::{lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The public key, the ciphertext and the commitment to the message are made public
+// - The secret key, the error of the public key, the message and the blinding factor of the commitment are not made public
+// - Q, T, B and the noise bound are public constants of the circuit
+// - The secret key is bound to the public key: otherwise a prover could solve c0 + c1 * s = delta * m for s and open the ciphertext to any message
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the secret key, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+const T: u64 = 8; // plaintext modulus
+const B: u64 = 2; // upper bound of the error distribution [-B, B]
+const NOISE_BOUND: u64 = Q / (2 * T) - T; // largest noise magnitude accepted on decryption
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub pk: Ciphertext, // public key (p0, p1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub ct: Ciphertext, // ciphertext (c0, c1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub s: Vec<u64>, // secret key polynomial coefficients little endian of degree N - 1 in {0, 1, Q - 1}
+    pub e: Vec<u64>, // error polynomial of the public key coefficients little endian of degree N - 1 in [0, B] or [Q - B, Q - 1]
+    pub blinding: String, // blinding factor of the commitment, a field element as a string for easier deserialization
+}
+
+// this algorithm takes a ciphertext under a public key, and proves that it decrypts to the message committed to by the public commitment
+// Poseidon(blinding, m), without revealing the message. The secret key is checked against the public key, the ciphertext is decrypted
+// with a bound on its noise, and the decrypted message is hashed together with the blinding factor
+fn decrypt_committed<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that every polynomial has N coefficients
+    let n = ring_n(input.s.len())?;
+    for poly in input.pk.iter().chain(input.ct.iter()).chain([&input.s, &input.e]) {
+        check_degree(poly, n - 1)?;
+    }
+
+    // Assign the public key and the ciphertext to the circuit and make them public
+    let [p0, p1, c0, c1] =
+        [&input.pk[0], &input.pk[1], &input.ct[0], &input.ct[1]].map(|c| load_poly(ctx, c));
+    for poly in [&p0, &p1, &c0, &c1] {
+        make_public.extend(poly.iter());
+    }
+
+    // Assign the secret key, the error and the blinding factor to the circuit as private witnesses
+    let [s, e] = [&input.s, &input.e].map(|c| load_poly(ctx, c));
+    let blinding = ctx.load_witness(F::from_str_vartime(&input.blinding).unwrap());
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Check that s is ternary, that e is in [-B, B], and that p0 + p1 * s + e = 0 in R_Q
+    bfv_verify_public_key(ctx, &range, [&p0, &p1], &s, &e, Q, B)?;
+
+    // Decrypt the ciphertext, with a noise of magnitude at most NOISE_BOUND
+    let m = bfv_decrypt(ctx, &range, &s, [&c0, &c1], Q, T, NOISE_BOUND)?;
+
+    // Commit to the message, and make the commitment public
+    let commitment = commit_poly(ctx, &range.gate, &m, blinding);
+    make_public.push(commitment);
+
+    // TEST
+    // Decrypt the ciphertext outside the circuit to see if this matches the message of the circuit
+    let expected = decrypt(&input.s, &input.ct, Q, T);
+    for (coeff, expected) in m.iter().zip(expected.iter()) {
+        assert_eq!(*coeff.value(), F::from(*expected));
+    }
+    println!("commitment: {:?}", commitment.value());
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| {
+            decrypt_committed(ctx, input, make_public).expect("invalid input")
+        },
+        args,
+    );
+}
@@ -0,0 +1,89 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::bfv::{decrypt, Ciphertext};
+use halo2_scaffold::fhe::gadgets::{bfv_assert_decrypts_to_zero, bfv_verify_public_key};
+use halo2_scaffold::params::{lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The public key and the ciphertext are made public
+// - The secret key and the error of the public key are not made public
+// - Q, T, B and the noise bound are public constants of the circuit
+// - The secret key is bound to the public key: otherwise a prover could solve c0 + c1 * s = 0 for s and make any ciphertext decrypt to zero
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the secret key, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+const T: u64 = 8; // plaintext modulus
+const B: u64 = 2; // upper bound of the error distribution [-B, B]
+const NOISE_BOUND: u64 = Q / (2 * T) - T; // largest noise magnitude accepted on decryption
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub pk: Ciphertext, // public key (p0, p1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub ct: Ciphertext, // ciphertext (c0, c1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub s: Vec<u64>, // secret key polynomial coefficients little endian of degree N - 1 in {0, 1, Q - 1}
+    pub e: Vec<u64>, // error polynomial of the public key coefficients little endian of degree N - 1 in [0, B] or [Q - B, Q - 1]
+}
+
+// this algorithm takes a ciphertext under a public key, and proves that it decrypts to the zero message, i.e. that
+// [c0 + c1 * s]_Q is a noise of magnitude at most NOISE_BOUND. The secret key is checked against the public key first
+fn decrypt_to_zero<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that every polynomial has N coefficients
+    let n = ring_n(input.s.len())?;
+    for poly in input.pk.iter().chain(input.ct.iter()).chain([&input.s, &input.e]) {
+        check_degree(poly, n - 1)?;
+    }
+
+    // Assign the public key and the ciphertext to the circuit and make them public
+    let [p0, p1, c0, c1] =
+        [&input.pk[0], &input.pk[1], &input.ct[0], &input.ct[1]].map(|c| load_poly(ctx, c));
+    for poly in [&p0, &p1, &c0, &c1] {
+        make_public.extend(poly.iter());
+    }
+
+    // Assign the secret key and the error to the circuit as private witnesses
+    let [s, e] = [&input.s, &input.e].map(|c| load_poly(ctx, c));
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Check that s is ternary, that e is in [-B, B], and that p0 + p1 * s + e = 0 in R_Q
+    bfv_verify_public_key(ctx, &range, [&p0, &p1], &s, &e, Q, B)?;
+
+    // Decrypt the ciphertext, and constrain the message to be zero with a noise of magnitude at most NOISE_BOUND
+    bfv_assert_decrypts_to_zero(ctx, &range, &s, [&c0, &c1], Q, T, NOISE_BOUND)?;
+
+    // TEST
+    // Decrypt the ciphertext outside the circuit to see if this is the zero message
+    assert_eq!(decrypt(&input.s, &input.ct, Q, T), vec![0; n]);
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| decrypt_to_zero(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
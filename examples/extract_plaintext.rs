@@ -0,0 +1,86 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::bfv::extract_plaintext;
+use halo2_scaffold::fhe::gadgets::bfv_extract_plaintext;
+use halo2_scaffold::params::{apply_auto_params, input_ring_n, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The decrypted noisy polynomial is not made public
+// - The message is made public
+// - Q and T are public constants of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the message, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+const T: u64 = 8; // plaintext modulus
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub v: Vec<u64>, // decrypted noisy polynomial [c0 + c1*s]_Q, coefficients little endian of degree N - 1 in [0, Q)
+    pub m: Vec<u64>, // message polynomial coefficients little endian of degree N - 1 in [0, T)
+}
+
+// this algorithm takes a decrypted noisy polynomial v and proves that rounding it to the plaintext space,
+// i.e. computing round(T * v / Q) mod T, yields the message m
+fn extract_plaintext_circuit<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that both polynomials have N coefficients
+    let n = ring_n(input.m.len())?;
+    check_degree(&input.v, n - 1)?;
+    check_degree(&input.m, n - 1)?;
+
+    // Assign the noisy polynomial and the message to the circuit, and make the message public
+    let v = load_poly(ctx, &input.v);
+    let m = load_poly(ctx, &input.m);
+    make_public.extend(m.iter());
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Round every coefficient of v to the plaintext space and check that the result is m
+    let extracted = bfv_extract_plaintext(ctx, &range, &v, Q, T)?;
+    for (x, y) in extracted.iter().zip(m.iter()) {
+        ctx.constrain_equal(x, y);
+    }
+
+    // TEST
+    // Extract the plaintext outside the circuit to see if this matches the result of the circuit
+    let expected = extract_plaintext(&input.v, Q, T);
+    for (coeff, expected) in extracted.iter().zip(expected.iter()) {
+        assert_eq!(*coeff.value(), F::from(*expected));
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse_with_inline_inputs();
+    // with --auto-params, derive the circuit degree and LOOKUP_BITS from Q and the N of the input
+    let n = input_ring_n(&mut args, |input: &CircuitInput| input.m.len()).expect("invalid input");
+    apply_auto_params(&mut args, Q, n);
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| {
+            extract_plaintext_circuit(ctx, input, make_public).expect("invalid input")
+        },
+        args,
+    );
+}
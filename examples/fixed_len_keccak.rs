@@ -1,5 +1,4 @@
 use axiom_eth::{keccak::KeccakChip, EthChip, Field};
-use clap::Parser;
 use ethers_core::utils::keccak256;
 use halo2_base::{gates::RangeInstructions, AssignedValue, Context};
 use halo2_scaffold::scaffold::{cmd::Cli, run_eth};
@@ -70,7 +69,7 @@ fn compute_fixed_len_keccak<F: Field>(
 fn main() {
     env_logger::init();
 
-    let args = Cli::parse();
+    let args = Cli::parse_with_inline_inputs();
     // use run_eth instead of run
     run_eth(compute_fixed_len_keccak, args);
 }
@@ -1,4 +1,3 @@
-use clap::Parser;
 use halo2_base::gates::{GateChip, GateInstructions};
 use halo2_base::utils::ScalarField;
 use halo2_base::AssignedValue;
@@ -69,7 +68,7 @@ fn some_algorithm_in_zk<F: ScalarField>(
 fn main() {
     env_logger::init();
 
-    let args = Cli::parse();
+    let args = Cli::parse_with_inline_inputs();
 
     // run different zk commands based on the command line arguments
     run(some_algorithm_in_zk, args);
@@ -0,0 +1,73 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::{infinity_norm_assigned, load_poly, norm_squared_assigned};
+use halo2_scaffold::poly::utils::{check_degree, infinity_norm, norm_squared};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The infinity norm and the squared Euclidean norm of the polynomial are made public, so that a verifier can check them against bounds
+// - The polynomial is not made public
+// - Q is a public constant of the circuit
+// - The number of coefficients N is the one of the input, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // modulus of the coefficients
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub coeffs: Vec<u64>, // polynomial coefficients little endian of degree N - 1 in [0, Q)
+}
+
+// this algorithm takes a polynomial with coefficients in [0, Q) and outputs its infinity norm max_i |coeffs_i| and its squared Euclidean norm
+// sum_i coeffs_i^2 to the public, where every coefficient is taken in its centered representation in (-Q/2, Q/2]
+fn poly_infinity_norm<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that the polynomial has N coefficients
+    let n = ring_n(input.coeffs.len())?;
+    check_degree(&input.coeffs, n - 1)?;
+
+    // Assign the polynomial to the circuit
+    let coeffs = load_poly(ctx, &input.coeffs);
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Compute the infinity norm and the squared Euclidean norm and make them public
+    let norm = infinity_norm_assigned(ctx, &range, &coeffs, Q);
+    let norm_sq = norm_squared_assigned(ctx, &range, &coeffs, Q)?;
+    make_public.extend([norm, norm_sq]);
+
+    // TEST
+    // Compute the norms outside the circuit to see if this matches the result of the circuit
+    assert_eq!(norm.value(), &F::from(infinity_norm(&input.coeffs, Q)));
+    assert_eq!(norm_sq.value(), &F::from_u128(norm_squared(&input.coeffs, Q)));
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| {
+            poly_infinity_norm(ctx, input, make_public).expect("invalid input")
+        },
+        args,
+    );
+}
@@ -0,0 +1,104 @@
+use halo2_base::gates::{RangeChip, RangeInstructions};
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::lookup_bits;
+use halo2_scaffold::poly::ntt::{ntt_mul, primitive_root_of_unity};
+use halo2_scaffold::poly::operations::{
+    assert_reduced, load_poly, poly_mul_assigned, poly_reduce_ring_assigned,
+};
+use halo2_scaffold::poly::utils::{check_coeffs_bound, check_degree, ring_mul};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The products are made public, one polynomial per degree in NS
+// - The input polynomials are not made public
+// - Q and the degrees in NS are public constants of the circuit
+// - This is a self-checking circuit: the mock prover fails if the two multiplications diverge for any degree
+
+const NS: [usize; 3] = [4, 8, 16]; // degrees of the cyclotomic polynomials x^N + 1 to compare the multiplications for
+const Q: u64 = 7681; // NTT-friendly modulus, i.e. 2N divides Q - 1 for every N in NS
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub a: Vec<u64>, // polynomial coefficients little endian of degree max(NS) - 1 in [0, Q)
+    pub b: Vec<u64>, // polynomial coefficients little endian of degree max(NS) - 1 in [0, Q)
+}
+
+// this algorithm takes two polynomials a and b, and for every N in NS multiplies their first N coefficients in R_Q = Z_Q[x]/(x^N + 1)
+// both with the schoolbook method followed by a reduction and with the NTT, and constrains the two products to be equal
+fn mul_consistency<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    let max_n = *NS.iter().max().unwrap();
+    check_degree(&input.a, max_n - 1)?;
+    check_degree(&input.b, max_n - 1)?;
+    check_coeffs_bound(&input.a, Q)?;
+    check_coeffs_bound(&input.b, Q)?;
+
+    // Assign the input polynomials to the circuit
+    let a = load_poly(ctx, &input.a);
+    let b = load_poly(ctx, &input.b);
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Check that the coefficients are in [0, Q)
+    assert_reduced(ctx, &range, &a, Q);
+    assert_reduced(ctx, &range, &b, Q);
+
+    for n in NS {
+        let (a, b) = (&a[..n], &b[..n]);
+
+        // Schoolbook multiplication, reduced mod x^N + 1 and mod Q
+        let schoolbook = poly_mul_assigned(ctx, range.gate(), a, b)?;
+        let max_abs = n as u128 * (Q as u128 - 1).pow(2);
+        let schoolbook = poly_reduce_ring_assigned(ctx, &range, &schoolbook, n, Q, max_abs)?;
+
+        // NTT multiplication
+        let psi = primitive_root_of_unity(2 * n as u64, Q)?;
+        let ntt = ntt_mul(ctx, &range, a, b, psi, Q)?;
+
+        // Report whether the two products diverge, then constrain them to be equal
+        let agree = schoolbook.iter().zip(ntt.iter()).all(|(x, y)| x.value() == y.value());
+        println!(
+            "N = {n}: the schoolbook and NTT products {}",
+            if agree { "agree" } else { "diverge" }
+        );
+        for (x, y) in schoolbook.iter().zip(ntt.iter()) {
+            ctx.constrain_equal(x, y);
+        }
+        make_public.extend(ntt.iter());
+
+        // TEST
+        // Compute the product outside the circuit to see if this matches the result of the circuit
+        let expected = ring_mul(&input.a[..n], &input.b[..n], Q);
+        for (x, y) in ntt.iter().zip(expected) {
+            assert_eq!(x.value(), &F::from(y));
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| mul_consistency(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
@@ -0,0 +1,89 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::bfv::{noise, Ciphertext};
+use halo2_scaffold::fhe::gadgets::bfv_noise;
+use halo2_scaffold::params::{log_coeffs, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::{assert_inf_norm_bound, load_poly};
+use halo2_scaffold::poly::utils::{check_degree, infinity_norm};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The plaintext m and the noise bound b are made public
+// - The ciphertext (c0, c1) and the secret key s are not made public
+// - Q and T are public constants of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the secret key, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+const T: u64 = 8; // plaintext modulus
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub ct: Ciphertext, // ciphertext (c0, c1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub s: Vec<u64>, // secret key polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub m: Vec<u64>, // plaintext polynomial coefficients little endian of degree N - 1 in [0, T)
+    pub b: u64,      // noise bound
+}
+
+// this algorithm takes a ciphertext (c0, c1), the secret key s, the plaintext m and a bound b,
+// computes the noise e = [c0 + c1 * s - delta * m]_Q with delta = floor(Q / T),
+// and checks that every coefficient of e, taken in its centered representation in (-Q/2, Q/2], is in [-b, b]
+fn noise_bound<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that every polynomial has N coefficients
+    let n = ring_n(input.s.len())?;
+    for poly in input.ct.iter().chain([&input.s, &input.m]) {
+        check_degree(poly, n - 1)?;
+    }
+
+    // Assign the plaintext and the bound to the circuit and make them public
+    let m = load_poly(ctx, &input.m);
+    let b = ctx.load_witness(F::from(input.b));
+    make_public.extend(m.iter());
+    make_public.push(b);
+
+    // Assign the ciphertext and the secret key to the circuit as private witnesses
+    let [c0, c1, s] = [&input.ct[0], &input.ct[1], &input.s].map(|c| load_poly(ctx, c));
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Compute the noise and check that its infinity norm is at most b
+    let e = bfv_noise(ctx, &range, &s, [&c0, &c1], &m, Q, T)?;
+    log_coeffs("noise", &e);
+    assert_inf_norm_bound(ctx, &range, &e, Q, b);
+
+    // TEST
+    // Compute the noise outside the circuit to see if this matches the result of the circuit
+    let expected = noise(&input.s, &input.ct, &input.m, Q, T);
+    for (x, y) in e.iter().zip(expected.iter()) {
+        assert_eq!(x.value(), &F::from(*y));
+    }
+    println!("noise infinity norm: {}, bound: {}", infinity_norm(&expected, Q), input.b);
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| noise_bound(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
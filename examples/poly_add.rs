@@ -1,42 +1,41 @@
-use ark_bn254::Fr;
-use ark_ff::fields::PrimeField;
 use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
-use clap::Parser;
 use halo2_base::gates::GateChip;
-use halo2_base::safe_types::GateInstructions;
-use halo2_base::utils::ScalarField;
 use halo2_base::AssignedValue;
 #[allow(unused_imports)]
 use halo2_base::{
     Context,
     QuantumCell::{Constant, Existing, Witness},
 };
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::circuit_n;
+use halo2_scaffold::poly::operations::poly_add_assigned;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::run_on_field;
 use halo2_scaffold::scaffold::cmd::Cli;
-use halo2_scaffold::scaffold::run;
+use halo2_scaffold::scaffold::field::ArkField;
 use serde::{Deserialize, Serialize};
 
 // Note:
 // - The input polynomials are not made public
 // - No range check is performed after addition
-
-const N: usize = 3;
+// - The degree N is the degree of a, or the one given with --n, so the same binary runs for every degree
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct CircuitInput<const N: usize> {
+pub struct CircuitInput {
     pub a: Vec<u8>, // polynomial coefficients little endian of degree N (first element = constant term)
     pub b: Vec<u8>, // polynomial coefficients little endian of degree N (first element = constant term)
 }
 
 // this algorithm takes two polynomials a and b of the same degree and output their sum to the public
-fn poly_add<F: ScalarField>(
+fn poly_add<F: ArkField>(
     ctx: &mut Context<F>,
-    input: CircuitInput<N>,
+    input: CircuitInput,
     make_public: &mut Vec<AssignedValue<F>>,
-) {
-    // assert that the input polynomials have the same degree
-    assert_eq!(input.a.len() - 1, input.b.len() - 1);
-    // assert that degree is equal to the constant DEGREE
-    assert_eq!(input.a.len() - 1, N);
+) -> Result<(), CircuitError> {
+    // check that the degree of both input polynomials is equal to N
+    let n = circuit_n(input.a.len().saturating_sub(1));
+    check_degree(&input.a, n)?;
+    check_degree(&input.b, n)?;
 
     // Assign the input polynomials to the circuit
     let a_assigned: Vec<AssignedValue<F>> = input
@@ -60,49 +59,45 @@ fn poly_add<F: ScalarField>(
     // assert the correct length of the assigned polynomails
     assert_eq!(a_assigned.len(), b_assigned.len());
 
-    // Enforce that a_assigned[i] * b_assigned[i] = sum_assigned[i]
+    // Enforce that a_assigned[i] + b_assigned[i] = sum_assigned[i]
     let gate = GateChip::<F>::default();
-    let sum_assigned: Vec<AssignedValue<F>> = a_assigned
-        .iter()
-        .zip(b_assigned.iter())
-        .take(2 * N - 1)
-        .map(|(&a, &b)| gate.add(ctx, a, b))
-        .collect();
+    let sum_assigned = poly_add_assigned(ctx, &gate, &a_assigned, &b_assigned)?;
 
-    for i in 0..(N + 1) {
-        make_public.push(sum_assigned[i]);
+    for sum in sum_assigned.iter() {
+        make_public.push(*sum);
     }
 
     // TEST
     // Perform the addition of the polynomials outside the circuit (using arkworks) to see if this matches the result of the circuit
-    let a = DensePolynomial::<Fr>::from_coefficients_vec(
-        input.a.iter().map(|x| Fr::from(*x as u64)).collect::<Vec<Fr>>(),
+    let a = DensePolynomial::<F::Ark>::from_coefficients_vec(
+        input.a.iter().map(|x| F::Ark::from(*x as u64)).collect::<Vec<F::Ark>>(),
     );
 
-    let b = DensePolynomial::<Fr>::from_coefficients_vec(
-        input.b.iter().map(|x| Fr::from(*x as u64)).collect::<Vec<Fr>>(),
+    let b = DensePolynomial::<F::Ark>::from_coefficients_vec(
+        input.b.iter().map(|x| F::Ark::from(*x as u64)).collect::<Vec<F::Ark>>(),
     );
 
-    let c: DensePolynomial<Fr> = &a + &b;
-
-    // Turn coefficients to string
-    let c_coeffs = c.coeffs.iter().map(|x| x.into_bigint().to_string()).collect::<Vec<String>>();
+    let c: DensePolynomial<F::Ark> = &a + &b;
 
-    // iter over the c coefficients and turn it into F
-    let c_f = c_coeffs.iter().map(|x| F::from_str_vartime(x).unwrap()).collect::<Vec<F>>();
+    // Turn the coefficients into F
+    let c_f = c.coeffs.iter().map(F::from_ark).collect::<Vec<F>>();
 
     // Compare the result of the circuit with the result of the addition
     for (sum, c) in sum_assigned.iter().zip(c_f) {
         assert_eq!(sum.value(), &c);
     }
-}
 
+    Ok(())
+}
 
 fn main() {
     env_logger::init();
 
-    let args = Cli::parse();
+    let args = Cli::parse_with_inline_inputs();
 
-    // run different zk commands based on the command line arguments
-    run(poly_add, args);
+    // run different zk commands based on the command line arguments, over the scalar field selected with --field
+    run_on_field!(
+        |ctx, input, make_public| poly_add(ctx, input, make_public).expect("invalid input"),
+        args
+    );
 }
@@ -0,0 +1,78 @@
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
+use halo2_base::gates::GateChip;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::circuit_n;
+use halo2_scaffold::poly::operations::poly_add_assigned;
+use halo2_scaffold::poly::utils::{check_degree, parse_coeff};
+use halo2_scaffold::run_on_field;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::field::ArkField;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The input polynomials are not made public
+// - The coefficients are elements of the scalar field of the circuit, i.e. the polynomials are added mod the field modulus, which does not fit in a u64
+// - The degree N is the degree of a, or the one given with --n, so the same binary runs for every degree
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub a: Vec<String>, // polynomial coefficients little endian of degree N, as decimal or 0x-prefixed hexadecimal strings less than the field modulus
+    pub b: Vec<String>, // polynomial coefficients little endian of degree N, as decimal or 0x-prefixed hexadecimal strings less than the field modulus
+}
+
+// this algorithm takes two polynomials a and b of the same degree, with coefficients of up to the size of the field, and outputs their sum to the public
+fn poly_add_hex<F: ArkField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that the degree of both input polynomials is equal to N
+    let n = circuit_n(input.a.len().saturating_sub(1));
+    check_degree(&input.a, n)?;
+    check_degree(&input.b, n)?;
+
+    // Parse the coefficients into field elements, rejecting any coefficient that is not less than the field modulus
+    let a = input.a.iter().map(|x| parse_coeff::<F>(x)).collect::<Result<Vec<_>, _>>()?;
+    let b = input.b.iter().map(|x| parse_coeff::<F>(x)).collect::<Result<Vec<_>, _>>()?;
+
+    // Assign the input polynomials to the circuit
+    let a_assigned = a.iter().map(|x| ctx.load_witness(*x)).collect::<Vec<_>>();
+    let b_assigned = b.iter().map(|x| ctx.load_witness(*x)).collect::<Vec<_>>();
+
+    // Enforce that a_assigned[i] + b_assigned[i] = sum_assigned[i] mod the field modulus, and make the sum public
+    let gate = GateChip::<F>::default();
+    let sum_assigned = poly_add_assigned(ctx, &gate, &a_assigned, &b_assigned)?;
+    make_public.extend(sum_assigned.iter());
+
+    // TEST
+    // Perform the addition of the polynomials outside the circuit (using arkworks) to see if this matches the result of the circuit
+    let a = DensePolynomial::<F::Ark>::from_coefficients_vec(a.iter().map(F::to_ark).collect());
+    let b = DensePolynomial::<F::Ark>::from_coefficients_vec(b.iter().map(F::to_ark).collect());
+    let c: DensePolynomial<F::Ark> = &a + &b;
+
+    // arkworks drops the leading zero coefficients of the sum
+    for (i, sum) in sum_assigned.iter().enumerate() {
+        let expected = c.coeffs.get(i).map_or_else(F::zero, F::from_ark);
+        assert_eq!(sum.value(), &expected);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments, over the scalar field selected with --field
+    run_on_field!(
+        |ctx, input, make_public| poly_add_hex(ctx, input, make_public).expect("invalid input"),
+        args
+    );
+}
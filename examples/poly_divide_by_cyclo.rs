@@ -1,4 +1,3 @@
-use clap::Parser;
 use halo2_base::gates::GateChip;
 use halo2_base::safe_types::GateInstructions;
 use halo2_base::utils::ScalarField;
@@ -8,21 +7,36 @@ use halo2_base::{
     Context,
     QuantumCell::{Constant, Existing, Witness},
 };
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{circuit_n, log_coeffs};
+use halo2_scaffold::poly::operations::{pad_to_len, poly_identity_check, poly_mul_assigned};
+use halo2_scaffold::poly::utils::{check_degree, check_max_degree, div_euclid, fe_from_i64};
 use halo2_scaffold::scaffold::cmd::Cli;
 use halo2_scaffold::scaffold::run;
+use poseidon::PoseidonChip;
 use serde::{Deserialize, Serialize};
 
 // Note:
 // - The polynomial are not made public to the outside
 // - No range check is performed after the division
-
-const N: usize = 4;
-const M: usize = 2;
+// - With identity_check set, quot * denominator + rem = nominator is checked at a single random point
+//   (Schwartz-Zippel) instead of coefficient by coefficient. The random point is the Poseidon hash of all the
+//   polynomials, so that it is fixed only after the prover has committed to them
+// - The degree N of the nominator is read from its length, or given with --n, and the degree M of the denominator from its length,
+//   so the same binary runs for every pair of degrees with M <= N
+
+// Poseidon parameters used to derive the challenge of the identity check
+const T: usize = 3;
+const RATE: usize = 2;
+const R_F: usize = 8;
+const R_P: usize = 57;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CircuitInput {
-    pub nominator:  Vec<i8>,// nominator polynomial coefficients little endian of degree N (last element = constant term)
-    pub denominator: Vec<i8>, // denominator polynomial coefficients little endian of degree M (last element = constant term)
+    pub nominator:  Vec<i64>,// nominator polynomial coefficients little endian of degree N (last element = constant term)
+    pub denominator: Vec<i64>, // denominator polynomial coefficients little endian of degree M (last element = constant term)
+    #[serde(default)]
+    pub identity_check: bool, // check the division at a random point instead of coefficient by coefficient
 }
 
 // takes a polynomial represented by its coefficients in a vector (public input)
@@ -31,19 +45,30 @@ fn poly_divide_by_cyclo<F: ScalarField>(
     ctx: &mut Context<F>,
     input: CircuitInput,
     make_public: &mut Vec<AssignedValue<F>>,
-) {
-
-	// Assert that degree of nominator poly is equal to the constant N
-    assert_eq!(input.nominator.len() - 1, N);
-    // Assert that degree of denominator poly is equal to the constant M
-    assert_eq!(input.denominator.len() - 1, M);
+) -> Result<(), CircuitError> {
+
+    // check that degree of nominator poly is equal to N, and that the denominator is not empty
+    // a mismatch is returned as a `WrongDegree` error. The nominator may have leading zeros, i.e. a lower actual degree
+    let n = circuit_n(input.nominator.len().saturating_sub(1));
+    let m = input.denominator.len().saturating_sub(1);
+    check_degree(&input.nominator, n)?;
+    check_degree(&input.denominator, m)?;
+    // the quotient has N - M + 1 coefficients, so the denominator cannot have a larger degree than the nominator
+    if m > n {
+        return Err(CircuitError::DegreeTooLarge { max: n, found: m });
+    }
+    // the quotient is only integral when the denominator is monic, as the cyclotomic polynomial x^M + 1
+    // (the first element of the big endian denominator is its leading coefficient)
+    if input.denominator[0] != 1 {
+        return Err(CircuitError::NotMonic { leading: input.denominator[0] });
+    }
 
     // Assign the input polynomials to the circuit
     let nom_assigned: Vec<AssignedValue<F>> = input
         .nominator
         .iter()
         .map(|x| {
-            let result = F::from(*x as u64);
+            let result = fe_from_i64::<F>(*x);
             ctx.load_witness(result)
         })
         .collect();
@@ -52,46 +77,43 @@ fn poly_divide_by_cyclo<F: ScalarField>(
         .denominator
         .iter()
         .map(|x| {
-            let result = F::from(*x as u64);
+            let result = fe_from_i64::<F>(*x);
             ctx.load_witness(result)
         })
         .collect();
 
-    // assert the correct length of the assigned polynomails
-    assert_eq!(nom_assigned.len() - 1, N);
-    assert_eq!(denom_assigned.len() - 1, M);
-
     // crete gate chip
     let gate = GateChip::<F>::default();
 
+    // constrain the leading coefficient of the denominator to 1, so that the prover cannot swap in a non-monic denominator
+    gate.assert_is_const(ctx, &denom_assigned[0], &F::one());
+
     // long division operation
     let (quot, rem) = div_euclid(&input.nominator, &input.denominator);
 
+    // the remainder is padded to the length of the nominator below, so it cannot be longer,
+    // which would mean that the division did not reduce it
+    check_max_degree(&rem, n)?;
+    // the quotient is trimmed, so it is shorter than N - M + 1 coefficients when the nominator has leading zeros,
+    // and empty when the actual degree of the nominator is less than M (then the remainder is the nominator)
+    check_max_degree(&quot, n - m)?;
+
     // assign the quot to the gate chip
-    let quot_assigned: Vec<AssignedValue<F>> = quot
-        .iter()
-        .map(|x| {
-            let result = F::from(*x as u64);
-            ctx.load_witness(result)
-        })
-        .collect();
+    // like rem below, it is padded with constant zeros to N - M + 1 coefficients, so that quot * denominator has as many coefficients as the nominator
+    let quot_le: Vec<AssignedValue<F>> =
+        quot.iter().rev().map(|x| ctx.load_witness(fe_from_i64::<F>(*x))).collect();
+    let mut quot_assigned = pad_to_len(ctx, &quot_le, n - m + 1)?;
+    quot_assigned.reverse();
 
     // assign the rem to the gate chip
     // note that it first pads with 0 to make the length of rem and nominator equal
-    let initial_size = input.nominator.len() - rem.len();
-
-    let mut rem_assigned = Vec::with_capacity(input.nominator.len());
-    for _i in 0..(initial_size) {
-        let zero = F::from(0 as u64);
-        rem_assigned.push(ctx.load_witness(zero));
-    }
-
-    rem
-    .iter()
-    .for_each(|x| {
-        let result = F::from(*x as u64);
-        rem_assigned.push(ctx.load_witness(result));
-    });
+    // rem is big endian, so we pad its little endian form with constant zeros and reverse it back
+    let rem_le: Vec<AssignedValue<F>> =
+        rem.iter().rev().map(|x| ctx.load_witness(fe_from_i64::<F>(*x))).collect();
+    let mut rem_assigned = pad_to_len(ctx, &rem_le, input.nominator.len())?;
+    rem_assigned.reverse();
+    log_coeffs("quotient", &quot_assigned);
+    log_coeffs("remainder", &rem_assigned);
 
 	// make the rem output public
 	for i in 0..(rem_assigned.len() - 1) {
@@ -101,32 +123,9 @@ fn poly_divide_by_cyclo<F: ScalarField>(
     // ---- constraint check -----
     // check that quotient * denominator + rem = nominator
     // quot_assigned * denom_assigned
-    let mut prod_val: Vec<AssignedValue<F>> = vec![];
-    for i in 0..(2 * M + 1) {
-        let mut coefficient_accumaltor: Vec<AssignedValue<F>> = vec![];
-
-        if i < M + 1 {
-            for a_idx in 0..=i {
-                let a = quot_assigned[a_idx];
-                let b = denom_assigned[i - a_idx];
-                // push the product of a and b to the coefficient_accumaltor
-                coefficient_accumaltor.push(gate.mul(ctx, a, b));
-            }
-        } else {
-            for a_idx in (i - M)..=M {
-                let a = quot_assigned[a_idx];
-                let b = denom_assigned[i - a_idx];
-                // push the product of a and b to the coefficient_accumaltor
-                coefficient_accumaltor.push(gate.mul(ctx, a, b));
-            }
-        }
-
-        let prod_value = coefficient_accumaltor
-            .iter()
-            .fold(ctx.load_witness(F::zero()), |acc, x| gate.add(ctx, acc, *x));
-
-        prod_val.push(prod_value);
-    }
+    // every coefficient of the product is accumulated with a single inner product over slices of the inputs
+    let prod_val = poly_mul_assigned(ctx, &gate, &quot_assigned, &denom_assigned)?;
+    log_coeffs("quotient * denominator", &prod_val);
 
     assert_eq!(prod_val.len(), rem_assigned.len());
 
@@ -134,60 +133,49 @@ fn poly_divide_by_cyclo<F: ScalarField>(
     let sum_assigned: Vec<AssignedValue<F>> = prod_val
     .iter()
     .zip(rem_assigned.iter())
-    .take(2 * N - 1)
     .map(|(&a, &b)| gate.add(ctx, a, b))
     .collect();
 
+    if input.identity_check {
+        // derive the challenge from all the polynomials involved in the division
+        let mut poseidon = PoseidonChip::<F, T, RATE>::new(ctx, R_F, R_P).unwrap();
+        for poly in [&nom_assigned, &denom_assigned, &quot_assigned, &rem_assigned] {
+            poseidon.update(poly);
+        }
+        let r = poseidon.squeeze(ctx, &gate).unwrap();
+
+        // both polynomials are big endian with the same number of coefficients, so evaluating them as
+        // little endian polynomials evaluates their reversals, which are equal iff the polynomials are
+        poly_identity_check(ctx, &gate, &sum_assigned, &nom_assigned, r)?;
+    } else {
+        // constrain that sum_assigned = nominator coefficient by coefficient
+        for (sum, nom) in sum_assigned.iter().zip(nom_assigned.iter()) {
+            ctx.constrain_equal(sum, nom);
+        }
+    }
+
     // check that sum_assined coeff = nominator coeff
 	let out_expected = input.nominator;
 
 	for (sum, out) in sum_assigned.iter().zip(out_expected) {
-        assert_eq!(sum.value().get_lower_32(), out as u32);
+        assert_eq!(*sum.value(), fe_from_i64::<F>(out));
     }
 
     // ---- constraint check -----
-}
-
-fn div_euclid(f: &Vec<i8>, g: &Vec<i8>) -> (Vec<i8>, Vec<i8>) {
-
-    if g.is_empty() || g.iter().all(|&x| x == 0) {
-        panic!("Cannot divide by a zero polynomial!");
-    }
-
-    let mut dividend = f.clone();
-    let divisor_degree = g.len() - 1;
-    let mut quotient = Vec::new();
-
-    while dividend.len() > divisor_degree {
-        let leading_coefficient_ratio = dividend[0] / g[0];
-        quotient.push(leading_coefficient_ratio);
-
-        for (i, coeff) in g.iter().enumerate() {
-            let diff = dividend[i] - leading_coefficient_ratio * *coeff;
-            dividend[i] = diff;
-        }
-
-        dividend.remove(0);
-    }
-
-    // Trim the leading zeros from quotient and remainder
-    while !quotient.is_empty() && quotient[0] == 0 {
-        quotient.remove(0);
-    }
-
-    while !dividend.is_empty() && dividend[0] == 0 {
-        dividend.remove(0);
-    }
-
-    (quotient, dividend)
 
+    Ok(())
 }
 
 fn main() {
     env_logger::init();
 
-    let args = Cli::parse();
+    let args = Cli::parse_with_inline_inputs();
 
     // run different zk commands based on the command line arguments
-    run(poly_divide_by_cyclo, args);
-}
\ No newline at end of file
+    run(
+        |ctx, input, make_public| {
+            poly_divide_by_cyclo(ctx, input, make_public).expect("invalid input")
+        },
+        args,
+    );
+}
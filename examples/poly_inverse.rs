@@ -0,0 +1,72 @@
+use halo2_base::gates::{GateInstructions, RangeChip, RangeInstructions};
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{log_coeffs, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::assert_poly_zero;
+use halo2_scaffold::poly::ring::CyclotomicRing;
+use halo2_scaffold::poly::utils::{check_coeffs_bound, check_degree, poly_inverse_mod};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The input polynomial and its inverse are not made public
+// - Q is a public constant of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the input polynomial, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // modulus of the ring R_q = Z_q[x]/(x^N + 1), a prime so that Z_q is a field
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub a: Vec<u64>, // polynomial coefficients little endian of degree N - 1 in [0, Q)
+}
+
+// this algorithm takes a polynomial a in R_q, computes its inverse outside of the circuit with the extended Euclidean algorithm,
+// and proves that a * a_inv = 1 in R_q by multiplying the two polynomials in R_q
+fn poly_inverse<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    _make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that the input polynomial is an element of R_q, and that it is invertible
+    let n = ring_n(input.a.len())?;
+    check_degree(&input.a, n - 1)?;
+    check_coeffs_bound(&input.a, Q)?;
+    let a_inv = poly_inverse_mod(&input.a, n, Q)?;
+
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+    let ring = CyclotomicRing::new(n, Q)?;
+
+    // Assign the polynomial and its inverse to the circuit, and range check them so that they are elements of R_q
+    let a = ring.load(ctx, &range, &input.a)?;
+    let a_inv = ring.load(ctx, &range, &a_inv)?;
+    log_coeffs("inverse", &a_inv);
+
+    // Multiply the polynomials in R_q
+    let out = ring.mul(ctx, &range, &a, &a_inv)?;
+
+    // Check that the product is the unit polynomial 1
+    range.gate().assert_is_const(ctx, &out[0], &F::one());
+    assert_poly_zero(ctx, range.gate(), &out[1..]);
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| poly_inverse(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
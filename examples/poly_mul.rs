@@ -1,54 +1,76 @@
-use ark_bn254::Fr;
-use ark_ff::fields::PrimeField;
-use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
-use clap::Parser;
-use halo2_base::gates::GateChip;
-use halo2_base::safe_types::GateInstructions;
-use halo2_base::utils::ScalarField;
+use ark_poly::univariate::{DenseOrSparsePolynomial, DensePolynomial};
+use ark_poly::DenseUVPolynomial;
+use halo2_base::gates::RangeChip;
 use halo2_base::AssignedValue;
 #[allow(unused_imports)]
 use halo2_base::{
     Context,
     QuantumCell::{Constant, Existing, Witness},
 };
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{circuit_n, log_coeffs, lookup_bits};
+use halo2_scaffold::poly::budget::Budgeted;
+use halo2_scaffold::poly::operations::{poly_mul_bounded_budgeted, poly_reduce_cyclo_assigned};
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::run_on_field;
 use halo2_scaffold::scaffold::cmd::Cli;
-use halo2_scaffold::scaffold::run;
+use halo2_scaffold::scaffold::field::ArkField;
+use halo2_scaffold::scaffold::input::{Versioned, VersionedInput};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 // Notes:
 // - The input polynomials are not made public
-// - Suppose that range check is performed on the coeffiicients in order to avoid overflow for happen during the multiplication
+// - With "reduce": true in the input, only the N+1 coefficients of the product mod x^(N+1) + 1 are made public instead of the 2N+1 coefficients of the full product
+// - The coefficients of the input polynomials are range checked to be in [0, 255] so that the coefficients of the product are bounded by (N+1) * 255 * 255 and cannot overflow
 
 // Complexity of the algorithm
 // The algorithm involves two nested loops: the outer loop runs for "2N+1" iterations and the inner loop runs for up to "N+1" iterations in the worst case.
 // The operations inside the inner loop are additions and multiplications in the field F which are O(1) operations.
 // Therefore, the complexity of the algorithm is O((2N+1)*(N+1)*1) = O(N^2)
 
-const N: usize = 3;
+// The degree N is the degree of a, or the one given with --n, so the same binary runs for every degree
 
 // The polynomial multiplication is performed using the direct method.
 // Given two polynomials a and b of degree n, the product c = a * b is a polynomial of degree 2n
 // The coefficients of c are computed as dot products of the coefficients of a and b
 // The coefficients of c are made public
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct CircuitInput<const N: usize> {
+pub struct CircuitInput {
     pub a: Vec<u8>, // polynomial coefficients little endian of degree n (first element = constant term)
     pub b: Vec<u8>, // polynomial coefficients little endian of degree n (first element = constant term)
+    #[serde(default)]
+    pub reduce: bool, // reduce the product mod x^(N+1) + 1 in the circuit and only make the N+1 coefficients of the result public
+}
+
+// Input versions:
+// - 1: a and b (files without a "version" field)
+// - 2: adds reduce, which is false for version 1 inputs
+impl VersionedInput for CircuitInput {
+    const VERSION: u64 = 2;
+
+    fn migrate(fields: &mut Map<String, Value>, from: u64) -> Result<(), CircuitError> {
+        if from == 1 {
+            fields.entry("reduce").or_insert(Value::Bool(false));
+        }
+        Ok(())
+    }
 }
 
 // this algorithm takes two polynomials a and b of the same degree and output their product to the public
-pub fn poly_mul<F: ScalarField>(
+pub fn poly_mul<F: ArkField>(
     ctx: &mut Context<F>,
-    input: CircuitInput<N>,
+    input: CircuitInput,
     make_public: &mut Vec<AssignedValue<F>>,
-) {
-    // assert that the input polynomials have the same degree
-    assert_eq!(input.a.len() - 1, input.b.len() - 1);
-    // assert that degree is equal to the constant N
-    assert_eq!(input.a.len() - 1, N);
+) -> Result<(), CircuitError> {
+    // check that the degree of both input polynomials is equal to N
+    let n = circuit_n(input.a.len().saturating_sub(1));
+    check_degree(&input.a, n)?;
+    check_degree(&input.b, n)?;
 
-    // Create a gate chip
-    let gate = GateChip::<F>::default();
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
 
     // Assign the input polynomials to the circuit
     let a_assigned: Vec<AssignedValue<F>> = input
@@ -69,56 +91,62 @@ pub fn poly_mul<F: ScalarField>(
         })
         .collect();
 
-    // Build the product of the polynomials as dot products of the coefficients of a and b
-    let mut prod_val: Vec<AssignedValue<F>> = vec![];
-    for i in 0..(2 * N + 1) {
-        let mut coefficient_accumaltor: Vec<AssignedValue<F>> = vec![];
-
-        if i < N + 1 {
-            for a_idx in 0..=i {
-                let a = a_assigned[a_idx];
-                let b = b_assigned[i - a_idx];
-                // push the product of a and b to the coefficient_accumaltor
-                coefficient_accumaltor.push(gate.mul(ctx, a, b));
-            }
-        } else {
-            for a_idx in (i - N)..=N {
-                let a = a_assigned[a_idx];
-                let b = b_assigned[i - a_idx];
-                // push the product of a and b to the coefficient_accumaltor
-                coefficient_accumaltor.push(gate.mul(ctx, a, b));
-            }
-        }
-
-        let prod_value = coefficient_accumaltor
-            .iter()
-            .fold(ctx.load_witness(F::zero()), |acc, x| gate.add(ctx, acc, *x));
-
-        prod_val.push(prod_value);
-    }
+    // Build the product of the polynomials as dot products of the coefficients of a and b,
+    // after range checking the coefficients of a and b to be in [0, 255].
+    // The product has a quadratic cost, so with --max-rows it fails at the first coefficient of the product that crosses the budget
+    let budget = Budgeted::from_env(&range);
+    let (prod_val, bound) = poly_mul_bounded_budgeted(
+        ctx,
+        &budget,
+        &a_assigned,
+        &b_assigned,
+        u8::MAX as u64,
+        u8::MAX as u64,
+    )?;
+    assert_eq!(bound, (n as u128 + 1) * 255 * 255);
+    log_coeffs("product", &prod_val);
+
+    // Fold the product mod x^(N+1) + 1, which gives the product in the ring of polynomials with N+1 coefficients.
+    // The folded coefficients can be negative, and are then represented by their negation mod the field modulus
+    let prod_val = if input.reduce {
+        let reduced = poly_reduce_cyclo_assigned(ctx, &range.gate, &prod_val, n + 1);
+        log_coeffs("reduced product", &reduced);
+        reduced
+    } else {
+        prod_val
+    };
 
     // Make the coefficients of the product public. The coefficients are in little endian order
-    for i in 0..(2 * N + 1) {
-        make_public.push(prod_val[i]);
+    for prod in prod_val.iter() {
+        make_public.push(*prod);
     }
 
     // TEST
     // Perform the multiplication of the polynomials outside the circuit (using arkworks) to see if this matches the result of the circuit
-    let a = DensePolynomial::<Fr>::from_coefficients_vec(
-        input.a.iter().map(|x| Fr::from(*x as u64)).collect::<Vec<Fr>>(),
+    let a = DensePolynomial::<F::Ark>::from_coefficients_vec(
+        input.a.iter().map(|x| F::Ark::from(*x as u64)).collect::<Vec<F::Ark>>(),
     );
 
-    let b = DensePolynomial::<Fr>::from_coefficients_vec(
-        input.b.iter().map(|x| Fr::from(*x as u64)).collect::<Vec<Fr>>(),
+    let b = DensePolynomial::<F::Ark>::from_coefficients_vec(
+        input.b.iter().map(|x| F::Ark::from(*x as u64)).collect::<Vec<F::Ark>>(),
     );
 
-    let c: DensePolynomial<Fr> = &a * &b;
-
-    // Turn coefficients to string
-    let c_coeffs = c.coeffs.iter().map(|x| x.into_bigint().to_string()).collect::<Vec<String>>();
+    let mut c: DensePolynomial<F::Ark> = &a * &b;
+    if input.reduce {
+        // the remainder of the division by x^(N+1) + 1, padded back to N+1 coefficients
+        let mut cyclo = vec![F::Ark::from(0u64); n + 2];
+        cyclo[0] = F::Ark::from(1u64);
+        cyclo[n + 1] = F::Ark::from(1u64);
+        let cyclo = DensePolynomial::from_coefficients_vec(cyclo);
+        let (_, rem) = DenseOrSparsePolynomial::from(&c)
+            .divide_with_q_and_r(&DenseOrSparsePolynomial::from(&cyclo))
+            .unwrap();
+        c = rem;
+        c.coeffs.resize(n + 1, F::Ark::from(0u64));
+    }
 
-    // iter over the c coefficients and turn it into F
-    let c_f = c_coeffs.iter().map(|x| F::from_str_vartime(x).unwrap()).collect::<Vec<F>>();
+    // Turn the coefficients into F
+    let c_f = c.coeffs.iter().map(F::from_ark).collect::<Vec<F>>();
 
     // assert that the length of the circuit result is equal to the length of the result of the multiplication
     assert_eq!(prod_val.len(), c_f.len());
@@ -127,13 +155,21 @@ pub fn poly_mul<F: ScalarField>(
     for (prod, c) in prod_val.iter().zip(c_f) {
         assert_eq!(prod.value(), &c);
     }
+
+    Ok(())
 }
 
 fn main() {
     env_logger::init();
 
-    let args = Cli::parse();
+    let args = Cli::parse_with_inline_inputs();
 
-    // run different zk commands based on the command line arguments
-    run(poly_mul, args);
+    // run different zk commands based on the command line arguments, over the scalar field selected with --field.
+    // The input is upgraded from older versions of CircuitInput before it is parsed
+    run_on_field!(
+        |ctx, Versioned(input), make_public| {
+            poly_mul(ctx, input, make_public).expect("invalid input")
+        },
+        args
+    );
 }
@@ -1,5 +1,4 @@
-use clap::Parser;
-use halo2_base::safe_types::{RangeChip, RangeInstructions};
+use halo2_base::safe_types::RangeChip;
 use halo2_base::utils::ScalarField;
 use halo2_base::AssignedValue;
 #[allow(unused_imports)]
@@ -7,22 +6,26 @@ use halo2_base::{
     Context,
     QuantumCell::{Constant, Existing, Witness},
 };
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{circuit_n, log_coeffs, lookup_bits};
+use halo2_scaffold::poly::operations::poly_reduce_mod_signed_assigned;
+use halo2_scaffold::poly::utils::{check_degree, fe_from_i64};
 use halo2_scaffold::scaffold::cmd::Cli;
-use halo2_scaffold::scaffold::run;
+use halo2_scaffold::scaffold::{constrain_equals_public, run};
 use serde::{Deserialize, Serialize};
-use std::env::var;
 
 // Assumptions:
-// - The coefficients of the dividend polynomial are in the range of 16 bits
-// - The divisor polynomial is a cyclotomic polynomial of degree M
+// - The coefficients of the dividend polynomial have absolute value less than 2^num_bits, where num_bits is part of the input
+// - Negative coefficients, e.g. the result of a subtraction, are reduced to their canonical representative in [0, MODULUS)
+// - The degree N is the degree of poly, or the one given with --n, so the same binary runs for every degree
 
-const N: usize = 3;
-const MODULUS: usize = 11;
+const MODULUS: u64 = 11;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CircuitInput {
-    pub poly: Vec<u8>, // polynomial coefficients big endian of degree N (last element = constant term)
-    pub out: Vec<u8>, // polynomial coefficients big endian of degree N (last element = constant term)
+    pub poly: Vec<i64>, // polynomial coefficients big endian of degree N (last element = constant term), possibly negative
+    pub out: Vec<u64>, // claimed remainder, a public input: polynomial coefficients big endian of degree N (last element = constant term)
+    pub num_bits: usize, // bit width of the absolute values of the coefficients of the input polynomial
 }
 
 // takes a polynomial represented by its coefficients in a vector (public input)
@@ -31,54 +34,41 @@ fn reduce_poly<F: ScalarField>(
     ctx: &mut Context<F>,
     input: CircuitInput,
     make_public: &mut Vec<AssignedValue<F>>,
-) {
+) -> Result<(), CircuitError> {
+    // check that the degree of the input polynomial is equal to N
+    check_degree(&input.poly, circuit_n(input.poly.len().saturating_sub(1)))?;
 
-
-	// Assert that degree is equal to the constant N
-    assert_eq!(input.poly.len() - 1, N);
-
-    // Assign the input polynomials to the circuit
-    let in_assigned: Vec<AssignedValue<F>> = input
-        .poly
-        .iter()
-        .map(|x| {
-            let result = F::from(*x as u64);
-            ctx.load_witness(result) // load the input as a witness
-        })
-        .collect();
+    // Assign the input polynomials to the circuit, with the negative coefficients encoded as p - |x|
+    let in_assigned =
+        input.poly.iter().map(|&x| ctx.load_witness(fe_from_i64(x))).collect::<Vec<_>>();
 
     // needs to be compatible with some backend setup for lookup table to do range check
     // so read from environemntal variable
-    let lookup_bits =
-        var("LOOKUP_BITS").unwrap_or_else(|_| panic!("LOOKUP_BITS nto set")).parse().unwrap();
+    let lookup_bits = lookup_bits();
 
     // instead of GateChip create a RangeChip, which allows you to do range check
     let range = RangeChip::default(lookup_bits);
 
-    // Enforce that in_assigned[i] % MODULUS = rem_assigned[i]
-    // coefficients of input polynomials are guaranteed to be at most 16 bits by assumption
-    let rem_assigned: Vec<AssignedValue<F>> =
-        in_assigned.iter().take(2 * N - 1).map(|&x| range.div_mod(ctx, x, MODULUS, 16).1).collect();
-
-    // make the output public
-    for i in 0..(N + 1) {
-        make_public.push(rem_assigned[i]);
-    }
-
+    // Enforce that in_assigned[i] mod MODULUS = rem_assigned[i], with rem_assigned[i] in [0, MODULUS) also for negative coefficients
+    // coefficients of the input polynomial are shifted by a multiple of MODULUS to be non-negative, and range checked against the declared bit width
+    let rem_assigned =
+        poly_reduce_mod_signed_assigned(ctx, &range, &in_assigned, MODULUS, input.num_bits)?;
+    log_coeffs("remainder", &rem_assigned);
 
-    // check that rem_assigned = output of the circuit
-    let out_expected = input.out;
+    // constrain the remainder to be the claimed output, which is made public
+    constrain_equals_public(ctx, &rem_assigned, &input.out, make_public)?;
 
-    for i in 0..N {
-        assert_eq!(*rem_assigned[i].value(), F::from(out_expected[i] as u64));
-    }
+    Ok(())
 }
 
 fn main() {
     env_logger::init();
 
-    let args = Cli::parse();
+    let args = Cli::parse_with_inline_inputs();
 
     // run different zk commands based on the command line arguments
-    run(reduce_poly, args);
+    run(
+        |ctx, input, make_public| reduce_poly(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
 }
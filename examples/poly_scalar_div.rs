@@ -0,0 +1,97 @@
+use halo2_base::gates::{GateInstructions, RangeChip, RangeInstructions};
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{log_coeffs, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::assert_reduced;
+use halo2_scaffold::poly::ring::CyclotomicRing;
+use halo2_scaffold::poly::utils::{
+    bit_length, check_coeffs_bound, check_degree, field_to_i64, from_centered, inv_mod,
+};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The input polynomial and the inverse of k are not made public
+// - The scalar k and the quotient are made public
+// - Q is a public constant of the circuit
+// - The ring dimension N is the number of coefficients of a, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // prime modulus of the coefficients, so that every k != 0 mod Q is invertible
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub a: Vec<u64>, // polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub k: u64,      // scalar in [0, Q) to divide by
+}
+
+// this algorithm takes a polynomial a and a scalar k, and outputs the quotient out_i = a_i * k^(-1) mod Q to the public.
+// The inverse of k is computed outside of the circuit, and the circuit proves that it is the inverse with k * k^(-1) = 1 mod Q
+fn poly_scalar_div<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that the input polynomial is an element of R_q, and that k is reduced mod Q
+    let n = ring_n(input.a.len())?;
+    check_degree(&input.a, n - 1)?;
+    check_coeffs_bound(&input.a, Q)?;
+    check_coeffs_bound(&[input.k], Q)?;
+
+    // k = 0 is the only scalar that has no inverse mod the prime Q
+    if input.k == 0 {
+        return Err(CircuitError::NotInvertible);
+    }
+    let k_inv = inv_mod(input.k, Q);
+
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+    let ring = CyclotomicRing::new(n, Q)?;
+
+    // Assign the polynomial, the scalar and its inverse to the circuit, and make k public
+    let a = ring.load(ctx, &range, &input.a)?;
+    let k = ctx.load_witness(F::from(input.k));
+    let k_inv = ctx.load_witness(F::from(k_inv));
+    make_public.push(k);
+
+    // Range check the scalars, so that the products below are bounded by (Q-1)^2
+    assert_reduced(ctx, &range, &[k, k_inv], Q);
+    let num_bits = bit_length((Q as u128 - 1).pow(2));
+
+    // Constrain k * k_inv = 1 mod Q
+    let prod = range.gate().mul(ctx, k, k_inv);
+    let (_, rem) = range.div_mod(ctx, prod, Q, num_bits);
+    range.gate().assert_is_const(ctx, &rem, &F::one());
+
+    // Multiply a by k_inv in R_q, then make the quotient public
+    let out = ring.scalar_mul(ctx, &range, &a, k_inv)?;
+    log_coeffs("quotient", &out);
+    make_public.extend(out.iter());
+
+    // TEST
+    // Multiply the quotient by k outside the circuit to see if this gives back the input polynomial
+    for (x, a) in out.iter().zip(input.a.iter()) {
+        let x = from_centered(field_to_i64(x.value(), Q)?, Q);
+        assert_eq!((x as u128 * input.k as u128 % Q as u128) as u64, *a);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| poly_scalar_div(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
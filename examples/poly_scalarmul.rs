@@ -1,38 +1,40 @@
-use ark_bn254::Fr;
-use ark_ff::fields::PrimeField;
 use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
-use clap::Parser;
 use halo2_base::gates::GateChip;
-use halo2_base::safe_types::GateInstructions;
-use halo2_base::utils::ScalarField;
 use halo2_base::AssignedValue;
 #[allow(unused_imports)]
 use halo2_base::{
     Context,
     QuantumCell::{Constant, Existing, Witness},
 };
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::circuit_n;
+use halo2_scaffold::poly::operations::poly_scalar_mul_assigned;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::run_on_field;
 use halo2_scaffold::scaffold::cmd::Cli;
-use halo2_scaffold::scaffold::run;
+use halo2_scaffold::scaffold::field::ArkField;
 use serde::{Deserialize, Serialize};
 
 // Note:
 // - The input polynomial and the scalar are not made public
 // - No range check is performed after multiplication
-
-const N: usize = 3;
+// - The degree N is the degree of a, or the one given with --n, so the same binary runs for every degree
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct CircuitInput<const N: usize> {
+pub struct CircuitInput {
     pub a: Vec<u8>, // polynomial coefficients little endian of degree N (first element = constant term)
     pub k: u8,      // scalar
 }
 
 // this algorithm takes a polynomial a and a scalar k output their product to the public
-fn poly_scalar_mul<F: ScalarField>(
+fn poly_scalar_mul<F: ArkField>(
     ctx: &mut Context<F>,
-    input: CircuitInput<N>,
+    input: CircuitInput,
     make_public: &mut Vec<AssignedValue<F>>,
-) {
+) -> Result<(), CircuitError> {
+    // check that the degree of the input polynomial is equal to N
+    check_degree(&input.a, circuit_n(input.a.len().saturating_sub(1)))?;
+
     // Assign the polynomial a and the scalar k to the circuit
     let a_assigned: Vec<AssignedValue<F>> = input
         .a
@@ -47,27 +49,22 @@ fn poly_scalar_mul<F: ScalarField>(
 
     // Enforce that a_assigned[i] * k = scalar_prod_assigned[i]
     let gate = GateChip::<F>::default();
-    let mut scalar_prod_assigned = Vec::new();
-    for i in 0..(N + 1) {
-        let scalar_prod = gate.mul(ctx, a_assigned[i], k_assigned);
-        scalar_prod_assigned.push(scalar_prod);
-        make_public.push(scalar_prod);
+    let scalar_prod_assigned = poly_scalar_mul_assigned(ctx, &gate, &a_assigned, k_assigned);
+    for scalar_prod in scalar_prod_assigned.iter() {
+        make_public.push(*scalar_prod);
     }
 
     // TEST
     // Perform the scalar multiplcation outside the circuit (using arkworks) to see if this matches the result of the circuit
-    let a = DensePolynomial::<Fr>::from_coefficients_vec(
-        input.a.iter().map(|x| Fr::from(*x as u64)).collect::<Vec<Fr>>(),
+    let a = DensePolynomial::<F::Ark>::from_coefficients_vec(
+        input.a.iter().map(|x| F::Ark::from(*x as u64)).collect::<Vec<F::Ark>>(),
     );
 
-    let k = Fr::from(input.k as u64);
-
-    let c: DensePolynomial<Fr> = &a * k;
-    // Turn coefficients to string
-    let c_coeffs = c.coeffs.iter().map(|x| x.into_bigint().to_string()).collect::<Vec<String>>();
+    let k = F::Ark::from(input.k as u64);
 
-    // iter over the c coefficients and turn it into F
-    let c_f = c_coeffs.iter().map(|x| F::from_str_vartime(x).unwrap()).collect::<Vec<F>>();
+    let c: DensePolynomial<F::Ark> = &a * k;
+    // Turn the coefficients into F
+    let c_f = c.coeffs.iter().map(F::from_ark).collect::<Vec<F>>();
 
     // assert that the length of the circuit result is equal to the length of the result of the multiplication
     assert_eq!(scalar_prod_assigned.len(), c_f.len());
@@ -76,13 +73,18 @@ fn poly_scalar_mul<F: ScalarField>(
     for (prod, c) in scalar_prod_assigned.iter().zip(c_f) {
         assert_eq!(prod.value(), &c);
     }
+
+    Ok(())
 }
 
 fn main() {
     env_logger::init();
 
-    let args = Cli::parse();
+    let args = Cli::parse_with_inline_inputs();
 
-    // run different zk commands based on the command line arguments
-    run(poly_scalar_mul, args);
+    // run different zk commands based on the command line arguments, over the scalar field selected with --field
+    run_on_field!(
+        |ctx, input, make_public| poly_scalar_mul(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
 }
@@ -0,0 +1,88 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{log_coeffs, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::poly_trace;
+use halo2_scaffold::poly::ring::CyclotomicRing;
+use halo2_scaffold::poly::utils::{
+    self, check_coeffs_bound, check_degree, field_to_i64, inv_mod, to_centered,
+};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The input polynomial is private, and its trace is made public
+// - The generators and the normalization are fixed in the circuit, since they decide how the coefficients are wired, so the proving and verifying keys depend on them
+// - Q is a public constant of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the input polynomial, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // prime modulus of the ring R_q = Z_q[x]/(x^N + 1)
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub a: Vec<u64>, // polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub generators: Vec<usize>, // exponents k of the automorphisms x -> x^k generating the subgroup H, coprime to 2N
+    pub normalize: bool,        // whether to divide the trace by |H|
+}
+
+// this algorithm takes a polynomial a in R_q and outputs its trace over the subgroup H of automorphisms generated by
+// the given exponents, sum_{k in H} a(x^k), to the public. The subgroup is enumerated by closing {1} under
+// multiplication by the generators mod 2N. With normalize, the trace is multiplied by |H|^(-1) mod Q, so that the
+// trace of a polynomial fixed by H is the polynomial itself
+fn trace<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that the polynomial is an element of R_q
+    let n = ring_n(input.a.len())?;
+    check_degree(&input.a, n - 1)?;
+    check_coeffs_bound(&input.a, Q)?;
+
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+    let ring = CyclotomicRing::new(n, Q)?;
+
+    // Assign the polynomial to the circuit as a private witness, and range check it
+    let a = ring.load(ctx, &range, &input.a)?;
+
+    // Sum the conjugates a(x^k) for k in H, reduced to R_q
+    let (subgroup, mut out) = poly_trace(ctx, &range, &a, &input.generators, ring.n, ring.q)?;
+    println!("subgroup H: {subgroup:?}");
+
+    // |H| divides the order N of the Galois group, so it is a power of two and invertible mod the odd prime Q
+    let inv = inv_mod(subgroup.len() as u64, Q);
+    if input.normalize {
+        let inv = ctx.load_constant(F::from(inv));
+        out = ring.scalar_mul(ctx, &range, &out, inv)?;
+    }
+    log_coeffs("trace", &out);
+    make_public.extend(out.iter());
+
+    // TEST
+    // Compute the trace outside the circuit to see if this matches the output of the circuit
+    let mut expected = utils::poly_trace(&input.a, &subgroup, Q);
+    if input.normalize {
+        expected.iter_mut().for_each(|x| *x = (*x as u128 * inv as u128 % Q as u128) as u64);
+    }
+    let values = out.iter().map(|x| field_to_i64(x.value(), Q)).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(values, expected.iter().map(|x| to_centered(*x, Q)).collect::<Vec<_>>());
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(|ctx, input, make_public| trace(ctx, input, make_public).expect("invalid input"), args);
+}
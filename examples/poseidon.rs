@@ -1,4 +1,3 @@
-use clap::Parser;
 use halo2_base::{gates::GateChip, utils::ScalarField, AssignedValue, Context};
 use halo2_scaffold::scaffold::{cmd::Cli, run};
 use poseidon::PoseidonChip;
@@ -38,6 +37,6 @@ fn hash_two<F: ScalarField>(
 fn main() {
     env_logger::init();
 
-    let args = Cli::parse();
+    let args = Cli::parse_with_inline_inputs();
     run(hash_two, args);
 }
@@ -1,11 +1,10 @@
-use clap::Parser;
 use halo2_base::gates::{GateInstructions, RangeChip, RangeInstructions};
 use halo2_base::utils::ScalarField;
 use halo2_base::{AssignedValue, Context};
+use halo2_scaffold::params::lookup_bits;
 use halo2_scaffold::scaffold::cmd::Cli;
 use halo2_scaffold::scaffold::run;
 use serde::{Deserialize, Serialize};
-use std::env::var;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CircuitInput {
@@ -22,8 +21,7 @@ fn some_algorithm_in_zk<F: ScalarField>(
     // More advanced usage with multi-threaded witness generation is possible, but we do not explain it here
 
     // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
-    let lookup_bits =
-        var("LOOKUP_BITS").unwrap_or_else(|_| panic!("LOOKUP_BITS not set")).parse().unwrap();
+    let lookup_bits = lookup_bits();
     // first we load a private input `x`
     let x = ctx.load_witness(x);
     // make it public
@@ -42,7 +40,7 @@ fn some_algorithm_in_zk<F: ScalarField>(
 fn main() {
     env_logger::init();
 
-    let args = Cli::parse();
+    let args = Cli::parse_with_inline_inputs();
 
     // run different zk commands based on the command line arguments
     run(some_algorithm_in_zk, args);
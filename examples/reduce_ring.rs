@@ -0,0 +1,86 @@
+use halo2_base::gates::{RangeChip, RangeInstructions};
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{log_coeffs, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::poly_mul_assigned;
+use halo2_scaffold::poly::ring::CyclotomicRing;
+use halo2_scaffold::poly::utils::{check_coeffs_bound, check_degree, ring_mul};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The input polynomials are not made public
+// - The output polynomial in R_q is made public
+// - Q is a public constant of the circuit
+// - N is the number of coefficients of the inputs, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1073741789; // modulus of the ring R_q = Z_q[x]/(x^N + 1), the largest prime below 2^30
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub a: Vec<u64>, // polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub b: Vec<u64>, // polynomial coefficients little endian of degree N - 1 in [0, Q)
+}
+
+// this algorithm takes two polynomials a and b in R_q, multiplies them over the integers and then finalizes the product
+// by folding it mod x^N + 1 and reducing every coefficient mod Q, so that the output is the canonical representative of a * b in R_q
+fn reduce_ring<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that the input polynomials are elements of R_q
+    let n = ring_n(input.a.len())?;
+    for poly in [&input.a, &input.b] {
+        check_degree(poly, n - 1)?;
+        check_coeffs_bound(poly, Q)?;
+    }
+
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+    let ring = CyclotomicRing::new(n, Q)?;
+
+    // Assign the input polynomials to the circuit, and range check them so that the coefficients of the raw product are bounded by N * (Q-1)^2
+    let a = ring.load(ctx, &range, &input.a)?;
+    let b = ring.load(ctx, &range, &input.b)?;
+
+    // Compute the raw product of degree 2N - 2
+    let prod = poly_mul_assigned(ctx, range.gate(), &a, &b)?;
+    log_coeffs("product", &prod);
+
+    // Fold the product mod x^N + 1 and reduce the coefficients mod Q
+    let max_abs = n as u128 * (Q as u128 - 1).pow(2);
+    let out = ring.reduce(ctx, &range, &prod, max_abs)?;
+    log_coeffs("reduced product", &out);
+
+    // Make the output public
+    make_public.extend(out.iter());
+
+    // TEST
+    // Perform the multiplication in R_q outside the circuit to see if this matches the result of the circuit
+    let expected = ring_mul(&input.a, &input.b, Q);
+    for (coeff, expected) in out.iter().zip(expected.iter()) {
+        assert_eq!(*coeff.value(), F::from(*expected));
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| reduce_ring(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
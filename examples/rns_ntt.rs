@@ -0,0 +1,90 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::params::{lookup_bits, ring_n};
+use halo2_scaffold::poly::ntt::{negacyclic_ntt, primitive_root_of_unity};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::rns::{assert_rns_ntt_representation, rns_decompose};
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The NTT representation of the polynomial (one vector of evaluations per prime) is made public
+// - The coefficients and their RNS residues are not made public
+// - The primes are public constants of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the polynomial, or the one given with --n, so the same binary runs for every N
+// - The NTT needs a primitive 2N-th root of unity mod every prime, so N must be a power of two with 2N dividing 7681 - 1 = 2^9 * 15 and 12289 - 1 = 2^12 * 3, i.e. N <= 256
+
+const PRIMES: [u64; 2] = [7681, 12289]; // NTT-friendly primes, i.e. 2N divides q - 1, whose product Q is the modulus of the coefficients
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub coeffs: Vec<u64>, // polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub residues: Vec<Vec<u64>>, // for every prime q, the coefficients mod q
+    pub evals: Vec<Vec<u64>>, // for every prime q, the negacyclic NTT of the residues mod q, in natural order
+}
+
+// this algorithm takes a polynomial mod Q together with its RNS and NTT representation,
+// and checks that the residues recompose to the polynomial with the CRT and that the evaluations are the NTT of the residues for every prime
+fn rns_ntt<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that every polynomial has N coefficients
+    let n = ring_n(input.coeffs.len())?;
+    for poly in [&input.coeffs].into_iter().chain(input.residues.iter()).chain(input.evals.iter()) {
+        check_degree(poly, n - 1)?;
+    }
+
+    // Assign the NTT representation to the circuit and make it public
+    let evals = input.evals.iter().map(|e| load_poly(ctx, e)).collect::<Vec<_>>();
+    for poly in evals.iter() {
+        make_public.extend(poly.iter());
+    }
+
+    // Assign the coefficients and the residues to the circuit as private witnesses
+    let coeffs = load_poly(ctx, &input.coeffs);
+    let residues = input.residues.iter().map(|r| load_poly(ctx, r)).collect::<Vec<_>>();
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Check the CRT recomposition of the residues and the NTT of every residue polynomial
+    let psis = PRIMES
+        .iter()
+        .map(|q| primitive_root_of_unity(2 * n as u64, *q))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_rns_ntt_representation(ctx, &range, &coeffs, &residues, &evals, &PRIMES, &psis)?;
+
+    // TEST
+    // Compute the RNS and NTT representation outside the circuit to see if this matches the input
+    let expected =
+        rns_decompose(&input.coeffs.iter().map(|c| *c as u128).collect::<Vec<_>>(), &PRIMES);
+    assert_eq!(expected, input.residues);
+    for ((residues, evals), (q, psi)) in
+        expected.iter().zip(input.evals.iter()).zip(PRIMES.iter().zip(psis))
+    {
+        assert_eq!(&negacyclic_ntt(residues, psi, *q), evals);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(|ctx, input, make_public| rns_ntt(ctx, input, make_public).expect("invalid input"), args);
+}
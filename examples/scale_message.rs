@@ -0,0 +1,82 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::bfv::scale_message;
+use halo2_scaffold::fhe::gadgets::bfv_scale_message;
+use halo2_scaffold::params::{lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The scaled message is made public, the message is not
+// - Q and T are public constants of the circuit, and so is DELTA = floor(Q/T). Q = 131071 * T + 5 is not divisible by T, so DELTA = 131071 is rounded down
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the message, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+const T: u64 = 8; // plaintext modulus
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub m: Vec<u64>, // message polynomial coefficients little endian of degree N - 1 in [0, T)
+    pub out: Vec<u64>, // claimed scaled message DELTA * m, coefficients little endian of degree N - 1 in [0, Q)
+}
+
+// this algorithm takes a message m and a claimed polynomial out, and proves that out_i = DELTA * m_i for every coefficient.
+// This is the encoding of the message that BFV encryption adds to p0*u + e1
+fn scale_message_circuit<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that both polynomials have N coefficients
+    let n = ring_n(input.m.len())?;
+    check_degree(&input.m, n - 1)?;
+    check_degree(&input.out, n - 1)?;
+
+    // Assign the message and the scaled message to the circuit, and make the scaled message public
+    let m = load_poly(ctx, &input.m);
+    let out = load_poly(ctx, &input.out);
+    make_public.extend(out.iter());
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Multiply every coefficient of m by the constant DELTA and check that the result is out
+    let scaled = bfv_scale_message(ctx, &range, &m, Q, T)?;
+    for (x, y) in scaled.iter().zip(out.iter()) {
+        ctx.constrain_equal(x, y);
+    }
+
+    // TEST
+    // Scale the message outside the circuit to see if this matches the result of the circuit
+    let expected = scale_message(&input.m, Q, T);
+    for (x, y) in scaled.iter().zip(expected) {
+        assert_eq!(x.value(), &F::from(y));
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let args = Cli::parse_with_inline_inputs();
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| {
+            scale_message_circuit(ctx, input, make_public).expect("invalid input")
+        },
+        args,
+    );
+}
@@ -0,0 +1,96 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::bfv::{encrypt_with_randomness, Ciphertext};
+use halo2_scaffold::fhe::gadgets::bfv_verify_encryption;
+use halo2_scaffold::params::{apply_auto_params, input_ring_n, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The public key and the ciphertext are made public
+// - The message m and the randomness u, e1, e2 used for the encryption are not made public
+// - Q, T and B are public constants of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the message, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+const T: u64 = 8; // plaintext modulus
+const B: u64 = 2; // upper bound of the error distribution [-B, B]
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub pk: Ciphertext, // public key (p0, p1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub ct: Ciphertext, // ciphertext (c0, c1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub m: Vec<u64>,    // message polynomial coefficients little endian of degree N - 1 in [0, T)
+    pub u: Vec<u64>, // ephemeral key polynomial coefficients little endian of degree N - 1 in {0, 1, Q - 1}
+    pub e1: Vec<u64>, // first error polynomial coefficients little endian of degree N - 1 in [0, B] or [Q - B, Q - 1]
+    pub e2: Vec<u64>, // second error polynomial coefficients little endian of degree N - 1 in [0, B] or [Q - B, Q - 1]
+}
+
+// this algorithm takes a public key, a ciphertext, and the message and randomness of the encryption,
+// and checks that the ciphertext is the honest encryption of the message under the public key, i.e. that
+// ct0 = [p0 * u + e1 + delta * m]_Q and ct1 = [p1 * u + e2]_Q with u ternary and e1, e2 sampled from the error distribution
+fn verifiable_encrypt<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that every polynomial has N coefficients
+    let n = ring_n(input.m.len())?;
+    for poly in
+        input.pk.iter().chain(input.ct.iter()).chain([&input.m, &input.u, &input.e1, &input.e2])
+    {
+        check_degree(poly, n - 1)?;
+    }
+
+    // Assign the public key and the ciphertext to the circuit and make them public
+    let [p0, p1, c0, c1] =
+        [&input.pk[0], &input.pk[1], &input.ct[0], &input.ct[1]].map(|c| load_poly(ctx, c));
+    for poly in [&p0, &p1, &c0, &c1] {
+        make_public.extend(poly.iter());
+    }
+
+    // Assign the message and the randomness to the circuit as private witnesses
+    let [m, u, e1, e2] = [&input.m, &input.u, &input.e1, &input.e2].map(|c| load_poly(ctx, c));
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Check that u is ternary, that e1 and e2 are in [-B, B], and that the ciphertext matches the encryption formula
+    bfv_verify_encryption(ctx, &range, [&p0, &p1], [&c0, &c1], &m, &u, [&e1, &e2], Q, T, B)?;
+
+    // TEST
+    // Encrypt the message outside the circuit with the same randomness to see if this matches the ciphertext
+    let expected =
+        encrypt_with_randomness(&input.pk, &input.m, &input.u, &input.e1, &input.e2, Q, T);
+    assert_eq!(expected, input.ct);
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse_with_inline_inputs();
+    // with --auto-params, derive the circuit degree and LOOKUP_BITS from Q and the N of the input
+    let n = input_ring_n(&mut args, |input: &CircuitInput| input.m.len()).expect("invalid input");
+    apply_auto_params(&mut args, Q, n);
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| {
+            verifiable_encrypt(ctx, input, make_public).expect("invalid input")
+        },
+        args,
+    );
+}
@@ -0,0 +1,92 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::bfv::{public_key, Ciphertext};
+use halo2_scaffold::fhe::gadgets::bfv_verify_public_key;
+use halo2_scaffold::params::{apply_auto_params, input_ring_n, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::check_degree;
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The public key (p0, p1) is made public
+// - The secret key s and the error e are not made public
+// - Q and B are public constants of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the secret key, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // ciphertext modulus
+const B: u64 = 2; // upper bound of the error distribution [-B, B]
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub pk: Ciphertext, // public key (p0, p1), polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub a: Vec<u64>,    // uniform polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub s: Vec<u64>, // secret key polynomial coefficients little endian of degree N - 1 in {0, 1, Q - 1}
+    pub e: Vec<u64>, // error polynomial coefficients little endian of degree N - 1 in [0, B] or [Q - B, Q - 1]
+}
+
+// this algorithm takes a public key (p0, p1), the uniform polynomial a, the secret key s and the error e,
+// and checks that the public key is well-formed, i.e. that p1 = a and p0 + a * s + e = 0 in R_Q
+// with s ternary and e sampled from the error distribution
+fn verify_pubkey<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that every polynomial has N coefficients
+    let n = ring_n(input.s.len())?;
+    for poly in input.pk.iter().chain([&input.a, &input.s, &input.e]) {
+        check_degree(poly, n - 1)?;
+    }
+
+    // Assign the public key to the circuit and make it public
+    let [p0, p1] = [&input.pk[0], &input.pk[1]].map(|c| load_poly(ctx, c));
+    for poly in [&p0, &p1] {
+        make_public.extend(poly.iter());
+    }
+
+    // Assign a, the secret key and the error to the circuit as private witnesses
+    let [a, s, e] = [&input.a, &input.s, &input.e].map(|c| load_poly(ctx, c));
+
+    // Check that p1 = a
+    for (x, y) in p1.iter().zip(a.iter()) {
+        ctx.constrain_equal(x, y);
+    }
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Check that s is ternary, that e is in [-B, B], and that p0 + a * s + e = 0 in R_Q
+    bfv_verify_public_key(ctx, &range, [&p0, &a], &s, &e, Q, B)?;
+
+    // TEST
+    // Compute the public key outside the circuit to see if this matches the input public key
+    let expected = public_key(&input.s, &input.a, &input.e, Q);
+    assert_eq!(expected, input.pk);
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse_with_inline_inputs();
+    // with --auto-params, derive the circuit degree and LOOKUP_BITS from Q and the N of the input
+    let n = input_ring_n(&mut args, |input: &CircuitInput| input.s.len()).expect("invalid input");
+    apply_auto_params(&mut args, Q, n);
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| verify_pubkey(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
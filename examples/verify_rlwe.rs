@@ -0,0 +1,86 @@
+use halo2_base::gates::RangeChip;
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+#[allow(unused_imports)]
+use halo2_base::{
+    Context,
+    QuantumCell::{Constant, Existing, Witness},
+};
+use halo2_scaffold::error::CircuitError;
+use halo2_scaffold::fhe::gadgets::rlwe_verify_sample;
+use halo2_scaffold::params::{apply_auto_params, input_ring_n, lookup_bits, ring_n};
+use halo2_scaffold::poly::operations::load_poly;
+use halo2_scaffold::poly::utils::{check_degree, ring_add, ring_mul};
+use halo2_scaffold::scaffold::cmd::Cli;
+use halo2_scaffold::scaffold::run;
+use serde::{Deserialize, Serialize};
+
+// Notes:
+// - The sample (a, b) is made public
+// - The secret s and the error e are not made public
+// - Q and B are public constants of the circuit
+// - The degree N of the cyclotomic polynomial x^N + 1 is the number of coefficients of the secret, or the one given with --n, so the same binary runs for every N
+
+const Q: u64 = 1048573; // modulus of the ring R_Q
+const B: u64 = 2; // upper bound of the error distribution [-B, B]
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CircuitInput {
+    pub a: Vec<u64>, // uniform polynomial coefficients little endian of degree N - 1 in [0, Q)
+    pub b: Vec<u64>, // polynomial coefficients of a * s + e, little endian of degree N - 1 in [0, Q)
+    pub s: Vec<u64>, // secret polynomial coefficients little endian of degree N - 1 in {0, 1, Q - 1}
+    pub e: Vec<u64>, // error polynomial coefficients little endian of degree N - 1 in [0, B] or [Q - B, Q - 1]
+}
+
+// this algorithm takes an RLWE sample (a, b), the secret s and the error e, and checks that the sample is well-formed,
+// i.e. that b = a * s + e in R_Q with s ternary and e sampled from the error distribution.
+// Public keys and ciphertexts of BFV are built from such samples
+fn verify_rlwe<F: ScalarField>(
+    ctx: &mut Context<F>,
+    input: CircuitInput,
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    // check that every polynomial has N coefficients
+    let n = ring_n(input.s.len())?;
+    for poly in [&input.a, &input.b, &input.s, &input.e] {
+        check_degree(poly, n - 1)?;
+    }
+
+    // Assign the sample to the circuit and make it public
+    let [a, b] = [&input.a, &input.b].map(|c| load_poly(ctx, c));
+    for poly in [&a, &b] {
+        make_public.extend(poly.iter());
+    }
+
+    // Assign the secret and the error to the circuit as private witnesses
+    let [s, e] = [&input.s, &input.e].map(|c| load_poly(ctx, c));
+
+    // lookup bits must agree with the size of the lookup table, which is specified by an environmental variable
+    let lookup_bits = lookup_bits();
+    let range = RangeChip::default(lookup_bits);
+
+    // Check that s is ternary, that e is in [-B, B], and that b = a * s + e in R_Q
+    rlwe_verify_sample(ctx, &range, [&a, &b], &s, &e, Q, B)?;
+
+    // TEST
+    // Compute b outside the circuit to see if this matches the input sample
+    let expected = ring_add(&ring_mul(&input.a, &input.s, Q), &input.e, Q);
+    assert_eq!(expected, input.b);
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = Cli::parse_with_inline_inputs();
+    // with --auto-params, derive the circuit degree and LOOKUP_BITS from Q and the N of the input
+    let n = input_ring_n(&mut args, |input: &CircuitInput| input.s.len()).expect("invalid input");
+    apply_auto_params(&mut args, Q, n);
+
+    // run different zk commands based on the command line arguments
+    run(
+        |ctx, input, make_public| verify_rlwe(ctx, input, make_public).expect("invalid input"),
+        args,
+    );
+}
@@ -0,0 +1,82 @@
+//! Enumerates BFV parameter sets for a plaintext modulus and a noise budget, and prints the ones the circuits of this crate support.
+//!
+//! For every power of two `n` in the range and every bit size of `q`, the candidate modulus is the largest NTT-friendly prime of that size.
+//! A candidate is printed when it satisfies the no-overflow conditions of the circuits over the BN254 scalar field (see `params::check_circuit_params`)
+//! and a fresh encryption leaves at least the requested noise budget (see `params::fresh_noise_budget`).
+//! The circuit degree and cell count are the rough estimates of `params::recommended_params`.
+//!
+//! ```bash
+//! cargo run --bin param_sweep -- --t 8 --budget 4
+//! cargo run --bin param_sweep -- --t 256 --budget 20 --b 3 --max-n 4096 --min-q-bits 30
+//! ```
+use clap::Parser;
+use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+use halo2_scaffold::params::sweep_params;
+
+#[derive(Parser, Debug)]
+#[command(about = "Lists the BFV parameter sets supported by the circuits of this crate")]
+struct Args {
+    /// plaintext modulus
+    #[arg(long)]
+    t: u64,
+    /// minimal noise budget of a fresh encryption, in bits
+    #[arg(long)]
+    budget: usize,
+    /// upper bound of the error distribution [-b, b]
+    #[arg(long, default_value_t = 2)]
+    b: u64,
+    /// smallest ring dimension, rounded up to a power of two
+    #[arg(long, default_value_t = 4)]
+    min_n: usize,
+    /// largest ring dimension
+    #[arg(long, default_value_t = 2048)]
+    max_n: usize,
+    /// smallest number of bits of the ciphertext modulus
+    #[arg(long, default_value_t = 16)]
+    min_q_bits: usize,
+    /// largest number of bits of the ciphertext modulus
+    #[arg(long, default_value_t = 64)]
+    max_q_bits: usize,
+}
+
+fn main() {
+    let args = Args::parse();
+    let degrees = (0..usize::BITS)
+        .map(|i| 1 << i)
+        .skip_while(|n| *n < args.min_n)
+        .take_while(|n| *n <= args.max_n);
+    let viable = sweep_params::<Fr>(
+        args.t,
+        args.b,
+        args.budget,
+        degrees,
+        args.min_q_bits..=args.max_q_bits.min(64),
+    );
+
+    println!(
+        "{} viable parameter sets for t = {}, b = {} and a noise budget of at least {} bits",
+        viable.len(),
+        args.t,
+        args.b,
+        args.budget
+    );
+    if viable.is_empty() {
+        return;
+    }
+    println!(
+        "  {:>6} {:>22} {:>7} {:>13} {:>3} {:>11} {:>12}",
+        "n", "q", "q bits", "noise budget", "k", "lookup bits", "advice cells"
+    );
+    for c in viable {
+        println!(
+            "  {:>6} {:>22} {:>7} {:>13} {:>3} {:>11} {:>12}",
+            c.params.n,
+            c.params.q,
+            64 - c.params.q.leading_zeros(),
+            c.noise_budget,
+            c.k,
+            c.lookup_bits,
+            c.cells
+        );
+    }
+}
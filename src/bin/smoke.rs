@@ -0,0 +1,160 @@
+//! Runs every example through the mock prover on its canonical input from `data/`, and reports which circuits pass.
+//!
+//! Every example of `scaffold::examples::EXAMPLES` runs with `cargo run --example <example> -- --name <example> -k <k> mock`, with its degree and `LOOKUP_BITS`,
+//! followed by the other inputs documented in the README. Cases with a malformed input (e.g. an error coefficient outside of its distribution) are expected to be rejected by the mock prover.
+//! The process exits with a nonzero status when any case does not behave as expected.
+//!
+//! The inputs are the files checked in under `data/`, as no test-vector generator exists yet to build them in the process.
+//!
+//! ```bash
+//! cargo run --bin smoke            # all cases
+//! cargo run --bin smoke -- ct_ ntt # only the cases whose example name contains `ct_` or `ntt`
+//! ```
+use std::env;
+use std::path::Path;
+use std::process::{exit, Command};
+
+use halo2_scaffold::scaffold::examples::{find_example, Example, EXAMPLES};
+
+/// A run of one example on one input.
+struct Case {
+    example: &'static Example,
+    /// the input in `data/`, which is `data/<example>.in` when it is not set
+    input: Option<&'static str>,
+    /// the degree of the circuit, when it differs from the one of the example
+    k: Option<u32>,
+    features: &'static [&'static str],
+    extra_args: &'static [&'static str],
+    /// whether the mock prover should accept the input
+    expect_pass: bool,
+}
+
+impl Case {
+    fn new(example: &'static Example) -> Self {
+        Self { example, input: None, k: None, features: &[], extra_args: &[], expect_pass: true }
+    }
+
+    // a case of the example with the given name, which must be in `EXAMPLES`
+    fn of(name: &str) -> Self {
+        Self::new(find_example(name).unwrap_or_else(|| panic!("{name} is not in EXAMPLES")))
+    }
+
+    fn input(self, input: &'static str) -> Self {
+        Self { input: Some(input), ..self }
+    }
+
+    fn degree(self, k: u32) -> Self {
+        Self { k: Some(k), ..self }
+    }
+
+    fn features(self, features: &'static [&'static str]) -> Self {
+        Self { features, ..self }
+    }
+
+    fn extra_args(self, extra_args: &'static [&'static str]) -> Self {
+        Self { extra_args, ..self }
+    }
+
+    fn rejected(self) -> Self {
+        Self { expect_pass: false, ..self }
+    }
+
+    fn label(&self) -> String {
+        let mut label = self.example.name.to_string();
+        if let Some(input) = self.input {
+            label += &format!(" ({input})");
+        }
+        for arg in self.extra_args.iter().chain(self.features) {
+            label += &format!(" {arg}");
+        }
+        label
+    }
+
+    // runs the example and returns whether the mock prover accepted the input, together with the captured stderr
+    fn run(&self, cargo: &str, manifest_dir: &Path) -> (bool, String) {
+        let Example { name, k, lookup_bits } = *self.example;
+        let mut cmd = Command::new(cargo);
+        cmd.current_dir(manifest_dir).env("LOOKUP_BITS", lookup_bits.to_string()).args([
+            "run",
+            "--quiet",
+            "--example",
+            name,
+        ]);
+        if !self.features.is_empty() {
+            cmd.args(["--features", &self.features.join(",")]);
+        }
+        cmd.args(["--", "--name", name, "-k", &self.k.unwrap_or(k).to_string()]);
+        if let Some(input) = self.input {
+            cmd.args(["--input", input]);
+        }
+        cmd.args(self.extra_args).arg("mock");
+        match cmd.output() {
+            Ok(output) => {
+                (output.status.success(), String::from_utf8_lossy(&output.stderr).into_owned())
+            }
+            Err(e) => (false, format!("could not run {cargo}: {e}")),
+        }
+    }
+}
+
+// every example on its own input, then the other inputs of the README, grouped by example
+fn cases() -> Vec<Case> {
+    let mut cases = EXAMPLES.iter().map(Case::new).collect::<Vec<_>>();
+    cases.extend([
+        Case::of("poly_mul").input("poly_input.in"),
+        Case::of("poly_mul").input("poly_input.in").extra_args(&["--field", "secp256k1"]),
+        Case::of("poly_mul").input("poly_input_reduced.in"),
+        Case::of("poly_add").input("poly_input.in"),
+        Case::of("poly_add").input("poly_input.in").extra_args(&["--field", "secp256k1"]),
+        Case::of("poly_scalarmul").input("poly_input_2.in"),
+        Case::of("poly_scalar_div").input("poly_scalar_div_not_invertible.in").rejected(),
+        Case::of("poly_reduce").input("poly_reduce_negative.in"),
+        Case::of("check_poly_from_distribution_chi_error").input("poly_input_3.in"),
+        Case::of("check_poly_from_distribution_chi_key").input("poly_input_4.in"),
+        Case::of("poly_inverse").input("poly_inverse_not_invertible.in").rejected(),
+        Case::of("coeff_to_eval").input("eval_to_coeff.in"),
+        Case::of("ct_mul").degree(14).features(&["relin"]),
+        Case::of("verifiable_encrypt").input("verifiable_encrypt_bad_error.in").rejected(),
+        Case::of("verify_pubkey").input("verify_pubkey_bad_error.in").rejected(),
+        Case::of("check_public_key").input("check_public_key_bad_error.in").rejected(),
+    ]);
+    // the sort is stable, so the canonical input of every example stays first
+    cases.sort_by_key(|case| EXAMPLES.iter().position(|example| example == case.example));
+    cases
+}
+
+fn main() {
+    // only run the cases whose example name contains one of the arguments, if any are given
+    let filters = env::args().skip(1).collect::<Vec<_>>();
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    let mut failures = vec![];
+    for case in cases() {
+        if !filters.is_empty() && !filters.iter().any(|f| case.example.name.contains(f.as_str())) {
+            continue;
+        }
+        let (accepted, stderr) = case.run(&cargo, manifest_dir);
+        let label = case.label();
+        // a rejected input must make the example panic, rather than e.g. fail to build
+        let ok = if case.expect_pass { accepted } else { !accepted && stderr.contains("panicked") };
+        if ok {
+            println!("PASS {label}");
+        } else {
+            let expected = if case.expect_pass { "accepted" } else { "rejected" };
+            println!("FAIL {label}: expected the input to be {expected}");
+            // the panic message of a failing example is at the end of its output
+            let lines = stderr.lines().collect::<Vec<_>>();
+            for line in &lines[lines.len().saturating_sub(10)..] {
+                println!("    {line}");
+            }
+            failures.push(label);
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("{} case(s) failed: {}", failures.len(), failures.join(", "));
+        exit(1);
+    }
+    println!("all cases passed");
+}
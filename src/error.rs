@@ -0,0 +1,128 @@
+//! The error type returned by the gadgets and input validation helpers in this crate.
+//!
+//! This module only uses `core` and `alloc`, so that the host-side arithmetic of `poly::math` can return it in `no_std` crates.
+use alloc::string::String;
+use core::fmt;
+
+/// An error caused by malformed circuit inputs or configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CircuitError {
+    /// An input polynomial has no coefficients.
+    EmptyInput,
+    /// A polynomial does not have the degree the circuit was built for.
+    WrongDegree { expected: usize, found: usize },
+    /// A polynomial has more coefficients than the circuit can hold, e.g. a remainder that was not fully reduced.
+    DegreeTooLarge { max: usize, found: usize },
+    /// Two polynomials that should have the same number of coefficients do not.
+    LengthMismatch { left: usize, right: usize },
+    /// A polynomial is reduced by a different modulus than the operation expects.
+    ModulusMismatch { expected: u64, found: u64 },
+    /// A value is reduced mod `0`, which has no remainders.
+    ZeroModulus,
+    /// A coefficient is outside of the allowed range `[0, bound)`.
+    OutOfRange { value: u64, bound: u64 },
+    /// A polynomial has no inverse in `R_q = Z_q[x]/(x^n + 1)`.
+    NotInvertible,
+    /// A divisor polynomial does not have `1` as its leading coefficient, so the division over the integers may not be exact.
+    NotMonic { leading: i64 },
+    /// The exponent `k` of the automorphism `x -> x^k` of `Z[x]/(x^n + 1)` is not coprime to `2n`, so the map is not a permutation of the coefficients.
+    InvalidGaloisElement { k: usize, n: usize },
+    /// The ring dimension `n` is not a power of two, so `x^n + 1` is not a cyclotomic polynomial.
+    InvalidRingDegree { n: usize },
+    /// There is no primitive `order`-th root of unity mod `q`, i.e. `q` is not a prime with `order | q - 1`, so the NTT of that size does not exist mod `q`.
+    NoRootOfUnity { order: u64, q: u64 },
+    /// The plaintext modulus `t` of a BFV parameter set is not in `[2, q)`.
+    InvalidPlaintextModulus { t: u64, q: u64 },
+    /// A declared coefficient bit width is too small for the modulus or too large for the field.
+    InvalidBitWidth { num_bits: usize, min: usize, max: usize },
+    /// The intermediate values of a computation could exceed the `capacity` bits that fit in a field element.
+    FieldOverflow { num_bits: usize, capacity: usize },
+    /// A proving or verifying key could not be written to or read from `path`.
+    KeyIo { path: String, reason: String },
+    /// A proving or verifying key was generated for a circuit of a different degree.
+    KeyDegreeMismatch { expected: u32, found: u32 },
+    /// A proving or verifying key at `path` was generated with another `LOOKUP_BITS` or other break points than the circuit (see `scaffold::KeyConfig`).
+    KeyConfigMismatch { path: String },
+    /// A versioned input (see `scaffold::input`) was written for a newer schema than the circuit reads.
+    UnsupportedInputVersion { found: u64, supported: u64 },
+    /// An input could not be parsed, or upgraded from an older schema.
+    InvalidInput(String),
+    /// The circuit assigned more advice cells than the row budget given with `--max-rows` (see `poly::budget::Budgeted`).
+    RowBudgetExceeded { estimated: usize, budget: usize },
+    /// The `LOOKUP_BITS` environment variable is not set.
+    LookupBitsUnset,
+    /// The `LOOKUP_BITS` environment variable is not a valid number of bits.
+    InvalidLookupBits(String),
+}
+
+impl fmt::Display for CircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyInput => write!(f, "input polynomial has no coefficients"),
+            Self::WrongDegree { expected, found } => {
+                write!(f, "expected a polynomial of degree {expected}, found degree {found}")
+            }
+            Self::DegreeTooLarge { max, found } => {
+                write!(f, "expected a polynomial of degree at most {max}, found degree {found}")
+            }
+            Self::LengthMismatch { left, right } => {
+                write!(f, "polynomials have different lengths: {left} and {right}")
+            }
+            Self::ModulusMismatch { expected, found } => {
+                write!(f, "expected a polynomial mod {expected}, found a polynomial mod {found}")
+            }
+            Self::ZeroModulus => write!(f, "cannot reduce mod 0"),
+            Self::OutOfRange { value, bound } => {
+                write!(f, "coefficient {value} is out of the range [0, {bound})")
+            }
+            Self::NotInvertible => write!(f, "polynomial has no inverse mod x^n + 1 and q"),
+            Self::NotMonic { leading } => {
+                write!(f, "divisor has leading coefficient {leading}, expected a monic polynomial")
+            }
+            Self::InvalidGaloisElement { k, n } => {
+                write!(f, "automorphism x -> x^{k} needs an exponent coprime to 2n = {}", 2 * n)
+            }
+            Self::InvalidRingDegree { n } => {
+                write!(f, "ring dimension {n} is not a power of two, so x^{n} + 1 is not cyclotomic")
+            }
+            Self::NoRootOfUnity { order, q } => {
+                write!(f, "there is no primitive {order}-th root of unity mod {q}")
+            }
+            Self::InvalidPlaintextModulus { t, q } => {
+                write!(f, "plaintext modulus {t} is outside of the range [2, {q}) allowed by the ciphertext modulus")
+            }
+            Self::InvalidBitWidth { num_bits, min, max } => {
+                write!(f, "bit width {num_bits} is outside of the supported range [{min}, {max}]")
+            }
+            Self::FieldOverflow { num_bits, capacity } => {
+                write!(f, "values of {num_bits} bits would overflow the field capacity of {capacity} bits")
+            }
+            Self::KeyIo { path, reason } => write!(f, "could not access the key at {path}: {reason}"),
+            Self::KeyDegreeMismatch { expected, found } => write!(
+                f,
+                "key was generated for a circuit of degree {found}, but the circuit has degree {expected}, run keygen again"
+            ),
+            Self::KeyConfigMismatch { path } => write!(
+                f,
+                "key at {path} was generated with another LOOKUP_BITS or break points than the circuit, run keygen again"
+            ),
+            Self::UnsupportedInputVersion { found, supported } => write!(
+                f,
+                "input has schema version {found}, but this circuit only reads versions up to {supported}"
+            ),
+            Self::InvalidInput(reason) => write!(f, "invalid input: {reason}"),
+            Self::RowBudgetExceeded { estimated, budget } => {
+                write!(f, "estimated rows {estimated} exceeds budget {budget}")
+            }
+            Self::LookupBitsUnset => write!(
+                f,
+                "LOOKUP_BITS environment variable is not set, run with `LOOKUP_BITS=<bits>` or pass `--auto-params` to the examples that support it"
+            ),
+            Self::InvalidLookupBits(value) => {
+                write!(f, "LOOKUP_BITS environment variable is not a number of bits: {value:?}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for CircuitError {}
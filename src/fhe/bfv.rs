@@ -0,0 +1,401 @@
+use rand::Rng;
+
+use crate::error::CircuitError;
+use crate::poly::ntt::{negacyclic_intt, negacyclic_ntt, primitive_root_of_unity};
+use crate::poly::utils::{
+    poly_mul, reduce_mod_cyclo, reduce_mod_q, ring_add, ring_mul, ring_neg, to_centered,
+};
+
+/// A ciphertext `(c0, c1)`, or equivalently a public key `(p0, p1)`, as a pair of polynomials in `R_q` with coefficients in `[0, q)`.
+pub type Ciphertext = [Vec<u64>; 2];
+
+/// The parameters of the BFV scheme: ciphertexts live in `R_q = Z_q[x]/(x^n + 1)` and plaintexts in `R_t = Z_t[x]/(x^n + 1)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BfvParams {
+    /// degree of the cyclotomic polynomial `x^n + 1`
+    pub n: usize,
+    /// ciphertext modulus
+    pub q: u64,
+    /// plaintext modulus
+    pub t: u64,
+}
+
+impl BfvParams {
+    /// Returns the parameters after checking that `n` is non-zero and that `t` is in `[2, q)`.
+    ///
+    /// `t` does not need to be coprime to `q`: when `t` does not divide `q`, the remainder `q mod t` only adds `(q mod t) * m / t` to the noise of an encryption of `m`.
+    pub fn new(n: usize, q: u64, t: u64) -> Result<Self, CircuitError> {
+        if n == 0 {
+            return Err(CircuitError::EmptyInput);
+        }
+        if t < 2 || t >= q {
+            return Err(CircuitError::InvalidPlaintextModulus { t, q });
+        }
+        Ok(Self { n, q, t })
+    }
+
+    /// Returns the scaling factor `delta = floor(q/t)` that lifts plaintexts into the ciphertext space.
+    pub fn delta(&self) -> u64 {
+        self.q / self.t
+    }
+}
+
+/// How a quotient `num / den` is rounded to an integer when scaling between the ciphertext and plaintext spaces.
+///
+/// The modes only differ when `num / den` lies exactly half-way between two integers, which requires `den` to be even.
+/// `HalfUp` computes `floor(num/den + 1/2)`, which is the rounding SEAL and OpenFHE use for BFV decryption and multiplication.
+/// Their moduli are products of odd primes, so there are no ties and `HalfEven` agrees with them as well.
+/// `HalfEven` matches implementations that round through floating point with the IEEE default (e.g. numpy's `round`), and `Truncate` matches schemes that scale without rounding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RoundMode {
+    /// ties are rounded up
+    #[default]
+    HalfUp,
+    /// ties are rounded to the even integer
+    HalfEven,
+    /// the quotient is rounded down
+    Truncate,
+}
+
+impl RoundMode {
+    /// Returns `num / den` rounded with this mode.
+    pub fn round_div(self, num: u128, den: u128) -> u128 {
+        let (quot, rem) = (num / den, num % den);
+        match self {
+            RoundMode::HalfUp => (num + den / 2) / den,
+            RoundMode::HalfEven if den % 2 == 0 && rem == den / 2 => quot + quot % 2,
+            RoundMode::HalfEven => (num + den / 2) / den,
+            RoundMode::Truncate => quot,
+        }
+    }
+}
+
+/// Samples a polynomial with coefficients uniformly drawn from `{-1, 0, 1}`, encoded as `{q-1, 0, 1}`.
+pub fn sample_ternary<R: Rng>(rng: &mut R, n: usize, q: u64) -> Vec<u64> {
+    (0..n).map(|_| [q - 1, 0, 1][rng.gen_range(0..3)]).collect()
+}
+
+/// Samples an error polynomial with coefficients uniformly drawn from `[-b, b]`, encoded in `[0, q)`.
+pub fn sample_error<R: Rng>(rng: &mut R, n: usize, b: u64, q: u64) -> Vec<u64> {
+    (0..n).map(|_| rng.gen_range(-(b as i64)..=b as i64).rem_euclid(q as i64) as u64).collect()
+}
+
+/// Samples a polynomial with coefficients uniformly drawn from `[0, q)`.
+pub fn sample_uniform<R: Rng>(rng: &mut R, n: usize, q: u64) -> Vec<u64> {
+    (0..n).map(|_| rng.gen_range(0..q)).collect()
+}
+
+/// An RLWE sample `(a, b = a*s + e)` in `R_q`, together with the secret `s` and the error `e` it was sampled with. All coefficients are in `[0, q)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RlweSample {
+    pub a: Vec<u64>,
+    pub b: Vec<u64>,
+    pub s: Vec<u64>,
+    pub e: Vec<u64>,
+}
+
+/// Samples an RLWE sample `(a, a*s + e)` with `a` uniform, `s` ternary and errors drawn from `[-b, b]`.
+pub fn rlwe_sample<R: Rng>(rng: &mut R, n: usize, q: u64, b: u64) -> RlweSample {
+    let s = sample_ternary(rng, n, q);
+    let a = sample_uniform(rng, n, q);
+    let e = sample_error(rng, n, b, q);
+    let b = ring_add(&ring_mul(&a, &s, q), &e, q);
+    RlweSample { a, b, s, e }
+}
+
+/// Generates a ternary secret key `s` and the public key `(-(a*s + e), a)`, where errors are drawn from `[-b, b]`.
+pub fn keygen<R: Rng>(rng: &mut R, n: usize, q: u64, b: u64) -> (Vec<u64>, Ciphertext) {
+    let s = sample_ternary(rng, n, q);
+    let a = sample_uniform(rng, n, q);
+    let e = sample_error(rng, n, b, q);
+    let pk = public_key(&s, &a, &e, q);
+    (s, pk)
+}
+
+/// Computes the public key `(-(a*s + e), a)` for the given secret key `s`, uniform polynomial `a` and error `e`.
+pub fn public_key(s: &[u64], a: &[u64], e: &[u64], q: u64) -> Ciphertext {
+    let p0 = ring_neg(&ring_add(&ring_mul(a, s, q), e, q), q);
+    [p0, a.to_vec()]
+}
+
+/// Scales the plaintext `m` into the ciphertext space as `delta*m` mod `q`, with `delta = floor(q/t)`.
+pub fn scale_message(m: &[u64], q: u64, t: u64) -> Vec<u64> {
+    let delta = q / t;
+    m.iter().map(|x| ((delta as u128 * *x as u128) % q as u128) as u64).collect()
+}
+
+/// Encrypts the plaintext `m` (coefficients in `[0, t)`) as `(p0*u + e1 + delta*m, p1*u + e2)` with `delta = floor(q/t)`.
+pub fn encrypt<R: Rng>(
+    rng: &mut R,
+    pk: &Ciphertext,
+    m: &[u64],
+    q: u64,
+    t: u64,
+    b: u64,
+) -> Ciphertext {
+    let n = m.len();
+    let u = sample_ternary(rng, n, q);
+    let e1 = sample_error(rng, n, b, q);
+    let e2 = sample_error(rng, n, b, q);
+    encrypt_with_randomness(pk, m, &u, &e1, &e2, q, t)
+}
+
+/// Encrypts the plaintext `m` as `encrypt` does, but with the given ternary `u` and errors `e1`, `e2` (encoded in `[0, q)`) instead of sampling them.
+pub fn encrypt_with_randomness(
+    pk: &Ciphertext,
+    m: &[u64],
+    u: &[u64],
+    e1: &[u64],
+    e2: &[u64],
+    q: u64,
+    t: u64,
+) -> Ciphertext {
+    let scaled_m = scale_message(m, q, t);
+    let c0 = ring_add(&ring_add(&ring_mul(&pk[0], u, q), e1, q), &scaled_m, q);
+    let c1 = ring_add(&ring_mul(&pk[1], u, q), e2, q);
+    [c0, c1]
+}
+
+/// Extracts the plaintext from a decrypted noisy polynomial `v = [c0 + c1*s]_q`, as `round(t * v / q) mod t` with ties rounded up.
+pub fn extract_plaintext(v: &[u64], q: u64, t: u64) -> Vec<u64> {
+    v.iter()
+        .map(|x| {
+            (RoundMode::HalfUp.round_div(t as u128 * *x as u128, q as u128) % t as u128) as u64
+        })
+        .collect()
+}
+
+/// Decrypts a ciphertext as `round(t * [c0 + c1*s]_q / q) mod t`.
+pub fn decrypt(s: &[u64], ct: &Ciphertext, q: u64, t: u64) -> Vec<u64> {
+    extract_plaintext(&ring_add(&ct[0], &ring_mul(&ct[1], s, q), q), q, t)
+}
+
+/// Computes the noise `[c0 + c1*s - delta*m]_q` of a ciphertext encrypting the plaintext `m`, with coefficients in `[0, q)` and `delta = floor(q/t)`.
+pub fn noise(s: &[u64], ct: &Ciphertext, m: &[u64], q: u64, t: u64) -> Vec<u64> {
+    let scaled_m = scale_message(m, q, t);
+    ring_add(&ring_add(&ct[0], &ring_mul(&ct[1], s, q), q), &ring_neg(&scaled_m, q), q)
+}
+
+/// Adds two ciphertexts component-wise in `R_q`, which gives an encryption of the sum of the plaintexts in `R_t`.
+pub fn add(ct_a: &Ciphertext, ct_b: &Ciphertext, q: u64) -> Ciphertext {
+    [ring_add(&ct_a[0], &ct_b[0], q), ring_add(&ct_a[1], &ct_b[1], q)]
+}
+
+/// Multiplies both components of a ciphertext by the plaintext polynomial `pt` in `R_q`, which gives an encryption of the product of the plaintexts in `R_t`.
+///
+/// The noise grows by a factor of at most `n * (t - 1)` for a plaintext with coefficients in `[0, t)`.
+pub fn mul_plain(ct: &Ciphertext, pt: &[u64], q: u64) -> Ciphertext {
+    [ring_mul(&ct[0], pt, q), ring_mul(&ct[1], pt, q)]
+}
+
+/// Computes the tensor product of two ciphertexts, i.e. the degree 2 ciphertext `(c0, c1, c2)` that decrypts under `(1, s, s^2)` to the product of the plaintexts.
+///
+/// The ciphertext coefficients are lifted to their centered representatives, multiplied over the integers mod `x^n + 1`, and then scaled by `t/q` with rounding (half up) before being reduced mod `q`.
+pub fn tensor(ct_a: &Ciphertext, ct_b: &Ciphertext, q: u64, t: u64) -> [Vec<u64>; 3] {
+    let n = ct_a[0].len();
+    let [a0, a1, b0, b1] = [&ct_a[0], &ct_a[1], &ct_b[0], &ct_b[1]]
+        .map(|c| c.iter().map(|x| to_centered(*x, q) as i128).collect::<Vec<_>>());
+    let e0 = reduce_mod_cyclo(&poly_mul(&a0, &b0), n);
+    let e1 = reduce_mod_cyclo(&poly_mul(&a0, &b1), n)
+        .iter()
+        .zip(reduce_mod_cyclo(&poly_mul(&a1, &b0), n))
+        .map(|(x, y)| x + y)
+        .collect::<Vec<_>>();
+    let e2 = reduce_mod_cyclo(&poly_mul(&a1, &b1), n);
+    [e0, e1, e2].map(|e| {
+        let scaled = e
+            .iter()
+            .map(|x| (t as i128 * x + q as i128 / 2).div_euclid(q as i128))
+            .collect::<Vec<_>>();
+        reduce_mod_q(&scaled, q)
+    })
+}
+
+/// Returns the number of base `w` digits needed to represent any value in `[0, q)`.
+pub fn num_digits(q: u64, w: u64) -> usize {
+    let mut l = 1;
+    let mut pow = w as u128;
+    while pow < q as u128 {
+        pow *= w as u128;
+        l += 1;
+    }
+    l
+}
+
+/// Decomposes `x` into `l` little endian digits in base `w`.
+pub fn decompose(x: u64, w: u64, l: usize) -> Vec<u64> {
+    let mut x = x;
+    (0..l)
+        .map(|_| {
+            let digit = x % w;
+            x /= w;
+            digit
+        })
+        .collect()
+}
+
+/// Generates the relinearization key `([-(a_i*s + e_i) + w^i * s^2]_q, a_i)` for every digit `i` of the base `w` decomposition.
+pub fn relin_keygen<R: Rng>(rng: &mut R, s: &[u64], q: u64, w: u64, b: u64) -> Vec<Ciphertext> {
+    let n = s.len();
+    let s_squared = ring_mul(s, s, q);
+    let mut w_pow = 1u128;
+    (0..num_digits(q, w))
+        .map(|_| {
+            let a = sample_uniform(rng, n, q);
+            let e = sample_error(rng, n, b, q);
+            let scaled_s_squared = s_squared
+                .iter()
+                .map(|x| ((w_pow * *x as u128) % q as u128) as u64)
+                .collect::<Vec<_>>();
+            w_pow = w_pow * w as u128 % q as u128;
+            let k0 =
+                ring_add(&ring_neg(&ring_add(&ring_mul(&a, s, q), &e, q), q), &scaled_s_squared, q);
+            [k0, a]
+        })
+        .collect()
+}
+
+/// Relinearizes a degree 2 ciphertext `(c0, c1, c2)` back into a regular ciphertext, by decomposing `c2` in base `w` and applying the relinearization key.
+pub fn relinearize(ct: &[Vec<u64>; 3], rlk: &[Ciphertext], q: u64, w: u64) -> Ciphertext {
+    let l = rlk.len();
+    let digits = ct[2].iter().map(|x| decompose(*x, w, l)).collect::<Vec<_>>();
+    let (mut c0, mut c1) = (ct[0].clone(), ct[1].clone());
+    for (i, key) in rlk.iter().enumerate() {
+        let digit_poly = digits.iter().map(|d| d[i]).collect::<Vec<_>>();
+        c0 = ring_add(&c0, &ring_mul(&key[0], &digit_poly, q), q);
+        c1 = ring_add(&c1, &ring_mul(&key[1], &digit_poly, q), q);
+    }
+    [c0, c1]
+}
+
+/// Encodes `n` plaintext slots with values in `[0, t)` into a single plaintext polynomial of degree `n - 1`, using the CRT decomposition of `x^n + 1` mod `t`.
+///
+/// The `i`-th slot is the evaluation of the plaintext at `psi^(2i+1)`, where `psi` is a primitive `2n`-th root of unity mod `t`, so `t` must be a prime with `t = 1 mod 2n`.
+/// Additions and multiplications of plaintexts in `R_t` then act slot-wise.
+/// Returns the errors of `primitive_root_of_unity` when `n` is not a power of two or there is no such `psi`.
+pub fn batch_encode(slots: &[u64], t: u64) -> Result<Vec<u64>, CircuitError> {
+    let psi = primitive_root_of_unity(2 * slots.len() as u64, t)?;
+    Ok(negacyclic_intt(slots, psi, t))
+}
+
+/// Decodes a plaintext polynomial into its `n` slots. This is the inverse of `batch_encode`.
+pub fn batch_decode(m: &[u64], t: u64) -> Result<Vec<u64>, CircuitError> {
+    let psi = primitive_root_of_unity(2 * m.len() as u64, t)?;
+    Ok(negacyclic_ntt(m, psi, t))
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{
+        add, batch_decode, batch_encode, decrypt, encrypt, encrypt_with_randomness, keygen,
+        mul_plain, relin_keygen, relinearize, tensor, BfvParams, RoundMode,
+    };
+    use crate::error::CircuitError;
+    use crate::poly::utils::{poly_mul, reduce_mod_cyclo, reduce_mod_q, ring_mul};
+
+    const N: usize = 4;
+    const Q: u64 = 1048573;
+    const T: u64 = 8;
+    const B: u64 = 2;
+    const W: u64 = 16;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let m = vec![1, 0, 7, 3];
+        let ct = encrypt(&mut rng, &pk, &m, Q, T, B);
+        assert_eq!(decrypt(&s, &ct, Q, T), m);
+    }
+
+    #[test]
+    fn test_round_mode_at_half() {
+        // 8 / 16 and 24 / 16 are exactly half-way, while an odd denominator has no ties
+        let cases =
+            [(8, 16, [1, 0, 0]), (24, 16, [2, 2, 1]), (9, 16, [1, 1, 0]), (7, 15, [0, 0, 0])];
+        for (num, den, expected) in cases {
+            let modes = [RoundMode::HalfUp, RoundMode::HalfEven, RoundMode::Truncate];
+            assert_eq!(modes.map(|mode| mode.round_div(num, den)), expected, "{num} / {den}");
+        }
+    }
+
+    #[test]
+    fn test_params_delta() {
+        // 257 = 32 * 8 + 1, so delta = 32 and the scaled plaintext leaves a rounding remainder of 1
+        let params = BfvParams::new(N, 257, 8).unwrap();
+        assert_eq!(params.delta(), 32);
+        let BfvParams { n, q, t } = params;
+
+        // without randomness the ciphertext is exactly (delta * m, 0)
+        let m = vec![1, 0, 7, 3];
+        let zero = vec![0; n];
+        let pk = [vec![5, 6, 7, 8], vec![1, 2, 3, 4]];
+        let ct = encrypt_with_randomness(&pk, &m, &zero, &zero, &zero, q, t);
+        assert_eq!(ct, [vec![32, 0, 224, 96], zero]);
+
+        // fresh encryptions with errors in [-1, 1] have noise below delta / 2
+        let mut rng = StdRng::seed_from_u64(2);
+        let (s, pk) = keygen(&mut rng, n, q, 1);
+        let ct = encrypt(&mut rng, &pk, &m, q, t, 1);
+        assert_eq!(decrypt(&s, &ct, q, t), m);
+    }
+
+    #[test]
+    fn test_params_invalid() {
+        assert_eq!(
+            BfvParams::new(N, 257, 257),
+            Err(CircuitError::InvalidPlaintextModulus { t: 257, q: 257 })
+        );
+        assert_eq!(
+            BfvParams::new(N, 257, 1),
+            Err(CircuitError::InvalidPlaintextModulus { t: 1, q: 257 })
+        );
+        assert_eq!(BfvParams::new(0, 257, 8), Err(CircuitError::EmptyInput));
+    }
+
+    #[test]
+    fn test_add() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let ct1 = encrypt(&mut rng, &pk, &[1, 2, 3, 4], Q, T, B);
+        let ct2 = encrypt(&mut rng, &pk, &[5, 0, 7, 1], Q, T, B);
+        assert_eq!(decrypt(&s, &add(&ct1, &ct2, Q), Q, T), vec![6, 2, 2, 5]);
+    }
+
+    #[test]
+    fn test_mul_plain() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let ct = encrypt(&mut rng, &pk, &[1, 2, 3, 4], Q, T, B);
+        // (1 + 2x + 3x^2 + 4x^3) * (2 + x) = 2 + 5x + 8x^2 + 11x^3 + 4x^4 = -2 + 5x + 8x^2 + 11x^3 mod x^4 + 1
+        assert_eq!(decrypt(&s, &mul_plain(&ct, &[2, 1, 0, 0], Q), Q, T), vec![6, 5, 0, 3]);
+    }
+
+    #[test]
+    fn test_mul_relinearize() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let rlk = relin_keygen(&mut rng, &s, Q, W, B);
+        let (m1, m2) = (vec![1, 2, 3, 4], vec![5, 0, 7, 1]);
+        let ct1 = encrypt(&mut rng, &pk, &m1, Q, T, B);
+        let ct2 = encrypt(&mut rng, &pk, &m2, Q, T, B);
+        let ct = relinearize(&tensor(&ct1, &ct2, Q, T), &rlk, Q, W);
+
+        let m1 = m1.iter().map(|x| *x as i128).collect::<Vec<_>>();
+        let m2 = m2.iter().map(|x| *x as i128).collect::<Vec<_>>();
+        let expected = reduce_mod_q(&reduce_mod_cyclo(&poly_mul(&m1, &m2), N), T);
+        assert_eq!(decrypt(&s, &ct, Q, T), expected);
+    }
+
+    #[test]
+    fn test_batch_encode_slotwise_mul() {
+        // 17 = 1 mod 8, so x^4 + 1 splits into 4 linear factors mod 17
+        let t = 17;
+        let (a, b) = (vec![1, 2, 3, 4], vec![5, 6, 7, 16]);
+        let (ma, mb) = (batch_encode(&a, t).unwrap(), batch_encode(&b, t).unwrap());
+        assert_eq!(batch_decode(&ma, t), Ok(a));
+        assert_eq!(batch_decode(&ring_mul(&ma, &mb, t), t), Ok(vec![5, 12, 4, 13]));
+    }
+}
@@ -0,0 +1,528 @@
+//! A chainable API for composing several homomorphic operations on BFV ciphertexts inside a single circuit.
+//!
+//! Every step of a chain calls the corresponding gadget of `fhe::gadgets` against the shared `Context`, after checking that its operands have the degree and the modulus the circuit was built for:
+//!
+//! ```ignore
+//! let mut builder = FheBuilder::new(ctx, &range, params, rng);
+//! let out = builder.encrypt(&pk, &m)?.add(&ct)?.decrypt(&sk)?;
+//! ```
+//!
+//! Chains of scalar multiplications of a polynomial, e.g. `builder.scale(p)?.scalar_mul(delta).scalar_mul(k).finish()?`, are only constrained when they end,
+//! so that consecutive multiplications by constants can be folded into a single one.
+use halo2_base::gates::{GateInstructions, RangeChip};
+use halo2_base::utils::ScalarField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+use rand::Rng;
+
+use crate::error::CircuitError;
+#[cfg(feature = "relin")]
+use crate::fhe::bfv::num_digits;
+use crate::fhe::bfv::{
+    encrypt_with_randomness, sample_error, sample_ternary, BfvParams, Ciphertext,
+};
+#[cfg(feature = "relin")]
+use crate::fhe::gadgets::bfv_relinearize;
+use crate::fhe::gadgets::{bfv_add, bfv_decrypt, bfv_tensor, bfv_verify_encryption};
+use crate::poly::operations::{
+    assert_reduced, load_poly, poly_reduce_mod_assigned, poly_scalar_mul_assigned,
+};
+use crate::poly::utils::bit_length;
+
+/// An assigned polynomial together with the modulus its coefficients are reduced by, i.e. `q` for ciphertexts and keys and `t` for plaintexts.
+#[derive(Clone, Debug)]
+pub struct Poly<F: ScalarField> {
+    pub coeffs: Vec<AssignedValue<F>>,
+    pub modulus: u64,
+}
+
+impl<F: ScalarField> Poly<F> {
+    /// Returns the coefficients of the polynomial as integers.
+    pub fn values(&self) -> Vec<u64> {
+        self.coeffs.iter().map(|x| x.value().get_lower_64()).collect()
+    }
+}
+
+/// The parameters that a chain of operations is built for: the BFV parameters together with the bounds and the decomposition base of the gadgets.
+#[derive(Clone, Copy, Debug)]
+pub struct FheParams {
+    /// ring dimension and moduli of the scheme
+    pub bfv: BfvParams,
+    /// upper bound of the error distribution `[-b, b]`
+    pub b: u64,
+    /// base of the decomposition used by the relinearization key
+    pub w: u64,
+    /// largest noise magnitude accepted on decryption
+    pub noise_bound: u64,
+}
+
+/// Builds a composite circuit out of homomorphic operations on BFV ciphertexts.
+///
+/// The randomness used by `encrypt` is sampled from `rng` and assigned as private witnesses.
+pub struct FheBuilder<'a, F: ScalarField, R: Rng> {
+    ctx: &'a mut Context<F>,
+    range: &'a RangeChip<F>,
+    params: FheParams,
+    rng: R,
+}
+
+impl<'a, F: ScalarField, R: Rng> FheBuilder<'a, F, R> {
+    pub fn new(
+        ctx: &'a mut Context<F>,
+        range: &'a RangeChip<F>,
+        params: FheParams,
+        rng: R,
+    ) -> Self {
+        Self { ctx, range, params, rng }
+    }
+
+    /// Assigns the polynomial as a witness, and constrains its coefficients to be reduced by `modulus` with `assert_reduced`.
+    ///
+    /// Returns a `ZeroModulus` error if `modulus` is `0`.
+    pub fn load(&mut self, coeffs: &[u64], modulus: u64) -> Result<Poly<F>, CircuitError> {
+        if modulus == 0 {
+            return Err(CircuitError::ZeroModulus);
+        }
+        let coeffs = load_poly(self.ctx, coeffs);
+        assert_reduced(self.ctx, self.range, &coeffs, modulus);
+        Ok(Poly { coeffs, modulus })
+    }
+
+    /// Assigns the ciphertext (or public key) as a witness, with both polynomials constrained to be reduced by `q`.
+    pub fn load_ciphertext(&mut self, ct: &Ciphertext) -> Result<[Poly<F>; 2], CircuitError> {
+        let q = self.params.bfv.q;
+        Ok([self.load(&ct[0], q)?, self.load(&ct[1], q)?])
+    }
+
+    // checks that the polynomial has n coefficients and is reduced by the expected modulus
+    fn check(&self, poly: &Poly<F>, modulus: u64) -> Result<(), CircuitError> {
+        if poly.coeffs.len() != self.params.bfv.n {
+            return Err(CircuitError::LengthMismatch {
+                left: self.params.bfv.n,
+                right: poly.coeffs.len(),
+            });
+        }
+        if poly.modulus != modulus {
+            return Err(CircuitError::ModulusMismatch { expected: modulus, found: poly.modulus });
+        }
+        Ok(())
+    }
+
+    /// Starts a chain of scalar multiplications of the polynomial, whose output is reduced by the modulus of the polynomial.
+    pub fn scale(&mut self, poly: Poly<F>) -> Result<ScaleChain<'_, 'a, F, R>, CircuitError> {
+        self.check(&poly, poly.modulus)?;
+        Ok(ScaleChain { builder: self, poly, scalars: vec![] })
+    }
+
+    /// Starts a chain from an existing ciphertext.
+    pub fn ciphertext(&mut self, ct: [Poly<F>; 2]) -> Result<CtChain<'_, 'a, F, R>, CircuitError> {
+        for poly in &ct {
+            self.check(poly, self.params.bfv.q)?;
+        }
+        Ok(CtChain { builder: self, ct })
+    }
+
+    /// Starts a chain from a fresh encryption of the plaintext `m` under the public key `pk`.
+    ///
+    /// The ciphertext is computed outside of the circuit with freshly sampled randomness, then `bfv_verify_encryption` constrains it to be an honest encryption of `m`.
+    pub fn encrypt(
+        &mut self,
+        pk: &[Poly<F>; 2],
+        m: &Poly<F>,
+    ) -> Result<CtChain<'_, 'a, F, R>, CircuitError> {
+        let FheParams { bfv: BfvParams { n, q, t }, b, .. } = self.params;
+        for poly in pk {
+            self.check(poly, q)?;
+        }
+        self.check(m, t)?;
+
+        let u = sample_ternary(&mut self.rng, n, q);
+        let e = [sample_error(&mut self.rng, n, b, q), sample_error(&mut self.rng, n, b, q)];
+        let ct = encrypt_with_randomness(
+            &[pk[0].values(), pk[1].values()],
+            &m.values(),
+            &u,
+            &e[0],
+            &e[1],
+            q,
+            t,
+        );
+
+        let ct = self.load_ciphertext(&ct)?;
+        let [u, e1, e2] = [&u, &e[0], &e[1]].map(|c| load_poly(self.ctx, c));
+        bfv_verify_encryption(
+            self.ctx,
+            self.range,
+            [&pk[0].coeffs, &pk[1].coeffs],
+            [&ct[0].coeffs, &ct[1].coeffs],
+            &m.coeffs,
+            &u,
+            [&e1, &e2],
+            q,
+            t,
+            b,
+        )?;
+        Ok(CtChain { builder: self, ct })
+    }
+}
+
+/// A regular ciphertext `(c0, c1)` in the middle of a chain of operations.
+pub struct CtChain<'b, 'a, F: ScalarField, R: Rng> {
+    builder: &'b mut FheBuilder<'a, F, R>,
+    ct: [Poly<F>; 2],
+}
+
+impl<'b, 'a, F: ScalarField, R: Rng> CtChain<'b, 'a, F, R> {
+    /// Adds the ciphertext `other` with `bfv_add`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, other: &[Poly<F>; 2]) -> Result<Self, CircuitError> {
+        let q = self.builder.params.bfv.q;
+        for poly in other {
+            self.builder.check(poly, q)?;
+        }
+        let sum = bfv_add(
+            self.builder.ctx,
+            self.builder.range,
+            [&self.ct[0].coeffs, &self.ct[1].coeffs],
+            [&other[0].coeffs, &other[1].coeffs],
+            q,
+        )?;
+        Ok(Self { builder: self.builder, ct: sum.map(|coeffs| Poly { coeffs, modulus: q }) })
+    }
+
+    /// Multiplies by the ciphertext `other` with `bfv_tensor`, which gives a degree 2 ciphertext.
+    #[allow(clippy::should_implement_trait)]
+    pub fn mul(self, other: &[Poly<F>; 2]) -> Result<TensorChain<'b, 'a, F, R>, CircuitError> {
+        let BfvParams { q, t, .. } = self.builder.params.bfv;
+        for poly in other {
+            self.builder.check(poly, q)?;
+        }
+        let prod = bfv_tensor(
+            self.builder.ctx,
+            self.builder.range,
+            [&self.ct[0].coeffs, &self.ct[1].coeffs],
+            [&other[0].coeffs, &other[1].coeffs],
+            q,
+            t,
+        )?;
+        Ok(TensorChain {
+            builder: self.builder,
+            ct: prod.map(|coeffs| Poly { coeffs, modulus: q }),
+        })
+    }
+
+    /// Ends the chain by decrypting the ciphertext under the secret key `sk` with `bfv_decrypt`, and returns the plaintext mod `t`.
+    pub fn decrypt(self, sk: &Poly<F>) -> Result<Poly<F>, CircuitError> {
+        let FheParams { bfv: BfvParams { q, t, .. }, noise_bound, .. } = self.builder.params;
+        self.builder.check(sk, q)?;
+        let m = bfv_decrypt(
+            self.builder.ctx,
+            self.builder.range,
+            &sk.coeffs,
+            [&self.ct[0].coeffs, &self.ct[1].coeffs],
+            q,
+            t,
+            noise_bound,
+        )?;
+        Ok(Poly { coeffs: m, modulus: t })
+    }
+
+    /// Ends the chain and returns the ciphertext.
+    pub fn finish(self) -> [Poly<F>; 2] {
+        self.ct
+    }
+}
+
+/// A degree 2 ciphertext `(c0, c1, c2)` output by a multiplication, in the middle of a chain of operations.
+pub struct TensorChain<'b, 'a, F: ScalarField, R: Rng> {
+    // only used to continue the chain with `relinearize`
+    #[cfg_attr(not(feature = "relin"), allow(dead_code))]
+    builder: &'b mut FheBuilder<'a, F, R>,
+    ct: [Poly<F>; 3],
+}
+
+impl<'b, 'a, F: ScalarField, R: Rng> TensorChain<'b, 'a, F, R> {
+    /// Relinearizes the ciphertext back into a regular ciphertext with `bfv_relinearize`, using one relinearization key per base `w` digit of `q`.
+    #[cfg(feature = "relin")]
+    pub fn relinearize(self, rlk: &[[Poly<F>; 2]]) -> Result<CtChain<'b, 'a, F, R>, CircuitError> {
+        let FheParams { bfv: BfvParams { q, .. }, w, .. } = self.builder.params;
+        let l = num_digits(q, w);
+        if rlk.len() != l {
+            return Err(CircuitError::LengthMismatch { left: l, right: rlk.len() });
+        }
+        for poly in rlk.iter().flatten() {
+            self.builder.check(poly, q)?;
+        }
+        let rlk = rlk
+            .iter()
+            .map(|key| [key[0].coeffs.clone(), key[1].coeffs.clone()])
+            .collect::<Vec<_>>();
+        let ct = bfv_relinearize(
+            self.builder.ctx,
+            self.builder.range,
+            [&self.ct[0].coeffs, &self.ct[1].coeffs, &self.ct[2].coeffs],
+            &rlk,
+            q,
+            w,
+        )?;
+        Ok(CtChain { builder: self.builder, ct: ct.map(|coeffs| Poly { coeffs, modulus: q }) })
+    }
+
+    /// Ends the chain and returns the degree 2 ciphertext.
+    pub fn finish(self) -> [Poly<F>; 3] {
+        self.ct
+    }
+}
+
+/// A scalar that a polynomial is multiplied by in a `ScaleChain`.
+#[derive(Clone, Copy, Debug)]
+pub enum Scalar<F: ScalarField> {
+    /// public constant, fixed by the circuit
+    Constant(u64),
+    /// assigned witness, reduced by the modulus of the polynomial
+    Witness(AssignedValue<F>),
+}
+
+/// A polynomial in the middle of a chain of scalar multiplications.
+///
+/// The scalars are only recorded until `finish`, which folds every run of consecutive constants into their product (see `fuse_constants`) before constraining the multiplications.
+pub struct ScaleChain<'b, 'a, F: ScalarField, R: Rng> {
+    builder: &'b mut FheBuilder<'a, F, R>,
+    poly: Poly<F>,
+    scalars: Vec<Scalar<F>>,
+}
+
+impl<'b, 'a, F: ScalarField, R: Rng> ScaleChain<'b, 'a, F, R> {
+    /// Multiplies by the public constant `k`.
+    pub fn scalar_mul(mut self, k: u64) -> Self {
+        self.scalars.push(Scalar::Constant(k));
+        self
+    }
+
+    /// Multiplies by the witness `k`, which must be reduced by the modulus of the polynomial. Constants are not folded across it.
+    pub fn scalar_mul_assigned(mut self, k: AssignedValue<F>) -> Self {
+        self.scalars.push(Scalar::Witness(k));
+        self
+    }
+
+    /// Ends the chain and returns the scaled polynomial, reduced by its modulus.
+    ///
+    /// Every scalar left after `fuse_constants` costs one multiplication and one reduction per coefficient.
+    pub fn finish(self) -> Result<Poly<F>, CircuitError> {
+        let Poly { mut coeffs, modulus } = self.poly;
+        let (ctx, range) = (&mut *self.builder.ctx, self.builder.range);
+        // the product of two values in [0, modulus) is below modulus^2
+        let num_bits = 2 * bit_length(modulus as u128);
+        for scalar in fuse_constants(&self.scalars, modulus)? {
+            let prod = match scalar {
+                Scalar::Constant(k) => coeffs
+                    .iter()
+                    .map(|&c| range.gate.mul(ctx, c, Constant(F::from(k))))
+                    .collect::<Vec<_>>(),
+                Scalar::Witness(k) => poly_scalar_mul_assigned(ctx, &range.gate, &coeffs, k),
+            };
+            coeffs = poly_reduce_mod_assigned(ctx, range, &prod, modulus, num_bits)?;
+        }
+        Ok(Poly { coeffs, modulus })
+    }
+}
+
+/// Folds every run of consecutive constants into their product mod `modulus`, computed outside of the circuit, and drops the products equal to 1.
+///
+/// Witnesses are kept in place, since their values are not fixed by the circuit.
+/// Returns a `ZeroModulus` error if `modulus` is `0`.
+pub fn fuse_constants<F: ScalarField>(
+    scalars: &[Scalar<F>],
+    modulus: u64,
+) -> Result<Vec<Scalar<F>>, CircuitError> {
+    if modulus == 0 {
+        return Err(CircuitError::ZeroModulus);
+    }
+    let mut fused = vec![];
+    for &scalar in scalars {
+        match (fused.last_mut(), scalar) {
+            (Some(Scalar::Constant(acc)), Scalar::Constant(k)) => {
+                *acc = (*acc as u128 * k as u128 % modulus as u128) as u64;
+            }
+            (_, Scalar::Constant(k)) => fused.push(Scalar::Constant(k % modulus)),
+            (_, scalar) => fused.push(scalar),
+        }
+    }
+    fused.retain(|scalar| !matches!(scalar, Scalar::Constant(1)));
+    Ok(fused)
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{fuse_constants, FheBuilder, FheParams, Scalar};
+    use crate::error::CircuitError;
+    use crate::fhe::bfv::{encrypt, keygen, BfvParams};
+    use crate::test_utils::mock_run;
+
+    const N: usize = 4;
+    const Q: u64 = 1048573;
+    const T: u64 = 8;
+    const B: u64 = 2;
+    const PARAMS: FheParams = FheParams {
+        bfv: BfvParams { n: N, q: Q, t: T },
+        b: B,
+        w: 16,
+        noise_bound: Q / (2 * T) - T,
+    };
+
+    #[test]
+    fn test_builder_encrypt_add_add() {
+        let mut rng = StdRng::seed_from_u64(13);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let ct = encrypt(&mut rng, &pk, &[3, 1, 4, 1], Q, T, B);
+
+        let (out, ok) = mock_run(13, 8, |ctx, range| {
+            let mut builder = FheBuilder::new(ctx, range, PARAMS, StdRng::seed_from_u64(14));
+            let [pk, ct] = [&pk, &ct].map(|c| builder.load_ciphertext(c).unwrap());
+            let m = builder.load(&[2, 7, 1, 0], T).unwrap();
+            let sk = builder.load(&s, Q).unwrap();
+            let out = builder
+                .encrypt(&pk, &m)
+                .and_then(|chain| chain.add(&ct))
+                .and_then(|chain| chain.add(&ct))
+                .and_then(|chain| chain.decrypt(&sk))
+                .unwrap();
+            out.values()
+        });
+        assert!(ok);
+        // m + 2 * [3, 1, 4, 1] mod T
+        assert_eq!(out, vec![0, 1, 1, 2]);
+    }
+
+    #[cfg(feature = "relin")]
+    #[test]
+    fn test_builder_encrypt_add_mul_relinearize() {
+        use crate::fhe::bfv::relin_keygen;
+        use crate::poly::utils::{poly_mul, reduce_mod_cyclo, reduce_mod_q};
+
+        let mut rng = StdRng::seed_from_u64(15);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let rlk = relin_keygen(&mut rng, &s, Q, PARAMS.w, B);
+        let (m1, m2, m3) = ([1, 2, 0, 3], [0, 1, 1, 1], [2, 0, 0, 1]);
+        let ct2 = encrypt(&mut rng, &pk, &m2, Q, T, B);
+        let ct3 = encrypt(&mut rng, &pk, &m3, Q, T, B);
+
+        let (out, ok) = mock_run(14, 8, |ctx, range| {
+            let mut builder = FheBuilder::new(ctx, range, PARAMS, StdRng::seed_from_u64(16));
+            let [pk, ct2, ct3] = [&pk, &ct2, &ct3].map(|c| builder.load_ciphertext(c).unwrap());
+            let rlk =
+                rlk.iter().map(|key| builder.load_ciphertext(key).unwrap()).collect::<Vec<_>>();
+            let m1 = builder.load(&m1, T).unwrap();
+            let sk = builder.load(&s, Q).unwrap();
+            let out = builder
+                .encrypt(&pk, &m1)
+                .and_then(|chain| chain.add(&ct2))
+                .and_then(|chain| chain.mul(&ct3))
+                .and_then(|chain| chain.relinearize(&rlk))
+                .and_then(|chain| chain.decrypt(&sk))
+                .unwrap();
+            out.values()
+        });
+        assert!(ok);
+
+        // (m1 + m2) * m3 in R_T
+        let sum = [m1, m2]
+            .map(|m| m.map(i128::from))
+            .iter()
+            .fold(vec![0; N], |acc, m| acc.iter().zip(m.iter()).map(|(x, y)| x + y).collect());
+        let expected = reduce_mod_q(&reduce_mod_cyclo(&poly_mul(&sum, &m3.map(i128::from)), N), T);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_builder_incompatible_operands() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+
+        let ((wrong_modulus, wrong_len), _) = mock_run(13, 8, |ctx, range| {
+            let mut builder = FheBuilder::new(ctx, range, PARAMS, StdRng::seed_from_u64(18));
+            let pk = builder.load_ciphertext(&pk).unwrap();
+            // the plaintext is reduced mod Q instead of mod T
+            let m = builder.load(&[2, 7, 1, 0], Q).unwrap();
+            let wrong_modulus = builder.encrypt(&pk, &m).err();
+
+            let m = builder.load(&[2, 7, 1, 0], T).unwrap();
+            let sk = builder.load(&s[..3], Q).unwrap();
+            let wrong_len = builder.encrypt(&pk, &m).and_then(|chain| chain.decrypt(&sk)).err();
+            (wrong_modulus, wrong_len)
+        });
+        assert_eq!(wrong_modulus, Some(CircuitError::ModulusMismatch { expected: T, found: Q }));
+        assert_eq!(wrong_len, Some(CircuitError::LengthMismatch { left: N, right: 3 }));
+    }
+
+    #[test]
+    fn test_builder_load_checks_reduced() {
+        let (zero_modulus, ok) = mock_run(13, 8, |ctx, range| {
+            let mut builder = FheBuilder::new(ctx, range, PARAMS, StdRng::seed_from_u64(21));
+            builder.load(&[2, 7, 1, 0], T).unwrap();
+            builder.load(&[2, 7, 1, 0], 0).err()
+        });
+        assert!(ok);
+        assert_eq!(zero_modulus, Some(CircuitError::ZeroModulus));
+
+        // 8 is not reduced mod T
+        let (_, ok) = mock_run(13, 8, |ctx, range| {
+            let mut builder = FheBuilder::new(ctx, range, PARAMS, StdRng::seed_from_u64(22));
+            builder.load(&[2, 8, 1, 0], T).unwrap();
+        });
+        assert!(!ok);
+
+        let scalars = [Scalar::<Fr>::Constant(3)];
+        assert_eq!(fuse_constants(&scalars, 0).err(), Some(CircuitError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_scale_fuses_constants() {
+        let (delta, k) = (PARAMS.bfv.delta(), 5);
+        let m = [3, 1, 4, 1];
+
+        let ((naive, fused, naive_cells, fused_cells), ok) = mock_run(13, 8, |ctx, range| {
+            let mut builder = FheBuilder::new(ctx, range, PARAMS, StdRng::seed_from_u64(19));
+            let p = builder.load(&m, Q).unwrap();
+            let start = builder.ctx.advice.len();
+            // one chain per scalar, which constrains every multiplication separately
+            let naive = builder.scale(p.clone()).unwrap().scalar_mul(delta).finish().unwrap();
+            let naive = builder.scale(naive).unwrap().scalar_mul(k).finish().unwrap();
+            let naive_cells = builder.ctx.advice.len() - start;
+
+            let start = builder.ctx.advice.len();
+            let fused = builder.scale(p).unwrap().scalar_mul(delta).scalar_mul(k).finish().unwrap();
+            (naive.values(), fused.values(), naive_cells, builder.ctx.advice.len() - start)
+        });
+        assert!(ok);
+        assert_eq!(fused, naive);
+        assert_eq!(fused, m.map(|c| c * delta * k % Q));
+        assert!(fused_cells < naive_cells, "{fused_cells} >= {naive_cells}");
+    }
+
+    #[test]
+    fn test_scale_does_not_fuse_witnesses() {
+        let m = [3, 1, 4, 1];
+        let (out, ok) = mock_run(13, 8, |ctx, range| {
+            let w = ctx.load_witness(Fr::from(7));
+            let scalars = [Scalar::Constant(2), Scalar::Constant(Q - 1), Scalar::Witness(w)];
+            let fused = fuse_constants(&scalars, Q).unwrap();
+            assert!(matches!(fused[..], [Scalar::Constant(c), Scalar::Witness(_)] if c == Q - 2));
+            // the constants around the witness are folded separately, and their products equal to 1 are dropped
+            let scalars = [Scalar::Constant(3), Scalar::Witness(w), Scalar::Constant(1)];
+            assert!(matches!(
+                fuse_constants(&scalars, Q).unwrap()[..],
+                [Scalar::Constant(3), Scalar::Witness(_)]
+            ));
+
+            let mut builder = FheBuilder::new(ctx, range, PARAMS, StdRng::seed_from_u64(20));
+            let p = builder.load(&m, Q).unwrap();
+            let chain = builder.scale(p).unwrap().scalar_mul(2).scalar_mul_assigned(w);
+            chain.scalar_mul(Q - 1).finish().unwrap().values()
+        });
+        assert!(ok);
+        assert_eq!(out, m.map(|c| (Q - c * 14 % Q) % Q));
+    }
+}
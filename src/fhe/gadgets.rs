@@ -0,0 +1,1241 @@
+use halo2_base::gates::{GateChip, GateInstructions, RangeChip, RangeInstructions};
+use halo2_base::utils::ScalarField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+use poseidon::PoseidonChip;
+
+use crate::error::CircuitError;
+use crate::fhe::bfv::{BfvParams, RoundMode};
+use crate::poly::distribution::{
+    check_poly_from_distribution_chi_error, check_poly_from_distribution_chi_key,
+};
+use crate::poly::ntt::{negacyclic_intt, negacyclic_ntt_assigned, primitive_root_of_unity};
+#[cfg(feature = "relin")]
+use crate::poly::operations::base_decompose;
+use crate::poly::operations::load_poly;
+use crate::poly::operations::{
+    assert_poly_zero, assert_poly_zero_mod, assert_reduced, centered_value, poly_add_assigned,
+    poly_mul_assigned, poly_mul_constant_assigned, poly_reduce_cyclo_assigned,
+    poly_reduce_ring_assigned,
+};
+use crate::poly::utils::{bit_length, check_coeffs_bound};
+
+// checks that the polynomials are non-empty and all have the same length, and returns that length
+fn check_same_len<F: ScalarField>(polys: &[&[AssignedValue<F>]]) -> Result<usize, CircuitError> {
+    let n = polys[0].len();
+    if n == 0 {
+        return Err(CircuitError::EmptyInput);
+    }
+    match polys.iter().find(|poly| poly.len() != n) {
+        Some(poly) => Err(CircuitError::LengthMismatch { left: n, right: poly.len() }),
+        None => Ok(n),
+    }
+}
+
+/// Constrains that `x` is in `[0, q)` and returns its centered representative in `(-q/2, q/2]`, encoded as a field element.
+fn centered_lift<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    q: u64,
+) -> AssignedValue<F> {
+    let (_, sign) = centered_value(ctx, range, x, q);
+    // x - q * sign
+    range.gate().mul_add(ctx, sign, Constant(-F::from(q)), x)
+}
+
+/// Computes `num / den` rounded with `mode` (see `fhe::bfv::RoundMode`), for `num` in `[0, 2^num_bits)`.
+///
+/// `HalfUp` divides `num + floor(den/2)`, and `Truncate` is the quotient of `num` by `den`.
+/// `HalfEven` only differs from `HalfUp` for an even `den`, where the quotient is rounded up when the remainder is above `den/2`, or equal to it and the quotient is odd.
+pub fn round_div<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    num: AssignedValue<F>,
+    den: u64,
+    num_bits: usize,
+    mode: RoundMode,
+) -> AssignedValue<F> {
+    let gate = range.gate();
+    match mode {
+        RoundMode::Truncate => range.div_mod(ctx, num, den, num_bits).0,
+        RoundMode::HalfEven if den % 2 == 0 => {
+            let half = den / 2;
+            let (quot, rem) = range.div_mod(ctx, num, den, num_bits);
+            let above =
+                range.is_less_than(ctx, Constant(F::from(half)), rem, bit_length(den as u128));
+            let tie = gate.is_equal(ctx, rem, Constant(F::from(half)));
+            let (_, odd) = range.div_mod(ctx, quot, 2u64, num_bits);
+            // the two cases are exclusive, so the quotient is rounded up at most once
+            let up = gate.mul_add(ctx, tie, odd, above);
+            gate.add(ctx, quot, up)
+        }
+        RoundMode::HalfUp | RoundMode::HalfEven => {
+            let shifted = gate.add(ctx, num, Constant(F::from(den / 2)));
+            let bits = num_bits.max(bit_length(den as u128)) + 1;
+            range.div_mod(ctx, shifted, den, bits).0
+        }
+    }
+}
+
+/// Computes `[round(t * x / q)]_q` for a signed integer `x` (encoded as a field element) with `|x| <= max_abs`, where ties are broken by `mode`.
+///
+/// `div_mod` only works on non-negative inputs, so we first shift `t * x` by a multiple of `q^2` that is larger than `t * max_abs`.
+/// After dividing by `q` the shift becomes a multiple of `q`, which vanishes in the final reduction mod `q`.
+/// Ties need an even `q`, in which case the shift is an even multiple of `q` and `HalfEven` still sees the parity of the unshifted quotient.
+/// `Truncate` rounds toward negative infinity, so `t * x / q = -1/2` becomes `-1`.
+pub fn scale_and_round<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    t: u64,
+    q: u64,
+    max_abs: u128,
+    mode: RoundMode,
+) -> AssignedValue<F> {
+    let q_squared = q as u128 * q as u128;
+    let offset = q_squared * (t as u128 * max_abs / q_squared + 1);
+    // t * x + offset is in [0, 2 * offset)
+    let shifted =
+        range.gate().mul_add(ctx, x, Constant(F::from(t)), Constant(F::from_u128(offset)));
+    let quot = round_div(ctx, range, shifted, q, bit_length(2 * offset), mode);
+    let (_, rem) =
+        range.div_mod(ctx, quot, q, bit_length((2 * offset + q as u128) / q as u128 + 1));
+    rem
+}
+
+/// Adds two BFV ciphertexts `ct_a = (a0, a1)` and `ct_b = (b0, b1)` and returns `([a0 + b0]_q, [a1 + b1]_q)`.
+/// The input coefficients are constrained to be in `[0, q)` and the output coefficients are in `[0, q)`.
+pub fn bfv_add<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    ct_a: [&[AssignedValue<F>]; 2],
+    ct_b: [&[AssignedValue<F>]; 2],
+    q: u64,
+) -> Result<[Vec<AssignedValue<F>>; 2], CircuitError> {
+    check_same_len(&[ct_a[0], ct_a[1], ct_b[0], ct_b[1]])?;
+    for poly in ct_a.iter().chain(ct_b.iter()) {
+        assert_reduced(ctx, range, poly, q);
+    }
+    let mut out = vec![];
+    for (a, b) in ct_a.into_iter().zip(ct_b) {
+        // every coefficient of the sum is in [0, 2q), so a single reduction mod q suffices
+        let sum = poly_add_assigned(ctx, range.gate(), a, b)?;
+        out.push(
+            sum.into_iter()
+                .map(|x| range.div_mod(ctx, x, q, bit_length(2 * q as u128)).1)
+                .collect(),
+        );
+    }
+    Ok(out.try_into().unwrap())
+}
+
+/// Multiplies both components of the BFV ciphertext `ct = (c0, c1)` by the public plaintext polynomial `pt` and returns `([c0 * pt]_q, [c1 * pt]_q)` in `R_q`.
+///
+/// The plaintext is part of the circuit (see `poly::operations::poly_mul_constant_assigned`), so it costs no witnesses and no range checks.
+/// The input coefficients are constrained to be in `[0, q)` and the output coefficients are in `[0, q)`.
+/// Returns an `OutOfRange` error when a coefficient of `pt` is not in `[0, q)`.
+pub fn bfv_mul_plain<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    ct: [&[AssignedValue<F>]; 2],
+    pt: &[u64],
+    q: u64,
+) -> Result<[Vec<AssignedValue<F>>; 2], CircuitError> {
+    let n = check_same_len(&[ct[0], ct[1]])?;
+    if pt.len() != n {
+        return Err(CircuitError::LengthMismatch { left: n, right: pt.len() });
+    }
+    check_coeffs_bound(pt, q)?;
+    let mut out = vec![];
+    for c in ct {
+        assert_reduced(ctx, range, c, q);
+        let prod = poly_mul_constant_assigned(ctx, range.gate(), c, pt)?;
+        // every coefficient of the product is a sum of at most n terms below q^2
+        let max_abs = n as u128 * (q as u128 - 1).pow(2);
+        out.push(poly_reduce_ring_assigned(ctx, range, &prod, n, q, max_abs)?);
+    }
+    Ok(out.try_into().unwrap())
+}
+
+/// Scales the plaintext `m` into the ciphertext space, returning `delta*m_i` for every coefficient with `delta = floor(q/t)`.
+///
+/// The coefficients of `m` are constrained to be in `[0, t)`, so the outputs are at most `delta * (t-1) < q` and need no reduction.
+/// Returns an `InvalidPlaintextModulus` error when `t` is not in `[2, q)` (see `fhe::bfv::BfvParams`).
+pub fn bfv_scale_message<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    m: &[AssignedValue<F>],
+    q: u64,
+    t: u64,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let delta = BfvParams::new(m.len(), q, t)?.delta();
+    assert_reduced(ctx, range, m, t);
+    Ok(m.iter().map(|m_i| range.gate().mul(ctx, *m_i, Constant(F::from(delta)))).collect())
+}
+
+/// Constrains that `ct = (c0, c1)` is an honest BFV encryption of the plaintext `m` under the public key `pk = (p0, p1)`, with the randomness `u`, `e1` and `e2`:
+/// - `c0 = [p0*u + e1 + delta*m]_q`
+/// - `c1 = [p1*u + e2]_q`
+///
+/// where `delta = floor(q/t)`. The ephemeral key `u` is constrained to come from `chi_key`, the errors `e1` and `e2` to come from `chi_error` with bound `b`, the coefficients of `m` to be in `[0, t)` and the coefficients of `pk` and `ct` to be in `[0, q)`.
+/// Returns an `InvalidPlaintextModulus` error when `t` is not in `[2, q)` (see `fhe::bfv::BfvParams`).
+#[allow(clippy::too_many_arguments)]
+pub fn bfv_verify_encryption<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    pk: [&[AssignedValue<F>]; 2],
+    ct: [&[AssignedValue<F>]; 2],
+    m: &[AssignedValue<F>],
+    u: &[AssignedValue<F>],
+    e: [&[AssignedValue<F>]; 2],
+    q: u64,
+    t: u64,
+    b: u64,
+) -> Result<(), CircuitError> {
+    let n = check_same_len(&[pk[0], pk[1], ct[0], ct[1], m, u, e[0], e[1]])?;
+    let delta = BfvParams::new(n, q, t)?.delta();
+    let gate = range.gate();
+
+    assert_reduced(ctx, range, pk[0], q);
+    assert_reduced(ctx, range, pk[1], q);
+    assert_reduced(ctx, range, m, t);
+    check_poly_from_distribution_chi_key(ctx, gate, u, q);
+    for e in e {
+        check_poly_from_distribution_chi_error(ctx, range, e, q, b);
+    }
+
+    // the coefficients of pk and u are in [0, q), so after the negacyclic folding every coefficient
+    // of p_i*u is bounded by n * (q-1)^2, and adding the error and the scaled message adds less than 2q
+    let max_abs = n as u128 * (q as u128 - 1).pow(2) + 2 * q as u128;
+    for (i, (p, c)) in pk.into_iter().zip(ct).enumerate() {
+        let mut v = poly_mul_assigned(ctx, gate, p, u)?;
+        for (j, e_j) in e[i].iter().enumerate() {
+            v[j] = gate.add(ctx, v[j], *e_j);
+            if i == 0 {
+                v[j] = gate.mul_add(ctx, m[j], Constant(F::from(delta)), v[j]);
+            }
+        }
+        // the reduced coefficients are in [0, q), so this also constrains c to be in [0, q)
+        let reduced = poly_reduce_ring_assigned(ctx, range, &v, n, q, max_abs)?;
+        for (x, y) in reduced.iter().zip(c.iter()) {
+            ctx.constrain_equal(x, y);
+        }
+    }
+    Ok(())
+}
+
+/// Constrains that `pk = (p0, p1)` is a well-formed BFV public key for the secret key `s` and the error `e`, i.e. that `p0 + p1*s + e = 0` in `R_q`.
+///
+/// The secret key `s` is constrained to come from `chi_key`, the error `e` to come from `chi_error` with bound `b` and the coefficients of `pk` to be in `[0, q)`.
+pub fn bfv_verify_public_key<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    pk: [&[AssignedValue<F>]; 2],
+    s: &[AssignedValue<F>],
+    e: &[AssignedValue<F>],
+    q: u64,
+    b: u64,
+) -> Result<(), CircuitError> {
+    let n = check_same_len(&[pk[0], pk[1], s, e])?;
+    let gate = range.gate();
+
+    assert_reduced(ctx, range, pk[0], q);
+    assert_reduced(ctx, range, pk[1], q);
+    check_poly_from_distribution_chi_key(ctx, gate, s, q);
+    check_poly_from_distribution_chi_error(ctx, range, e, q, b);
+
+    let mut v = poly_mul_assigned(ctx, gate, pk[1], s)?;
+    for (j, (p0_j, e_j)) in pk[0].iter().zip(e).enumerate() {
+        v[j] = gate.add(ctx, v[j], *p0_j);
+        v[j] = gate.add(ctx, v[j], *e_j);
+    }
+    // every coefficient of p1*s is bounded by n * (q-1)^2 after the negacyclic folding, and p0 + e adds less than 2q
+    let max_abs = n as u128 * (q as u128 - 1).pow(2) + 2 * q as u128;
+    assert_poly_zero_mod(ctx, range, &v, n, q, max_abs)
+}
+
+/// Constrains that `sample = (a, b)` is an RLWE sample for the secret `s` and the error `e`, i.e. that `b = a*s + e` in `R_q`.
+///
+/// The secret `s` is constrained to come from `chi_key`, the error `e` to come from `chi_error` with bound `b` and the coefficients of `a` and `b` to be in `[0, q)`.
+/// This is the relation underlying both the public key (see `bfv_verify_public_key`) and the encryption of BFV.
+pub fn rlwe_verify_sample<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    sample: [&[AssignedValue<F>]; 2],
+    s: &[AssignedValue<F>],
+    e: &[AssignedValue<F>],
+    q: u64,
+    b: u64,
+) -> Result<(), CircuitError> {
+    let n = check_same_len(&[sample[0], sample[1], s, e])?;
+    let gate = range.gate();
+
+    assert_reduced(ctx, range, sample[0], q);
+    assert_reduced(ctx, range, sample[1], q);
+    check_poly_from_distribution_chi_key(ctx, gate, s, q);
+    check_poly_from_distribution_chi_error(ctx, range, e, q, b);
+
+    let mut v = poly_mul_assigned(ctx, gate, sample[0], s)?;
+    for (j, (b_j, e_j)) in sample[1].iter().zip(e).enumerate() {
+        v[j] = gate.add(ctx, v[j], *e_j);
+        v[j] = gate.sub(ctx, v[j], *b_j);
+    }
+    // every coefficient of a*s is bounded by n * (q-1)^2 after the negacyclic folding, and e - b adds less than 2q
+    let max_abs = n as u128 * (q as u128 - 1).pow(2) + 2 * q as u128;
+    assert_poly_zero_mod(ctx, range, &v, n, q, max_abs)
+}
+
+/// Computes `round(t * x / q) mod t` for `x` in `[0, q)`. Ties are rounded up.
+fn round_to_plaintext<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    q: u64,
+    t: u64,
+) -> AssignedValue<F> {
+    // round(t * x / q) is in [0, t], and reducing it mod t maps t to 0
+    let y = range.gate().mul(ctx, x, Constant(F::from(t)));
+    let rounded = round_div(ctx, range, y, q, bit_length(t as u128 * q as u128), RoundMode::HalfUp);
+    let (_, m) = range.div_mod(ctx, rounded, t, bit_length(t as u128 + 1));
+    m
+}
+
+/// Computes the tensor product `(c0, c1, c2)` of two BFV ciphertexts `ct_a = (a0, a1)` and `ct_b = (b0, b1)`:
+/// - `c0 = [round(t/q * a0*b0)]_q`
+/// - `c1 = [round(t/q * (a0*b1 + a1*b0))]_q`
+/// - `c2 = [round(t/q * a1*b1)]_q`
+///
+/// where the products are computed over the integers mod `x^n + 1` on the centered representatives of the input coefficients.
+/// The input coefficients are constrained to be in `[0, q)` and the output coefficients are in `[0, q)`.
+pub fn bfv_tensor<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    ct_a: [&[AssignedValue<F>]; 2],
+    ct_b: [&[AssignedValue<F>]; 2],
+    q: u64,
+    t: u64,
+) -> Result<[Vec<AssignedValue<F>>; 3], CircuitError> {
+    let polys = [ct_a[0], ct_a[1], ct_b[0], ct_b[1]];
+    let n = check_same_len(&polys)?;
+    let gate = range.gate();
+
+    let mut lifted = vec![];
+    for poly in polys {
+        lifted.push(poly.iter().map(|x| centered_lift(ctx, range, *x, q)).collect::<Vec<_>>());
+    }
+    let (a0, a1, b0, b1) = (&lifted[0], &lifted[1], &lifted[2], &lifted[3]);
+
+    // the lifted coefficients are bounded by q/2 in absolute value, so each coefficient of
+    // c1 (the sum of two products reduced mod x^n+1) is bounded by 2 * n * (q/2)^2
+    let max_abs = 2 * n as u128 * (q as u128 / 2).pow(2);
+
+    let e0 = poly_mul_assigned(ctx, gate, a0, b0)?;
+    let e1 = {
+        let a0b1 = poly_mul_assigned(ctx, gate, a0, b1)?;
+        let a1b0 = poly_mul_assigned(ctx, gate, a1, b0)?;
+        poly_add_assigned(ctx, gate, &a0b1, &a1b0)?
+    };
+    let e2 = poly_mul_assigned(ctx, gate, a1, b1)?;
+
+    let mut out = vec![];
+    for e in [e0, e1, e2] {
+        let reduced = poly_reduce_cyclo_assigned(ctx, gate, &e, n);
+        out.push(
+            reduced
+                .into_iter()
+                .map(|x| scale_and_round(ctx, range, x, t, q, max_abs, RoundMode::HalfUp))
+                .collect(),
+        );
+    }
+    Ok(out.try_into().unwrap())
+}
+
+/// Relinearizes a degree 2 ciphertext `ct = (c0, c1, c2)` back into a regular ciphertext `([c0 + sum_i rlk[i][0] * d_i]_q, [c1 + sum_i rlk[i][1] * d_i]_q)`, where `d_i` is the polynomial made of the `i`-th base `w` digits of the coefficients of `c2`.
+///
+/// There must be one relinearization key `rlk[i]` for every digit (see `fhe::bfv::relin_keygen`). The coefficients of `ct` and `rlk` are constrained to be in `[0, q)` and the output coefficients are in `[0, q)`.
+#[cfg(feature = "relin")]
+pub fn bfv_relinearize<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    ct: [&[AssignedValue<F>]; 3],
+    rlk: &[[Vec<AssignedValue<F>>; 2]],
+    q: u64,
+    w: u64,
+) -> Result<[Vec<AssignedValue<F>>; 2], CircuitError> {
+    let mut polys = ct.to_vec();
+    polys.extend(rlk.iter().flat_map(|key| key.iter().map(|poly| poly.as_slice())));
+    let n = check_same_len(&polys)?;
+    for poly in &polys {
+        assert_reduced(ctx, range, poly, q);
+    }
+    let gate = range.gate();
+    let l = rlk.len();
+
+    // digits[i] is the polynomial made of the i-th digits of the coefficients of c2
+    let decomposed = ct[2].iter().map(|x| base_decompose(ctx, range, *x, w, l)).collect::<Vec<_>>();
+    let digits = (0..l).map(|i| decomposed.iter().map(|d| d[i]).collect::<Vec<_>>());
+
+    let (mut c0, mut c1) = (ct[0].to_vec(), ct[1].to_vec());
+    for (key, digit_poly) in rlk.iter().zip(digits) {
+        for (c, k) in [(&mut c0, &key[0]), (&mut c1, &key[1])] {
+            let prod = poly_mul_assigned(ctx, gate, k, &digit_poly)?;
+            let prod = poly_reduce_cyclo_assigned(ctx, gate, &prod, n);
+            *c = poly_add_assigned(ctx, gate, c, &prod)?;
+        }
+    }
+
+    // every product coefficient is bounded by n * (q - 1) * (w - 1) in absolute value after the negacyclic folding
+    let max_abs = (q as u128 - 1) * (1 + l as u128 * n as u128 * (w as u128 - 1));
+    let c0 = poly_reduce_ring_assigned(ctx, range, &c0, n, q, max_abs)?;
+    Ok([c0, poly_reduce_ring_assigned(ctx, range, &c1, n, q, max_abs)?])
+}
+
+/// Decrypts a BFV ciphertext `ct = (c0, c1)` under the secret key `sk` and returns the plaintext coefficients in `[0, t)`, computed as `round(t * [c0 + c1*s]_q / q) mod t`.
+///
+/// Decryption only recovers the encrypted message while the noise is small enough, so we also constrain that the noise `[c0 + c1*s - delta*m]_q` of every coefficient, with `delta = floor(q/t)`, has magnitude at most `noise_bound`.
+/// The coefficients of `ct` and `sk` are constrained to be in `[0, q)`.
+pub fn bfv_decrypt<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    sk: &[AssignedValue<F>],
+    ct: [&[AssignedValue<F>]; 2],
+    q: u64,
+    t: u64,
+    noise_bound: u64,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let polys = [ct[0], ct[1], sk];
+    let n = check_same_len(&polys)?;
+    let delta = BfvParams::new(n, q, t)?.delta();
+    let gate = range.gate();
+
+    let mut lifted = vec![];
+    for poly in polys {
+        lifted.push(poly.iter().map(|x| centered_lift(ctx, range, *x, q)).collect::<Vec<_>>());
+    }
+    let (c0, c1, s) = (&lifted[0], &lifted[1], &lifted[2]);
+
+    // c0 + c1*s mod x^n + 1, computed over the integers
+    let c1s = poly_mul_assigned(ctx, gate, c1, s)?;
+    let c1s = poly_reduce_cyclo_assigned(ctx, gate, &c1s, n);
+    let v = poly_add_assigned(ctx, gate, c0, &c1s)?;
+
+    // every coefficient of v is bounded by q/2 + n * (q/2)^2 in absolute value, so we shift it by a
+    // larger multiple of q before reducing it mod q
+    let max_abs = q as u128 / 2 + n as u128 * (q as u128 / 2).pow(2);
+    let offset = q as u128 * (max_abs / q as u128 + 1);
+
+    let mut m = Vec::with_capacity(n);
+    for v in v {
+        let shifted = gate.add(ctx, v, Constant(F::from_u128(offset)));
+        let (_, x) = range.div_mod(ctx, shifted, q, bit_length(2 * offset));
+
+        let m_i = round_to_plaintext(ctx, range, x, q, t);
+
+        // x + q - delta * m is in (0, 2q), and its reduction mod q is the noise
+        let x_plus_q = gate.add(ctx, x, Constant(F::from(q)));
+        let noise = gate.mul_add(ctx, m_i, Constant(-F::from(delta)), x_plus_q);
+        let (_, noise) = range.div_mod(ctx, noise, q, bit_length(2 * q as u128));
+        let (magnitude, _) = centered_value(ctx, range, noise, q);
+        range.check_less_than_safe(ctx, magnitude, noise_bound + 1);
+
+        m.push(m_i);
+    }
+    Ok(m)
+}
+
+/// Constrains the ciphertext `ct = (c0, c1)` to decrypt to the zero message under the secret key `sk`, with a noise `[c0 + c1*s]_q` of magnitude at most `noise_bound`.
+///
+/// This is `bfv_decrypt` with the message fixed to zero, e.g. to check that a homomorphic computation or a relinearization key yields an encryption of zero.
+/// The secret key must be bound to something public, e.g. a public key with `bfv_verify_public_key`: otherwise a prover could solve `c0 + c1*s = 0` for `s`.
+pub fn bfv_assert_decrypts_to_zero<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    sk: &[AssignedValue<F>],
+    ct: [&[AssignedValue<F>]; 2],
+    q: u64,
+    t: u64,
+    noise_bound: u64,
+) -> Result<(), CircuitError> {
+    let m = bfv_decrypt(ctx, range, sk, ct, q, t, noise_bound)?;
+    assert_poly_zero(ctx, range.gate(), &m);
+    Ok(())
+}
+
+/// Computes the noise `e = [c0 + c1*s - delta*m]_q` of the ciphertext `ct = (c0, c1)` under the secret key `sk`, for the plaintext `m` and `delta = floor(q/t)`, and returns it with coefficients in `[0, q)`.
+///
+/// The coefficients of `ct` and `sk` are constrained to be in `[0, q)` and lifted to their centered representatives, and those of `m` are constrained to be in `[0, t)`.
+/// The noise can then be bounded with `poly::operations::assert_inf_norm_bound`, which takes every coefficient in its centered representation.
+pub fn bfv_noise<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    sk: &[AssignedValue<F>],
+    ct: [&[AssignedValue<F>]; 2],
+    m: &[AssignedValue<F>],
+    q: u64,
+    t: u64,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let n = check_same_len(&[ct[0], ct[1], sk, m])?;
+    let delta = BfvParams::new(n, q, t)?.delta();
+    let gate = range.gate();
+    assert_reduced(ctx, range, m, t);
+
+    let [c0, c1, s] = [ct[0], ct[1], sk]
+        .map(|poly| poly.iter().map(|x| centered_lift(ctx, range, *x, q)).collect::<Vec<_>>());
+
+    // c0 + c1*s - delta*m mod x^n + 1, computed over the integers
+    let c1s = poly_mul_assigned(ctx, gate, &c1, &s)?;
+    let c1s = poly_reduce_cyclo_assigned(ctx, gate, &c1s, n);
+    let v = poly_add_assigned(ctx, gate, &c0, &c1s)?;
+    let e = v
+        .iter()
+        .zip(m.iter())
+        .map(|(v, m)| gate.mul_add(ctx, *m, Constant(-F::from(delta)), *v))
+        .collect::<Vec<_>>();
+
+    // every coefficient is bounded by q/2 + n * (q/2)^2 + delta * (t - 1) in absolute value
+    let max_abs =
+        q as u128 / 2 + n as u128 * (q as u128 / 2).pow(2) + delta as u128 * (t as u128 - 1);
+    poly_reduce_ring_assigned(ctx, range, &e, n, q, max_abs)
+}
+
+/// Extracts the plaintext from a decrypted noisy polynomial `v = [c0 + c1*s]_q`, i.e. returns `round(t * v / q) mod t` coefficient-wise.
+///
+/// Coefficients just below `q` round up to `t` and wrap around to `0`. The coefficients of `v` are constrained to be in `[0, q)`.
+pub fn bfv_extract_plaintext<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    v: &[AssignedValue<F>],
+    q: u64,
+    t: u64,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    check_same_len(&[v])?;
+    assert_reduced(ctx, range, v, q);
+    Ok(v.iter().map(|x| round_to_plaintext(ctx, range, *x, q, t)).collect())
+}
+
+// returns the primitive 2n-th root of unity mod t that batching uses for n slots, where n must be a non-zero power of two and 2n must divide t - 1
+fn batching_root(n: usize, t: u64) -> Result<u64, CircuitError> {
+    if n == 0 {
+        return Err(CircuitError::EmptyInput);
+    }
+    if !n.is_power_of_two() {
+        return Err(CircuitError::InvalidRingDegree { n });
+    }
+    primitive_root_of_unity(2 * n as u64, t)
+}
+
+/// Decodes a plaintext polynomial `m` into its slots in `[0, t)` (see `fhe::bfv::batch_encode`), by evaluating it at the roots of `x^n + 1` mod `t`.
+/// The coefficients of `m` are constrained to be in `[0, t)`.
+///
+/// Returns an `EmptyInput` error for an empty `m`, an `InvalidRingDegree` error when its length `n` is not a power of two, and a `NoRootOfUnity` error when `t` is not a prime with `2n | t - 1`.
+pub fn bfv_batch_decode<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    m: &[AssignedValue<F>],
+    t: u64,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let psi = batching_root(m.len(), t)?;
+    assert_reduced(ctx, range, m, t);
+    negacyclic_ntt_assigned(ctx, range, m, psi, t)
+}
+
+/// Encodes the slots (with values in `[0, t)`) into a plaintext polynomial and returns its coefficients in `[0, t)`.
+///
+/// The plaintext is computed outside of the circuit and loaded as a witness, then we constrain that decoding it gives back `slots`.
+/// Returns the same errors as `bfv_batch_decode` for the number of slots and `t`.
+pub fn bfv_batch_encode<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    slots: &[AssignedValue<F>],
+    t: u64,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let psi = batching_root(slots.len(), t)?;
+    let m = negacyclic_intt(
+        &slots.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>(),
+        psi,
+        t,
+    );
+    let m = load_poly(ctx, &m);
+    let decoded = bfv_batch_decode(ctx, range, &m, t)?;
+    for (x, y) in decoded.iter().zip(slots.iter()) {
+        ctx.constrain_equal(x, y);
+    }
+    Ok(m)
+}
+
+// Poseidon parameters of `commit_poly`, the same as in the `poseidon` example
+const POSEIDON_T: usize = 3;
+const POSEIDON_RATE: usize = 2;
+const POSEIDON_R_F: usize = 8;
+const POSEIDON_R_P: usize = 57;
+
+/// Commits to a polynomial, e.g. a plaintext, as the Poseidon hash of the blinding factor `blinding` followed by its coefficients.
+///
+/// The commitment is binding as long as Poseidon is collision resistant, for polynomials with the fixed number of coefficients of the circuit.
+/// It is only hiding if the blinding factor is sampled uniformly from the field and kept private: the plaintext space is small, so a commitment without it can be opened by trying every plaintext.
+pub fn commit_poly<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &GateChip<F>,
+    coeffs: &[AssignedValue<F>],
+    blinding: AssignedValue<F>,
+) -> AssignedValue<F> {
+    let mut poseidon =
+        PoseidonChip::<F, POSEIDON_T, POSEIDON_RATE>::new(ctx, POSEIDON_R_F, POSEIDON_R_P).unwrap();
+    poseidon.update(&[blinding]);
+    poseidon.update(coeffs);
+    poseidon.squeeze(ctx, gate).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use ark_bn254::Fr as ArkFr;
+    use ark_ff::PrimeField;
+    use ark_poly::univariate::{DenseOrSparsePolynomial, DensePolynomial};
+    use ark_poly::DenseUVPolynomial;
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_base::utils::ScalarField;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{
+        bfv_add, bfv_assert_decrypts_to_zero, bfv_batch_decode, bfv_batch_encode, bfv_decrypt,
+        bfv_extract_plaintext, bfv_mul_plain, bfv_noise, bfv_scale_message, bfv_tensor,
+        bfv_verify_encryption, bfv_verify_public_key, commit_poly, rlwe_verify_sample, round_div,
+        scale_and_round,
+    };
+    use crate::error::CircuitError;
+    use crate::fhe::bfv::{
+        add, batch_decode, batch_encode, decrypt, encrypt, encrypt_with_randomness,
+        extract_plaintext, keygen, mul_plain, noise, public_key, relin_keygen, relinearize,
+        rlwe_sample, sample_error, sample_ternary, sample_uniform, scale_message, tensor,
+        Ciphertext, RoundMode,
+    };
+    use crate::poly::operations::{assert_inf_norm_bound, load_poly};
+    use crate::poly::utils::{
+        pad_coeffs, poly_mul, reduce_mod_cyclo, reduce_mod_q, ring_add, ring_mul, ring_neg,
+        to_centered,
+    };
+    use crate::test_utils::mock_run;
+
+    const N: usize = 4;
+    const Q: u64 = 1048573;
+    const T: u64 = 8;
+    const B: u64 = 2;
+    const W: u64 = 16;
+    // the largest noise that still decrypts correctly, with some slack for the rounding of q/t
+    const NOISE_BOUND: u64 = Q / (2 * T) - T;
+
+    #[test]
+    fn test_bfv_add() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let (_, pk) = keygen(&mut rng, N, Q, B);
+        let ct1 = encrypt(&mut rng, &pk, &[1, 2, 3, 4], Q, T, B);
+        let ct2 = encrypt(&mut rng, &pk, &[5, 0, 7, 1], Q, T, B);
+        let expected = add(&ct1, &ct2, Q);
+
+        let (out, ok) = mock_run(11, 8, |ctx, range| {
+            let [a0, a1, b0, b1] = [&ct1[0], &ct1[1], &ct2[0], &ct2[1]].map(|c| load_poly(ctx, c));
+            let out = bfv_add(ctx, range, [&a0, &a1], [&b0, &b1], Q).unwrap();
+            out.map(|c| c.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>())
+        });
+        assert!(ok);
+        assert_eq!(out, expected);
+
+        // claiming a wrong second component of the output is rejected
+        let mut wrong = expected.clone();
+        wrong[1][2] = (wrong[1][2] + 1) % Q;
+        let (_, ok) = mock_run(11, 8, |ctx, range| {
+            let [a0, a1, b0, b1] = [&ct1[0], &ct1[1], &ct2[0], &ct2[1]].map(|c| load_poly(ctx, c));
+            let out = bfv_add(ctx, range, [&a0, &a1], [&b0, &b1], Q).unwrap();
+            for (poly, claimed) in out.iter().zip(wrong.iter()) {
+                let claimed = load_poly(ctx, claimed);
+                for (x, y) in poly.iter().zip(claimed.iter()) {
+                    ctx.constrain_equal(x, y);
+                }
+            }
+        });
+        assert!(!ok);
+    }
+
+    // runs bfv_verify_encryption on the given public key, ciphertext, message and randomness
+    fn verify_encryption(
+        pk: &[Vec<u64>; 2],
+        ct: &[Vec<u64>; 2],
+        m: &[u64],
+        u: &[u64],
+        e: &[Vec<u64>; 2],
+    ) -> bool {
+        let (_, ok) = mock_run(12, 8, |ctx, range| {
+            let [p0, p1, c0, c1, e1, e2] =
+                [&pk[0], &pk[1], &ct[0], &ct[1], &e[0], &e[1]].map(|c| load_poly(ctx, c));
+            let (m, u) = (load_poly(ctx, m), load_poly(ctx, u));
+            bfv_verify_encryption(ctx, range, [&p0, &p1], [&c0, &c1], &m, &u, [&e1, &e2], Q, T, B)
+                .unwrap();
+        });
+        ok
+    }
+
+    #[test]
+    fn test_bfv_verify_encryption() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let m = vec![7, 0, 2, 5];
+        let u = sample_ternary(&mut rng, N, Q);
+        let e = [sample_error(&mut rng, N, B, Q), sample_error(&mut rng, N, B, Q)];
+        let ct = encrypt_with_randomness(&pk, &m, &u, &e[0], &e[1], Q, T);
+        assert_eq!(decrypt(&s, &ct, Q, T), m);
+        assert!(verify_encryption(&pk, &ct, &m, &u, &e));
+
+        // a ciphertext of a different message is rejected
+        assert!(!verify_encryption(&pk, &ct, &[6, 0, 2, 5], &u, &e));
+    }
+
+    #[test]
+    fn test_bfv_scale_message() {
+        let m = [3, 0, 7, 1];
+        let run = |q: u64, t: u64, m: &[u64]| {
+            mock_run(10, 8, |ctx, range| {
+                let m = load_poly(ctx, m);
+                bfv_scale_message(ctx, range, &m, q, t)
+                    .map(|out| out.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>())
+            })
+        };
+        // 256 = 32 * 8 is divisible by t, 257 = 32 * 8 + 1 and Q = 131071 * 8 + 5 are not, so delta is the floor of q/t
+        for (q, delta) in [(256, 32), (257, 32), (Q, 131071)] {
+            let (out, ok) = run(q, T, &m);
+            assert!(ok);
+            assert_eq!(out, Ok(m.iter().map(|x| delta * x).collect::<Vec<_>>()));
+            assert_eq!(out.unwrap(), scale_message(&m, q, T));
+        }
+
+        // a message coefficient outside of [0, t)
+        let (_, ok) = run(Q, T, &[3, 0, 8, 1]);
+        assert!(!ok);
+        assert_eq!(run(Q, Q, &m).0, Err(CircuitError::InvalidPlaintextModulus { t: Q, q: Q }));
+    }
+
+    #[test]
+    fn test_bfv_small_moduli() {
+        // q = 257 and t = 8, so delta = 32
+        let (q, t) = (257, 8);
+        let mut rng = StdRng::seed_from_u64(12);
+        let (s, pk) = keygen(&mut rng, N, q, 1);
+        let m = vec![1, 0, 7, 3];
+        let (u, e1, e2) = (
+            sample_ternary(&mut rng, N, q),
+            sample_error(&mut rng, N, 1, q),
+            sample_error(&mut rng, N, 1, q),
+        );
+        let ct = encrypt_with_randomness(&pk, &m, &u, &e1, &e2, q, t);
+
+        let (decrypted, ok) = mock_run(11, 8, |ctx, range| {
+            let [p0, p1, c0, c1] = [&pk[0], &pk[1], &ct[0], &ct[1]].map(|c| load_poly(ctx, c));
+            let [m, u, e1, e2, sk] = [&m, &u, &e1, &e2, &s].map(|c| load_poly(ctx, c));
+            bfv_verify_encryption(ctx, range, [&p0, &p1], [&c0, &c1], &m, &u, [&e1, &e2], q, t, 1)
+                .unwrap();
+            let decrypted =
+                bfv_decrypt(ctx, range, &sk, [&c0, &c1], q, t, q / (2 * t) - 1).unwrap();
+            decrypted.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        assert_eq!(decrypted, m);
+
+        // a plaintext modulus that is not below q is rejected
+        let (err, _) = mock_run(11, 8, |ctx, range| {
+            let [sk, c0, c1] = [&s, &ct[0], &ct[1]].map(|c| load_poly(ctx, c));
+            bfv_decrypt(ctx, range, &sk, [&c0, &c1], q, q, 1).err()
+        });
+        assert_eq!(err, Some(CircuitError::InvalidPlaintextModulus { t: q, q }));
+    }
+
+    #[test]
+    fn test_bfv_verify_encryption_error_out_of_range() {
+        let mut rng = StdRng::seed_from_u64(10);
+        let (_, pk) = keygen(&mut rng, N, Q, B);
+        let m = vec![7, 0, 2, 5];
+        let u = sample_ternary(&mut rng, N, Q);
+        let mut e = [sample_error(&mut rng, N, B, Q), sample_error(&mut rng, N, B, Q)];
+        // the ciphertext matches the encryption formula, but its error is outside of [-B, B]
+        e[0][1] = Q - B - 1;
+        let ct = encrypt_with_randomness(&pk, &m, &u, &e[0], &e[1], Q, T);
+        assert!(!verify_encryption(&pk, &ct, &m, &u, &e));
+    }
+
+    // runs bfv_verify_public_key on the given public key, secret key and error
+    fn verify_public_key(pk: &[Vec<u64>; 2], s: &[u64], e: &[u64]) -> bool {
+        let (_, ok) = mock_run(12, 8, |ctx, range| {
+            let [p0, p1] = [&pk[0], &pk[1]].map(|c| load_poly(ctx, c));
+            let (s, e) = (load_poly(ctx, s), load_poly(ctx, e));
+            bfv_verify_public_key(ctx, range, [&p0, &p1], &s, &e, Q, B).unwrap();
+        });
+        ok
+    }
+
+    #[test]
+    fn test_bfv_verify_public_key() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let s = sample_ternary(&mut rng, N, Q);
+        let a = sample_uniform(&mut rng, N, Q);
+        let e = sample_error(&mut rng, N, B, Q);
+        let pk = public_key(&s, &a, &e, Q);
+        assert!(verify_public_key(&pk, &s, &e));
+
+        // a public key for a different error is rejected
+        let mut other = e.clone();
+        other[0] = (other[0] + 1) % Q;
+        assert!(!verify_public_key(&public_key(&s, &a, &other, Q), &s, &e));
+    }
+
+    #[test]
+    fn test_bfv_verify_public_key_error_too_large() {
+        let mut rng = StdRng::seed_from_u64(12);
+        let s = sample_ternary(&mut rng, N, Q);
+        let a = sample_uniform(&mut rng, N, Q);
+        let mut e = sample_error(&mut rng, N, B, Q);
+        // the public key satisfies p0 + a*s + e = 0, but its error is outside of [-B, B]
+        e[2] = B + 1;
+        let pk = public_key(&s, &a, &e, Q);
+        assert!(!verify_public_key(&pk, &s, &e));
+    }
+
+    #[test]
+    fn test_rlwe_verify_sample() {
+        let sample = rlwe_sample(&mut StdRng::seed_from_u64(13), N, Q, B);
+        let verify = |b: &[u64]| {
+            mock_run(12, 8, |ctx, range| {
+                let [a, b, s, e] = [&sample.a, b, &sample.s, &sample.e].map(|c| load_poly(ctx, c));
+                rlwe_verify_sample(ctx, range, [&a, &b], &s, &e, Q, B).unwrap();
+            })
+            .1
+        };
+        assert!(verify(&sample.b));
+
+        // a sample with a different b is rejected
+        let mut wrong = sample.b.clone();
+        wrong[1] = (wrong[1] + 1) % Q;
+        assert!(!verify(&wrong));
+    }
+
+    #[test]
+    fn test_bfv_tensor_round_trip() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let rlk = relin_keygen(&mut rng, &s, Q, W, B);
+        let (m1, m2) = (vec![3, 1, 0, 6], vec![2, 7, 1, 1]);
+        let ct1 = encrypt(&mut rng, &pk, &m1, Q, T, B);
+        let ct2 = encrypt(&mut rng, &pk, &m2, Q, T, B);
+
+        let (out, ok) = mock_run(13, 8, |ctx, range| {
+            let [a0, a1, b0, b1] = [&ct1[0], &ct1[1], &ct2[0], &ct2[1]].map(|c| load_poly(ctx, c));
+            let out = bfv_tensor(ctx, range, [&a0, &a1], [&b0, &b1], Q, T).unwrap();
+            out.map(|c| c.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>())
+        });
+        assert!(ok);
+        assert_eq!(out, tensor(&ct1, &ct2, Q, T));
+
+        let ct = relinearize(&out, &rlk, Q, W);
+        let m1 = m1.iter().map(|x| *x as i128).collect::<Vec<_>>();
+        let m2 = m2.iter().map(|x| *x as i128).collect::<Vec<_>>();
+        let expected = reduce_mod_q(&reduce_mod_cyclo(&poly_mul(&m1, &m2), N), T);
+        assert_eq!(decrypt(&s, &ct, Q, T), expected);
+    }
+
+    // lifts the coefficients to their centered representatives mod Q and embeds them in the arkworks field
+    fn to_ark(poly: &[u64]) -> DensePolynomial<ArkFr> {
+        DensePolynomial::from_coefficients_vec(
+            poly.iter().map(|x| ArkFr::from(to_centered(*x, Q))).collect(),
+        )
+    }
+
+    // interprets a small arkworks field element as a signed integer
+    fn from_ark(x: ArkFr) -> i128 {
+        let (neg, abs) = if x.into_bigint() > ArkFr::MODULUS_MINUS_ONE_DIV_TWO {
+            (true, -x)
+        } else {
+            (false, x)
+        };
+        let limbs = abs.into_bigint().0;
+        let value = limbs[0] as i128 | (limbs[1] as i128) << 64;
+        if neg {
+            -value
+        } else {
+            value
+        }
+    }
+
+    #[test]
+    fn test_bfv_tensor_against_arkworks() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let (_, pk) = keygen(&mut rng, N, Q, B);
+        let ct1 = encrypt(&mut rng, &pk, &[1, 2, 3, 4], Q, T, B);
+        let ct2 = encrypt(&mut rng, &pk, &[5, 0, 7, 1], Q, T, B);
+
+        let (out, ok) = mock_run(13, 8, |ctx, range| {
+            let [a0, a1, b0, b1] = [&ct1[0], &ct1[1], &ct2[0], &ct2[1]].map(|c| load_poly(ctx, c));
+            let out = bfv_tensor(ctx, range, [&a0, &a1], [&b0, &b1], Q, T).unwrap();
+            out.map(|c| c.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>())
+        });
+        assert!(ok);
+
+        // multiply over the integers (the products are much smaller than the arkworks field modulus) and reduce mod x^N + 1
+        let [a0, a1, b0, b1] = [&ct1[0], &ct1[1], &ct2[0], &ct2[1]].map(|c| to_ark(c));
+        let mut cyclo = vec![ArkFr::from(0u64); N + 1];
+        cyclo[0] = ArkFr::from(1u64);
+        cyclo[N] = ArkFr::from(1u64);
+        let cyclo = DensePolynomial::from_coefficients_vec(cyclo);
+        let products = [&a0 * &b0, &(&a0 * &b1) + &(&a1 * &b0), &a1 * &b1];
+        for (c, prod) in out.iter().zip(products) {
+            let (_, rem) = DenseOrSparsePolynomial::from(&prod)
+                .divide_with_q_and_r(&DenseOrSparsePolynomial::from(&cyclo))
+                .unwrap();
+            let rem =
+                pad_coeffs(&rem.coeffs.into_iter().map(from_ark).collect::<Vec<_>>(), N).unwrap();
+            let expected = rem
+                .iter()
+                .map(|x| {
+                    (T as i128 * x + Q as i128 / 2).div_euclid(Q as i128).rem_euclid(Q as i128)
+                        as u64
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(*c, expected);
+        }
+    }
+
+    #[cfg(feature = "relin")]
+    #[test]
+    fn test_bfv_relinearize() {
+        use super::bfv_relinearize;
+
+        let mut rng = StdRng::seed_from_u64(8);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let rlk = relin_keygen(&mut rng, &s, Q, W, B);
+        let ct1 = encrypt(&mut rng, &pk, &[1, 2, 3, 4], Q, T, B);
+        let ct2 = encrypt(&mut rng, &pk, &[5, 0, 7, 1], Q, T, B);
+        let ct = tensor(&ct1, &ct2, Q, T);
+
+        let (out, ok) = mock_run(13, 8, |ctx, range| {
+            let [c0, c1, c2] = [&ct[0], &ct[1], &ct[2]].map(|c| load_poly(ctx, c));
+            let rlk = rlk
+                .iter()
+                .map(|key| [&key[0], &key[1]].map(|c| load_poly(ctx, c)))
+                .collect::<Vec<_>>();
+            let out = bfv_relinearize(ctx, range, [&c0, &c1, &c2], &rlk, Q, W).unwrap();
+            out.map(|c| c.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>())
+        });
+        assert!(ok);
+        assert_eq!(out, relinearize(&ct, &rlk, Q, W));
+
+        let m1 = [1, 2, 3, 4].map(i128::from);
+        let m2 = [5, 0, 7, 1].map(i128::from);
+        let expected = reduce_mod_q(&reduce_mod_cyclo(&poly_mul(&m1, &m2), N), T);
+        assert_eq!(decrypt(&s, &out, Q, T), expected);
+    }
+
+    #[test]
+    fn test_bfv_tensor_malformed_inputs() {
+        let ((wrong_len, empty), _) = mock_run(9, 8, |ctx, range| {
+            let a = load_poly(ctx, &[1, 2, 3, 4]);
+            let b = load_poly(ctx, &[1, 2, 3]);
+            (
+                bfv_tensor(ctx, range, [&a, &a], [&a, &b], Q, T).err(),
+                bfv_tensor(ctx, range, [&[], &[]], [&[], &[]], Q, T).err(),
+            )
+        });
+        assert_eq!(wrong_len, Some(CircuitError::LengthMismatch { left: 4, right: 3 }));
+        assert_eq!(empty, Some(CircuitError::EmptyInput));
+    }
+
+    #[test]
+    fn test_bfv_decrypt_same_message() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let m = vec![3, 0, 5, 1];
+        let ct1 = encrypt(&mut rng, &pk, &m, Q, T, B);
+        let ct2 = encrypt(&mut rng, &pk, &m, Q, T, B);
+        assert_ne!(ct1, ct2);
+
+        let ([m1, m2], ok) = mock_run(13, 8, |ctx, range| {
+            let sk = load_poly(ctx, &s);
+            let [a0, a1, b0, b1] = [&ct1[0], &ct1[1], &ct2[0], &ct2[1]].map(|c| load_poly(ctx, c));
+            let m1 = bfv_decrypt(ctx, range, &sk, [&a0, &a1], Q, T, NOISE_BOUND).unwrap();
+            let m2 = bfv_decrypt(ctx, range, &sk, [&b0, &b1], Q, T, NOISE_BOUND).unwrap();
+            for (x, y) in m1.iter().zip(m2.iter()) {
+                ctx.constrain_equal(x, y);
+            }
+            [m1, m2].map(|m| m.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>())
+        });
+        assert!(ok);
+        assert_eq!(m1, m);
+        assert_eq!(m2, m);
+    }
+
+    #[test]
+    fn test_bfv_decrypt_to_committed_message() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let (m, other) = (vec![3, 0, 5, 1], vec![3, 0, 5, 2]);
+        let ct = encrypt(&mut rng, &pk, &m, Q, T, B);
+        let blinding = Fr::from(0x5eed);
+
+        // the commitments to both messages, computed on their own
+        let (commitments, _) = mock_run(12, 8, |ctx, range| {
+            let blinding = ctx.load_witness(blinding);
+            [&m, &other].map(|m| {
+                let m = load_poly(ctx, m);
+                *commit_poly(ctx, &range.gate, &m, blinding).value()
+            })
+        });
+        assert_ne!(commitments[0], commitments[1]);
+
+        // the ciphertext decrypts to the message committed to by the first commitment only
+        for (commitment, expected) in commitments.into_iter().zip([true, false]) {
+            let (_, ok) = mock_run(13, 8, |ctx, range| {
+                let sk = load_poly(ctx, &s);
+                let [c0, c1] = [&ct[0], &ct[1]].map(|c| load_poly(ctx, c));
+                let m = bfv_decrypt(ctx, range, &sk, [&c0, &c1], Q, T, NOISE_BOUND).unwrap();
+                let blinding = ctx.load_witness(blinding);
+                let computed = commit_poly(ctx, &range.gate, &m, blinding);
+                let commitment = ctx.load_witness(commitment);
+                ctx.constrain_equal(&computed, &commitment);
+            });
+            assert_eq!(ok, expected);
+        }
+    }
+
+    #[test]
+    fn test_bfv_decrypt_noise_too_large() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let mut ct = encrypt(&mut rng, &pk, &[3, 0, 5, 1], Q, T, B);
+        // adding delta/4 to a coefficient shifts its noise beyond the bound, but not enough to change the decryption
+        ct[0][0] = (ct[0][0] + Q / T / 4) % Q;
+        assert_eq!(decrypt(&s, &ct, Q, T), vec![3, 0, 5, 1]);
+
+        let (_, ok) = mock_run(13, 8, |ctx, range| {
+            let sk = load_poly(ctx, &s);
+            let [c0, c1] = [&ct[0], &ct[1]].map(|c| load_poly(ctx, c));
+            bfv_decrypt(ctx, range, &sk, [&c0, &c1], Q, T, Q / T / 8).unwrap();
+        });
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_bfv_assert_decrypts_to_zero() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let c1 = sample_uniform(&mut rng, N, Q);
+        // the ciphertext (c0, c1) with c0 = e - c1 * s, whose noise is e
+        let with_noise =
+            |e: &[u64]| [ring_add(&ring_neg(&ring_mul(&c1, &s, Q), Q), e, Q), c1.clone()];
+        let run = |ct: &Ciphertext| {
+            mock_run(13, 8, |ctx, range| {
+                let sk = load_poly(ctx, &s);
+                let [c0, c1] = [&ct[0], &ct[1]].map(|c| load_poly(ctx, c));
+                bfv_assert_decrypts_to_zero(ctx, range, &sk, [&c0, &c1], Q, T, NOISE_BOUND)
+                    .unwrap();
+            })
+            .1
+        };
+        assert!(run(&encrypt(&mut rng, &pk, &[0; N], Q, T, B)));
+        assert!(run(&with_noise(&[NOISE_BOUND, 0, Q - NOISE_BOUND, 0])));
+
+        // one more than the bound still decrypts to zero outside of the circuit, but is too noisy for it
+        let ct = with_noise(&[NOISE_BOUND + 1, 0, 0, 0]);
+        assert_eq!(decrypt(&s, &ct, Q, T), vec![0; N]);
+        assert!(!run(&ct));
+
+        // from (q - q/2) / t on, the noise rounds to a nonzero message
+        let ct = with_noise(&[0, 0, 0, (Q - Q / 2 + T - 1) / T]);
+        assert_eq!(decrypt(&s, &ct, Q, T), vec![0, 0, 0, 1]);
+        assert!(!run(&ct));
+    }
+
+    #[test]
+    fn test_bfv_mul_plain_decrypts_to_product() {
+        let mut rng = StdRng::seed_from_u64(9);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let m = vec![3, 0, 5, 7];
+        let pt = vec![2, 7, 0, 1];
+        let ct = encrypt(&mut rng, &pk, &m, Q, T, B);
+        let expected = ring_mul(&m, &pt, T);
+
+        let ((prod, dec), ok) = mock_run(13, 8, |ctx, range| {
+            let sk = load_poly(ctx, &s);
+            let [c0, c1] = [&ct[0], &ct[1]].map(|c| load_poly(ctx, c));
+            let prod = bfv_mul_plain(ctx, range, [&c0, &c1], &pt, Q).unwrap();
+            let dec =
+                bfv_decrypt(ctx, range, &sk, [&prod[0], &prod[1]], Q, T, NOISE_BOUND).unwrap();
+            (
+                prod.map(|c| c.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()),
+                dec.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>(),
+            )
+        });
+        assert!(ok);
+        assert_eq!(prod, mul_plain(&ct, &pt, Q));
+        // Dec(ct * pt) = Dec(ct) * pt in R_t
+        assert_eq!(dec, expected);
+        assert_eq!(decrypt(&s, &ct, Q, T), m);
+
+        let (errs, _) = mock_run(10, 8, |ctx, range| {
+            let [c0, c1] = [&ct[0], &ct[1]].map(|c| load_poly(ctx, c));
+            [&pt[..3], &[Q, 0, 0, 0]].map(|pt| bfv_mul_plain(ctx, range, [&c0, &c1], pt, Q).err())
+        });
+        assert_eq!(
+            errs,
+            [
+                Some(CircuitError::LengthMismatch { left: 4, right: 3 }),
+                Some(CircuitError::OutOfRange { value: Q, bound: Q })
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bfv_noise_bound() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let (s, pk) = keygen(&mut rng, N, Q, B);
+        let m = vec![3, 0, 5, 7];
+        let mut ct = encrypt(&mut rng, &pk, &m, Q, T, B);
+        // shift c0 so that the noise is exactly e, whose largest magnitude is 1000 from the coefficient -1000
+        let e = vec![1000 - 1, 0, Q - 1000, 1];
+        let shift = ring_add(
+            &e,
+            &noise(&s, &ct, &m, Q, T).iter().map(|x| (Q - x) % Q).collect::<Vec<_>>(),
+            Q,
+        );
+        ct[0] = ring_add(&ct[0], &shift, Q);
+        assert_eq!(noise(&s, &ct, &m, Q, T), e);
+
+        // the noise bound holds exactly at 1000 and fails just below it
+        for (bound, expected) in [(1000, true), (999, false)] {
+            let (e_assigned, ok) = mock_run(13, 8, |ctx, range| {
+                let sk = load_poly(ctx, &s);
+                let [c0, c1, m] = [&ct[0], &ct[1], &m].map(|c| load_poly(ctx, c));
+                let e = bfv_noise(ctx, range, &sk, [&c0, &c1], &m, Q, T).unwrap();
+                let bound = ctx.load_witness(Fr::from(bound));
+                assert_inf_norm_bound(ctx, range, &e, Q, bound);
+                e.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+            });
+            assert_eq!(ok, expected, "bound {bound}");
+            assert_eq!(e_assigned, e);
+        }
+    }
+
+    #[test]
+    fn test_bfv_batch_encode_decode() {
+        let t = 17;
+        let slots = [1, 2, 3, 4];
+        let ((m, decoded), ok) = mock_run(10, 8, |ctx, range| {
+            let slots = load_poly(ctx, &slots);
+            let m = bfv_batch_encode(ctx, range, &slots, t).unwrap();
+            let decoded = bfv_batch_decode(ctx, range, &m, t).unwrap();
+            for (x, y) in decoded.iter().zip(slots.iter()) {
+                ctx.constrain_equal(x, y);
+            }
+            (
+                m.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>(),
+                decoded.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>(),
+            )
+        });
+        assert!(ok);
+        assert_eq!(m, batch_encode(&slots, t).unwrap());
+        assert_eq!(decoded, batch_decode(&m, t).unwrap());
+        assert_eq!(decoded, slots);
+    }
+
+    #[test]
+    fn test_bfv_batch_invalid_slots() {
+        // t = 17 has primitive 2n-th roots of unity up to n = 8
+        let cases = [
+            (vec![], 17, CircuitError::EmptyInput),
+            (vec![1, 2, 3], 17, CircuitError::InvalidRingDegree { n: 3 }),
+            (vec![1; 16], 17, CircuitError::NoRootOfUnity { order: 32, q: 17 }),
+            (vec![1, 2, 3, 4], 19, CircuitError::NoRootOfUnity { order: 8, q: 19 }),
+            // 2n | t - 1, but t = 25 is not prime
+            (vec![1, 2, 3, 4], 25, CircuitError::NoRootOfUnity { order: 8, q: 25 }),
+        ];
+        for (slots, t, err) in cases {
+            let ((encoded, decoded), _) = mock_run(10, 8, |ctx, range| {
+                let slots = load_poly(ctx, &slots);
+                (
+                    bfv_batch_encode(ctx, range, &slots, t).unwrap_err(),
+                    bfv_batch_decode(ctx, range, &slots, t).unwrap_err(),
+                )
+            });
+            assert_eq!(encoded, err);
+            assert_eq!(decoded, err);
+        }
+    }
+
+    #[test]
+    fn test_bfv_extract_plaintext_boundaries() {
+        // q / (2t) is about 65535.8, so 65535 rounds down to 0 and 65536 rounds up to 1,
+        // while q - 1 rounds up to t and wraps around to 0
+        let half = Q / (2 * T);
+        let v = [0, half, half + 1, Q / 2, Q / 2 + 1, Q - half - 1, Q - half, Q - 1];
+        let (m, ok) = mock_run(11, 8, |ctx, range| {
+            let v = load_poly(ctx, &v);
+            let m = bfv_extract_plaintext(ctx, range, &v, Q, T).unwrap();
+            m.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        assert_eq!(m, extract_plaintext(&v, Q, T));
+        assert_eq!(m, vec![0, 0, 1, 4, 4, 7, 0, 0]);
+    }
+
+    #[test]
+    fn test_bfv_extract_plaintext_out_of_range() {
+        let (_, ok) = mock_run(11, 8, |ctx, range| {
+            let v = load_poly(ctx, &[Q]);
+            bfv_extract_plaintext(ctx, range, &v, Q, T).unwrap();
+        });
+        assert!(!ok);
+    }
+
+    const MODES: [RoundMode; 3] = [RoundMode::HalfUp, RoundMode::HalfEven, RoundMode::Truncate];
+
+    #[test]
+    fn test_round_div_modes_at_half() {
+        // num = q/2 is exactly half-way between 0 and 1, and 3q/2 between 1 and 2
+        let q = 1 << 20;
+        for num in [q / 2, q / 2 + 1, 3 * q / 2, q - 1] {
+            let (rounded, ok) = mock_run(11, 8, |ctx, range| {
+                let num = ctx.load_witness(Fr::from(num));
+                MODES.map(|mode| round_div(ctx, range, num, q, 22, mode).value().get_lower_64())
+            });
+            assert!(ok);
+            assert_eq!(rounded, MODES.map(|mode| mode.round_div(num as u128, q as u128) as u64));
+        }
+        assert_eq!([1, 0, 0], MODES.map(|mode| mode.round_div(q as u128 / 2, q as u128)));
+    }
+
+    #[test]
+    fn test_round_div_wrong_mode() {
+        // the result of round half up at q/2 does not satisfy the constraints of round half to even
+        let q = 1 << 20;
+        let (_, ok) = mock_run(11, 8, |ctx, range| {
+            let num = ctx.load_witness(Fr::from(q / 2));
+            let rounded = round_div(ctx, range, num, q, 22, RoundMode::HalfEven);
+            let one = ctx.load_constant(Fr::from(1));
+            ctx.constrain_equal(&rounded, &one);
+        });
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_scale_and_round_modes_at_half() {
+        // with t = 2 and q = 16, t * x / q is half-way for x = 4, -4 and 12
+        let (t, q) = (2, 16);
+        let xs = [4, -4, 12, 5];
+        let expected = [[1, 0, 0], [0, 0, 15], [2, 2, 1], [1, 1, 0]];
+        let (rounded, ok) = mock_run(11, 8, |ctx, range| {
+            xs.map(|x| {
+                let x =
+                    ctx.load_witness(if x < 0 { -Fr::from(-x as u64) } else { Fr::from(x as u64) });
+                MODES.map(|mode| {
+                    scale_and_round(ctx, range, x, t, q, 12, mode).value().get_lower_64()
+                })
+            })
+        });
+        assert!(ok);
+        assert_eq!(rounded, expected);
+    }
+}
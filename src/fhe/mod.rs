@@ -0,0 +1,11 @@
+//! This module contains a toy implementation of the BFV fully homomorphic encryption scheme, together with circuits proving statements about it.
+//!
+//! The `bfv` submodule is a host-side (out of circuit) reference implementation of the scheme, used to generate inputs and to test the circuits.
+//! The `gadgets` submodule holds the in-circuit counterparts of the homomorphic operations, written with the `halo2-lib` API.
+//! The `builder` submodule, behind the `builder` feature, chains the gadgets into composite circuits such as encrypt, then add, then decrypt.
+//! None of this is meant to be secure: the parameters are chosen to keep the circuits small.
+
+pub mod bfv;
+#[cfg(feature = "builder")]
+pub mod builder;
+pub mod gadgets;
@@ -1,5 +1,20 @@
 #![feature(return_position_impl_trait_in_trait)]
+// `CircuitError` implements `core::error::Error`, so that `error` also builds without `std`
+#![feature(error_in_core)]
 #![allow(incomplete_features)]
 
+// `poly::math` and `error` only use `alloc`, so that they can also be compiled in `no_std` crates
+extern crate alloc;
+
 pub mod circuits;
+pub mod error;
+pub mod fhe;
+// reading inputs and parameters from files, JSON and the environment, and logging, need `std`
+#[cfg(feature = "std")]
+pub mod params;
+pub mod poly;
+#[cfg(feature = "std")]
 pub mod scaffold;
+
+#[cfg(test)]
+mod test_utils;
@@ -0,0 +1,461 @@
+//! Helpers to read and validate the circuit configuration.
+use std::env::{set_var, var};
+use std::ops::RangeInclusive;
+
+use halo2_base::utils::ScalarField;
+use halo2_base::AssignedValue;
+use serde::de::DeserializeOwned;
+
+use crate::error::CircuitError;
+use crate::fhe::bfv::BfvParams;
+use crate::poly::budget::Budgeted;
+use crate::poly::utils::{bit_length, decode_signed, pow_mod};
+use crate::scaffold::cmd::Cli;
+use crate::scaffold::peek_inputs;
+
+/// Reads the number of lookup bits from the `LOOKUP_BITS` environment variable.
+///
+/// The lookup bits must agree with the size of the lookup table created by the circuit builder, which is why they are passed through the environment.
+pub fn lookup_bits_from_env() -> Result<usize, CircuitError> {
+    let value = var("LOOKUP_BITS").map_err(|_| CircuitError::LookupBitsUnset)?;
+    value.parse().map_err(|_| CircuitError::InvalidLookupBits(value))
+}
+
+/// The number of lookup bits used by `lookup_bits` when `LOOKUP_BITS` is not set.
+pub const DEFAULT_LOOKUP_BITS: usize = 8;
+
+/// Returns the number of lookup bits to use for range checks, as read from the `LOOKUP_BITS` environment variable.
+///
+/// If `LOOKUP_BITS` is not set (the scaffold sets it to `0` in that case), we log a warning explaining how to set it and fall back to `DEFAULT_LOOKUP_BITS`.
+/// The default is also written back to the environment, so that the lookup table created by the circuit builder has the matching size, which requires a circuit degree `k` larger than `DEFAULT_LOOKUP_BITS`.
+///
+/// # Panics
+/// If `LOOKUP_BITS` is set but is not a number.
+pub fn lookup_bits() -> usize {
+    match lookup_bits_from_env() {
+        Ok(lookup_bits) if lookup_bits > 0 => lookup_bits,
+        Ok(_) | Err(CircuitError::LookupBitsUnset) => {
+            log::warn!(
+                "{}, falling back to LOOKUP_BITS={DEFAULT_LOOKUP_BITS}",
+                CircuitError::LookupBitsUnset
+            );
+            set_var("LOOKUP_BITS", DEFAULT_LOOKUP_BITS.to_string());
+            DEFAULT_LOOKUP_BITS
+        }
+        Err(e) => panic!("{e}"),
+    }
+}
+
+/// Enables or disables `log_coeffs` through the `DEBUG_COEFFS` environment variable. The scaffold calls this with the `--debug-coeffs` flag before building the circuit.
+pub fn set_debug_coeffs(enabled: bool) {
+    set_var("DEBUG_COEFFS", if enabled { "1" } else { "0" });
+}
+
+/// Returns whether `--debug-coeffs` was passed, as read from the `DEBUG_COEFFS` environment variable.
+pub fn debug_coeffs() -> bool {
+    matches!(var("DEBUG_COEFFS").as_deref(), Ok("1"))
+}
+
+/// Sets the row budget returned by `max_rows` through the `MAX_ROWS` environment variable. The scaffold calls this with the `--max-rows` argument before building the circuit.
+pub fn set_max_rows(max_rows: Option<usize>) {
+    set_var("MAX_ROWS", max_rows.map_or_else(String::new, |rows| rows.to_string()));
+}
+
+/// Sets the `N` returned by `circuit_n` through the `CIRCUIT_N` environment variable. The scaffold calls this with the `--n` argument before building the circuit.
+pub fn set_circuit_n(n: Option<usize>) {
+    set_var("CIRCUIT_N", n.map_or_else(String::new, |n| n.to_string()));
+}
+
+/// Returns the `N` given with `--n`, as read from the `CIRCUIT_N` environment variable, or `default` when it was not passed.
+///
+/// The examples that only need their polynomials to have consistent lengths call this with the `N` implied by the length of an input polynomial instead of using a constant, so that one binary runs at every size.
+/// With `--n`, the size no longer depends on the input: the inputs are checked against it, which fails with a `WrongDegree` error for an input of another size.
+pub fn circuit_n(default: usize) -> usize {
+    var("CIRCUIT_N").ok().and_then(|n| n.parse().ok()).unwrap_or(default)
+}
+
+/// Same as `circuit_n` for the ring dimension `N` of `Z_q[x]/(x^N + 1)`, which defaults to `len`, the number of coefficients of an input polynomial.
+/// Returns an `EmptyInput` error when `N` is zero, so that the callers can check their inputs against the degree `N - 1`.
+pub fn ring_n(len: usize) -> Result<usize, CircuitError> {
+    match circuit_n(len) {
+        0 => Err(CircuitError::EmptyInput),
+        n => Ok(n),
+    }
+}
+
+/// Returns the ring dimension `N` of a circuit before it is built, e.g. to pass it to `apply_auto_params`: the `N` given with `--n`, or `len` of the input of `cli`, read ahead with `scaffold::peek_inputs`.
+/// As `ring_n`, returns an `EmptyInput` error when `N` is zero.
+pub fn input_ring_n<T: DeserializeOwned>(
+    cli: &mut Cli,
+    len: impl FnOnce(&T) -> usize,
+) -> Result<usize, CircuitError> {
+    let n = match cli.n {
+        Some(n) => n,
+        None => len(&peek_inputs(cli)),
+    };
+    match n {
+        0 => Err(CircuitError::EmptyInput),
+        n => Ok(n),
+    }
+}
+
+/// Returns the row budget given with `--max-rows`, as read from the `MAX_ROWS` environment variable, or `None` when there is no budget.
+pub fn max_rows() -> Option<usize> {
+    var("MAX_ROWS").ok().and_then(|rows| rows.parse().ok())
+}
+
+impl<'a, C> Budgeted<'a, C> {
+    /// Wraps `chip` with the budget given with `--max-rows`, see `max_rows`.
+    pub fn from_env(chip: &'a C) -> Self {
+        Self::new(chip, max_rows())
+    }
+}
+
+/// Logs the witness values of an intermediate coefficient vector (e.g. a quotient, a remainder or a product), decoded into signed integers with `decode_signed`.
+///
+/// The values are logged at the debug level with the target `coeffs`, so they are shown with `RUST_LOG=coeffs=debug`. Values that do not encode a small signed integer are shown as `?`.
+/// This only reads the witnesses and does not add any constraint, and it returns immediately unless `--debug-coeffs` was passed.
+pub fn log_coeffs<F: ScalarField>(label: &str, coeffs: &[AssignedValue<F>]) {
+    if !debug_coeffs() || !log::log_enabled!(target: "coeffs", log::Level::Debug) {
+        return;
+    }
+    let values = coeffs
+        .iter()
+        .map(|x| decode_signed(x.value()).map_or_else(|| "?".to_string(), |x| x.to_string()))
+        .collect::<Vec<_>>();
+    log::debug!(target: "coeffs", "{label}: [{}]", values.join(", "));
+}
+
+/// Recommends a circuit degree `k` and a number of lookup bits for circuits over `R_q = Z_q[x]/(x^n + 1)`, returned as `(k, lookup_bits)`.
+///
+/// The lookup bits are chosen so that any value in `[0, q)` is range checked with at most two lookups when `q` has at most 32 bits. They are kept between 8 and 16, so that the lookup table is neither wasted on small moduli nor too large for big ones.
+/// The degree is chosen so that the circuits in this crate (a few polynomial products, plus a few range checks per coefficient) fit in a single advice column, and is always larger than the lookup bits so that the lookup table fits.
+pub fn recommended_params(q: u64, n: usize) -> (usize, usize) {
+    let lookup_bits = ((bit_length(q as u128) + 1) / 2).clamp(8, 16);
+    let k = bit_length(estimated_cells(q, n) as u128).max(lookup_bits + 1);
+    (k, lookup_bits)
+}
+
+/// Estimates the number of advice cells of the circuits in this crate over `R_q = Z_q[x]/(x^n + 1)`, which `recommended_params` fits in a single advice column.
+pub fn estimated_cells(q: u64, n: usize) -> usize {
+    // rough number of cells: about 4 polynomial products with 3 cells per term, and the range checks of the
+    // intermediate values of every coefficient, which grow with the number of bits of q
+    12 * n * n + 64 * n * bit_length(q as u128)
+}
+
+/// Returns the smallest circuit degree `k = ceil(log2(cells + minimum_rows))` whose `2^k` rows hold `cells` advice cells in a single column next to the `minimum_rows` rows reserved for the blinding factors,
+/// and which is larger than `lookup_bits` so that the lookup table fits. The scaffold uses it when no degree is given, with the advice cells counted by a first run of the circuit.
+pub fn degree_for_cells(cells: usize, minimum_rows: usize, lookup_bits: usize) -> usize {
+    bit_length((cells + minimum_rows).saturating_sub(1) as u128).max(lookup_bits + 1)
+}
+
+// the number of bits of the product of the factors, or an upper bound on it when the product does not fit in a u128
+fn product_bits(factors: &[u128]) -> usize {
+    match factors.iter().try_fold(1u128, |acc, x| acc.checked_mul(*x)) {
+        Some(product) => bit_length(product),
+        None => factors.iter().map(|x| bit_length(*x)).sum(),
+    }
+}
+
+/// Checks that the BFV circuits of this crate are correct over the scalar field `F` for the ring dimension `n`, the moduli `q` and `t`, and errors in `[-b, b]`, and returns the parameters:
+/// - `n` is a power of two, so that `x^n + 1` is cyclotomic, and `t` is in `[2, q)` (see `BfvParams::new`)
+/// - the error distribution fits in `Z_q`, i.e. `2b + 1 <= q`
+/// - the sums of products of a polynomial product of coefficients in `[0, q)` fit in the field, i.e. `(n + 1) * (q - 1)^2 < 2^(CAPACITY - 1)`, as `poly_mul_mod_assigned` requires
+/// - the tensor product of two ciphertexts scaled by `t` in `bfv_tensor`, whose coefficients are bounded by `t * 2n * (q/2)^2`, fits in the field and in the `u128` arithmetic of `scale_and_round`
+///
+/// Returns an `InvalidRingDegree`, `InvalidPlaintextModulus`, `OutOfRange` or `FieldOverflow` error for the first condition that does not hold.
+pub fn check_circuit_params<F: ScalarField>(
+    n: usize,
+    q: u64,
+    t: u64,
+    b: u64,
+) -> Result<BfvParams, CircuitError> {
+    if !n.is_power_of_two() {
+        return Err(CircuitError::InvalidRingDegree { n });
+    }
+    let params = BfvParams::new(n, q, t)?;
+    if b > (q - 1) / 2 {
+        return Err(CircuitError::OutOfRange { value: b, bound: (q - 1) / 2 + 1 });
+    }
+    let capacity = F::CAPACITY as usize - 1;
+    let num_bits = product_bits(&[n as u128 + 1, q as u128 - 1, q as u128 - 1]);
+    if num_bits > capacity {
+        return Err(CircuitError::FieldOverflow { num_bits, capacity });
+    }
+    // `scale_and_round` shifts the scaled coefficients by up to twice their bound, plus q^2
+    let half_q = q as u128 / 2;
+    let num_bits = product_bits(&[t as u128, 2 * n as u128, half_q, half_q]) + 2;
+    if num_bits > capacity.min(127) {
+        return Err(CircuitError::FieldOverflow { num_bits, capacity: capacity.min(127) });
+    }
+    Ok(params)
+}
+
+/// Returns the noise budget in bits of a fresh encryption with errors in `[-b, b]` and a ternary secret and ephemeral key, or `None` when its noise can already exceed the decryption bound.
+///
+/// The noise of a fresh encryption is bounded by `(2n + 1) * b`, and the BFV examples accept a noise of at most `q / (2t) - t` on decryption.
+/// The budget is the number of bits by which the noise can still grow, i.e. `floor(log2(bound / noise))`.
+pub fn fresh_noise_budget(params: &BfvParams, b: u64) -> Option<usize> {
+    let noise = (2 * params.n as u128 + 1) * b.max(1) as u128;
+    let bound = (params.q / (2 * params.t)).checked_sub(params.t)? as u128;
+    (bound >= noise).then(|| bit_length(bound / noise) - 1)
+}
+
+/// A parameter set reported viable by `sweep_params`, with its noise budget and the recommended circuit parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParamCandidate {
+    pub params: BfvParams,
+    /// noise budget of a fresh encryption in bits, see `fresh_noise_budget`
+    pub noise_budget: usize,
+    /// circuit degree and lookup bits, see `recommended_params`
+    pub k: usize,
+    pub lookup_bits: usize,
+    /// estimated number of advice cells, see `estimated_cells`
+    pub cells: usize,
+}
+
+// deterministic Miller-Rabin test, the bases are enough for any u64
+fn is_prime(p: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if p < 2 {
+        return false;
+    }
+    if let Some(base) = BASES.iter().find(|base| p % **base == 0) {
+        return p == *base;
+    }
+    let s = (p - 1).trailing_zeros();
+    let d = (p - 1) >> s;
+    BASES.iter().all(|base| {
+        let mut x = pow_mod(*base, d, p);
+        if x == 1 || x == p - 1 {
+            return true;
+        }
+        (1..s).any(|_| {
+            x = (x as u128 * x as u128 % p as u128) as u64;
+            x == p - 1
+        })
+    })
+}
+
+/// Returns the largest prime `q < 2^bits` with `q = 1 mod 2n`, for which the negacyclic NTT of size `n` exists (see `poly::ntt`), or `None` if there is none.
+pub fn ntt_prime(bits: usize, n: usize) -> Option<u64> {
+    let step = 2 * n as u128;
+    let max = (1u128 << bits.min(64)) - 1;
+    if n == 0 || max <= step {
+        return None;
+    }
+    let mut q = max - (max - 1) % step;
+    while q > step {
+        if is_prime(q as u64) {
+            return Some(q as u64);
+        }
+        q -= step;
+    }
+    None
+}
+
+/// Enumerates candidate parameter sets for the plaintext modulus `t` and the error bound `b`, and returns the viable ones.
+///
+/// For every ring dimension `n` in `degrees` and every bit size in `q_bits`, the candidate modulus is the NTT-friendly prime `ntt_prime(bits, n)`.
+/// A candidate is viable when it passes `check_circuit_params` over `F` and leaves a fresh noise budget of at least `min_budget` bits.
+pub fn sweep_params<F: ScalarField>(
+    t: u64,
+    b: u64,
+    min_budget: usize,
+    degrees: impl IntoIterator<Item = usize>,
+    q_bits: RangeInclusive<usize>,
+) -> Vec<ParamCandidate> {
+    let mut viable = vec![];
+    for n in degrees {
+        for bits in q_bits.clone() {
+            let params = match ntt_prime(bits, n).map(|q| check_circuit_params::<F>(n, q, t, b)) {
+                Some(Ok(params)) => params,
+                _ => continue,
+            };
+            let noise_budget = match fresh_noise_budget(&params, b) {
+                Some(budget) if budget >= min_budget => budget,
+                _ => continue,
+            };
+            let (k, lookup_bits) = recommended_params(params.q, n);
+            let cells = estimated_cells(params.q, n);
+            viable.push(ParamCandidate { params, noise_budget, k, lookup_bits, cells });
+        }
+    }
+    viable
+}
+
+/// If `--auto-params` was passed on the command line, sets the circuit degree and the `LOOKUP_BITS` environment variable to `recommended_params(q, n)`.
+pub fn apply_auto_params(cli: &mut Cli, q: u64, n: usize) {
+    if cli.auto_params {
+        let (k, lookup_bits) = recommended_params(q, n);
+        cli.degree = Some(k as u32);
+        set_var("LOOKUP_BITS", lookup_bits.to_string());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env::{remove_var, set_var};
+
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_base::utils::ScalarField;
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_json::Value;
+
+    use super::{
+        check_circuit_params, debug_coeffs, degree_for_cells, fresh_noise_budget, input_ring_n,
+        lookup_bits, lookup_bits_from_env, max_rows, ntt_prime, recommended_params,
+        set_debug_coeffs, set_max_rows, sweep_params, DEFAULT_LOOKUP_BITS,
+    };
+    use crate::error::CircuitError;
+    use crate::fhe::bfv::{decrypt, encrypt, keygen, BfvParams};
+    use crate::fhe::gadgets::bfv_decrypt;
+    use crate::poly::operations::load_poly;
+    use crate::poly::utils::bit_length;
+    use crate::scaffold::cmd::Cli;
+    use crate::test_utils::{mock_run, MOCK_LOCK};
+
+    #[test]
+    fn test_lookup_bits_from_env() {
+        let _guard = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_var("LOOKUP_BITS", "8");
+        assert_eq!(lookup_bits_from_env(), Ok(8));
+        set_var("LOOKUP_BITS", "eight");
+        assert_eq!(lookup_bits_from_env(), Err(CircuitError::InvalidLookupBits("eight".into())));
+        remove_var("LOOKUP_BITS");
+        assert_eq!(lookup_bits_from_env(), Err(CircuitError::LookupBitsUnset));
+    }
+
+    #[test]
+    fn test_lookup_bits_default() {
+        let _guard = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_var("LOOKUP_BITS", "10");
+        assert_eq!(lookup_bits(), 10);
+
+        // the scaffold sets LOOKUP_BITS to 0 when it is not set
+        for unset in [Some("0"), None] {
+            match unset {
+                Some(value) => set_var("LOOKUP_BITS", value),
+                None => remove_var("LOOKUP_BITS"),
+            }
+            assert_eq!(lookup_bits(), DEFAULT_LOOKUP_BITS);
+            assert_eq!(lookup_bits_from_env(), Ok(DEFAULT_LOOKUP_BITS));
+        }
+        remove_var("LOOKUP_BITS");
+
+        // the message tells the user how to fix it
+        let message = CircuitError::LookupBitsUnset.to_string();
+        assert!(message.contains("LOOKUP_BITS=<bits>") && message.contains("--auto-params"));
+    }
+
+    #[test]
+    fn test_debug_coeffs_flag() {
+        set_debug_coeffs(true);
+        assert!(debug_coeffs());
+        set_debug_coeffs(false);
+        assert!(!debug_coeffs());
+    }
+
+    #[test]
+    fn test_max_rows_flag() {
+        let _guard = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_max_rows(Some(1000));
+        assert_eq!(max_rows(), Some(1000));
+        set_max_rows(None);
+        assert_eq!(max_rows(), None);
+    }
+
+    #[test]
+    fn test_input_ring_n() {
+        let cli = |args: &[&str]| {
+            let args = ["example", "--name", "example"].iter().chain(args).chain(&["mock"]);
+            Cli::parse_from_with_inline_inputs(args.map(|arg| arg.to_string()))
+        };
+        let len = |input: &Value| input["a"].as_array().unwrap().len();
+
+        // N is the length of the input, which is kept for the circuit to read it again
+        let mut args = cli(&["--a", "1,2,3,4"]);
+        assert_eq!(input_ring_n(&mut args, len), Ok(4));
+        assert!(args.peeked_input.is_some());
+
+        // --n takes precedence over the input, which is then not read
+        let mut args = cli(&["--n", "8", "--a", "1,2,3,4"]);
+        assert_eq!(input_ring_n(&mut args, len), Ok(8));
+        assert!(args.peeked_input.is_none());
+        assert_eq!(input_ring_n(&mut cli(&["--n", "0"]), len), Err(CircuitError::EmptyInput));
+    }
+
+    #[test]
+    fn test_recommended_params() {
+        for q in [257, 1048573, 1073741789, (1 << 32) - 5, (1 << 61) - 1] {
+            let (k, lookup_bits) = recommended_params(q, 4);
+            assert!(q >= 1 << 32 || 2 * lookup_bits >= bit_length(q as u128));
+            assert!((8..=16).contains(&lookup_bits));
+            assert!(lookup_bits < k);
+        }
+
+        // the recommended parameters are enough to decrypt a ciphertext
+        let (n, q, t, b) = (4, 1048573, 8, 2);
+        let mut rng = StdRng::seed_from_u64(0);
+        let (s, pk) = keygen(&mut rng, n, q, b);
+        let m = vec![3, 0, 5, 1];
+        let ct = encrypt(&mut rng, &pk, &m, q, t, b);
+        assert_eq!(decrypt(&s, &ct, q, t), m);
+
+        let (k, lookup_bits) = recommended_params(q, n);
+        let (out, ok) = mock_run(k, lookup_bits, |ctx, range| {
+            let sk = load_poly(ctx, &s);
+            let [c0, c1] = [&ct[0], &ct[1]].map(|c| load_poly(ctx, c));
+            let out = bfv_decrypt(ctx, range, &sk, [&c0, &c1], q, t, q / (2 * t) - t).unwrap();
+            out.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        assert_eq!(out, m);
+    }
+
+    #[test]
+    fn test_degree_for_cells() {
+        // 2^10 - 9 cells fit in 2^10 rows, one more cell does not
+        assert_eq!(degree_for_cells(1015, 9, 8), 10);
+        assert_eq!(degree_for_cells(1016, 9, 8), 11);
+        // the lookup table needs more rows than a small circuit
+        assert_eq!(degree_for_cells(0, 9, 8), 9);
+        assert_eq!(degree_for_cells(100, 9, 12), 13);
+    }
+
+    #[test]
+    fn test_sweep_params() {
+        let (t, b) = (8, 2);
+        assert_eq!(ntt_prime(20, 4), Some(1048433));
+        let viable = sweep_params::<Fr>(t, b, 4, [4, 1024], 20..=64);
+
+        // the parameters of the BFV examples, with a fresh noise of at most 9 * 2 below q / 16 - 8
+        let good = viable.iter().find(|c| c.params == BfvParams { n: 4, q: 1048433, t }).unwrap();
+        assert_eq!(good.noise_budget, 11);
+        assert_eq!((good.k, good.lookup_bits), recommended_params(1048433, 4));
+
+        // n = 1024 leaves a budget of only 3 bits with a 20-bit modulus, and a 64-bit modulus overflows the tensor product
+        let q = ntt_prime(20, 1024).unwrap();
+        assert_eq!(fresh_noise_budget(&BfvParams::new(1024, q, t).unwrap(), b), Some(3));
+        let q = ntt_prime(64, 1024).unwrap();
+        assert!(matches!(
+            check_circuit_params::<Fr>(1024, q, t, b),
+            Err(CircuitError::FieldOverflow { capacity: 127, .. })
+        ));
+        let large = viable.iter().filter(|c| c.params.n == 1024);
+        let q_bits = large.map(|c| bit_length(c.params.q as u128)).collect::<Vec<_>>();
+        assert!(q_bits.contains(&30));
+        assert!(q_bits.iter().all(|bits| (21..64).contains(bits)));
+
+        // the other conditions
+        assert_eq!(
+            check_circuit_params::<Fr>(6, q, t, b),
+            Err(CircuitError::InvalidRingDegree { n: 6 })
+        );
+        assert_eq!(
+            check_circuit_params::<Fr>(4, 17, t, 9),
+            Err(CircuitError::OutOfRange { value: 9, bound: 9 })
+        );
+        assert!(check_circuit_params::<Fr>(4, 17, t, 8).is_ok());
+    }
+}
@@ -0,0 +1,110 @@
+//! A row budget around the chips of a circuit, which fails witness generation once the circuit assigns too many cells.
+//!
+//! The budget given with `--max-rows` is read from the environment by `params::max_rows`, which needs `std`; this module does not, so the budgeted operations of `operations` also build without it.
+use halo2_base::utils::ScalarField;
+use halo2_base::Context;
+
+use crate::error::CircuitError;
+
+/// A chip wrapped with a row budget, which counts the advice cells assigned by every step of the circuit built with it and fails as soon as the count crosses the budget.
+///
+/// The estimated number of rows is the number of advice cells in the context, i.e. the rows of a layout with a single advice column.
+/// This lets a circuit with a quadratic cost, such as a polynomial product for a large `n`, fail during witness generation, instead of after configuring the circuit and generating the keys.
+pub struct Budgeted<'a, C> {
+    chip: &'a C,
+    max_rows: Option<usize>,
+}
+
+impl<'a, C> Budgeted<'a, C> {
+    /// Wraps `chip` with a budget of `max_rows` rows, or without a budget for `None`.
+    pub fn new(chip: &'a C, max_rows: Option<usize>) -> Self {
+        Self { chip, max_rows }
+    }
+
+    /// Returns the wrapped chip.
+    pub fn chip(&self) -> &'a C {
+        self.chip
+    }
+
+    /// Returns the same budget around another chip, e.g. the gate of a wrapped range chip.
+    pub fn with_chip<'b, D>(&self, chip: &'b D) -> Budgeted<'b, D> {
+        Budgeted::new(chip, self.max_rows)
+    }
+
+    /// Returns a `RowBudgetExceeded` error if the context holds more advice cells than the budget.
+    pub fn check<F: ScalarField>(&self, ctx: &Context<F>) -> Result<(), CircuitError> {
+        match self.max_rows {
+            Some(budget) if ctx.advice.len() > budget => {
+                Err(CircuitError::RowBudgetExceeded { estimated: ctx.advice.len(), budget })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs one step of the circuit with the wrapped chip, and returns a `RowBudgetExceeded` error if the context holds more advice cells than the budget afterwards.
+    ///
+    /// The budget is only checked once the step returns, so a step with a large cost should be split with `map_rows`.
+    pub fn step<F: ScalarField, R>(
+        &self,
+        ctx: &mut Context<F>,
+        f: impl FnOnce(&mut Context<F>, &C) -> Result<R, CircuitError>,
+    ) -> Result<R, CircuitError> {
+        let out = f(ctx, self.chip)?;
+        self.check(ctx)?;
+        Ok(out)
+    }
+
+    /// Calls `f` with the wrapped chip on every item and collects the outputs, checking the budget after every call.
+    ///
+    /// The iteration stops at the first call that crosses the budget, so that e.g. a polynomial product fails after the row of its output that crosses it (see `operations::poly_mul_budgeted`) rather than after all its rows.
+    pub fn map_rows<F: ScalarField, T, R>(
+        &self,
+        ctx: &mut Context<F>,
+        items: impl IntoIterator<Item = T>,
+        mut f: impl FnMut(&mut Context<F>, &C, T) -> R,
+    ) -> Result<Vec<R>, CircuitError> {
+        items
+            .into_iter()
+            .map(|item| {
+                let out = f(ctx, self.chip, item);
+                self.check(ctx).map(|_| out)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_base::gates::builder::GateThreadBuilder;
+    use halo2_base::gates::GateChip;
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::Budgeted;
+    use crate::error::CircuitError;
+    use crate::poly::operations::{load_poly, poly_mul_budgeted};
+
+    #[test]
+    fn test_row_budget() {
+        // a product of two polynomials of degree 63 takes at least one cell for each of the 64^2 terms
+        let run = |max_rows| {
+            let mut builder = GateThreadBuilder::<Fr>::new(true);
+            let ctx = builder.main(0);
+            let gate = GateChip::default();
+            let budget = Budgeted::new(&gate, max_rows);
+            let a = budget.step(ctx, |ctx, _| Ok(load_poly(ctx, &[1; 64])))?;
+            poly_mul_budgeted(ctx, &budget, &a, &a)
+        };
+        // the product stops at the row that crosses the budget, and a row is an inner product of at most 64 terms
+        let err = run(Some(1000)).unwrap_err();
+        assert!(matches!(
+            err,
+            CircuitError::RowBudgetExceeded { estimated, budget: 1000 } if estimated <= 1000 + 4 * 64
+        ));
+        assert!(err.to_string().starts_with("estimated rows"));
+        assert!(err.to_string().ends_with("exceeds budget 1000"));
+
+        // the 64 loaded coefficients are within a budget of 100 rows, the product crosses it
+        assert!(matches!(run(Some(100)), Err(CircuitError::RowBudgetExceeded { budget: 100, .. })));
+        assert_eq!(run(None).unwrap().len(), 127);
+    }
+}
@@ -0,0 +1,291 @@
+use halo2_base::gates::{GateInstructions, RangeChip, RangeInstructions};
+use halo2_base::utils::ScalarField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+
+use crate::poly::operations::{assert_poly_zero, centered_value};
+
+/// Constrains that every coefficient of the polynomial is in `[0, b]` or in `[q-b, q-1]`, i.e. that it was sampled from the error distribution `chi_error` over `[-b, b]`.
+///
+/// This is the same as checking that the centered representative of each coefficient in `(-q/2, q/2]` has magnitude at most `b`.
+pub fn check_poly_from_distribution_chi_error<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    q: u64,
+    b: u64,
+) {
+    for &coeff in coeffs {
+        // `centered_value` also constrains the coefficient to be in [0, q)
+        let (magnitude, _) = centered_value(ctx, range, coeff, q);
+        // `in_range` is already a boolean, so we can directly enforce it to be true
+        let in_range = range.is_less_than_safe(ctx, magnitude, b + 1);
+        range.gate().assert_is_const(ctx, &in_range, &F::one());
+    }
+}
+
+/// Constrains that every coefficient of the polynomial is in `{0, 1, q-1}`, i.e. that it was sampled from the ternary distribution `chi_key` over `{-1, 0, 1}`.
+///
+/// Since we cannot represent negative numbers in the circuit, the value `-1` is represented as the field element `q - 1`.
+pub fn check_poly_from_distribution_chi_key<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    coeffs: &[AssignedValue<F>],
+    q: u64,
+) {
+    // The constraint that we want to enforce is:
+    // (a - 0) * (a - 1) * (a - (q-1)) = 0
+    let products = coeffs
+        .iter()
+        .map(|&coeff| {
+            // constrain (a - 0)
+            let factor_1 = gate.sub(ctx, coeff, Constant(F::from(0)));
+
+            // constrain (a - 1)
+            let factor_2 = gate.sub(ctx, coeff, Constant(F::from(1)));
+
+            // constrain (a - (q-1))
+            let factor_3 = gate.sub(ctx, coeff, Constant(F::from(q - 1)));
+
+            // constrain (a - 0) * (a - 1)
+            let factor_1_2 = gate.mul(ctx, factor_1, factor_2);
+
+            // constrain (a - 0) * (a - 1) * (a - (q-1))
+            gate.mul(ctx, factor_1_2, factor_3)
+        })
+        .collect::<Vec<_>>();
+
+    // constrain every product to be zero directly, instead of computing `is_zero` and constraining its result
+    assert_poly_zero(ctx, gate, &products);
+}
+
+/// Returns the number of nonzero coefficients of the polynomial (its Hamming weight).
+pub fn hamming_weight<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    coeffs: &[AssignedValue<F>],
+) -> AssignedValue<F> {
+    let nonzero = coeffs
+        .iter()
+        .map(|&x| {
+            let is_zero = gate.is_zero(ctx, x);
+            gate.not(ctx, is_zero)
+        })
+        .collect::<Vec<_>>();
+    gate.sum(ctx, nonzero)
+}
+
+/// Constrains that exactly `h` coefficients of the polynomial are nonzero, as is the case for a sparse secret key of Hamming weight `h`.
+pub fn assert_hamming_weight<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    coeffs: &[AssignedValue<F>],
+    h: usize,
+) {
+    let weight = hamming_weight(ctx, gate, coeffs);
+    gate.assert_is_const(ctx, &weight, &F::from(h as u64));
+}
+
+/// Constrains that the coefficients are sorted in non-decreasing order, with the smallest one at least `0` and the largest one at most `b`.
+///
+/// Instead of bounding every coefficient on its own, only the difference of every coefficient to its predecessor is checked to be non-negative,
+/// with a range check on `lookup_bits` bits, and the bounds are checked at both ends of the vector: the differences telescope, so every coefficient is between the first and the last one.
+/// This takes a single lookup per coefficient instead of the two of `check_less_than_safe`, which makes it cheaper for large `N`, and `b + 1` does not need to be a power of two.
+///
+/// The coefficients of a polynomial are not sorted in general: to bound a polynomial, this must be applied to its coefficients in sorted order,
+/// together with a proof that they are a permutation of the coefficients of the polynomial, which this gadget does not provide.
+/// Coefficients from `chi_error` over `[-b, b]` are first shifted by `b` into `[0, 2b]`.
+pub fn assert_sorted_bounded<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    b: u64,
+) {
+    if coeffs.is_empty() {
+        return;
+    }
+    let (first, last) = (coeffs[0], coeffs[coeffs.len() - 1]);
+    // a range check on exactly `lookup_bits` bits is a single lookup
+    let num_bits = range.lookup_bits();
+    // the smallest coefficient is in [0, 2^num_bits), so that the sum of the differences below cannot wrap around the field
+    range.range_check(ctx, first, num_bits);
+    for pair in coeffs.windows(2) {
+        let diff = range.gate().sub(ctx, pair[1], pair[0]);
+        range.range_check(ctx, diff, num_bits);
+    }
+    range.check_less_than_safe(ctx, last, b + 1);
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_base::gates::RangeInstructions;
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::{
+        assert_hamming_weight, assert_sorted_bounded, check_poly_from_distribution_chi_error,
+        check_poly_from_distribution_chi_key,
+    };
+    use crate::poly::operations::load_poly;
+    use crate::test_utils::mock_run;
+
+    const N: usize = 256;
+    const Q: u64 = 1048573;
+    const H: usize = 64;
+
+    // a ternary polynomial with a nonzero coefficient at every 4th index
+    fn sparse_key() -> Vec<u64> {
+        (0..N)
+            .map(|i| {
+                if i % 4 != 0 {
+                    0
+                } else if i % 8 == 0 {
+                    1
+                } else {
+                    Q - 1
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hamming_weight() {
+        let key = sparse_key();
+        let (_, ok) = mock_run(12, 8, |ctx, range| {
+            let key = load_poly(ctx, &key);
+            assert_hamming_weight(ctx, &range.gate, &key, H);
+        });
+        assert!(ok);
+    }
+
+    #[test]
+    fn test_hamming_weight_wrong() {
+        let mut key = sparse_key();
+        key[1] = 1;
+        let (_, ok) = mock_run(12, 8, |ctx, range| {
+            let key = load_poly(ctx, &key);
+            assert_hamming_weight(ctx, &range.gate, &key, H);
+        });
+        assert!(!ok);
+    }
+
+    // checks that every coefficient of the polynomial is in [-b, b]
+    fn check_chi_error(coeffs: &[u64], b: u64) -> bool {
+        let (_, ok) = mock_run(12, 8, |ctx, range| {
+            let coeffs = load_poly(ctx, coeffs);
+            check_poly_from_distribution_chi_error(ctx, range, &coeffs, Q, b);
+        });
+        ok
+    }
+
+    #[test]
+    fn test_chi_error() {
+        assert!(check_chi_error(&[0, 1, 30, Q - 30, Q - 1], 30));
+    }
+
+    #[test]
+    fn test_chi_error_out_of_range() {
+        // 31 and -31 are just outside of [-30, 30]
+        assert!(!check_chi_error(&[0, 1, 31, Q - 1], 30));
+        assert!(!check_chi_error(&[0, 1, Q - 31, Q - 1], 30));
+        // coefficients must be reduced mod q
+        assert!(!check_chi_error(&[0, 1, Q, Q - 1], 30));
+    }
+
+    // checks that the key is ternary with Hamming weight `h`, as in `examples/check_secret_key.rs`
+    fn check_secret_key(key: &[u64], h: usize) -> bool {
+        let (_, ok) = mock_run(12, 8, |ctx, range| {
+            let key = load_poly(ctx, key);
+            check_poly_from_distribution_chi_key(ctx, &range.gate, &key, Q);
+            assert_hamming_weight(ctx, &range.gate, &key, h);
+        });
+        ok
+    }
+
+    #[test]
+    fn test_chi_key_nonzero_product() {
+        // 2 * (2 - 1) * (2 - (q-1)) is not zero
+        let (_, ok) = mock_run(9, 8, |ctx, range| {
+            let key = load_poly(ctx, &[0, 1, Q - 1, 2]);
+            check_poly_from_distribution_chi_key(ctx, &range.gate, &key, Q);
+        });
+        assert!(!ok);
+    }
+
+    // with the parameters of the `check_poly_from_distribution_chi_key` example (q = 257), a key in {0, 1, q - 1}
+    // is accepted, while a coefficient of 2 is rejected
+    #[test]
+    fn test_chi_key_example_params() {
+        let q = 257;
+        for (key, expected) in
+            [([0, 1, q - 1, 0], true), ([0, 1, q - 1, 2], false), ([2, 2, 2, 2], false)]
+        {
+            let (_, ok) = mock_run(9, 8, |ctx, range| {
+                let key = load_poly(ctx, &key);
+                check_poly_from_distribution_chi_key(ctx, &range.gate, &key, q);
+            });
+            assert_eq!(ok, expected);
+        }
+    }
+
+    #[test]
+    fn test_secret_key() {
+        assert!(check_secret_key(&sparse_key(), H));
+    }
+
+    #[test]
+    fn test_secret_key_wrong_weight() {
+        assert!(!check_secret_key(&sparse_key(), H + 1));
+    }
+
+    #[test]
+    fn test_secret_key_not_ternary() {
+        let mut key = sparse_key();
+        key[0] = 2;
+        assert!(!check_secret_key(&key, H));
+    }
+
+    // checks that the coefficients are sorted and in [0, b]
+    fn check_sorted_bounded(coeffs: &[u64], b: u64) -> bool {
+        let (_, ok) = mock_run(12, 8, |ctx, range| {
+            let coeffs = load_poly(ctx, coeffs);
+            assert_sorted_bounded(ctx, range, &coeffs, b);
+        });
+        ok
+    }
+
+    #[test]
+    fn test_sorted_bounded() {
+        assert!(check_sorted_bounded(&[0, 0, 1, 7, 7, 30], 30));
+        assert!(check_sorted_bounded(&[12], 30));
+        // not sorted
+        assert!(!check_sorted_bounded(&[0, 7, 1, 30], 30));
+        // the largest coefficient is above b
+        assert!(!check_sorted_bounded(&[0, 1, 31], 30));
+        // the smallest coefficient is negative, i.e. above the field modulus minus b, although the last one is in range
+        let minus_one = -Fr::from(1);
+        let (_, ok) = mock_run(12, 8, |ctx, range| {
+            let coeffs = [minus_one, Fr::from(0), Fr::from(1)].map(|c| ctx.load_witness(c));
+            assert_sorted_bounded(ctx, range, &coeffs, 30);
+        });
+        assert!(!ok);
+    }
+
+    // the sorted vector needs fewer lookups than bounding every coefficient with `check_less_than_safe`
+    #[test]
+    fn test_sorted_bounded_cells() {
+        let (b, coeffs) = (30, (0..N as u64).map(|i| i * 30 / N as u64).collect::<Vec<_>>());
+        let ((sorted, per_coeff), ok) = mock_run(12, 8, |ctx, range| {
+            let coeffs = load_poly(ctx, &coeffs);
+            let start = ctx.cells_to_lookup.len();
+            assert_sorted_bounded(ctx, range, &coeffs, b);
+            let sorted = ctx.cells_to_lookup.len() - start;
+
+            let start = ctx.cells_to_lookup.len();
+            for &c in &coeffs {
+                range.check_less_than_safe(ctx, c, b + 1);
+            }
+            (sorted, ctx.cells_to_lookup.len() - start)
+        });
+        assert!(ok);
+        assert!(sorted < per_coeff, "{sorted} >= {per_coeff}");
+    }
+}
@@ -0,0 +1,378 @@
+//! Host-side (out of circuit) arithmetic on integers and polynomials.
+//!
+//! This module only uses `core` and `alloc`, so it can be reused outside of this crate, e.g. by a verifier running in a `no_std` environment.
+//! The `no_std_math` integration test compiles it inside a `#![no_std]` crate.
+use alloc::{vec, vec::Vec};
+
+use crate::error::CircuitError;
+
+/// Returns the number of bits needed to represent `x`.
+pub fn bit_length(x: u128) -> usize {
+    (u128::BITS - x.leading_zeros()) as usize
+}
+
+/// Computes `base^exp mod q` by square-and-multiply.
+pub fn pow_mod(base: u64, exp: u64, q: u64) -> u64 {
+    let (mut base, mut exp, mut acc) = (base as u128 % q as u128, exp, 1u128 % q as u128);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            acc = acc * base % q as u128;
+        }
+        base = base * base % q as u128;
+        exp >>= 1;
+    }
+    acc as u64
+}
+
+/// Computes the inverse of `x` mod the prime `q`, using Fermat's little theorem.
+pub fn inv_mod(x: u64, q: u64) -> u64 {
+    assert_ne!(x % q, 0, "0 has no inverse mod {q}");
+    pow_mod(x, q - 2, q)
+}
+
+/// Computes the quotient and remainder of `x` divided by `q` with Barrett reduction, where `x < 2^num_bits`.
+///
+/// The precomputed factor `mu = floor(2^num_bits / q)` gives an estimate of the quotient that is at most 2 too small, which we then correct.
+/// The product `x * mu` must fit in a `u128`, i.e. `2 * num_bits - bit_length(q) < 128`, otherwise a `FieldOverflow` error is returned, as it is when `x` does not fit in `num_bits` bits.
+/// Returns a `ZeroModulus` error if `q` is `0`.
+pub fn barrett_reduce(x: u128, q: u64, num_bits: usize) -> Result<(u128, u64), CircuitError> {
+    if q == 0 {
+        return Err(CircuitError::ZeroModulus);
+    }
+    let capacity = 127.min((127 + bit_length(q as u128)) / 2);
+    if num_bits > capacity {
+        return Err(CircuitError::FieldOverflow { num_bits, capacity });
+    }
+    if x >> num_bits != 0 {
+        return Err(CircuitError::FieldOverflow { num_bits: bit_length(x), capacity: num_bits });
+    }
+    let mu = (1u128 << num_bits) / q as u128;
+    let mut quot = (x * mu) >> num_bits;
+    let mut rem = x - quot * q as u128;
+    while rem >= q as u128 {
+        rem -= q as u128;
+        quot += 1;
+    }
+    Ok((quot, rem as u64))
+}
+
+/// Returns the number of bits `r` of the Montgomery factor `2^r` used to reduce values below `2^num_bits` mod `q`, the smallest `r >= 1` with `2^num_bits <= q * 2^r`.
+pub fn montgomery_bits(q: u64, num_bits: usize) -> usize {
+    (num_bits + 1).saturating_sub(bit_length(q as u128)).max(1)
+}
+
+/// Computes the Montgomery reduction of `x < q * 2^r_bits` and returns `(m, t)`, where `m = -x * q^(-1) mod 2^r_bits` and `t = (x + m * q) / 2^r_bits`.
+///
+/// `t` is congruent to `x * 2^(-r_bits)` mod `q` and in `[0, 2q)`, so a single conditional subtraction of `q` gives the reduced value.
+/// `x + m * q` must fit in a `u128`, i.e. `r_bits + bit_length(q) < 128`, otherwise a `FieldOverflow` error is returned, as it is when `x` is not below `q * 2^r_bits`.
+/// Returns a `ZeroModulus` error if `q` is `0`, and a `NotInvertible` error if `q` is even, as it has no inverse mod `2^r_bits` then.
+pub fn montgomery_reduce(x: u128, q: u64, r_bits: usize) -> Result<(u128, u128), CircuitError> {
+    if q == 0 {
+        return Err(CircuitError::ZeroModulus);
+    }
+    if q % 2 == 0 {
+        return Err(CircuitError::NotInvertible);
+    }
+    let q_bits = bit_length(q as u128);
+    let capacity = 127 - q_bits;
+    if r_bits > capacity {
+        return Err(CircuitError::FieldOverflow { num_bits: r_bits, capacity });
+    }
+    if x >= (q as u128) << r_bits {
+        return Err(CircuitError::FieldOverflow {
+            num_bits: bit_length(x),
+            capacity: r_bits + q_bits - 1,
+        });
+    }
+    // q^(-1) mod 2^128 by Newton iteration: every step doubles the number of correct low bits, starting from 3 for an odd q
+    let mut inv = q as u128;
+    for _ in 0..6 {
+        inv = inv.wrapping_mul(2u128.wrapping_sub((q as u128).wrapping_mul(inv)));
+    }
+    let m = x.wrapping_mul(inv.wrapping_neg()) & ((1u128 << r_bits) - 1);
+    Ok((m, (x + m * q as u128) >> r_bits))
+}
+
+/// Returns the centered representative of `x` mod `q` in `(-q/2, q/2]`, where `x` is in `[0, q)`.
+pub fn to_centered(x: u64, q: u64) -> i64 {
+    debug_assert!(x < q);
+    if x > q / 2 {
+        x as i64 - q as i64
+    } else {
+        x as i64
+    }
+}
+
+/// Returns the representative of `x` mod `q` in `[0, q)`. This is the inverse of `to_centered`.
+pub fn from_centered(x: i64, q: u64) -> u64 {
+    x.rem_euclid(q as i64) as u64
+}
+
+/// Returns the infinity norm `max_i |c_i|` of a polynomial with coefficients in `[0, q)`, where every `c_i` is taken in its centered representation in `(-q/2, q/2]`.
+pub fn infinity_norm(coeffs: &[u64], q: u64) -> u64 {
+    coeffs.iter().map(|c| to_centered(*c, q).unsigned_abs()).max().unwrap_or(0)
+}
+
+/// Returns the squared Euclidean norm `sum_i c_i^2` of a polynomial with coefficients in `[0, q)`, where every `c_i` is taken in its centered representation in `(-q/2, q/2]`.
+pub fn norm_squared(coeffs: &[u64], q: u64) -> u128 {
+    coeffs.iter().map(|c| (to_centered(*c, q).unsigned_abs() as u128).pow(2)).sum()
+}
+
+/// Multiplies two polynomials given by their little endian coefficients (first element = constant term) over the integers.
+pub fn poly_mul(a: &[i128], b: &[i128]) -> Vec<i128> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut c = vec![0; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            c[i + j] += x * y;
+        }
+    }
+    c
+}
+
+/// Reduces a little endian polynomial modulo `x^n + 1` over the integers, using `x^n = -1`.
+pub fn reduce_mod_cyclo(c: &[i128], n: usize) -> Vec<i128> {
+    let mut out = vec![0; n];
+    for (j, x) in c.iter().enumerate() {
+        if (j / n) % 2 == 0 {
+            out[j % n] += x;
+        } else {
+            out[j % n] -= x;
+        }
+    }
+    out
+}
+
+/// Reduces every coefficient to its representative in `[0, q)`.
+pub fn reduce_mod_q(c: &[i128], q: u64) -> Vec<u64> {
+    c.iter().map(|x| x.rem_euclid(q as i128) as u64).collect()
+}
+
+/// Multiplies two elements of `R_q = Z_q[x]/(x^n + 1)` with coefficients in `[0, q)`, where `n` is the length of `a`.
+pub fn ring_mul(a: &[u64], b: &[u64], q: u64) -> Vec<u64> {
+    let a = a.iter().map(|x| *x as i128).collect::<Vec<_>>();
+    let b = b.iter().map(|x| *x as i128).collect::<Vec<_>>();
+    reduce_mod_q(&reduce_mod_cyclo(&poly_mul(&a, &b), a.len()), q)
+}
+
+/// Adds two elements of `R_q` with coefficients in `[0, q)`.
+pub fn ring_add(a: &[u64], b: &[u64], q: u64) -> Vec<u64> {
+    assert_eq!(a.len(), b.len());
+    a.iter().zip(b.iter()).map(|(x, y)| ((*x as u128 + *y as u128) % q as u128) as u64).collect()
+}
+
+/// Negates an element of `R_q` with coefficients in `[0, q)`, keeping `0` at `0`.
+pub fn ring_neg(a: &[u64], q: u64) -> Vec<u64> {
+    a.iter().map(|x| (q - x) % q).collect()
+}
+
+/// Returns the greatest common divisor of `a` and `b`.
+pub fn gcd(a: u64, b: u64) -> u64 {
+    let (mut a, mut b) = (a, b);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Returns where the automorphism `x -> x^k` of `Z[x]/(x^n + 1)` moves the coefficient of `x^i`, as the index `j` of its image `x^j` and whether it is negated.
+///
+/// `x^(i*k)` is first reduced mod `x^(2n) = 1`, and then `x^n = -1` turns an exponent in `[n, 2n)` into `-x^(i*k mod 2n - n)`.
+pub fn automorphism_index(i: usize, k: usize, n: usize) -> (usize, bool) {
+    let j = (i % (2 * n)) * (k % (2 * n)) % (2 * n);
+    if j < n {
+        (j, false)
+    } else {
+        (j - n, true)
+    }
+}
+
+/// Applies the automorphism `x -> x^k` to an element of `R_q = Z_q[x]/(x^n + 1)` with coefficients in `[0, q)`, where `n` is the length of `a` and `k` is coprime to `2n`.
+pub fn apply_automorphism(a: &[u64], k: usize, q: u64) -> Vec<u64> {
+    let n = a.len();
+    let mut out = vec![0; n];
+    for (i, x) in a.iter().enumerate() {
+        let (j, negate) = automorphism_index(i, k, n);
+        out[j] = if negate { (q - x) % q } else { *x };
+    }
+    out
+}
+
+/// Applies the conjugation `x -> x^(-1) = x^(2n-1)` to an element of `R_q` with coefficients in `[0, q)`, where `n` is the length of `a`.
+///
+/// Since `x^(-i) = -x^(n-i)`, the constant coefficient stays in place and the coefficient of `x^i` moves to `x^(n-i)` negated. This equals `apply_automorphism(a, 2n - 1, q)`.
+pub fn conjugate(a: &[u64], q: u64) -> Vec<u64> {
+    let mut out = a.to_vec();
+    if let Some((_, rest)) = out.split_first_mut() {
+        rest.reverse();
+        rest.iter_mut().for_each(|x| *x = (q - *x) % q);
+    }
+    out
+}
+
+/// Computes the trace `sum_{k in H} a(x^k)` of an element of `R_q` with coefficients in `[0, q)` over the automorphisms `x -> x^k` for `k` in `subgroup`.
+///
+/// The trace is not normalized, so the trace of a constant `c` is `|H| * c`.
+pub fn poly_trace(a: &[u64], subgroup: &[usize], q: u64) -> Vec<u64> {
+    subgroup
+        .iter()
+        .fold(vec![0; a.len()], |acc, &k| ring_add(&acc, &apply_automorphism(a, k, q), q))
+}
+
+/// Computes `x * y mod q` for `x, y` in `[0, q)` with `q < 2^127`, by double-and-add so that no intermediate value overflows a `u128`.
+pub fn mul_mod_wide(x: u128, y: u128, q: u128) -> u128 {
+    let mut acc = 0;
+    for i in (0..bit_length(y)).rev() {
+        acc = (acc << 1) % q;
+        if (y >> i) & 1 == 1 {
+            acc = (acc + x) % q;
+        }
+    }
+    acc
+}
+
+/// Multiplies two elements of `R_q = Z_q[x]/(x^n + 1)` with coefficients in `[0, q)` for a modulus `q < 2^127`, where `n` is the length of `a`.
+pub fn ring_mul_wide(a: &[u128], b: &[u128], q: u128) -> Vec<u128> {
+    let n = a.len();
+    let mut out = vec![0; n];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            let prod = mul_mod_wide(*x, *y, q);
+            // x^(i+j) = -x^(i+j-n) when i + j >= n
+            out[(i + j) % n] = if i + j < n {
+                (out[(i + j) % n] + prod) % q
+            } else {
+                (out[(i + j) % n] + q - prod) % q
+            };
+        }
+    }
+    out
+}
+
+/// Performs long division of `f` by `g` over the integers and returns `(quotient, remainder)`.
+///
+/// Both polynomials are given in big endian order (first element = leading coefficient).
+/// The leading coefficients are trimmed from the outputs, so an empty vector represents the zero polynomial.
+///
+/// The arithmetic is performed on `i64` so that signed coefficients do not overflow in the intermediate steps.
+/// Since we are dividing over the integers, every leading coefficient of the running dividend must be an exact multiple of the leading coefficient of `g` (this always holds when `g` is monic, e.g. the cyclotomic polynomial `x^m + 1`).
+pub fn div_euclid(f: &[i64], g: &[i64]) -> (Vec<i64>, Vec<i64>) {
+    if g.is_empty() || g.iter().all(|&x| x == 0) {
+        panic!("Cannot divide by a zero polynomial!");
+    }
+
+    // strip leading zeros from the divisor so that `g[0]` is its actual leading coefficient
+    let g = &g[g.iter().position(|&x| x != 0).unwrap()..];
+
+    let mut dividend = f.to_vec();
+    let divisor_degree = g.len() - 1;
+    let mut quotient = Vec::new();
+
+    while dividend.len() > divisor_degree {
+        // `%` and `/` on signed integers round toward zero, so we explicitly require an exact division
+        assert_eq!(
+            dividend[0] % g[0],
+            0,
+            "Leading coefficient {} is not divisible by the divisor leading coefficient {}",
+            dividend[0],
+            g[0]
+        );
+        let leading_coefficient_ratio = dividend[0] / g[0];
+        quotient.push(leading_coefficient_ratio);
+
+        for (i, coeff) in g.iter().enumerate() {
+            dividend[i] -= leading_coefficient_ratio * *coeff;
+        }
+
+        dividend.remove(0);
+    }
+
+    // Trim the leading zeros from quotient and remainder
+    while !quotient.is_empty() && quotient[0] == 0 {
+        quotient.remove(0);
+    }
+
+    while !dividend.is_empty() && dividend[0] == 0 {
+        dividend.remove(0);
+    }
+
+    (quotient, dividend)
+}
+
+// removes the zero high degree coefficients of a little endian polynomial, so that an empty vector represents the zero polynomial
+fn trim(mut p: Vec<u64>) -> Vec<u64> {
+    while p.last() == Some(&0) {
+        p.pop();
+    }
+    p
+}
+
+// divides the little endian polynomial `f` by the non-zero trimmed `g` over `Z_q` for a prime `q`, and returns the trimmed `(quotient, remainder)`
+fn div_rem_mod_q(f: &[u64], g: &[u64], q: u64) -> (Vec<u64>, Vec<u64>) {
+    let q = q as u128;
+    let lead_inv = inv_mod(g[g.len() - 1], q as u64) as u128;
+    let mut rem = f.to_vec();
+    let mut quot = vec![0; (f.len() + 1).saturating_sub(g.len())];
+    for i in (0..quot.len()).rev() {
+        let c = rem[i + g.len() - 1] as u128 * lead_inv % q;
+        quot[i] = c as u64;
+        for (j, g_j) in g.iter().enumerate() {
+            rem[i + j] = ((rem[i + j] as u128 + q - c * *g_j as u128 % q) % q) as u64;
+        }
+    }
+    rem.truncate(g.len() - 1);
+    (trim(quot), trim(rem))
+}
+
+// computes `a - b * c` over `Z_q` for little endian polynomials, without any reduction mod a cyclotomic polynomial
+fn sub_mul_mod_q(a: &[u64], b: &[u64], c: &[u64], q: u64) -> Vec<u64> {
+    let q = q as u128;
+    let mut out = a.to_vec();
+    out.resize(a.len().max((b.len() + c.len()).saturating_sub(1)), 0);
+    for (i, b_i) in b.iter().enumerate() {
+        for (j, c_j) in c.iter().enumerate() {
+            out[i + j] = ((out[i + j] as u128 + q - *b_i as u128 * *c_j as u128 % q) % q) as u64;
+        }
+    }
+    trim(out)
+}
+
+/// Computes the inverse of the polynomial `a` (with coefficients in `[0, q)`) in `R_q = Z_q[x]/(x^n + 1)` for a prime `q`, and returns its `n` coefficients in `[0, q)`.
+///
+/// The inverse is found with the extended Euclidean algorithm on `x^n + 1` and `a` over `Z_q`. Returns a `NotInvertible` error when `gcd(a, x^n + 1)` is not a constant, i.e. when `a` has no inverse (which includes `a = 0`).
+/// Returns an `EmptyInput` error if `n` is `0`, a `DegreeTooLarge` error when `a` has more than `n` coefficients and a `ZeroModulus` error if `q` is `0`.
+pub fn poly_inverse_mod(a: &[u64], n: usize, q: u64) -> Result<Vec<u64>, CircuitError> {
+    if n == 0 {
+        return Err(CircuitError::EmptyInput);
+    }
+    if a.len() > n {
+        return Err(CircuitError::DegreeTooLarge { max: n - 1, found: a.len() - 1 });
+    }
+    if q == 0 {
+        return Err(CircuitError::ZeroModulus);
+    }
+    let mut cyclo = vec![0; n + 1];
+    cyclo[0] = 1 % q;
+    cyclo[n] = 1 % q;
+
+    // invariant: t_i * a = r_i mod x^n + 1
+    let (mut r0, mut r1) = (trim(cyclo), trim(a.iter().map(|x| x % q).collect()));
+    let (mut t0, mut t1) = (vec![], vec![1]);
+    while !r1.is_empty() {
+        let (quot, rem) = div_rem_mod_q(&r0, &r1, q);
+        let t2 = sub_mul_mod_q(&t0, &quot, &t1, q);
+        (r0, r1) = (r1, rem);
+        (t0, t1) = (t1, t2);
+    }
+
+    // r0 is the gcd, which must be a non-zero constant
+    if r0.len() != 1 {
+        return Err(CircuitError::NotInvertible);
+    }
+    let scale = inv_mod(r0[0], q) as u128;
+    let mut inv = t0.iter().map(|x| (*x as u128 * scale % q as u128) as u64).collect::<Vec<_>>();
+    inv.resize(n, 0);
+    Ok(inv)
+}
@@ -0,0 +1,19 @@
+//! This module contains the polynomial arithmetic used by the FHE circuits in the `examples` directory.
+//!
+//! The `utils` submodule holds host-side (out of circuit) helpers, which are used both to compute witnesses and as a reference to test the circuits against.
+//! Its pure integer and polynomial arithmetic lives in the `math` submodule, which only depends on `core` and `alloc` so that it also builds in `no_std` environments.
+//! The `operations` submodule holds the in-circuit counterparts, written with the `halo2-lib` API.
+//! The `distribution` submodule holds in-circuit checks that a polynomial was sampled from a given distribution, e.g. for secret keys.
+//! The `ntt` submodule holds the index permutations needed to work with NTT (number theoretic transform) ordered coefficients.
+//! The `ring` submodule holds `CyclotomicRing`, which ties the dimension and the modulus of `R_q = Z_q[x]/(x^n + 1)` to the in-circuit operations of `operations` on its elements.
+//! The `budget` submodule holds `Budgeted`, a row budget around a chip that the budgeted operations of `operations` check as they assign cells.
+//! The `rns` submodule holds the decomposition of polynomials into their RNS (residue number system) representation, and its check together with the NTT of every residue polynomial.
+
+pub mod budget;
+pub mod distribution;
+pub mod math;
+pub mod ntt;
+pub mod operations;
+pub mod ring;
+pub mod rns;
+pub mod utils;
@@ -0,0 +1,586 @@
+use halo2_base::gates::{GateInstructions, RangeChip, RangeInstructions};
+use halo2_base::utils::ScalarField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+
+use crate::error::CircuitError;
+use crate::poly::operations::{assert_reduced, batch_reduce, poly_eval_assigned};
+use crate::poly::utils::{bit_length, check_degree, inv_mod, pow_mod};
+
+/// Reverses the lowest `log_n` bits of `i`.
+pub fn bit_reverse_index(i: usize, log_n: usize) -> usize {
+    if log_n == 0 {
+        return 0;
+    }
+    i.reverse_bits() >> (usize::BITS as usize - log_n)
+}
+
+/// Reorders `2^log_n` assigned coefficients into bit-reversed order, i.e. the output at index `i` is the input at index `bit_reverse_index(i, log_n)`.
+///
+/// This only moves references to already assigned cells around, so no constraints are added and no context is needed.
+/// Use `assert_bit_reverse_permuted` instead when the reordered vector is supplied by the prover.
+pub fn bit_reverse_permute<F: ScalarField>(
+    coeffs: &[AssignedValue<F>],
+    log_n: usize,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    check_degree(coeffs, (1 << log_n) - 1)?;
+    Ok((0..coeffs.len()).map(|i| coeffs[bit_reverse_index(i, log_n)]).collect())
+}
+
+/// Constrains that `claimed` is the bit-reversal permutation of `coeffs`, by adding a copy constraint between every pair of matching cells.
+pub fn assert_bit_reverse_permuted<F: ScalarField>(
+    ctx: &mut Context<F>,
+    coeffs: &[AssignedValue<F>],
+    claimed: &[AssignedValue<F>],
+    log_n: usize,
+) -> Result<(), CircuitError> {
+    check_degree(coeffs, (1 << log_n) - 1)?;
+    check_degree(claimed, (1 << log_n) - 1)?;
+    for (i, c) in claimed.iter().enumerate() {
+        ctx.constrain_equal(c, &coeffs[bit_reverse_index(i, log_n)]);
+    }
+    Ok(())
+}
+
+pub fn primitive_root_of_unity(order: u64, q: u64) -> Result<u64, CircuitError> {
+    if !order.is_power_of_two() || order < 2 {
+        return Err(CircuitError::InvalidRingDegree { n: order as usize / 2 });
+    }
+    if q < 2 || (q - 1) % order != 0 {
+        return Err(CircuitError::NoRootOfUnity { order, q });
+    }
+    // for a power of two order, g is primitive exactly when g^(order/2) = -1
+    (2..q)
+        .map(|g| pow_mod(g, (q - 1) / order, q))
+        .find(|&psi| pow_mod(psi, order / 2, q) == q - 1)
+        .ok_or(CircuitError::NoRootOfUnity { order, q })
+}
+
+/// Returns the `2n` twiddle factors `root^0, ..., root^(2n-1)` mod `q` as field elements, to be loaded as `Constant` cells by the NTT gadgets.
+///
+/// Returns an `InvalidRingDegree` error when `n` is not a power of two, and a `NoRootOfUnity` error when `root` is not a primitive `2n`-th root of unity mod `q`,
+/// i.e. `root^n != -1` mod `q` (see `primitive_root_of_unity`).
+pub fn twiddles<F: ScalarField>(n: usize, root: u64, q: u64) -> Result<Vec<F>, CircuitError> {
+    Ok(twiddle_powers(n, root, q)?.into_iter().map(F::from).collect())
+}
+
+// the powers of `twiddles` as integers in [0, q)
+fn twiddle_powers(n: usize, root: u64, q: u64) -> Result<Vec<u64>, CircuitError> {
+    if !n.is_power_of_two() {
+        return Err(CircuitError::InvalidRingDegree { n });
+    }
+    // for a power of two n, root has order exactly 2n when root^n = -1
+    if q < 2 || pow_mod(root, n as u64, q) != q - 1 {
+        return Err(CircuitError::NoRootOfUnity { order: 2 * n as u64, q });
+    }
+    let mut powers = vec![1; 2 * n];
+    for k in 1..2 * n {
+        powers[k] = (powers[k - 1] as u128 * root as u128 % q as u128) as u64;
+    }
+    Ok(powers)
+}
+
+/// Evaluates a polynomial with coefficients in `[0, q)` at the `n` roots `psi^(2i+1)` of `x^n + 1`, where `psi` is a primitive `2n`-th root of unity mod `q`.
+///
+/// The evaluations are returned in natural order, i.e. the `i`-th output is the evaluation at `psi^(2i+1)`.
+pub fn negacyclic_ntt(coeffs: &[u64], psi: u64, q: u64) -> Vec<u64> {
+    let n = coeffs.len();
+    (0..n)
+        .map(|i| {
+            let root = pow_mod(psi, 2 * i as u64 + 1, q);
+            coeffs.iter().rev().fold(0u128, |acc, c| (acc * root as u128 + *c as u128) % q as u128)
+                as u64
+        })
+        .collect()
+}
+
+/// Interpolates the polynomial whose evaluations at `psi^(2i+1)` are `evals`. This is the inverse of `negacyclic_ntt`.
+pub fn negacyclic_intt(evals: &[u64], psi: u64, q: u64) -> Vec<u64> {
+    let n = evals.len();
+    let n_inv = inv_mod(n as u64, q) as u128;
+    let psi_inv = inv_mod(psi, q);
+    (0..n)
+        .map(|j| {
+            let sum = evals.iter().enumerate().fold(0u128, |acc, (i, e)| {
+                (acc + *e as u128 * pow_mod(psi_inv, (2 * i as u64 + 1) * j as u64, q) as u128)
+                    % q as u128
+            });
+            (sum * n_inv % q as u128) as u64
+        })
+        .collect()
+}
+
+/// Computes `negacyclic_ntt` in the circuit and returns the evaluations in `[0, q)`.
+///
+/// Every evaluation is a single `inner_product` of the coefficients with the constant powers of `psi`, and the evaluations are then reduced mod `q` with `batch_reduce`.
+/// The coefficients are assumed to be in `[0, q)`, which the caller needs to constrain.
+/// Returns a `FieldOverflow` error when the inner products, bounded by `n * (q - 1)^2`, do not fit in the field, and the errors of `twiddles` when `psi` is not a primitive `2n`-th root of unity mod `q`.
+pub fn negacyclic_ntt_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    psi: u64,
+    q: u64,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let n = coeffs.len();
+    let twiddles = twiddles::<F>(n, psi, q)?;
+    // each of the n terms of the inner product is less than q^2
+    let num_bits = bit_length(n as u128 * (q as u128 - 1).pow(2));
+    let evals = (0..n)
+        .map(|i| {
+            // (psi^(2i+1))^j, where psi has order 2n
+            let powers = (0..n).map(|j| Constant(twiddles[(2 * i + 1) * j % (2 * n)]));
+            range.gate().inner_product(ctx, coeffs.iter().copied(), powers)
+        })
+        .collect::<Vec<_>>();
+    batch_reduce(ctx, range, &evals, q, num_bits)
+}
+
+/// Loads the twiddle factors `psi^0, ..., psi^(n-1)` of a negacyclic NTT of size `n` mod `q` as constants and constrains that they form a valid sequence.
+///
+/// We constrain that `psi^0 = 1`, that every twiddle is the previous one times `psi` mod `q`, and that `psi^(n-1) * psi = psi^n = -1` mod `q`.
+/// For a power of two `n` this makes `psi` a primitive `2n`-th root of unity (so also `psi^(2n) = 1`), and rules out any bogus twiddles supplied by the host.
+/// Returns an `InvalidRingDegree` error when `n` is not a power of two of at least 2.
+pub fn load_twiddles<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    twiddles: &[u64],
+    q: u64,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if twiddles.len() < 2 || !twiddles.len().is_power_of_two() {
+        return Err(CircuitError::InvalidRingDegree { n: twiddles.len() });
+    }
+    let twiddles = twiddles.iter().map(|x| ctx.load_constant(F::from(*x))).collect::<Vec<_>>();
+    assert_reduced(ctx, range, &twiddles, q);
+    range.gate().assert_is_const(ctx, &twiddles[0], &F::one());
+
+    let psi = twiddles[1];
+    let num_bits = bit_length((q as u128 - 1).pow(2));
+    for k in 1..twiddles.len() {
+        let prod = range.gate().mul(ctx, twiddles[k - 1], psi);
+        let (_, rem) = range.div_mod(ctx, prod, q, num_bits);
+        ctx.constrain_equal(&rem, &twiddles[k]);
+    }
+    let prod = range.gate().mul(ctx, twiddles[twiddles.len() - 1], psi);
+    let (_, rem) = range.div_mod(ctx, prod, q, num_bits);
+    range.gate().assert_is_const(ctx, &rem, &F::from(q - 1));
+    Ok(twiddles)
+}
+
+/// Computes `negacyclic_ntt` in the circuit with the Cooley-Tukey butterfly network, and returns the evaluations in `[0, q)` in natural order.
+///
+/// The twiddle factors `psi^0, ..., psi^(n-1)` are precomputed, loaded and checked with `load_twiddles`, and every butterfly `(u, v) -> (u + w * v, u - w * v)` is reduced mod `q`.
+/// The reductions of each stage go through `batch_reduce`, first for all the products `w * v`, then for all the sums and differences.
+/// This costs `O(n log n)` reductions, against the `O(n^2)` cells of the inner products of `negacyclic_ntt_assigned`. The butterflies output the evaluations in bit-reversed order,
+/// which are reordered for free with `bit_reverse_permute`.
+/// The number of coefficients must be a power of two, and the coefficients are assumed to be in `[0, q)`, which the caller needs to constrain.
+pub fn negacyclic_ntt_butterfly_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    psi: u64,
+    q: u64,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let n = coeffs.len();
+    if n == 0 {
+        return Err(CircuitError::EmptyInput);
+    }
+    if !n.is_power_of_two() {
+        return Err(CircuitError::WrongDegree {
+            expected: n.next_power_of_two() - 1,
+            found: n - 1,
+        });
+    }
+    if n == 1 {
+        return Ok(coeffs.to_vec());
+    }
+    let log_n = n.trailing_zeros() as usize;
+    let twiddles = load_twiddles(ctx, range, &twiddle_powers(n, psi, q)?[..n], q)?;
+
+    let gate = range.gate();
+    let mul_bits = bit_length((q as u128 - 1).pow(2));
+    let sum_bits = bit_length(2 * q as u128);
+    let mut a = coeffs.to_vec();
+    // at stage m, the i-th block of 2t coefficients is split with the twiddle psi^bitrev(m + i)
+    let (mut m, mut t) = (1, n);
+    while m < n {
+        t /= 2;
+        // the indices j of the upper halves of the butterflies of this stage, with their twiddles
+        let pairs = (0..m)
+            .flat_map(|i| {
+                let w = twiddles[bit_reverse_index(m + i, log_n)];
+                (2 * i * t..(2 * i + 1) * t).map(move |j| (j, w))
+            })
+            .collect::<Vec<_>>();
+        let prods = pairs.iter().map(|(j, w)| gate.mul(ctx, a[j + t], *w)).collect::<Vec<_>>();
+        let vs = batch_reduce(ctx, range, &prods, q, mul_bits)?;
+        // the sums u + v, followed by the differences u + q - v
+        let mut outs = Vec::with_capacity(2 * pairs.len());
+        for ((j, _), v) in pairs.iter().zip(&vs) {
+            outs.push(gate.add(ctx, a[*j], *v));
+        }
+        for ((j, _), v) in pairs.iter().zip(&vs) {
+            let diff = gate.sub(ctx, Constant(F::from(q)), *v);
+            outs.push(gate.add(ctx, a[*j], diff));
+        }
+        let outs = batch_reduce(ctx, range, &outs, q, sum_bits)?;
+        for (k, (j, _)) in pairs.iter().enumerate() {
+            a[*j] = outs[k];
+            a[j + t] = outs[pairs.len() + k];
+        }
+        m *= 2;
+    }
+    bit_reverse_permute(&a, log_n)
+}
+
+/// Multiplies two polynomials with coefficients in `[0, q)` in `R_q = Z_q[x]/(x^n + 1)` through the negacyclic NTT, and returns the `n` coefficients of the product in `[0, q)`.
+///
+/// Both inputs are transformed with `negacyclic_ntt_butterfly_assigned` and multiplied pointwise, and the products are reduced mod `q` with `batch_reduce`. Rather than running the inverse transform in the circuit,
+/// the product is interpolated outside the circuit and loaded as a witness with coefficients in `[0, q)`, and we constrain that its NTT is the pointwise product.
+/// As the NTT is a bijection on `R_q`, this pins down the product.
+/// `psi` must be a primitive `2n`-th root of unity mod `q`, and the coefficients of `a` and `b` are assumed to be in `[0, q)`, which the caller needs to constrain.
+pub fn ntt_mul<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+    psi: u64,
+    q: u64,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if a.len() != b.len() {
+        return Err(CircuitError::LengthMismatch { left: a.len(), right: b.len() });
+    }
+    let a_evals = negacyclic_ntt_butterfly_assigned(ctx, range, a, psi, q)?;
+    let b_evals = negacyclic_ntt_butterfly_assigned(ctx, range, b, psi, q)?;
+    let num_bits = bit_length((q as u128 - 1).pow(2));
+    let prods = a_evals
+        .iter()
+        .zip(b_evals.iter())
+        .map(|(x, y)| range.gate().mul(ctx, *x, *y))
+        .collect::<Vec<_>>();
+    let evals = batch_reduce(ctx, range, &prods, q, num_bits)?;
+
+    let prod = negacyclic_intt(
+        &evals.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>(),
+        psi,
+        q,
+    );
+    let prod = prod.iter().map(|x| ctx.load_witness(F::from(*x))).collect::<Vec<_>>();
+    assert_reduced(ctx, range, &prod, q);
+    let prod_evals = negacyclic_ntt_butterfly_assigned(ctx, range, &prod, psi, q)?;
+    for (x, y) in prod_evals.iter().zip(evals.iter()) {
+        ctx.constrain_equal(x, y);
+    }
+    Ok(prod)
+}
+
+/// Returns the quotients `floor(coeffs(psi^(2i+1)) / q)` of the evaluations of `negacyclic_ntt` before their reduction mod `q`, where the powers of `psi` are reduced mod `q`.
+///
+/// These are the witnesses of `assert_ntt_at_challenge`.
+pub fn ntt_quotients(coeffs: &[u64], psi: u64, q: u64) -> Vec<u64> {
+    let n = coeffs.len();
+    (0..n)
+        .map(|i| {
+            let eval = coeffs.iter().enumerate().fold(0u128, |acc, (j, c)| {
+                acc + *c as u128 * pow_mod(psi, (2 * i as u64 + 1) * j as u64, q) as u128
+            });
+            (eval / q as u128) as u64
+        })
+        .collect()
+}
+
+/// Constrains that `evals` is the negacyclic NTT of `coeffs`, i.e. that `evals[i] = coeffs(psi^(2i+1)) mod q`, by checking all `n` equations at once at the challenge `r` (Schwartz-Zippel).
+///
+/// Each equation is written over the integers as `coeffs(psi^(2i+1)) - q * quotients[i] - evals[i] = 0`, with the quotients computed outside of the circuit by `ntt_quotients`.
+/// The evaluations are range checked to `[0, q)` and the quotients to the bit length of the largest possible quotient, so an equation holds in the field exactly when it holds over the integers.
+/// Instead of constraining every equation, we constrain that the polynomial in `r` with the `n` left-hand sides as coefficients vanishes at `r`, which passes for a wrong `evals`
+/// with probability at most `n / |F|` as long as `r` is sampled after `coeffs`, `evals` and `quotients` are fixed (see `poly_identity_check`).
+///
+/// This replaces the `n` reductions mod `q` of `negacyclic_ntt_assigned` by the range checks of host-computed quotients, but the `n^2` terms of its inner products remain,
+/// so for large `n` the butterfly network of `negacyclic_ntt_butterfly_assigned` is cheaper. The same check verifies the conversion in both directions, from coefficients to evaluations and back.
+/// The coefficients are assumed to be in `[0, q)`, which the caller needs to constrain. Returns a `LengthMismatch` error when the three vectors do not have the same length.
+/// Returns the errors of `twiddles` when `psi` is not a primitive `2n`-th root of unity mod `q`.
+#[allow(clippy::too_many_arguments)]
+pub fn assert_ntt_at_challenge<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    evals: &[AssignedValue<F>],
+    quotients: &[AssignedValue<F>],
+    psi: u64,
+    q: u64,
+    r: AssignedValue<F>,
+) -> Result<(), CircuitError> {
+    let n = coeffs.len();
+    for other in [evals, quotients] {
+        if other.len() != n {
+            return Err(CircuitError::LengthMismatch { left: n, right: other.len() });
+        }
+    }
+    assert_reduced(ctx, range, evals, q);
+    // each of the n terms of an evaluation is less than q^2
+    let num_bits = bit_length(n as u128 * (q as u128 - 1).pow(2) / q as u128);
+    for &quot in quotients {
+        range.range_check(ctx, quot, num_bits);
+    }
+
+    let twiddles = twiddles::<F>(n, psi, q)?;
+    let gate = range.gate();
+    let diffs = (0..n)
+        .map(|i| {
+            // (psi^(2i+1))^j, where psi has order 2n
+            let powers = (0..n).map(|j| Constant(twiddles[(2 * i + 1) * j % (2 * n)]));
+            let eval = gate.inner_product(ctx, coeffs.iter().copied(), powers);
+            let rem = gate.mul_add(ctx, quotients[i], Constant(-F::from(q)), eval);
+            gate.sub(ctx, rem, evals[i])
+        })
+        .collect::<Vec<_>>();
+    let combined = poly_eval_assigned(ctx, gate, &diffs, r)?;
+    gate.assert_is_const(ctx, &combined, &F::zero());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_base::utils::ScalarField;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{
+        assert_bit_reverse_permuted, assert_ntt_at_challenge, bit_reverse_index,
+        bit_reverse_permute, load_twiddles, negacyclic_intt, negacyclic_ntt,
+        negacyclic_ntt_assigned, negacyclic_ntt_butterfly_assigned, ntt_mul, ntt_quotients,
+        primitive_root_of_unity, twiddles,
+    };
+    use crate::error::CircuitError;
+    use crate::poly::operations::{load_poly, poly_mul_assigned, poly_reduce_ring_assigned};
+    use crate::poly::utils::{pow_mod, ring_mul};
+    use crate::test_utils::mock_run;
+
+    const BIT_REVERSED_8: [usize; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+    #[test]
+    fn test_bit_reverse_permute() {
+        assert_eq!((0..8).map(|i| bit_reverse_index(i, 3)).collect::<Vec<_>>(), BIT_REVERSED_8);
+
+        let coeffs = [10, 11, 12, 13, 14, 15, 16, 17];
+        let (out, ok) = mock_run(9, 8, |ctx, _| {
+            let assigned = load_poly(ctx, &coeffs);
+            let permuted = bit_reverse_permute(&assigned, 3).unwrap();
+            let claimed = load_poly(ctx, &BIT_REVERSED_8.map(|i| coeffs[i]));
+            assert_bit_reverse_permuted(ctx, &assigned, &claimed, 3).unwrap();
+            permuted.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        assert_eq!(out, BIT_REVERSED_8.map(|i| coeffs[i]));
+    }
+
+    #[test]
+    fn test_bit_reverse_permute_wrong_degree() {
+        let (err, _) = mock_run(9, 8, |ctx, _| {
+            let coeffs = load_poly(ctx, &[10, 11, 12]);
+            bit_reverse_permute(&coeffs, 2).err()
+        });
+        assert_eq!(err, Some(CircuitError::WrongDegree { expected: 3, found: 2 }));
+    }
+
+    #[test]
+    fn test_bit_reverse_permuted_wrong_order() {
+        let coeffs = [10, 11, 12, 13, 14, 15, 16, 17];
+        let (_, ok) = mock_run(9, 8, |ctx, _| {
+            let coeffs = load_poly(ctx, &coeffs);
+            // the identity ordering is not the bit-reversed one
+            let claimed = load_poly(ctx, &[10, 11, 12, 13, 14, 15, 16, 17]);
+            assert_bit_reverse_permuted(ctx, &coeffs, &claimed, 3).unwrap();
+        });
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_negacyclic_ntt() {
+        let (n, q) = (4, 17);
+        let psi = primitive_root_of_unity(2 * n, q).unwrap();
+        assert_eq!(psi, 2);
+
+        let coeffs = [11, 5, 2, 5];
+        let (evals, ok) = mock_run(10, 8, |ctx, range| {
+            let coeffs = load_poly(ctx, &coeffs);
+            let evals = negacyclic_ntt_assigned(ctx, range, &coeffs, psi, q).unwrap();
+            evals.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        assert_eq!(evals, negacyclic_ntt(&coeffs, psi, q));
+        assert_eq!(evals, vec![1, 2, 3, 4]);
+        assert_eq!(negacyclic_intt(&evals, psi, q), coeffs);
+    }
+
+    #[test]
+    fn test_load_twiddles() {
+        let (_, ok) = mock_run(10, 8, |ctx, range| {
+            load_twiddles(ctx, range, &[1, 2, 4, 8], 17).unwrap();
+        });
+        assert!(ok);
+    }
+
+    #[test]
+    fn test_load_twiddles_invalid_size() {
+        for twiddles in [&[1][..], &[1, 2, 4]] {
+            let (err, _) =
+                mock_run(10, 8, |ctx, range| load_twiddles::<Fr>(ctx, range, twiddles, 17).err());
+            assert_eq!(err, Some(CircuitError::InvalidRingDegree { n: twiddles.len() }));
+        }
+    }
+
+    #[test]
+    fn test_load_twiddles_corrupted() {
+        let (_, ok) = mock_run(10, 8, |ctx, range| {
+            load_twiddles(ctx, range, &[1, 2, 4, 9], 17).unwrap();
+        });
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_load_twiddles_not_primitive() {
+        // 4 is a 4-th root of unity mod 17, so 4^4 = 1 instead of -1
+        let (_, ok) = mock_run(10, 8, |ctx, range| {
+            load_twiddles(ctx, range, &[1, 4, 16, 13], 17).unwrap();
+        });
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_negacyclic_ntt_butterfly() {
+        // known (n, q, psi) triple, see test_negacyclic_ntt
+        let (q, psi) = (17, 2);
+        let coeffs = [11, 5, 2, 5];
+        let (evals, ok) = mock_run(10, 8, |ctx, range| {
+            let coeffs = load_poly(ctx, &coeffs);
+            let evals = negacyclic_ntt_butterfly_assigned(ctx, range, &coeffs, psi, q).unwrap();
+            evals.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        assert_eq!(evals, vec![1, 2, 3, 4]);
+
+        let (q, psi) = (7681, primitive_root_of_unity(16, 7681).unwrap());
+        let coeffs = [4662, 6858, 2298, 6896, 1313, 1240, 651, 2357];
+        let (evals, ok) = mock_run(12, 11, |ctx, range| {
+            let coeffs = load_poly(ctx, &coeffs);
+            let evals = negacyclic_ntt_butterfly_assigned(ctx, range, &coeffs, psi, q).unwrap();
+            evals.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        assert_eq!(evals, negacyclic_ntt(&coeffs, psi, q));
+        assert_eq!(evals, vec![723, 3733, 7651, 773, 277, 684, 953, 7140]);
+    }
+
+    #[test]
+    fn test_negacyclic_ntt_butterfly_corrupted_output() {
+        let (q, psi) = (17, 2);
+        let (_, ok) = mock_run(10, 8, |ctx, range| {
+            let coeffs = load_poly(ctx, &[11, 5, 2, 5]);
+            let evals = negacyclic_ntt_butterfly_assigned(ctx, range, &coeffs, psi, q).unwrap();
+            // the third evaluation should be 3
+            let claimed = load_poly(ctx, &[1, 2, 4, 4]);
+            for (x, y) in evals.iter().zip(claimed.iter()) {
+                ctx.constrain_equal(x, y);
+            }
+        });
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_negacyclic_ntt_butterfly_wrong_degree() {
+        let (err, _) = mock_run(10, 8, |ctx, range| {
+            let coeffs = load_poly(ctx, &[11, 5, 2, 5, 1, 1]);
+            negacyclic_ntt_butterfly_assigned(ctx, range, &coeffs, 2, 17).err()
+        });
+        assert_eq!(err, Some(CircuitError::WrongDegree { expected: 7, found: 5 }));
+    }
+
+    #[test]
+    fn test_twiddles() {
+        for (n, q) in [(4, 17), (8, 7681), (16, 12289)] {
+            let root = primitive_root_of_unity(2 * n as u64, q).unwrap();
+            assert_eq!(pow_mod(root, 2 * n as u64, q), 1);
+            assert_eq!(pow_mod(root, n as u64, q), q - 1);
+
+            let table = twiddles::<Fr>(n, root, q).unwrap();
+            assert_eq!(table.len(), 2 * n);
+            assert_eq!(table[0], Fr::one());
+            assert_eq!(table[n], -Fr::one());
+            for (k, w) in table.iter().enumerate() {
+                assert_eq!(*w, Fr::from(pow_mod(root, k as u64, q)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_twiddles_wrong_order() {
+        // 4 has order 4 mod 17
+        assert_eq!(twiddles::<Fr>(4, 4, 17), Err(CircuitError::NoRootOfUnity { order: 8, q: 17 }));
+        for n in [0, 3] {
+            assert_eq!(twiddles::<Fr>(n, 2, 17), Err(CircuitError::InvalidRingDegree { n }));
+        }
+        // 32 does not divide 17 - 1
+        assert_eq!(
+            primitive_root_of_unity(32, 17),
+            Err(CircuitError::NoRootOfUnity { order: 32, q: 17 })
+        );
+    }
+
+    #[test]
+    fn test_ntt_mul_matches_schoolbook() {
+        let q = 7681;
+        let mut rng = StdRng::seed_from_u64(0);
+        for n in [4, 8, 16] {
+            let psi = primitive_root_of_unity(2 * n as u64, q).unwrap();
+            let a = (0..n).map(|_| rng.gen_range(0..q)).collect::<Vec<_>>();
+            let b = (0..n).map(|_| rng.gen_range(0..q)).collect::<Vec<_>>();
+            let (prod, ok) = mock_run(14, 13, |ctx, range| {
+                let (a, b) = (load_poly(ctx, &a), load_poly(ctx, &b));
+                let prod = ntt_mul(ctx, range, &a, &b, psi, q).unwrap();
+                // the schoolbook product reduced in R_q must be the same
+                let schoolbook = poly_mul_assigned(ctx, &range.gate, &a, &b).unwrap();
+                let max_abs = n as u128 * (q as u128 - 1).pow(2);
+                let schoolbook =
+                    poly_reduce_ring_assigned(ctx, range, &schoolbook, n, q, max_abs).unwrap();
+                for (x, y) in prod.iter().zip(schoolbook.iter()) {
+                    ctx.constrain_equal(x, y);
+                }
+                prod.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+            });
+            assert!(ok);
+            assert_eq!(prod, ring_mul(&a, &b, q));
+        }
+    }
+
+    // checks the conversion between the coefficients and the evaluations at a fixed challenge
+    fn check_ntt_at_challenge(coeffs: &[u64], evals: &[u64], psi: u64, q: u64) -> bool {
+        let quotients = ntt_quotients(coeffs, psi, q);
+        let (_, ok) = mock_run(10, 8, |ctx, range| {
+            let [coeffs, evals, quotients] = [coeffs, evals, &quotients].map(|p| load_poly(ctx, p));
+            let r = ctx.load_witness(Fr::from(0x1234_5678));
+            assert_ntt_at_challenge(ctx, range, &coeffs, &evals, &quotients, psi, q, r).unwrap();
+        });
+        ok
+    }
+
+    #[test]
+    fn test_ntt_at_challenge_round_trip() {
+        let (n, q) = (8, 7681);
+        let psi = primitive_root_of_unity(2 * n as u64, q).unwrap();
+        let coeffs = [4662, 6858, 2298, 6896, 1313, 1240, 651, 2357];
+
+        // coefficient form to evaluation form and back
+        let evals = negacyclic_ntt(&coeffs, psi, q);
+        assert_eq!(negacyclic_intt(&evals, psi, q), coeffs);
+        assert!(check_ntt_at_challenge(&coeffs, &evals, psi, q));
+
+        // evaluations that are off by one, or not reduced mod q, are rejected
+        let mut wrong = evals.clone();
+        wrong[3] = (wrong[3] + 1) % q;
+        assert!(!check_ntt_at_challenge(&coeffs, &wrong, psi, q));
+        let mut unreduced = evals;
+        unreduced[0] += q;
+        assert!(!check_ntt_at_challenge(&coeffs, &unreduced, psi, q));
+    }
+}
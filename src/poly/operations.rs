@@ -0,0 +1,2274 @@
+use std::ops::RangeInclusive;
+
+use halo2_base::gates::{GateInstructions, RangeChip, RangeInstructions};
+use halo2_base::utils::ScalarField;
+use halo2_base::{
+    AssignedValue, Context,
+    QuantumCell::{Constant, Existing},
+};
+
+use crate::error::CircuitError;
+use crate::poly::budget::Budgeted;
+use crate::poly::utils::{
+    automorphism_index, barrett_reduce, bit_length, check_degree, galois_subgroup, gcd,
+    montgomery_bits, montgomery_reduce,
+};
+
+/// Assigns the coefficients of a polynomial to the circuit as private witnesses.
+pub fn load_poly<F: ScalarField>(ctx: &mut Context<F>, coeffs: &[u64]) -> Vec<AssignedValue<F>> {
+    coeffs.iter().map(|x| ctx.load_witness(F::from(*x))).collect()
+}
+
+/// Pads a little endian polynomial with zero high degree coefficients to exactly `len` coefficients, e.g. to match the degree of another polynomial.
+///
+/// The padding uses constant cells rather than fresh witnesses, so a prover cannot fill them with anything else. This is the in-circuit counterpart of `poly::utils::pad_coeffs`.
+/// Returns a `DegreeTooLarge` error when the polynomial already has more than `len` coefficients, see `resize_poly` to truncate it.
+pub fn pad_to_len<F: ScalarField>(
+    ctx: &mut Context<F>,
+    coeffs: &[AssignedValue<F>],
+    len: usize,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if coeffs.len() > len {
+        return Err(CircuitError::DegreeTooLarge {
+            max: len.saturating_sub(1),
+            found: coeffs.len() - 1,
+        });
+    }
+    Ok(resize_poly(ctx, coeffs, len))
+}
+
+/// Resizes a little endian polynomial to exactly `n` coefficients, either by padding the high degree coefficients with constant zeros (see `pad_to_len`) or by dropping them.
+///
+/// When truncating, the caller is responsible for the dropped coefficients being zero (or for reducing them first).
+pub fn resize_poly<F: ScalarField>(
+    ctx: &mut Context<F>,
+    coeffs: &[AssignedValue<F>],
+    n: usize,
+) -> Vec<AssignedValue<F>> {
+    let mut out = coeffs[..n.min(coeffs.len())].to_vec();
+    out.resize_with(n, || ctx.load_constant(F::zero()));
+    out
+}
+
+/// Adds two polynomials of the same length coefficient-wise. No reduction is performed on the output.
+pub fn poly_add_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if a.len() != b.len() {
+        return Err(CircuitError::LengthMismatch { left: a.len(), right: b.len() });
+    }
+    Ok(a.iter().zip(b.iter()).map(|(&a, &b)| gate.add(ctx, a, b)).collect())
+}
+
+/// Multiplies every coefficient of a polynomial by the scalar `k`. No reduction is performed on the output.
+pub fn poly_scalar_mul_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: &[AssignedValue<F>],
+    k: AssignedValue<F>,
+) -> Vec<AssignedValue<F>> {
+    a.iter().map(|&a| gate.mul(ctx, a, k)).collect()
+}
+
+/// Multiplies a polynomial by a public polynomial `b` and returns all `a.len() + b.len() - 1` coefficients of the product.
+///
+/// The coefficients of `b` enter the circuit as `Constant` cells of the inner products, so they are fixed by the circuit rather than assigned as witnesses.
+/// No reduction is performed on the output.
+pub fn poly_mul_constant_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: &[AssignedValue<F>],
+    b: &[u64],
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if a.is_empty() || b.is_empty() {
+        return Err(CircuitError::EmptyInput);
+    }
+    Ok((0..(a.len() + b.len() - 1))
+        .map(|i| {
+            let indices = conv_indices(i, a.len(), b.len());
+            gate.inner_product(
+                ctx,
+                a[indices.clone()].iter().copied(),
+                indices.map(|j| Constant(F::from(b[i - j]))),
+            )
+        })
+        .collect())
+}
+
+/// Multiplies two polynomials given by their little endian coefficients and returns all `a.len() + b.len() - 1` coefficients of the product.
+///
+/// Each output coefficient is the dot product of a slice of `a` with the reversed matching slice of `b`, which we constrain with a single `inner_product` call.
+/// With the `parallel` feature, the witnesses of all the inner products are computed in parallel before being assigned, with the same layout as `inner_product`.
+/// No reduction is performed on the output, so the caller needs to make sure that the coefficients do not overflow the field.
+#[cfg_attr(feature = "parallel", allow(unused_variables))]
+pub fn poly_mul_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if a.is_empty() || b.is_empty() {
+        return Err(CircuitError::EmptyInput);
+    }
+    #[cfg(feature = "parallel")]
+    let prod = poly_mul_parallel(ctx, a, b);
+    #[cfg(not(feature = "parallel"))]
+    let prod = poly_mul_serial(ctx, gate, a, b);
+    Ok(prod)
+}
+
+// the indices j such that a[j] * b[i - j] contributes to the i-th coefficient of the product
+fn conv_indices(i: usize, a_len: usize, b_len: usize) -> RangeInclusive<usize> {
+    i.saturating_sub(b_len - 1)..=i.min(a_len - 1)
+}
+
+// the i-th coefficient of the product, constrained with a single inner product
+fn poly_mul_row<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+    i: usize,
+) -> AssignedValue<F> {
+    let indices = conv_indices(i, a.len(), b.len());
+    gate.inner_product(ctx, a[indices.clone()].iter().copied(), indices.map(|j| Existing(b[i - j])))
+}
+
+#[cfg_attr(feature = "parallel", allow(dead_code))]
+fn poly_mul_serial<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+) -> Vec<AssignedValue<F>> {
+    (0..(a.len() + b.len() - 1)).map(|i| poly_mul_row(ctx, gate, a, b, i)).collect()
+}
+
+/// Multiplies two polynomials as `poly_mul_assigned` does, with the gate wrapped in a row budget (see `budget::Budgeted`) that is checked after every coefficient of the product.
+///
+/// A product too large for the budget fails with a `RowBudgetExceeded` error at the coefficient that crosses it, instead of after all its `a.len() * b.len()` terms.
+/// The coefficients are computed one after the other, with the layout of `poly_mul_assigned` without the `parallel` feature.
+pub fn poly_mul_budgeted<F: ScalarField, G: GateInstructions<F>>(
+    ctx: &mut Context<F>,
+    budget: &Budgeted<G>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if a.is_empty() || b.is_empty() {
+        return Err(CircuitError::EmptyInput);
+    }
+    budget
+        .map_rows(ctx, 0..(a.len() + b.len() - 1), |ctx, gate, i| poly_mul_row(ctx, gate, a, b, i))
+}
+
+// `Context` is not `Send`, so only the running sums of the inner products are computed in parallel.
+// They are then assigned in order, with the same cells and gates as `inner_product`: a zero cell followed by
+// `(a[j], b[i - j], running sum)` for every term, with the gate `a + b * c = d` enabled at the start of every triple
+#[cfg(feature = "parallel")]
+fn poly_mul_parallel<F: ScalarField>(
+    ctx: &mut Context<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+) -> Vec<AssignedValue<F>> {
+    use halo2_base::QuantumCell::Witness;
+    use rayon::prelude::*;
+    use std::iter;
+
+    let sums = (0..(a.len() + b.len() - 1))
+        .into_par_iter()
+        .map(|i| {
+            let mut sum = F::zero();
+            conv_indices(i, a.len(), b.len())
+                .map(|j| {
+                    sum += *a[j].value() * b[i - j].value();
+                    sum
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    sums.into_iter()
+        .enumerate()
+        .map(|(i, sums)| {
+            let num_terms = sums.len();
+            let terms = conv_indices(i, a.len(), b.len())
+                .zip(sums)
+                .flat_map(|(j, sum)| [Existing(a[j]), Existing(b[i - j]), Witness(sum)]);
+            ctx.assign_region_last(
+                iter::once(Constant(F::zero())).chain(terms),
+                (0..num_terms).map(|k| 3 * k as isize),
+            )
+        })
+        .collect()
+}
+
+/// The algorithms available to multiply two assigned polynomials, see `poly_mul_with`.
+///
+/// Counting the advice cells of each method (with `3` cells per term of an inner product and `4` cells per addition or multiplication by a constant):
+/// - `Schoolbook` (`poly_mul_assigned`) uses about `3n^2` cells and is the cheapest below about `36` coefficients.
+/// - `Toom3` (`poly_mul_toom3`) uses about `5/9` of the schoolbook cost plus `~48n` cells of evaluation and interpolation per level, so it wins from about `36` coefficients on (about 30% fewer cells at `n = 128`, 65% at `n = 512`).
+/// - When only the product mod `q` matters and a challenge is available, loading the product as a witness and checking it with `poly_identity_check` needs `O(n)` cells, which beats both for large `n`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MulAlgorithm {
+    Schoolbook,
+    Toom3,
+}
+
+/// Multiplies two polynomials with the given algorithm and returns all `a.len() + b.len() - 1` coefficients of the product.
+pub fn poly_mul_with<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+    algorithm: MulAlgorithm,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    match algorithm {
+        MulAlgorithm::Schoolbook => poly_mul_assigned(ctx, gate, a, b),
+        MulAlgorithm::Toom3 => poly_mul_toom3(ctx, gate, a, b),
+    }
+}
+
+/// The smallest number of coefficients for which a sub-product of `poly_mul_toom3` is split again instead of being computed with `poly_mul_assigned`.
+pub const TOOM3_THRESHOLD: usize = 36;
+
+/// Multiplies two polynomials with the Toom-Cook-3 algorithm and returns all `a.len() + b.len() - 1` coefficients of the product.
+///
+/// Both polynomials are split into three parts of `k = ceil(n/3)` coefficients, evaluated at the points `0, 1, -1, -2, infinity`, multiplied pointwise and interpolated back.
+/// The top level product is always split, and the five sub-products are split again as long as they have at least `TOOM3_THRESHOLD` coefficients.
+/// The interpolation divides by `2` and `3`, which we do by multiplying by their inverses in the field: the results are integers, so this is exact as long as the coefficients do not overflow the field.
+pub fn poly_mul_toom3<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if a.is_empty() || b.is_empty() {
+        return Err(CircuitError::EmptyInput);
+    }
+    let len = a.len() + b.len() - 1;
+    let n = a.len().max(b.len());
+    let (a, b) = (resize_poly(ctx, a, n), resize_poly(ctx, b, n));
+    let mut prod = toom3_split(ctx, gate, &a, &b)?;
+    // the dropped coefficients are products of the zero padding, so they are constrained to be zero
+    prod.truncate(len);
+    Ok(prod)
+}
+
+// one level of Toom-Cook-3 on two polynomials with the same number n of coefficients, returning the 2n - 1 coefficients of the product
+fn toom3_split<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let n = a.len();
+    let k = (n + 2) / 3;
+    let [a, b] = [a, b].map(|p| {
+        let p = resize_poly(ctx, p, 3 * k);
+        toom3_evaluate(ctx, gate, &p, k)
+    });
+
+    let mut r = Vec::with_capacity(5);
+    for (x, y) in a.iter().zip(b.iter()) {
+        r.push(if k >= TOOM3_THRESHOLD {
+            toom3_split(ctx, gate, x, y)?
+        } else {
+            poly_mul_assigned(ctx, gate, x, y)?
+        });
+    }
+
+    // interpolation (Bodrato's sequence) of r(0), r(1), r(-1), r(-2), r(infinity), coefficient-wise
+    let (inv2, inv3) = (F::from(2).invert().unwrap(), F::from(3).invert().unwrap());
+    let mut c = vec![vec![]; 5];
+    for [r0, r1, rm1, rm2, rinf] in (0..(2 * k - 1)).map(|j| [0, 1, 2, 3, 4].map(|i| r[i][j])) {
+        let diff = gate.sub(ctx, rm2, r1);
+        let c3 = gate.mul(ctx, diff, Constant(inv3));
+        let diff = gate.sub(ctx, r1, rm1);
+        let c1 = gate.mul(ctx, diff, Constant(inv2));
+        let c2 = gate.sub(ctx, rm1, r0);
+        // c3 = (c2 - c3) / 2 + 2 * r(infinity)
+        let diff = gate.sub(ctx, c2, c3);
+        let twice_rinf = gate.add(ctx, rinf, rinf);
+        let c3 = gate.mul_add(ctx, diff, Constant(inv2), twice_rinf);
+        // c2 = c2 + c1 - r(infinity)
+        let c2 = gate.add(ctx, c2, c1);
+        let c2 = gate.sub(ctx, c2, rinf);
+        let c1 = gate.sub(ctx, c1, c3);
+        for (i, c_i) in [r0, c1, c2, c3, rinf].into_iter().enumerate() {
+            c[i].push(c_i);
+        }
+    }
+
+    // the product is sum_i c_i * x^(i*k)
+    let mut prod: Vec<Option<AssignedValue<F>>> = vec![None; 6 * k - 1];
+    for (i, c_i) in c.into_iter().enumerate() {
+        for (j, x) in c_i.into_iter().enumerate() {
+            let out = &mut prod[i * k + j];
+            *out = Some(match *out {
+                Some(y) => gate.add(ctx, y, x),
+                None => x,
+            });
+        }
+    }
+    // every coefficient is covered since the parts overlap, and the ones above 2n - 2 are zero
+    Ok(prod.into_iter().take(2 * n - 1).map(Option::unwrap).collect())
+}
+
+// evaluates the polynomial p(y) = p0 + p1 * y + p2 * y^2, where p0, p1, p2 are the three parts of k coefficients of the input,
+// at the points 0, 1, -1, -2, infinity
+fn toom3_evaluate<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    p: &[AssignedValue<F>],
+    k: usize,
+) -> [Vec<AssignedValue<F>>; 5] {
+    let (p0, rest) = p.split_at(k);
+    let (p1, p2) = rest.split_at(k);
+    let mut evals = [vec![], vec![], vec![], vec![], vec![]];
+    for j in 0..k {
+        let sum = gate.add(ctx, p0[j], p2[j]);
+        let at_one = gate.add(ctx, sum, p1[j]);
+        let at_minus_one = gate.sub(ctx, sum, p1[j]);
+        // p0 + 4 * p2 - 2 * p1
+        let at_minus_two = gate.mul_add(ctx, p2[j], Constant(F::from(4)), p0[j]);
+        let at_minus_two = gate.mul_add(ctx, p1[j], Constant(-F::from(2)), at_minus_two);
+        for (i, x) in [p0[j], at_one, at_minus_one, at_minus_two, p2[j]].into_iter().enumerate() {
+            evals[i].push(x);
+        }
+    }
+    evals
+}
+
+/// Multiplies two polynomials with coefficients in `[0, q)` and returns all `a.len() + b.len() - 1` coefficients of the product reduced to `[0, q)`.
+///
+/// Without `reduce_intermediate`, the product is computed with `poly_mul_assigned` and every output coefficient is reduced once at the end, which requires the full sums `min(a.len(), b.len()) * (q-1)^2` to fit in the field.
+/// With `reduce_intermediate`, every output coefficient is accumulated term by term and the running sum is reduced mod `q` whenever adding the next term could overflow the field, so only `q^2` needs to fit. This trades more lookups for correctness at large `n` and `q`.
+/// The reductions use `div_mod`, which range checks its input, so coefficients of `a` or `b` outside of `[0, q)` can make the circuit unsatisfiable but never give a wrong result.
+/// Returns a `FieldOverflow` error when the required sums do not fit in the field.
+pub fn poly_mul_mod_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+    q: u128,
+    reduce_intermediate: bool,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if a.is_empty() || b.is_empty() {
+        return Err(CircuitError::EmptyInput);
+    }
+    let gate = range.gate();
+    let capacity = F::CAPACITY as usize - 1;
+    let q_bits = bit_length(q - 1);
+    let term_bits = 2 * q_bits;
+
+    if !reduce_intermediate {
+        let num_bits = term_bits + bit_length(a.len().min(b.len()) as u128);
+        if num_bits > capacity {
+            return Err(CircuitError::FieldOverflow { num_bits, capacity });
+        }
+        let prod = poly_mul_assigned(ctx, gate, a, b)?;
+        return Ok(prod.into_iter().map(|x| range.div_mod(ctx, x, q, num_bits).1).collect());
+    }
+
+    // a reduced running sum plus one more term must fit in the field
+    if term_bits + 1 > capacity {
+        return Err(CircuitError::FieldOverflow { num_bits: term_bits + 1, capacity });
+    }
+    Ok((0..(a.len() + b.len() - 1))
+        .map(|i| {
+            let mut indices = conv_indices(i, a.len(), b.len());
+            let first = indices.next().unwrap();
+            let (mut sum, mut num_bits) = (gate.mul(ctx, a[first], b[i - first]), term_bits);
+            for j in indices {
+                if num_bits.max(term_bits) + 1 > capacity {
+                    sum = range.div_mod(ctx, sum, q, num_bits).1;
+                    num_bits = q_bits;
+                }
+                sum = gate.mul_add(ctx, a[j], b[i - j], sum);
+                num_bits = num_bits.max(term_bits) + 1;
+            }
+            range.div_mod(ctx, sum, q, num_bits).1
+        })
+        .collect())
+}
+
+/// Multiplies two polynomials whose coefficients are constrained to be in `[0, a_bound]` and `[0, b_bound]` respectively, and returns all `a.len() + b.len() - 1` coefficients of the product together with their bound.
+///
+/// Every output coefficient is a sum of at most `min(a.len(), b.len())` products, so it is in `[0, min(a.len(), b.len()) * a_bound * b_bound]`. Downstream reductions can use `bit_length` of the returned bound as the bit width of their input.
+/// Returns a `FieldOverflow` error when this bound does not fit in the field or in a `u128`.
+pub fn poly_mul_bounded_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+    a_bound: u64,
+    b_bound: u64,
+) -> Result<(Vec<AssignedValue<F>>, u128), CircuitError> {
+    let bound = check_mul_bounds(ctx, range, a, b, a_bound, b_bound)?;
+    Ok((poly_mul_assigned(ctx, range.gate(), a, b)?, bound))
+}
+
+/// Same as `poly_mul_bounded_assigned`, with the product computed by `poly_mul_budgeted` so that it fails with a `RowBudgetExceeded` error as soon as it crosses the budget.
+pub fn poly_mul_bounded_budgeted<F: ScalarField>(
+    ctx: &mut Context<F>,
+    budget: &Budgeted<RangeChip<F>>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+    a_bound: u64,
+    b_bound: u64,
+) -> Result<(Vec<AssignedValue<F>>, u128), CircuitError> {
+    let range = budget.chip();
+    let bound = check_mul_bounds(ctx, range, a, b, a_bound, b_bound)?;
+    budget.check(ctx)?;
+    Ok((poly_mul_budgeted(ctx, &budget.with_chip(range.gate()), a, b)?, bound))
+}
+
+// range checks the coefficients of the factors of `poly_mul_bounded_assigned` and returns the bound on the coefficients of their product
+fn check_mul_bounds<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+    a_bound: u64,
+    b_bound: u64,
+) -> Result<u128, CircuitError> {
+    if a.is_empty() || b.is_empty() {
+        return Err(CircuitError::EmptyInput);
+    }
+    let num_terms = a.len().min(b.len()) as u128;
+    let product_bound = a_bound as u128 * b_bound as u128;
+    // the bound is returned as a u128, so it must also fit in 128 bits
+    let capacity = (F::CAPACITY as usize - 1).min(128);
+    let bound = product_bound.checked_mul(num_terms);
+    let num_bits = match bound {
+        Some(bound) => bit_length(bound),
+        None => bit_length(product_bound) + bit_length(num_terms),
+    };
+    if num_bits > capacity {
+        return Err(CircuitError::FieldOverflow { num_bits, capacity });
+    }
+
+    for (coeffs, bound) in [(a, a_bound), (b, b_bound)] {
+        for x in coeffs {
+            match bound.checked_add(1) {
+                Some(bound) => range.check_less_than_safe(ctx, *x, bound),
+                None => range.range_check(ctx, *x, 64),
+            }
+        }
+    }
+    Ok(bound.unwrap())
+}
+
+/// Evaluates a polynomial given by its little endian coefficients at `x`, using Horner's rule.
+pub fn poly_eval_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    coeffs: &[AssignedValue<F>],
+    x: AssignedValue<F>,
+) -> Result<AssignedValue<F>, CircuitError> {
+    let (last, rest) = coeffs.split_last().ok_or(CircuitError::EmptyInput)?;
+    Ok(rest.iter().rev().fold(*last, |acc, &coeff| gate.mul_add(ctx, acc, x, coeff)))
+}
+
+/// Constrains that two polynomials are equal by evaluating both at the challenge `r` and constraining a single equality (Schwartz-Zippel), instead of constraining every pair of coefficients.
+///
+/// If the polynomials differ, the check passes with probability at most `max(lhs.len(), rhs.len()) / |F|` over the choice of `r`.
+/// This only holds if `r` is sampled after `lhs` and `rhs` are fixed, e.g. derived from a hash of all their coefficients or from the proof transcript.
+/// A prover that can pick `r` can make any two different polynomials pass by choosing one of the roots of `lhs - rhs`.
+pub fn poly_identity_check<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    lhs: &[AssignedValue<F>],
+    rhs: &[AssignedValue<F>],
+    r: AssignedValue<F>,
+) -> Result<(), CircuitError> {
+    let lhs = poly_eval_assigned(ctx, gate, lhs, r)?;
+    let rhs = poly_eval_assigned(ctx, gate, rhs, r)?;
+    ctx.constrain_equal(&lhs, &rhs);
+    Ok(())
+}
+
+/// Constrains that the coefficients of `b` are a permutation of those of `a`, i.e. that both are the same multiset, by checking `prod_i (r - a_i) = prod_i (r - b_i)` at the challenge `r`.
+///
+/// Both products are polynomials in `r` whose roots are the coefficients, so they are equal exactly when the multisets are.
+/// As in `poly_identity_check`, different multisets pass with probability at most `a.len() / |F|`, and only if `r` is sampled after `a` and `b` are fixed, e.g. from a public input or a hash of both vectors.
+/// Returns a `LengthMismatch` error when `a` and `b` have different lengths.
+pub fn assert_permutation<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+    r: AssignedValue<F>,
+) -> Result<(), CircuitError> {
+    if a.len() != b.len() {
+        return Err(CircuitError::LengthMismatch { left: a.len(), right: b.len() });
+    }
+    let [lhs, rhs] = [a, b].map(|coeffs| {
+        coeffs.iter().fold(ctx.load_constant(F::one()), |acc, &c| {
+            let diff = gate.sub(ctx, r, c);
+            gate.mul(ctx, acc, diff)
+        })
+    });
+    ctx.constrain_equal(&lhs, &rhs);
+    Ok(())
+}
+
+/// Constrains that every coefficient of the polynomial is zero.
+pub fn assert_poly_zero<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    coeffs: &[AssignedValue<F>],
+) {
+    for coeff in coeffs {
+        gate.assert_is_const(ctx, coeff, &F::zero());
+    }
+}
+
+/// Returns `1` if every coefficient of the polynomial is zero and `0` otherwise.
+pub fn is_poly_zero<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    coeffs: &[AssignedValue<F>],
+) -> AssignedValue<F> {
+    let is_zero = coeffs.iter().map(|&x| gate.is_zero(ctx, x)).collect::<Vec<_>>();
+    // the polynomial is zero iff all of its coefficients are, i.e. iff the number of zero coefficients is its length
+    let num_zero = gate.sum(ctx, is_zero);
+    gate.is_equal(ctx, num_zero, Constant(F::from(coeffs.len() as u64)))
+}
+
+/// Reduces a polynomial modulo the cyclotomic polynomial `x^n + 1` by folding the coefficients negacyclically, using `x^n = -1`.
+///
+/// The output has exactly `n` coefficients. Since coefficients are subtracted, they may become negative integers (i.e. `p - x` in the field), so the caller is responsible for any further reduction mod `q`.
+pub fn poly_reduce_cyclo_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    coeffs: &[AssignedValue<F>],
+    n: usize,
+) -> Vec<AssignedValue<F>> {
+    let mut out = resize_poly(ctx, coeffs, n);
+    for (j, coeff) in coeffs.iter().enumerate().skip(n) {
+        let i = j % n;
+        out[i] = if (j / n) % 2 == 1 {
+            gate.sub(ctx, out[i], *coeff)
+        } else {
+            gate.add(ctx, out[i], *coeff)
+        };
+    }
+    out
+}
+
+/// Negates every coefficient of a polynomial in `R_q`, i.e. returns `q - c` for every coefficient `c`, and `0` for `c = 0` so that the result stays in `[0, q)`.
+///
+/// The coefficients are constrained to be in `[0, q)` with `assert_reduced`, so the output coefficients are too and no further reduction is needed:
+/// an unreduced coefficient `c >= q` would otherwise become `q - c`, i.e. a field element close to the field modulus.
+/// Use `poly_reduce_cyclo_assigned` instead when the negation can stay a negative integer in the field.
+/// `a - b` in `R_q` is `poly_add_assigned(a, poly_neg(b))` followed by `poly_reduce_mod_assigned`, as the sum is below `2q`.
+pub fn poly_neg<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    q: u64,
+) -> Vec<AssignedValue<F>> {
+    assert_reduced(ctx, range, coeffs, q);
+    let gate = range.gate();
+    coeffs
+        .iter()
+        .map(|&c| {
+            // q - c - q * [c == 0]
+            let diff = gate.sub(ctx, Constant(F::from(q)), c);
+            let is_zero = gate.is_zero(ctx, c);
+            gate.mul_add(ctx, is_zero, Constant(-F::from(q)), diff)
+        })
+        .collect()
+}
+
+/// Applies the automorphism `x -> x^k` to a polynomial of `Z[x]/(x^n + 1)` with `n` coefficients, which is how BFV and CKKS rotate the slots of a plaintext.
+///
+/// The coefficient of `x^i` moves to `x^(i*k mod 2n)` and is negated when the exponent wraps around `x^n = -1` (see `poly::utils::automorphism_index`).
+/// Moving coefficients only rewires cells, so the only constraints are the negations. As in `poly_reduce_cyclo_assigned`, a negated coefficient `c` becomes `p - c` in the field, so the caller is responsible for any further reduction mod `q`.
+/// Returns an `InvalidGaloisElement` error when `k` is not coprime to `2n`.
+pub fn apply_automorphism<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    coeffs: &[AssignedValue<F>],
+    k: usize,
+    n: usize,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if n == 0 {
+        return Err(CircuitError::EmptyInput);
+    }
+    check_degree(coeffs, n - 1)?;
+    if gcd(k as u64, 2 * n as u64) != 1 {
+        return Err(CircuitError::InvalidGaloisElement { k, n });
+    }
+    let mut out = vec![None; n];
+    for (i, c) in coeffs.iter().enumerate() {
+        let (j, negate) = automorphism_index(i, k, n);
+        out[j] = Some(if negate { gate.neg(ctx, *c) } else { *c });
+    }
+    // k is invertible mod 2n, so every index is hit exactly once
+    Ok(out.into_iter().map(Option::unwrap).collect())
+}
+
+/// Applies the conjugation `x -> x^(-1) = x^(2n-1)` to a polynomial of `Z[x]/(x^n + 1)` with `n` coefficients, which is the complex conjugation of the slots of CKKS.
+///
+/// This is `apply_automorphism` with `k = 2n - 1`, which is always coprime to `2n`, on a fast path: since `x^(-i) = -x^(n-i)`, the constant coefficient stays in place
+/// and the coefficients of `x^1, ..., x^(n-1)` are negated in reverse order, without computing the index of every coefficient. As in `apply_automorphism`, the caller is responsible for any further reduction mod `q`.
+pub fn conjugate<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    coeffs: &[AssignedValue<F>],
+    n: usize,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if n == 0 {
+        return Err(CircuitError::EmptyInput);
+    }
+    check_degree(coeffs, n - 1)?;
+    let mut out = Vec::with_capacity(n);
+    out.push(coeffs[0]);
+    out.extend(coeffs[1..].iter().rev().map(|c| gate.neg(ctx, *c)));
+    Ok(out)
+}
+
+/// Computes the trace `sum_{k in H} a(x^k)` of an element of `R_q = Z_q[x]/(x^n + 1)` over the subgroup `H` of automorphisms generated by `generators`, as used by the slot-to-coefficient steps of bootstrapping.
+///
+/// The subgroup is enumerated with `poly::utils::galois_subgroup`, every conjugate `a(x^k)` is computed with `apply_automorphism`, and they are summed with `poly_add_assigned`.
+/// The coefficients of `a` must be in `[0, q)`, so that the coefficients of the sum are at most `|H| * (q - 1)` in absolute value, and the sum is reduced to `R_q` with `poly_reduce_ring_assigned`.
+/// The trace is not normalized: the trace of a constant `c` is `|H| * c`, so the subgroup is returned together with the trace for the caller to divide by `|H|` if needed.
+pub fn poly_trace<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    generators: &[usize],
+    n: usize,
+    q: u64,
+) -> Result<(Vec<usize>, Vec<AssignedValue<F>>), CircuitError> {
+    let subgroup = galois_subgroup(generators, n)?;
+    // the subgroup starts with the identity x -> x, which only rewires the cells
+    let mut sum = apply_automorphism(ctx, range.gate(), coeffs, subgroup[0], n)?;
+    for &k in &subgroup[1..] {
+        let conj = apply_automorphism(ctx, range.gate(), coeffs, k, n)?;
+        sum = poly_add_assigned(ctx, range.gate(), &sum, &conj)?;
+    }
+    let max_abs = subgroup.len() as u128 * (q as u128 - 1);
+    let trace = poly_reduce_ring_assigned(ctx, range, &sum, n, q, max_abs)?;
+    Ok((subgroup, trace))
+}
+
+/// Composes two polynomials and returns the `n` coefficients of `p(q_poly(x))` reduced mod `x^n + 1`.
+///
+/// The composition is computed with Horner's rule over polynomials, `p(q) = (...(p_d * q + p_(d-1)) * q + ...) * q + p_0`, where every product by `q` is computed
+/// with `poly_mul_assigned` and folded back to `n` coefficients with `poly_reduce_cyclo_assigned`, so that this costs `deg(p)` products of `n` coefficients.
+/// As in `poly_reduce_cyclo_assigned`, no reduction mod a modulus is performed and negative coefficients are `p - c` in the field: the coefficients grow like
+/// `(n * max_i |q_i|)^deg(p)`, so the caller is responsible for them staying below the field modulus. Returns an `EmptyInput` error when either polynomial is empty or `n` is zero.
+pub fn poly_compose<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    p: &[AssignedValue<F>],
+    q_poly: &[AssignedValue<F>],
+    n: usize,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let (last, rest) = match p.split_last() {
+        Some(split) if !q_poly.is_empty() && n > 0 => split,
+        _ => return Err(CircuitError::EmptyInput),
+    };
+    let q_poly = poly_reduce_cyclo_assigned(ctx, gate, q_poly, n);
+    let mut acc = resize_poly(ctx, &[*last], n);
+    for &coeff in rest.iter().rev() {
+        let prod = poly_mul_assigned(ctx, gate, &acc, &q_poly)?;
+        acc = poly_reduce_cyclo_assigned(ctx, gate, &prod, n);
+        acc[0] = gate.add(ctx, acc[0], coeff);
+    }
+    Ok(acc)
+}
+
+/// Constrains that `a = b + k * (x^n + 1)` for the witness quotient `k`, i.e. that `a` and `b` are congruent mod the cyclotomic polynomial `x^n + 1`, without reducing either of them.
+///
+/// The polynomials can have any number of coefficients, the missing high degree ones being constrained to be zero.
+/// The identity is checked in the field, so the caller is responsible for the coefficients (in particular those of `k`, which may be negative) being small enough that it also holds over the integers.
+pub fn assert_congruent_mod_cyclo<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+    k: &[AssignedValue<F>],
+    n: usize,
+) {
+    let len = a.len().max(b.len()).max(k.len() + n);
+    let a = resize_poly(ctx, a, len);
+    let mut sum = resize_poly(ctx, b, len);
+    // k * (x^n + 1) adds k_i to the coefficients of both x^i and x^(i+n)
+    for (i, k_i) in k.iter().enumerate() {
+        sum[i] = gate.add(ctx, sum[i], *k_i);
+        sum[i + n] = gate.add(ctx, sum[i + n], *k_i);
+    }
+    for (x, y) in a.iter().zip(sum.iter()) {
+        ctx.constrain_equal(x, y);
+    }
+}
+
+/// Constrains that `a` and `b` are congruent mod the public scalar `t` coefficient by coefficient, i.e. that `a_i - b_i = t * k_i` for witness quotients `k_i`, without reducing either of them.
+///
+/// The coefficients of `a` and `b` must be known to be in `[0, 2^num_bits)`, e.g. reduced mod `q`. The quotients are then in `(-2^num_bits / t, 2^num_bits / t)`, and are range checked after a shift by `2^(num_bits + 1 - bit_length(t))`
+/// so that the identity also holds over the integers: any `a_i - b_i` is a multiple of `t` in the field, so a quotient that is not bounded would prove nothing.
+/// Returns a `LengthMismatch` error for polynomials of different lengths, an `OutOfRange` error for `t < 2` and an `InvalidBitWidth` error when the shifted values could overflow the field.
+pub fn assert_congruent_scalar<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    a: &[AssignedValue<F>],
+    b: &[AssignedValue<F>],
+    t: u64,
+    num_bits: usize,
+) -> Result<(), CircuitError> {
+    if a.len() != b.len() {
+        return Err(CircuitError::LengthMismatch { left: a.len(), right: b.len() });
+    }
+    if t < 2 {
+        return Err(CircuitError::OutOfRange { value: t, bound: 2 });
+    }
+    // both sides of a_i + t * 2^shift_bits = b_i + t * (k_i + 2^shift_bits) are below 2^(num_bits + 3)
+    let max = F::CAPACITY as usize - 3;
+    if num_bits > max {
+        return Err(CircuitError::InvalidBitWidth { num_bits, min: 0, max });
+    }
+    let gate = range.gate();
+    let shift_bits = (num_bits + 1).saturating_sub(bit_length(t as u128));
+    let offset = F::from(t) * gate.pow_of_two()[shift_bits];
+    for (a_i, b_i) in a.iter().zip(b) {
+        // a_i - b_i + t * 2^shift_bits is non-negative, and its quotient by t is k_i + 2^shift_bits when t divides a_i - b_i
+        let shifted = gate.add(ctx, *a_i, Constant(offset));
+        let (quot, _) = long_division(&(*shifted.value() - b_i.value()), t as u128);
+        let quot = ctx.load_witness(quot);
+        range.range_check(ctx, quot, shift_bits + 1);
+        let rhs = gate.mul_add(ctx, quot, Constant(F::from(t)), *b_i);
+        ctx.constrain_equal(&shifted, &rhs);
+    }
+    Ok(())
+}
+
+/// Constrains that every coefficient is fully reduced mod `q`, i.e. in `[0, q)`, so that a prover cannot substitute a coefficient `c` with `c + q`.
+///
+/// When `q` is a power of two this is a single range check of `log2(q)` bits. Otherwise every coefficient is range checked against `bit_length(q)` bits, the smallest bit width that contains `[0, q)`, and then compared to `q`.
+pub fn assert_reduced<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    q: u64,
+) {
+    for x in coeffs {
+        if q > 1 && q.is_power_of_two() {
+            range.range_check(ctx, *x, q.trailing_zeros() as usize);
+        } else {
+            range.check_less_than_safe(ctx, *x, q);
+        }
+    }
+}
+
+/// Constrains that `x` is in `[0, q)` and returns the magnitude and the sign bit of its centered representative in `(-q/2, q/2]`.
+///
+/// The sign bit is `1` exactly when `x > q/2`, in which case the magnitude is `q - x`. Otherwise the magnitude is `x` itself.
+pub fn centered_value<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    q: u64,
+) -> (AssignedValue<F>, AssignedValue<F>) {
+    assert_reduced(ctx, range, &[x], q);
+    let is_low = range.is_less_than_safe(ctx, x, q / 2 + 1);
+    let sign = range.gate().not(ctx, is_low);
+    let neg = range.gate().sub(ctx, Constant(F::from(q)), x);
+    let magnitude = range.gate().select(ctx, neg, x, sign);
+    (magnitude, sign)
+}
+
+/// Constrains that the coefficients are in `[0, q)` and returns the infinity norm `max_i |c_i|` of the polynomial, where every `c_i` is taken in its centered representation in `(-q/2, q/2]`.
+///
+/// The magnitudes are computed with `centered_value` and reduced to their maximum with pairwise comparisons. The norm of an empty polynomial is zero.
+/// Making the norm public lets a verifier check it against a bound, e.g. the noise budget of a ciphertext.
+pub fn infinity_norm_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    q: u64,
+) -> AssignedValue<F> {
+    // the magnitudes are at most q/2
+    let num_bits = bit_length(q as u128 / 2);
+    let magnitudes = coeffs.iter().map(|x| centered_value(ctx, range, *x, q).0).collect::<Vec<_>>();
+    match magnitudes.split_first() {
+        None => ctx.load_zero(),
+        Some((first, rest)) => rest.iter().fold(*first, |max, x| {
+            let is_less = range.is_less_than(ctx, max, *x, num_bits);
+            range.gate().select(ctx, *x, max, is_less)
+        }),
+    }
+}
+
+/// Constrains that the coefficients are in `[0, q)` and that the infinity norm of the polynomial is at most `bound`, where every coefficient is taken in its centered representation in `(-q/2, q/2]`.
+///
+/// Unlike `infinity_norm_assigned` this compares every magnitude to the bound directly, which avoids the selects of the running maximum. The bound is an assigned value so that it can be made public,
+/// and it is constrained to be less than `2^bit_length(q/2 + 1) - 1`, which includes every meaningful bound.
+pub fn assert_inf_norm_bound<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    q: u64,
+    bound: AssignedValue<F>,
+) {
+    // the magnitudes are at most q/2, so bound + 1 fits in the same number of bits as q/2 + 1
+    let num_bits = bit_length(q as u128 / 2 + 1);
+    let bound_plus_one = range.gate().add(ctx, bound, Constant(F::one()));
+    range.range_check(ctx, bound_plus_one, num_bits);
+    for x in coeffs {
+        let (magnitude, _) = centered_value(ctx, range, *x, q);
+        range.check_less_than(ctx, magnitude, bound_plus_one, num_bits);
+    }
+}
+
+/// Constrains that the coefficients are in `[0, q)` and returns the squared Euclidean norm `sum_i c_i^2` of the polynomial, where every `c_i` is taken in its centered representation in `(-q/2, q/2]`.
+///
+/// Squaring the centered magnitudes from `centered_value`, rather than the coefficients in `[0, q)`, keeps small negative coefficients small. The sum is at most `n * (q/2)^2`,
+/// and a `FieldOverflow` error is returned when this bound does not fit in the field.
+pub fn norm_squared_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    q: u64,
+) -> Result<AssignedValue<F>, CircuitError> {
+    let num_bits = bit_length(coeffs.len() as u128) + 2 * bit_length(q as u128 / 2);
+    let capacity = F::CAPACITY as usize - 1;
+    if num_bits > capacity {
+        return Err(CircuitError::FieldOverflow { num_bits, capacity });
+    }
+    let magnitudes = coeffs.iter().map(|x| centered_value(ctx, range, *x, q).0).collect::<Vec<_>>();
+    Ok(range.gate().inner_product(ctx, magnitudes.clone(), magnitudes.into_iter().map(Existing)))
+}
+
+/// Constrains that `x = quot * q + rem` with `rem` in `[0, q)` and returns `(quot, rem)`, where `x` is known to be less than `2^num_bits`.
+///
+/// The quotient and remainder are computed outside the circuit with Barrett reduction (see `poly::utils::barrett_reduce`), so only the final relation needs to be constrained.
+/// Returns the `ZeroModulus` and `FieldOverflow` errors of `barrett_reduce`.
+pub fn barrett_reduce_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    q: u64,
+    num_bits: usize,
+) -> Result<(AssignedValue<F>, AssignedValue<F>), CircuitError> {
+    let limbs = x.value().to_u64_limbs(2, 64);
+    let (quot, rem) = barrett_reduce(limbs[0] as u128 | (limbs[1] as u128) << 64, q, num_bits)?;
+    let quot = ctx.load_witness(F::from_u128(quot));
+    let rem = ctx.load_witness(F::from(rem));
+
+    // quot < 2^num_bits / q <= 2^(num_bits - bit_length(q) + 1), so x = quot * q + rem cannot overflow the field
+    constrain_quotient(ctx, range, x, quot, rem, q as u128, num_bits + 1 - bit_length(q as u128));
+    Ok((quot, rem))
+}
+
+/// Returns `x * 2^(-r) mod q` in `[0, q)` with Montgomery reduction, where `x` is known to be less than `2^num_bits` and `r = montgomery_bits(q, num_bits)`.
+///
+/// The witnesses `m` and `t` are computed outside the circuit with `poly::utils::montgomery_reduce`, and the circuit constrains `x + m * q = t * 2^r` with `m` in `[0, 2^r)`,
+/// then subtracts `q` from `t` when `t >= q` and checks that the result is below `q`. As the output carries the factor `2^(-r)`, it is only the residue of the product of two values
+/// when both of them are kept in Montgomery form, i.e. multiplied by `2^r`.
+/// Returns the `ZeroModulus`, `NotInvertible` and `FieldOverflow` errors of `montgomery_reduce`.
+pub fn montgomery_reduce_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    q: u64,
+    num_bits: usize,
+) -> Result<AssignedValue<F>, CircuitError> {
+    let (q_bits, r_bits) = (bit_length(q as u128), montgomery_bits(q, num_bits));
+    let limbs = x.value().to_u64_limbs(2, 64);
+    let (m, t) = montgomery_reduce(limbs[0] as u128 | (limbs[1] as u128) << 64, q, r_bits)?;
+    let [m, t] = [m, t].map(|v| ctx.load_witness(F::from_u128(v)));
+
+    // both sides are below 2^(r + bit_length(q) + 1) <= 2^128, so the equality cannot wrap around the field
+    range.range_check(ctx, m, r_bits);
+    range.range_check(ctx, t, q_bits + 1);
+    let lhs = range.gate().mul_add(ctx, m, Constant(F::from(q)), x);
+    let rhs = range.gate().mul(ctx, t, Constant(F::from_u128(1 << r_bits)));
+    ctx.constrain_equal(&lhs, &rhs);
+
+    let is_reduced = range.is_less_than(ctx, t, Constant(F::from(q)), q_bits + 1);
+    let sub = range.gate().sub(ctx, t, Constant(F::from(q)));
+    let out = range.gate().select(ctx, t, sub, is_reduced);
+    range.check_less_than_safe(ctx, out, q);
+    Ok(out)
+}
+
+/// Constrains that `x = quot * q + rem` with `rem` in `[0, q)` and `quot` in `[0, 2^quot_bits)`.
+///
+/// `2^quot_bits * q` must not exceed `2^F::CAPACITY`, so that `quot * q + rem` cannot wrap around the field and the decomposition is unique.
+fn constrain_quotient<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    quot: AssignedValue<F>,
+    rem: AssignedValue<F>,
+    q: u128,
+    quot_bits: usize,
+) {
+    let q_bits = bit_length(q);
+    range.range_check(ctx, quot, quot_bits);
+    range.range_check(ctx, rem, q_bits);
+    range.check_less_than(ctx, rem, Constant(F::from_u128(q)), q_bits);
+    let recomposed = range.gate().mul_add(ctx, quot, Constant(F::from_u128(q)), rem);
+    ctx.constrain_equal(&recomposed, &x);
+}
+
+/// Reduces `x` mod `q` and returns `(quot, rem)` with `x = quot * q + rem` and `rem` in `[0, q)`, for any non-zero `q` below `2^128`.
+///
+/// Unlike `div_mod` and `barrett_reduce_assigned`, it needs no bound on the bit width of `x`: the quotient and remainder are computed outside the circuit,
+/// and `quot` is range checked on `F::CAPACITY - bit_length(q)` bits, which is the widest range for which `quot * q + rem` cannot wrap around the field.
+/// This covers every `x` below `q * 2^(F::CAPACITY - bit_length(q))`, and in particular every `x` below `2^(F::CAPACITY - 1)`, at the cost of a wider range check on `quot`.
+/// Use `div_mod` or `barrett_reduce_assigned` instead when a tight bit width of `x` is known.
+///
+/// Returns `CircuitError::ZeroModulus` if `q` is `0`.
+pub fn reduce_with_quotient<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    q: u128,
+) -> Result<(AssignedValue<F>, AssignedValue<F>), CircuitError> {
+    if q == 0 {
+        return Err(CircuitError::ZeroModulus);
+    }
+    let (quot, rem) = long_division(x.value(), q);
+    let quot = ctx.load_witness(quot);
+    let rem = ctx.load_witness(F::from_u128(rem));
+
+    constrain_quotient(ctx, range, x, quot, rem, q, F::CAPACITY as usize - bit_length(q));
+    Ok((quot, rem))
+}
+
+// the quotient and remainder of the long division of x by q, one bit at a time from the most significant one
+fn long_division<F: ScalarField>(x: &F, q: u128) -> (F, u128) {
+    let (mut quot, mut rem) = (F::zero(), 0u128);
+    for limb in x.to_u64_limbs(4, 64).into_iter().rev() {
+        for i in (0..64).rev() {
+            let carry = rem >> 127;
+            rem = rem << 1 | (limb >> i) as u128 & 1;
+            let bit = carry == 1 || rem >= q;
+            if bit {
+                rem = rem.wrapping_sub(q);
+            }
+            quot = quot.double() + F::from(bit as u64);
+        }
+    }
+    (quot, rem)
+}
+
+/// Reduces every value mod `q` and returns the remainders in `[0, q)`, where all the values are known to be less than `2^num_bits`, e.g. the evaluations of an NTT before their reduction.
+///
+/// The quotients and remainders of the whole batch are computed outside the circuit first, and every value only constrains `x = quot * q + rem`, as in `barrett_reduce_assigned`.
+/// All the quotients share the bound `2^(num_bits + 1 - bit_length(q))`, which is checked with a single `range_check` each, while `div_mod` checks every quotient against `2^num_bits / q`
+/// with both a `range_check` and a `check_less_than`. This saves the lookups of one range check per value.
+///
+/// Returns a `FieldOverflow` error when `num_bits` is larger than `F::CAPACITY - 1`, so that `quot * q + rem` could wrap around the field.
+pub fn batch_reduce<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    values: &[AssignedValue<F>],
+    q: u64,
+    num_bits: usize,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let capacity = F::CAPACITY as usize - 1;
+    if num_bits > capacity {
+        return Err(CircuitError::FieldOverflow { num_bits, capacity });
+    }
+    let q_bits = bit_length(q as u128);
+    let quot_bits = num_bits.max(q_bits) + 1 - q_bits;
+    let divided = values.iter().map(|x| long_division(x.value(), q as u128)).collect::<Vec<_>>();
+    Ok(values
+        .iter()
+        .zip(divided)
+        .map(|(x, (quot, rem))| {
+            let quot = ctx.load_witness(quot);
+            let rem = ctx.load_witness(F::from_u128(rem));
+            constrain_quotient(ctx, range, *x, quot, rem, q as u128, quot_bits);
+            rem
+        })
+        .collect())
+}
+
+/// Computes `base^exp mod q` for `base` in `[0, q)` with square-and-multiply, where every squaring and multiplication is reduced mod `q` with `div_mod`.
+///
+/// The exponent is a constant of the circuit, so the sequence of gates only depends on `exp` and not on `base`. `base` is constrained to be in `[0, q)` and the output is in `[0, q)`.
+pub fn mod_pow<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    base: AssignedValue<F>,
+    exp: u64,
+    q: u64,
+) -> AssignedValue<F> {
+    range.check_less_than_safe(ctx, base, q);
+    if exp == 0 {
+        return ctx.load_constant(F::from(1 % q));
+    }
+    // the product of two values in [0, q) is less than q^2
+    let num_bits = bit_length((q as u128 - 1).pow(2));
+    let mod_mul = |ctx: &mut Context<F>, a, b| {
+        let prod = range.gate().mul(ctx, a, b);
+        range.div_mod(ctx, prod, q, num_bits).1
+    };
+
+    // scan the bits of exp from the most significant one, which accounts for the initial value of acc
+    let mut acc = base;
+    for i in (0..(63 - exp.leading_zeros())).rev() {
+        acc = mod_mul(ctx, acc, acc);
+        if (exp >> i) & 1 == 1 {
+            acc = mod_mul(ctx, acc, base);
+        }
+    }
+    acc
+}
+
+/// Reduces a polynomial (e.g. the raw output of `poly_mul_assigned`) to the canonical representative of its class in `R_q = Z_q[x]/(x^n + 1)`, with `n` coefficients in `[0, q)`.
+///
+/// The polynomial is first folded negacyclically, after which every coefficient is a signed integer with absolute value at most `max_abs`.
+/// Since the reduction only works on non-negative values, we shift every coefficient by a multiple of `q` larger than `max_abs` before reducing it mod `q`.
+/// Returns a `ZeroModulus` error if `q` is `0`, and a `FieldOverflow` error when the shifted coefficients are too large for `barrett_reduce`.
+pub fn poly_reduce_ring_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    n: usize,
+    q: u64,
+    max_abs: u128,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    if q == 0 {
+        return Err(CircuitError::ZeroModulus);
+    }
+    let folded = poly_reduce_cyclo_assigned(ctx, range.gate(), coeffs, n);
+    let offset = q as u128 * (max_abs / q as u128 + 1);
+    let num_bits = bit_length(offset + max_abs);
+    folded
+        .into_iter()
+        .map(|x| {
+            let shifted = range.gate().add(ctx, x, Constant(F::from_u128(offset)));
+            Ok(barrett_reduce_assigned(ctx, range, shifted, q, num_bits)?.1)
+        })
+        .collect()
+}
+
+/// Constrains that the polynomial is the zero polynomial in `R_q = Z_q[x]/(x^n + 1)`, i.e. that it reduces to zero mod `x^n + 1` and mod `q`.
+///
+/// As in `poly_reduce_ring_assigned`, every coefficient after the negacyclic folding must be a signed integer with absolute value at most `max_abs`, and its errors are returned.
+pub fn assert_poly_zero_mod<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    n: usize,
+    q: u64,
+    max_abs: u128,
+) -> Result<(), CircuitError> {
+    let reduced = poly_reduce_ring_assigned(ctx, range, coeffs, n, q, max_abs)?;
+    assert_poly_zero(ctx, range.gate(), &reduced);
+    Ok(())
+}
+
+/// Reduces every coefficient of a polynomial mod `modulus`, where the coefficients are declared to be less than `2^num_bits`.
+///
+/// Every coefficient is range checked against `num_bits` before the reduction, so a coefficient exceeding the declared bit width makes the circuit unsatisfiable.
+/// The range checks are decomposed into limbs of `LOOKUP_BITS` bits by the range chip, so any bit width between `bit_length(modulus)` and `F::CAPACITY - 1` can be used with any lookup table size.
+pub fn poly_reduce_mod_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    modulus: u64,
+    num_bits: usize,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    // div_mod range checks the quotient on num_bits - bit_length(modulus) + 1 bits, and the recomposed value must not wrap around the field
+    let (min, max) = (bit_length(modulus as u128), F::CAPACITY as usize - 1);
+    if num_bits < min || num_bits > max {
+        return Err(CircuitError::InvalidBitWidth { num_bits, min, max });
+    }
+    Ok(coeffs
+        .iter()
+        .map(|&x| {
+            range.range_check(ctx, x, num_bits);
+            range.div_mod(ctx, x, modulus, num_bits).1
+        })
+        .collect())
+}
+
+/// Reduces every coefficient of a polynomial mod `modulus` to its canonical representative in `[0, modulus)`, where the coefficients are signed integers with absolute value less than `2^num_bits`.
+///
+/// A negative coefficient `-x` is encoded as the field element `p - x`, e.g. after a subtraction, so `div_mod` would reduce `p - x` instead of `-x` and return a wrong residue.
+/// As in `poly_reduce_ring_assigned`, every coefficient is first shifted by a multiple of `modulus` larger than `2^num_bits`, which makes it non-negative without changing its residue,
+/// then range checked and reduced with `poly_reduce_mod_assigned`. Every coefficient accepted by the range check is reduced as the signed integer it encodes, while coefficients
+/// far outside of `(-2^num_bits, 2^num_bits)`, or field elements that do not encode a small signed integer, make the circuit unsatisfiable.
+/// `num_bits` must be at most `126`, so that the shifted coefficients fit in a `u128`.
+pub fn poly_reduce_mod_signed_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    modulus: u64,
+    num_bits: usize,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let max = (F::CAPACITY as usize - 2).min(126);
+    if num_bits == 0 || num_bits > max {
+        return Err(CircuitError::InvalidBitWidth { num_bits, min: 1, max });
+    }
+    let bound = 1u128 << num_bits;
+    let offset = modulus as u128 * (bound / modulus as u128 + 1);
+    // the shifted coefficients are in (offset - 2^num_bits, offset + 2^num_bits)
+    let shifted = coeffs
+        .iter()
+        .map(|&x| range.gate().add(ctx, x, Constant(F::from_u128(offset))))
+        .collect::<Vec<_>>();
+    poly_reduce_mod_assigned(ctx, range, &shifted, modulus, bit_length(offset + bound - 1))
+}
+
+/// Decomposes `x` into `l` little endian digits in base `w` and returns them. The digits are constrained to be in `[0, w)` and to recompose to `x`.
+///
+/// The decomposition is unique as long as `w^l` is smaller than the field modulus, so `x` must be less than `w^l`.
+pub fn base_decompose<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    w: u64,
+    l: usize,
+) -> Vec<AssignedValue<F>> {
+    let limbs = x.value().to_u64_limbs(2, 64);
+    let mut value = limbs[0] as u128 | (limbs[1] as u128) << 64;
+    let digits = (0..l)
+        .map(|_| {
+            let digit = ctx.load_witness(F::from((value % w as u128) as u64));
+            value /= w as u128;
+            digit
+        })
+        .collect::<Vec<_>>();
+    assert_base_decomposition(ctx, range, x, &digits, w);
+    digits
+}
+
+/// Constrains that `digits` are the little endian digits of `x` in base `w`, i.e. that every digit is in `[0, w)` and that `sum_i digits_i * w^i = x`.
+///
+/// The base does not need to be a power of two. As for `base_decompose`, the digits are only unique when `w^digits.len()` is smaller than the field modulus.
+pub fn assert_base_decomposition<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    x: AssignedValue<F>,
+    digits: &[AssignedValue<F>],
+    w: u64,
+) {
+    for &digit in digits {
+        range.check_less_than_safe(ctx, digit, w);
+    }
+
+    let mut w_pow = F::one();
+    let powers = digits.iter().map(|_| {
+        let pow = w_pow;
+        w_pow *= F::from(w);
+        Constant(pow)
+    });
+    let recomposed = range.gate().inner_product(ctx, digits.iter().copied(), powers);
+    ctx.constrain_equal(&recomposed, &x);
+}
+
+// the number of coefficients of `bits_each` bits that fit in one field element without wrapping around the modulus
+fn coeffs_per_element<F: ScalarField>(bits_each: usize) -> Result<usize, CircuitError> {
+    // the witness digits are computed with `to_u64_limbs`, so a coefficient has at most 64 bits
+    let max = 64.min(F::CAPACITY as usize - 1);
+    if bits_each == 0 || bits_each > max {
+        return Err(CircuitError::InvalidBitWidth { num_bits: bits_each, min: 1, max });
+    }
+    Ok((F::CAPACITY as usize - 1) / bits_each)
+}
+
+// constrains `sum_i coeffs_i * 2^(i * bits_each)`
+fn recompose_packed<F: ScalarField>(
+    ctx: &mut Context<F>,
+    gate: &impl GateInstructions<F>,
+    coeffs: &[AssignedValue<F>],
+    bits_each: usize,
+) -> AssignedValue<F> {
+    let base = F::from_u128(1 << bits_each);
+    let mut pow = F::one();
+    let powers = coeffs.iter().map(|_| {
+        let current = pow;
+        pow *= base;
+        Constant(current)
+    });
+    gate.inner_product(ctx, coeffs.iter().copied(), powers)
+}
+
+/// Packs coefficients of at most `bits_each` bits into as few field elements as possible, e.g. to make a small-coefficient polynomial public with fewer instances.
+///
+/// Every field element holds `(F::CAPACITY - 1) / bits_each` little endian coefficients, the last one possibly fewer, as `sum_i c_i * 2^(i * bits_each)`.
+/// The coefficients are range checked to `bits_each` bits, so the packing is injective. Use `unpack_coeffs` to recover them.
+/// Returns an `InvalidBitWidth` error when `bits_each` is zero, more than 64 or does not fit in the field.
+pub fn pack_coeffs<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    bits_each: usize,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let per_element = coeffs_per_element::<F>(bits_each)?;
+    for &c in coeffs {
+        range.range_check(ctx, c, bits_each);
+    }
+    Ok(coeffs
+        .chunks(per_element)
+        .map(|chunk| recompose_packed(ctx, range.gate(), chunk, bits_each))
+        .collect())
+}
+
+/// Unpacks `len` coefficients of `bits_each` bits from field elements packed as in `pack_coeffs`, and constrains them to repack to `packed`.
+///
+/// Returns a `LengthMismatch` error when `packed` does not have the number of field elements that `len` coefficients pack into, and the errors of `pack_coeffs` for `bits_each`.
+pub fn unpack_coeffs<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    packed: &[AssignedValue<F>],
+    bits_each: usize,
+    len: usize,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let per_element = coeffs_per_element::<F>(bits_each)?;
+    let expected = (len + per_element - 1) / per_element;
+    if packed.len() != expected {
+        return Err(CircuitError::LengthMismatch { left: expected, right: packed.len() });
+    }
+    let mut coeffs = Vec::with_capacity(len);
+    for (i, &element) in packed.iter().enumerate() {
+        let count = per_element.min(len - i * per_element);
+        let chunk = element
+            .value()
+            .to_u64_limbs(count, bits_each)
+            .into_iter()
+            .map(|c| ctx.load_witness(F::from(c)))
+            .collect::<Vec<_>>();
+        for &c in &chunk {
+            range.range_check(ctx, c, bits_each);
+        }
+        let repacked = recompose_packed(ctx, range.gate(), &chunk, bits_each);
+        ctx.constrain_equal(&repacked, &element);
+        coeffs.extend(chunk);
+    }
+    Ok(coeffs)
+}
+
+#[cfg(test)]
+mod test {
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    use halo2_base::gates::{GateInstructions, RangeInstructions};
+    use halo2_base::utils::ScalarField;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{
+        apply_automorphism, assert_base_decomposition, assert_congruent_mod_cyclo,
+        assert_congruent_scalar, assert_inf_norm_bound, assert_permutation, assert_poly_zero,
+        assert_poly_zero_mod, assert_reduced, base_decompose, batch_reduce, centered_value,
+        conjugate, infinity_norm_assigned, is_poly_zero, load_poly, mod_pow,
+        montgomery_reduce_assigned, norm_squared_assigned, pack_coeffs, pad_to_len,
+        poly_add_assigned, poly_compose, poly_eval_assigned, poly_identity_check,
+        poly_mul_assigned, poly_mul_bounded_assigned, poly_mul_constant_assigned,
+        poly_mul_mod_assigned, poly_mul_toom3, poly_mul_with, poly_neg, poly_reduce_cyclo_assigned,
+        poly_reduce_mod_assigned, poly_reduce_mod_signed_assigned, poly_reduce_ring_assigned,
+        poly_trace, reduce_with_quotient, resize_poly, unpack_coeffs, MulAlgorithm,
+        TOOM3_THRESHOLD,
+    };
+    use crate::error::CircuitError;
+    use crate::poly::utils::{
+        apply_automorphism as apply_automorphism_host, bit_length, conjugate as conjugate_host,
+        decode_signed, fe_from_i64, infinity_norm, inv_mod, montgomery_bits, norm_squared,
+        poly_mul, poly_trace as poly_trace_host, pow_mod, reduce_mod_cyclo, ring_add, ring_mul,
+        ring_mul_wide, ring_neg, to_centered,
+    };
+    use crate::test_utils::mock_run;
+
+    #[test]
+    fn test_poly_mul_reduce_cyclo() {
+        let a = [3, 2, 5, 7];
+        let b = [0, 5, 1, 1, 9];
+        let ((prod, reduced), ok) = mock_run(10, 8, |ctx, range| {
+            let a = load_poly(ctx, &a);
+            let b = load_poly(ctx, &b);
+            let prod = poly_mul_assigned(ctx, &range.gate, &a, &b).unwrap();
+            let reduced = poly_reduce_cyclo_assigned(ctx, &range.gate, &prod, 4);
+            (
+                prod.iter().map(|x| *x.value()).collect::<Vec<_>>(),
+                reduced.iter().map(|x| *x.value()).collect::<Vec<_>>(),
+            )
+        });
+        assert!(ok);
+
+        let expected = poly_mul(&a.map(i128::from), &b.map(i128::from));
+        assert_eq!(prod, expected.iter().map(|x| fe_from_i64::<Fr>(*x as i64)).collect::<Vec<_>>());
+        let expected = reduce_mod_cyclo(&expected, 4);
+        assert_eq!(
+            reduced,
+            expected.iter().map(|x| fe_from_i64::<Fr>(*x as i64)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_poly_mul_reduce_ring() {
+        let (n, q) = (4, 1073741789);
+        let a = [347712782, 1017762681, 161973069, 423938499];
+        let b = [698935572, 51847156, 77777868, 881836553];
+        let (out, ok) = mock_run(11, 8, |ctx, range| {
+            let (a, b) = (load_poly(ctx, &a), load_poly(ctx, &b));
+            let prod = poly_mul_assigned(ctx, &range.gate, &a, &b).unwrap();
+            let max_abs = n as u128 * (q as u128 - 1).pow(2);
+            let out = poly_reduce_ring_assigned(ctx, range, &prod, n, q, max_abs).unwrap();
+            out.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        assert_eq!(out, ring_mul(&a, &b, q));
+        assert_eq!(out, vec![63580987, 826365980, 200528788, 269820977]);
+    }
+
+    #[test]
+    fn test_poly_mul_constant() {
+        let (n, q) = (4, 1073741789);
+        let a = [347712782, 1017762681, 161973069, 423938499];
+        let b = [698935572, 51847156, 77777868, 881836553];
+        let (out, ok) = mock_run(11, 8, |ctx, range| {
+            let a = load_poly(ctx, &a);
+            let prod = poly_mul_constant_assigned(ctx, &range.gate, &a, &b).unwrap();
+            assert_eq!(prod.len(), 2 * n - 1);
+            let max_abs = n as u128 * (q as u128 - 1).pow(2);
+            let out = poly_reduce_ring_assigned(ctx, range, &prod, n, q, max_abs).unwrap();
+            out.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        assert_eq!(out, ring_mul(&a, &b, q));
+
+        let (err, _) = mock_run(10, 8, |ctx, range| {
+            let a = load_poly(ctx, &a);
+            poly_mul_constant_assigned(ctx, &range.gate, &a, &[]).err()
+        });
+        assert_eq!(err, Some(CircuitError::EmptyInput));
+    }
+
+    #[test]
+    fn test_poly_mul_toom3() {
+        let mut rng = StdRng::seed_from_u64(6);
+        // 3 * TOOM3_THRESHOLD + 2 coefficients make the sub-products split again
+        for (a_len, b_len) in [(12, 12), (24, 24), (24, 17), (5, 1), (3 * TOOM3_THRESHOLD + 2, 100)]
+        {
+            let a = (0..a_len).map(|_| rng.gen_range(0..1 << 20)).collect::<Vec<u64>>();
+            let b = (0..b_len).map(|_| rng.gen_range(0..1 << 20)).collect::<Vec<u64>>();
+            let ((toom3, direct), ok) = mock_run(13, 8, |ctx, range| {
+                let (a, b) = (load_poly(ctx, &a), load_poly(ctx, &b));
+                let toom3 = poly_mul_with(ctx, &range.gate, &a, &b, MulAlgorithm::Toom3).unwrap();
+                let direct =
+                    poly_mul_with(ctx, &range.gate, &a, &b, MulAlgorithm::Schoolbook).unwrap();
+                for (x, y) in toom3.iter().zip(direct.iter()) {
+                    ctx.constrain_equal(x, y);
+                }
+                (
+                    toom3.iter().map(|x| *x.value()).collect::<Vec<_>>(),
+                    direct.iter().map(|x| *x.value()).collect::<Vec<_>>(),
+                )
+            });
+            assert!(ok);
+            assert_eq!(toom3, direct);
+            let expected = poly_mul(
+                &a.iter().map(|x| *x as i128).collect::<Vec<_>>(),
+                &b.iter().map(|x| *x as i128).collect::<Vec<_>>(),
+            );
+            assert_eq!(
+                toom3,
+                expected.iter().map(|x| fe_from_i64::<Fr>(*x as i64)).collect::<Vec<_>>()
+            );
+        }
+
+        let (err, _) = mock_run(9, 8, |ctx, range| {
+            let a = load_poly(ctx, &[1, 2, 3]);
+            poly_mul_toom3(ctx, &range.gate, &a, &[]).err()
+        });
+        assert_eq!(err, Some(CircuitError::EmptyInput));
+    }
+
+    #[test]
+    fn test_poly_mul_mod_small_modulus() {
+        let q = 1048573;
+        let a = [347712, 1017762, 161973, 423938];
+        let b = [698935, 51847, 77777, 881836];
+        let (out, ok) = mock_run(11, 8, |ctx, range| {
+            let (a, b) = (load_poly(ctx, &a), load_poly(ctx, &b));
+            [false, true].map(|reduce_intermediate| {
+                let prod =
+                    poly_mul_mod_assigned(ctx, range, &a, &b, q as u128, reduce_intermediate)
+                        .unwrap();
+                let out = poly_reduce_ring_assigned(ctx, range, &prod, 4, q, q as u128).unwrap();
+                out.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+            })
+        });
+        assert!(ok);
+        assert_eq!(out[0], ring_mul(&a, &b, q));
+        assert_eq!(out[1], ring_mul(&a, &b, q));
+    }
+
+    #[test]
+    fn test_poly_mul_mod_intermediate_overflow() {
+        // with n = 16 and q close to 2^125, the unreduced sums n * (q-1)^2 are about 2^254,
+        // which is larger than the field modulus
+        let (n, q) = (16, (1u128 << 125) - 9);
+        let mut rng = StdRng::seed_from_u64(7);
+        let a = (0..n).map(|_| rng.gen_range(0..q)).collect::<Vec<_>>();
+        let b = (0..n).map(|_| rng.gen_range(0..q)).collect::<Vec<_>>();
+
+        let ((unreduced, prod), ok) = mock_run(14, 8, |ctx, range| {
+            let a = a.iter().map(|x| ctx.load_witness(Fr::from_u128(*x))).collect::<Vec<_>>();
+            let b = b.iter().map(|x| ctx.load_witness(Fr::from_u128(*x))).collect::<Vec<_>>();
+            let unreduced = poly_mul_mod_assigned(ctx, range, &a, &b, q, false).err();
+            let prod = poly_mul_mod_assigned(ctx, range, &a, &b, q, true).unwrap();
+            (
+                unreduced,
+                prod.iter()
+                    .map(|x| {
+                        let limbs = x.value().to_u64_limbs(2, 64);
+                        limbs[0] as u128 | (limbs[1] as u128) << 64
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        });
+        assert_eq!(unreduced, Some(CircuitError::FieldOverflow { num_bits: 255, capacity: 252 }));
+        assert!(ok);
+
+        // fold the product mod x^n + 1 to compare it with the host multiplication in R_q
+        assert!(prod.iter().all(|x| *x < q));
+        let folded = (0..n)
+            .map(|i| if i + n < prod.len() { (prod[i] + q - prod[i + n]) % q } else { prod[i] })
+            .collect::<Vec<_>>();
+        assert_eq!(folded, ring_mul_wide(&a, &b, q));
+    }
+
+    #[test]
+    fn test_poly_mul_bounded() {
+        // with every coefficient at its bound, the middle coefficient of the product reaches the computed bound
+        let (a_bound, b_bound) = ((1 << 16) - 1, 1000);
+        let (a, b) = (vec![a_bound; 4], vec![b_bound; 6]);
+        let ((prod, bound), ok) = mock_run(10, 8, |ctx, range| {
+            let (a, b) = (load_poly(ctx, &a), load_poly(ctx, &b));
+            let (prod, bound) =
+                poly_mul_bounded_assigned(ctx, range, &a, &b, a_bound, b_bound).unwrap();
+            (prod.iter().map(|x| *x.value()).collect::<Vec<_>>(), bound)
+        });
+        assert!(ok);
+        assert_eq!(bound, 4 * a_bound as u128 * b_bound as u128);
+        assert_eq!(prod[4], Fr::from_u128(bound));
+
+        // a coefficient above its declared bound is rejected
+        let (_, ok) = mock_run(10, 8, |ctx, range| {
+            let (a, b) = (load_poly(ctx, &[3, 2, 5, 7]), load_poly(ctx, &[0, 5, 1, 1001]));
+            poly_mul_bounded_assigned(ctx, range, &a, &b, a_bound, b_bound).unwrap();
+        });
+        assert!(!ok);
+
+        let (err, _) = mock_run(10, 8, |ctx, range| {
+            let a = load_poly(ctx, &[1; 4]);
+            poly_mul_bounded_assigned(ctx, range, &a, &a, u64::MAX, u64::MAX).err()
+        });
+        assert_eq!(err, Some(CircuitError::FieldOverflow { num_bits: 130, capacity: 128 }));
+    }
+
+    #[test]
+    fn test_assert_reduced() {
+        // an odd modulus, and a power of two for which a single range check is used
+        for q in [1048573, 1 << 20, 8] {
+            for (coeffs, expected) in [(vec![0, q - 1], true), (vec![0, q], false)] {
+                let (_, ok) = mock_run(10, 8, |ctx, range| {
+                    let coeffs = load_poly(ctx, &coeffs);
+                    assert_reduced(ctx, range, &coeffs, q);
+                });
+                assert_eq!(ok, expected, "coefficients {coeffs:?} mod {q}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_infinity_norm() {
+        let q = 1048573;
+        // in the first polynomial, the largest magnitude is the one of the negative coefficient q - 40000
+        for coeffs in
+            [vec![12, q - 3, 30000, q - 40000, 0, 1], vec![q / 2 + 1, 5, q - 1], vec![7], vec![]]
+        {
+            let (norm, ok) = mock_run(10, 8, |ctx, range| {
+                let coeffs = load_poly(ctx, &coeffs);
+                infinity_norm_assigned(ctx, range, &coeffs, q).value().get_lower_64()
+            });
+            assert!(ok);
+            assert_eq!(norm, infinity_norm(&coeffs, q));
+        }
+        assert_eq!(infinity_norm(&[12, q - 3, 30000, q - 40000, 0, 1], q), 40000);
+        assert_eq!(infinity_norm(&[q / 2 + 1, 5, q - 1], q), q / 2);
+    }
+
+    #[test]
+    fn test_assert_inf_norm_bound() {
+        let q = 1048573;
+        let coeffs = [12, q - 3, 30000, q - 40000, 0, 1];
+        // the norm is 40000, so the bound holds exactly at 40000 and fails just below it
+        for (bound, expected) in [(40000, true), (39999, false), (q / 2, true)] {
+            let (_, ok) = mock_run(10, 8, |ctx, range| {
+                let coeffs = load_poly(ctx, &coeffs);
+                let bound = ctx.load_witness(Fr::from(bound));
+                assert_inf_norm_bound(ctx, range, &coeffs, q, bound);
+            });
+            assert_eq!(ok, expected, "bound {bound}");
+        }
+    }
+
+    #[test]
+    fn test_norm_squared() {
+        let q = 1048573;
+        // a small error polynomial with coefficients in [-3, 3], the negative ones close to q
+        let e = [2, q - 3, 0, 1, q - 1, 3, q - 2, 0];
+        let (norm, ok) = mock_run(10, 8, |ctx, range| {
+            let e = load_poly(ctx, &e);
+            norm_squared_assigned(ctx, range, &e, q).unwrap().value().get_lower_64()
+        });
+        assert!(ok);
+        assert_eq!(norm as u128, norm_squared(&e, q));
+        assert_eq!(norm, 4 + 9 + 1 + 1 + 9 + 4);
+    }
+
+    #[test]
+    fn test_poly_malformed_inputs() {
+        let ((add, mul), _) = mock_run(9, 8, |ctx, range| {
+            let a = load_poly(ctx, &[1, 2, 3]);
+            let b = load_poly(ctx, &[4, 5]);
+            (
+                poly_add_assigned(ctx, &range.gate, &a, &b).err(),
+                poly_mul_assigned(ctx, &range.gate, &a, &[]).err(),
+            )
+        });
+        assert_eq!(add, Some(CircuitError::LengthMismatch { left: 3, right: 2 }));
+        assert_eq!(mul, Some(CircuitError::EmptyInput));
+    }
+
+    #[test]
+    fn test_resize_poly() {
+        let a = [3, 2, 5, 7];
+        let ((padded, truncated), ok) = mock_run(9, 8, |ctx, _| {
+            let a = load_poly(ctx, &a);
+            let padded = resize_poly(ctx, &a, 6);
+            let truncated = resize_poly(ctx, &a, 2);
+            (
+                padded.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>(),
+                truncated.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>(),
+            )
+        });
+        assert!(ok);
+        assert_eq!(padded, vec![3, 2, 5, 7, 0, 0]);
+        assert_eq!(truncated, vec![3, 2]);
+    }
+
+    #[test]
+    fn test_pad_to_len() {
+        let a = [3, 2, 5];
+        for (value, expected) in [(0, true), (1, false)] {
+            let (padded, ok) = mock_run(9, 8, |ctx, _| {
+                let a = load_poly(ctx, &a);
+                let padded = pad_to_len(ctx, &a, 5).unwrap();
+                // the padding is constrained to zero, so it cannot be equal to any other value
+                let x = ctx.load_witness(Fr::from(value));
+                ctx.constrain_equal(&padded[4], &x);
+                padded.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+            });
+            assert_eq!(ok, expected);
+            assert_eq!(padded, vec![3, 2, 5, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn test_pad_to_len_too_long() {
+        let (err, _) = mock_run(9, 8, |ctx, _| {
+            let a = load_poly(ctx, &[3, 2, 5]);
+            pad_to_len(ctx, &a, 2).err()
+        });
+        assert_eq!(err, Some(CircuitError::DegreeTooLarge { max: 1, found: 2 }));
+    }
+
+    #[test]
+    fn test_centered_value_boundary() {
+        let q = 1048573;
+        let xs = [0, 1, q / 2 - 1, q / 2, q / 2 + 1, q - 1];
+        let (out, ok) = mock_run(11, 8, |ctx, range| {
+            xs.map(|x| {
+                let x = ctx.load_witness(Fr::from(x));
+                let (magnitude, sign) = centered_value(ctx, range, x, q);
+                (magnitude.value().get_lower_64(), sign.value().get_lower_64())
+            })
+        });
+        assert!(ok);
+        for (x, (magnitude, sign)) in xs.iter().zip(out) {
+            let centered = to_centered(*x, q);
+            assert_eq!(magnitude, centered.unsigned_abs());
+            assert_eq!(sign, (centered < 0) as u64);
+        }
+    }
+
+    #[test]
+    fn test_centered_value_out_of_range() {
+        let q = 1048573;
+        let (_, ok) = mock_run(11, 8, |ctx, range| {
+            let x = ctx.load_witness(Fr::from(q));
+            centered_value(ctx, range, x, q);
+        });
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_poly_reduce_mod_bit_widths() {
+        let q = 11;
+        for num_bits in [8, 16, 32] {
+            let a = [0, 1, 10, 11, (1u64 << num_bits) - 1];
+            let (out, ok) = mock_run(11, 8, |ctx, range| {
+                let a = load_poly(ctx, &a);
+                let out = poly_reduce_mod_assigned(ctx, range, &a, q, num_bits).unwrap();
+                out.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+            });
+            assert!(ok);
+            assert_eq!(out, a.map(|x| x % q));
+
+            // a coefficient one bit too wide is rejected
+            let (_, ok) = mock_run(11, 8, |ctx, range| {
+                let a = load_poly(ctx, &[1u64 << num_bits]);
+                poly_reduce_mod_assigned(ctx, range, &a, q, num_bits).unwrap();
+            });
+            assert!(!ok);
+        }
+    }
+
+    #[test]
+    fn test_poly_reduce_mod_invalid_bit_width() {
+        let (errs, _) = mock_run(9, 8, |ctx, range| {
+            let a = load_poly(ctx, &[3, 2, 5, 7]);
+            [2, 300].map(|num_bits| poly_reduce_mod_assigned(ctx, range, &a, 11, num_bits).err())
+        });
+        // Fr can hold any value of up to 253 bits
+        let max = 252;
+        assert_eq!(errs[0], Some(CircuitError::InvalidBitWidth { num_bits: 2, min: 4, max }));
+        assert_eq!(errs[1], Some(CircuitError::InvalidBitWidth { num_bits: 300, min: 4, max }));
+    }
+
+    #[test]
+    fn test_poly_reduce_mod_signed() {
+        let q = 11;
+        let a = [-3, 0, 3, -11, -12, 21, (1 << 16) - 1, -(1 << 16) + 1];
+        let (out, ok) = mock_run(11, 8, |ctx, range| {
+            let a = a.map(|x| ctx.load_witness(fe_from_i64::<Fr>(x)));
+            let out = poly_reduce_mod_signed_assigned(ctx, range, &a, q, 16).unwrap();
+            out.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        // the field encoding of -3 reduces to 8, not to (p - 3) mod 11
+        assert_eq!(out[0], 8);
+        assert_eq!(out, a.map(|x| x.rem_euclid(q as i64) as u64));
+
+        // coefficients far outside of (-2^16, 2^16) are rejected
+        for x in [1 << 18, -(1 << 17)] {
+            let (_, ok) = mock_run(11, 8, |ctx, range| {
+                let a = [ctx.load_witness(fe_from_i64::<Fr>(x))];
+                poly_reduce_mod_signed_assigned(ctx, range, &a, q, 16).unwrap();
+            });
+            assert!(!ok);
+        }
+    }
+
+    #[test]
+    fn test_poly_eval() {
+        let (out, ok) = mock_run(9, 8, |ctx, range| {
+            let a = load_poly(ctx, &[3, 2, 5, 7]);
+            let x = ctx.load_witness(Fr::from(10));
+            poly_eval_assigned(ctx, &range.gate, &a, x).unwrap().value().get_lower_64()
+        });
+        assert!(ok);
+        assert_eq!(out, 7523);
+    }
+
+    #[test]
+    fn test_poly_identity_check() {
+        let a = [3, 2, 5, 7, 1];
+        let b = [0, 5, 1, 1, 9];
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..4 {
+            let r = Fr::from(rng.gen::<u64>());
+            // (a + b) and (b + a) are the same polynomial, computed from different witnesses
+            let (_, ok) = mock_run(9, 8, |ctx, range| {
+                let (a, b) = (load_poly(ctx, &a), load_poly(ctx, &b));
+                let lhs = poly_add_assigned(ctx, &range.gate, &a, &b).unwrap();
+                let rhs = poly_add_assigned(ctx, &range.gate, &b, &a).unwrap();
+                let r = ctx.load_witness(r);
+                poly_identity_check(ctx, &range.gate, &lhs, &rhs, r).unwrap();
+            });
+            assert!(ok);
+
+            // tampering with the i-th coefficient shifts the evaluation by r^i, so the check fails for any nonzero r
+            let mut tampered = a;
+            tampered[rng.gen_range(0..a.len())] += 1;
+            let (_, ok) = mock_run(9, 8, |ctx, range| {
+                let (a, tampered) = (load_poly(ctx, &a), load_poly(ctx, &tampered));
+                let r = ctx.load_witness(r);
+                poly_identity_check(ctx, &range.gate, &a, &tampered, r).unwrap();
+            });
+            assert!(!ok);
+        }
+    }
+
+    #[test]
+    fn test_assert_permutation() {
+        let a = [3, 2, 5, 7, 2, 0];
+        let shuffled = [2, 0, 7, 2, 3, 5];
+        let mut rng = StdRng::seed_from_u64(6);
+        let r = Fr::from(rng.gen::<u64>());
+        let run = |b: &[u64]| {
+            mock_run(9, 8, |ctx, range| {
+                let (a, b) = (load_poly(ctx, &a), load_poly(ctx, b));
+                let r = ctx.load_witness(r);
+                assert_permutation(ctx, &range.gate, &a, &b, r)
+            })
+        };
+        assert_eq!(run(&shuffled), (Ok(()), true));
+        // changing a single coefficient, or the multiplicity of a repeated one, changes the multiset
+        assert_eq!(run(&[2, 0, 7, 2, 3, 6]), (Ok(()), false));
+        assert_eq!(run(&[2, 0, 7, 3, 3, 5]), (Ok(()), false));
+        assert_eq!(run(&shuffled[1..]).0, Err(CircuitError::LengthMismatch { left: 6, right: 5 }));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_poly_mul_parallel_matches_serial() {
+        use halo2_base::gates::GateChip;
+        use halo2_base::Context;
+
+        use super::{poly_mul_parallel, poly_mul_serial};
+
+        let mut rng = StdRng::seed_from_u64(6);
+        let a = (0..17).map(|_| rng.gen::<u32>() as u64).collect::<Vec<_>>();
+        let b = (0..9).map(|_| rng.gen::<u32>() as u64).collect::<Vec<_>>();
+        let gate = GateChip::<Fr>::default();
+
+        let mut serial = Context::new(false, 0);
+        let (a_s, b_s) = (load_poly(&mut serial, &a), load_poly(&mut serial, &b));
+        let out_s = poly_mul_serial(&mut serial, &gate, &a_s, &b_s);
+
+        let mut parallel = Context::new(false, 0);
+        let (a_p, b_p) = (load_poly(&mut parallel, &a), load_poly(&mut parallel, &b));
+        let out_p = poly_mul_parallel(&mut parallel, &a_p, &b_p);
+
+        assert_eq!(
+            out_s.iter().map(|x| *x.value()).collect::<Vec<_>>(),
+            out_p.iter().map(|x| *x.value()).collect::<Vec<_>>()
+        );
+        assert_eq!(serial.advice, parallel.advice);
+        assert_eq!(serial.selector, parallel.selector);
+        assert_eq!(serial.advice_equality_constraints, parallel.advice_equality_constraints);
+    }
+
+    #[test]
+    fn test_base_decompose() {
+        let x = 1048572;
+        let (digits, ok) = mock_run(10, 8, |ctx, range| {
+            let x = ctx.load_witness(Fr::from(x));
+            let digits = base_decompose(ctx, range, x, 16, 5);
+            digits.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        assert_eq!(digits, vec![12, 15, 15, 15, 15]);
+
+        // 16^5 does not fit in 4 digits
+        let (_, ok) = mock_run(10, 8, |ctx, range| {
+            let x = ctx.load_witness(Fr::from(1 << 20));
+            base_decompose(ctx, range, x, 16, 4);
+        });
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_assert_base_decomposition() {
+        let run = |x: u64, digits: &[u64], w: u64| {
+            mock_run(10, 8, |ctx, range| {
+                let x = ctx.load_witness(Fr::from(x));
+                let digits = load_poly(ctx, digits);
+                assert_base_decomposition(ctx, range, x, &digits, w);
+            })
+            .1
+        };
+        // w = 16 with 4 digits, for a coefficient near q = 65521 and for 16^4 - 1, which exactly fits the digits
+        assert!(run(65519, &[15, 14, 15, 15], 16));
+        assert!(run(65535, &[15, 15, 15, 15], 16));
+        // a base that is not a power of two
+        assert!(run(9041, &[1, 4, 0, 9], 10));
+        // a digit out of range that still recomposes to x, and digits recomposing to another value
+        assert!(!run(9041, &[11, 3, 0, 9], 10));
+        assert!(!run(65519, &[15, 15, 15, 15], 16));
+        // 16^4 needs a fifth digit
+        assert!(!run(65536, &[0, 0, 0, 0], 16));
+    }
+
+    #[test]
+    fn test_pack_coeffs_round_trip() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let coeffs = (0..30).map(|_| rng.gen_range(0..1024)).collect::<Vec<u64>>();
+        let ((num_packed, unpacked), ok) = mock_run(11, 8, |ctx, range| {
+            let assigned = load_poly(ctx, &coeffs);
+            let packed = pack_coeffs(ctx, range, &assigned, 10).unwrap();
+            let unpacked = unpack_coeffs(ctx, range, &packed, 10, coeffs.len()).unwrap();
+            (packed.len(), unpacked.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>())
+        });
+        assert!(ok);
+        // 25 coefficients of 10 bits fit in the 252 bits of an Fr element
+        assert_eq!(num_packed, 2);
+        assert_eq!(unpacked, coeffs);
+
+        // a coefficient of more than 10 bits cannot be packed
+        let (_, ok) = mock_run(11, 8, |ctx, range| {
+            let assigned = load_poly(ctx, &[3, 1024, 5]);
+            pack_coeffs(ctx, range, &assigned, 10).unwrap();
+        });
+        assert!(!ok);
+
+        let (errs, _) = mock_run(11, 8, |ctx, range| {
+            let assigned = load_poly(ctx, &coeffs);
+            [
+                pack_coeffs(ctx, range, &assigned, 0).err(),
+                unpack_coeffs(ctx, range, &assigned[..1], 10, 30).err(),
+            ]
+        });
+        assert_eq!(errs[0], Some(CircuitError::InvalidBitWidth { num_bits: 0, min: 1, max: 64 }));
+        assert_eq!(errs[1], Some(CircuitError::LengthMismatch { left: 2, right: 1 }));
+    }
+
+    #[test]
+    fn test_poly_zero() {
+        let ((zero, nonzero), ok) = mock_run(9, 8, |ctx, range| {
+            let (zero, nonzero) = (load_poly(ctx, &[0, 0, 0, 0]), load_poly(ctx, &[0, 0, 3, 0]));
+            assert_poly_zero(ctx, &range.gate, &zero);
+            (
+                is_poly_zero(ctx, &range.gate, &zero).value().get_lower_64(),
+                is_poly_zero(ctx, &range.gate, &nonzero).value().get_lower_64(),
+            )
+        });
+        assert!(ok);
+        assert_eq!((zero, nonzero), (1, 0));
+
+        let (_, ok) = mock_run(9, 8, |ctx, range| {
+            let nonzero = load_poly(ctx, &[0, 0, 3, 0]);
+            assert_poly_zero(ctx, &range.gate, &nonzero);
+        });
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_poly_zero_noise_cancellation() {
+        let q = 1048573;
+        let mut rng = StdRng::seed_from_u64(5);
+        let e0 = (0..4).map(|_| rng.gen_range(0..q)).collect::<Vec<_>>();
+        let e1 = (0..4).map(|_| rng.gen_range(0..q)).collect::<Vec<_>>();
+        // -(e0 + e1) mod q
+        let neg = e0.iter().zip(e1.iter()).map(|(x, y)| (2 * q - x - y) % q).collect::<Vec<_>>();
+
+        let (_, ok) = mock_run(10, 8, |ctx, range| {
+            let [e0, e1, neg] = [&e0, &e1, &neg].map(|c| load_poly(ctx, c));
+            let sum = poly_add_assigned(ctx, &range.gate, &e0, &e1).unwrap();
+
+            // e0 + e1 - e0 - e1 is zero over the integers
+            let diff = sum
+                .iter()
+                .zip(e0.iter().zip(e1.iter()))
+                .map(|(s, (x, y))| {
+                    let d = range.gate.sub(ctx, *s, *x);
+                    range.gate.sub(ctx, d, *y)
+                })
+                .collect::<Vec<_>>();
+            assert_poly_zero(ctx, &range.gate, &diff);
+
+            // e0 + e1 + [-(e0 + e1)]_q is only zero mod q
+            let total = poly_add_assigned(ctx, &range.gate, &sum, &neg).unwrap();
+            assert!(!total.iter().all(|x| x.value().get_lower_64() == 0));
+            assert_poly_zero_mod(ctx, range, &total, 4, q, 3 * q as u128).unwrap();
+        });
+        assert!(ok);
+
+        let (_, ok) = mock_run(10, 8, |ctx, range| {
+            let [e0, e1] = [&e0, &e1].map(|c| load_poly(ctx, c));
+            let sum = poly_add_assigned(ctx, &range.gate, &e0, &e1).unwrap();
+            assert_poly_zero_mod(ctx, range, &sum, 4, q, 2 * q as u128).unwrap();
+        });
+        assert!(!ok);
+    }
+
+    #[test]
+    fn test_mod_pow() {
+        let q = 257;
+        let cases = [(3, 10), (3, 0), (3, 1), (0, 5), (256, 2), (5, 255), (123, 0b1011_0110_1101)];
+        let (out, ok) = mock_run(10, 8, |ctx, range| {
+            cases.map(|(base, exp)| {
+                let base = ctx.load_witness(Fr::from(base));
+                mod_pow(ctx, range, base, exp, q).value().get_lower_64()
+            })
+        });
+        assert!(ok);
+        assert_eq!(out[0], 196);
+        for ((base, exp), out) in cases.iter().zip(out) {
+            assert_eq!(out, pow_mod(*base, *exp, q));
+        }
+    }
+
+    #[test]
+    fn test_batch_reduce() {
+        // the evaluations of an NTT of 8 coefficients in [0, q) are below 8 * (q - 1)^2
+        let q = 7681;
+        let num_bits = bit_length(8 * (q as u128 - 1).pow(2));
+        let mut rng = StdRng::seed_from_u64(0);
+        let xs = (0..16).map(|_| rng.gen_range(0..1u64 << num_bits)).collect::<Vec<_>>();
+        let ((out, expected, lookups), ok) = mock_run(12, 8, |ctx, range| {
+            let xs = xs.iter().map(|x| ctx.load_witness(Fr::from(*x))).collect::<Vec<_>>();
+            let start = ctx.cells_to_lookup.len();
+            let out = batch_reduce(ctx, range, &xs, q, num_bits).unwrap();
+            let batched = ctx.cells_to_lookup.len() - start;
+            let start = ctx.cells_to_lookup.len();
+            let expected =
+                xs.iter().map(|x| range.div_mod(ctx, *x, q, num_bits).1).collect::<Vec<_>>();
+            let lookups = (batched, ctx.cells_to_lookup.len() - start);
+            let [out, expected] = [out, expected]
+                .map(|p| p.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>());
+            (out, expected, lookups)
+        });
+        assert!(ok);
+        assert!(out.iter().zip(&xs).all(|(out, x)| *out < q && *out == x % q));
+        assert_eq!(out, expected);
+        // the batch skips one range check of the quotient per value
+        assert!(
+            lookups.0 < lookups.1,
+            "{} lookups for the batch, {} for div_mod",
+            lookups.0,
+            lookups.1
+        );
+
+        // values wider than the field capacity could wrap around
+        let (err, _) = mock_run(10, 8, |ctx, range| {
+            let x = ctx.load_witness(Fr::from(1));
+            batch_reduce(ctx, range, &[x], q, Fr::CAPACITY as usize).unwrap_err()
+        });
+        assert_eq!(err, CircuitError::FieldOverflow { num_bits: 253, capacity: 252 });
+    }
+
+    #[test]
+    fn test_montgomery_reduce_assigned() {
+        // the product of two values in [0, q) is below (q - 1)^2
+        let q = 7681;
+        let num_bits = bit_length((q as u128 - 1).pow(2));
+        let r_inv = inv_mod(pow_mod(2, montgomery_bits(q, num_bits) as u64, q), q);
+        let mut rng = StdRng::seed_from_u64(0);
+        let xs = (0..16).map(|_| rng.gen_range(0..(q - 1).pow(2))).collect::<Vec<_>>();
+        let (out, ok) = mock_run(11, 8, |ctx, range| {
+            xs.iter()
+                .map(|x| {
+                    let x = ctx.load_witness(Fr::from(*x));
+                    montgomery_reduce_assigned(ctx, range, x, q, num_bits)
+                        .unwrap()
+                        .value()
+                        .get_lower_64()
+                })
+                .collect::<Vec<_>>()
+        });
+        assert!(ok);
+        for (out, x) in out.iter().zip(&xs) {
+            assert_eq!(*out, x % q * r_inv % q);
+        }
+
+        // an even modulus has no inverse mod 2^r
+        let (err, _) = mock_run(10, 8, |ctx, range| {
+            let x = ctx.load_witness(Fr::from(1));
+            montgomery_reduce_assigned(ctx, range, x, 1 << 12, num_bits).unwrap_err()
+        });
+        assert_eq!(err, CircuitError::NotInvertible);
+    }
+
+    #[test]
+    fn test_reduce_with_quotient() {
+        // for a small q, the quotient and remainder match those of div_mod
+        let q = 11;
+        let xs = [0u64, 10, 11, 1000, 2047];
+        let (out, ok) = mock_run(10, 8, |ctx, range| {
+            xs.map(|x| {
+                let x = ctx.load_witness(Fr::from(x));
+                let (quot, rem) = reduce_with_quotient(ctx, range, x, q as u128).unwrap();
+                let (div_quot, div_rem) = range.div_mod(ctx, x, q, 11);
+                [quot, rem, div_quot, div_rem].map(|v| v.value().get_lower_64())
+            })
+        });
+        assert!(ok);
+        for (x, [quot, rem, div_quot, div_rem]) in xs.iter().zip(out) {
+            assert_eq!((quot, rem), (x / q, x % q));
+            assert_eq!((quot, rem), (div_quot, div_rem));
+        }
+
+        // for q close to 2^127, x = a * b is about 2^250 and does not fit in a u128, so the quotient needs more than 64 bits
+        let q = (1u128 << 127) - 1;
+        let (a, b) = ((1u128 << 125) - 3, (1u128 << 125) + 7);
+        let ((quot, rem), ok) = mock_run(10, 8, |ctx, range| {
+            let (a, b) = (ctx.load_witness(Fr::from_u128(a)), ctx.load_witness(Fr::from_u128(b)));
+            let x = range.gate().mul(ctx, a, b);
+            let (quot, rem) = reduce_with_quotient(ctx, range, x, q).unwrap();
+            (*quot.value(), *rem.value())
+        });
+        assert!(ok);
+        // a * b = (2^125 - 3) * (2^125 + 7) = 2^250 + 2^127 - 21 = (2^123 + 1) * q + 2^123 - 20
+        assert_eq!(quot, Fr::from_u128((1 << 123) + 1));
+        assert_eq!(rem, Fr::from_u128((1 << 123) - 20));
+        assert_eq!(quot * Fr::from_u128(q) + rem, Fr::from_u128(a) * Fr::from_u128(b));
+
+        // there are no remainders mod 0
+        let (err, _) = mock_run(10, 8, |ctx, range| {
+            let x = ctx.load_witness(Fr::from(5));
+            reduce_with_quotient(ctx, range, x, 0).unwrap_err()
+        });
+        assert_eq!(err, CircuitError::ZeroModulus);
+    }
+
+    #[test]
+    fn test_apply_automorphism() {
+        let q = 1048573;
+        let a = [1, 2, 3, 4];
+        let (rotated, ok) = mock_run(10, 8, |ctx, range| {
+            let a = load_poly(ctx, &a);
+            let rotated = apply_automorphism(ctx, &range.gate, &a, 3, 4).unwrap();
+            // the negated coefficients are negative integers, which are reduced mod q
+            let reduced =
+                poly_reduce_ring_assigned(ctx, range, &rotated, 4, q, q as u128 - 1).unwrap();
+            (
+                rotated.iter().map(|x| *x.value()).collect::<Vec<_>>(),
+                reduced.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>(),
+            )
+        });
+        assert!(ok);
+        // x -> x^3 maps 1 + 2x + 3x^2 + 4x^3 to 1 + 2x^3 + 3x^6 + 4x^9 = 1 + 4x - 3x^2 + 2x^3, as x^4 = -1
+        assert_eq!(rotated.0, vec![Fr::from(1), Fr::from(4), -Fr::from(3), Fr::from(2)]);
+        assert_eq!(rotated.1, apply_automorphism_host(&a, 3, q));
+    }
+
+    #[test]
+    fn test_conjugate() {
+        let q = 1048573;
+        let a = [1, 2, 3, 4];
+        let (out, ok) = mock_run(10, 8, |ctx, range| {
+            let a = load_poly(ctx, &a);
+            let conj = conjugate(ctx, &range.gate, &a, 4).unwrap();
+            let general = apply_automorphism(ctx, &range.gate, &a, 7, 4).unwrap();
+            let reduced =
+                poly_reduce_ring_assigned(ctx, range, &conj, 4, q, q as u128 - 1).unwrap();
+            [conj, general, reduced].map(|p| p.iter().map(|x| *x.value()).collect::<Vec<_>>())
+        });
+        assert!(ok);
+        // x -> x^7 = x^(-1) maps 1 + 2x + 3x^2 + 4x^3 to 1 - 4x - 3x^2 - 2x^3, as x^4 = -1
+        assert_eq!(out[0], vec![Fr::from(1), -Fr::from(4), -Fr::from(3), -Fr::from(2)]);
+        assert_eq!(out[0], out[1]);
+        let host = conjugate_host(&a, q);
+        assert_eq!(host, apply_automorphism_host(&a, 7, q));
+        assert_eq!(out[2], host.iter().map(|x| Fr::from(*x)).collect::<Vec<_>>());
+
+        let (errors, _) = mock_run(10, 8, |ctx, range| {
+            let a = load_poly(ctx, &[1, 2, 3, 4]);
+            [3, 0].map(|n| conjugate(ctx, &range.gate, &a, n).unwrap_err())
+        });
+        assert_eq!(
+            errors,
+            [CircuitError::WrongDegree { expected: 2, found: 3 }, CircuitError::EmptyInput]
+        );
+    }
+
+    #[test]
+    fn test_poly_trace() {
+        let (n, q) = (8, 1048573);
+        let mut rng = StdRng::seed_from_u64(0);
+        let a = (0..n).map(|_| rng.gen_range(0..q)).collect::<Vec<_>>();
+        let generators: [&[usize]; 4] = [&[5], &[15], &[5, 15], &[]];
+        let (out, ok) = mock_run(11, 8, |ctx, range| {
+            let a = load_poly(ctx, &a);
+            generators.map(|g| {
+                let (subgroup, trace) = poly_trace(ctx, range, &a, g, n, q).unwrap();
+                (subgroup, trace.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>())
+            })
+        });
+        assert!(ok);
+        for (subgroup, trace) in &out {
+            assert_eq!(*trace, poly_trace_host(&a, subgroup, q));
+        }
+        assert_eq!(out[0].0, vec![1, 5, 9, 13]);
+        // 15 = 2n - 1 is the conjugation
+        assert_eq!(out[1].1, ring_add(&a, &conjugate_host(&a, q), q));
+        // over the whole Galois group, the sum of x^(ik) vanishes for every i > 0, so the trace is n * a_0
+        let mut expected = vec![0; n];
+        expected[0] = n as u64 * a[0] % q;
+        assert_eq!(out[2].1, expected);
+        assert_eq!(out[3], (vec![1], a.clone()));
+
+        let (err, _) = mock_run(11, 8, |ctx, range| {
+            let a = load_poly(ctx, &a);
+            poly_trace(ctx, range, &a, &[5, 4], n, q).unwrap_err()
+        });
+        assert_eq!(err, CircuitError::InvalidGaloisElement { k: 4, n });
+    }
+
+    #[test]
+    fn test_poly_compose_against_arkworks() {
+        use ark_bn254::Fr as ArkFr;
+        use ark_poly::univariate::{DenseOrSparsePolynomial, DensePolynomial};
+        use ark_poly::DenseUVPolynomial;
+
+        let n = 4;
+        let (p, q) = ([2, -1, 0, 3], [1, 0, 2, -1, 1]);
+        let (out, ok) = mock_run(10, 8, |ctx, range| {
+            let [p, q] = [&p[..], &q].map(|c| {
+                c.iter().map(|x| ctx.load_witness(fe_from_i64::<Fr>(*x))).collect::<Vec<_>>()
+            });
+            let out = poly_compose(ctx, &range.gate, &p, &q, n).unwrap();
+            out.iter().map(|x| *x.value()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+
+        // compose over the integers with Horner's rule, then reduce mod x^n + 1
+        let to_ark = |c: &[i64]| {
+            DensePolynomial::from_coefficients_vec(c.iter().map(|x| ArkFr::from(*x)).collect())
+        };
+        let q = to_ark(&q);
+        let composed = p.iter().rev().fold(to_ark(&[0]), |acc, c| &(&acc * &q) + &to_ark(&[*c]));
+        let mut cyclo = vec![0; n + 1];
+        (cyclo[0], cyclo[n]) = (1, 1);
+        let (_, rem) = DenseOrSparsePolynomial::from(&composed)
+            .divide_with_q_and_r(&DenseOrSparsePolynomial::from(&to_ark(&cyclo)))
+            .unwrap();
+        let mut expected = rem.coeffs;
+        expected.resize(n, ArkFr::from(0));
+        // the coefficients are small signed integers, which are compared in the arkworks field
+        let out = out.iter().map(|x| ArkFr::from(decode_signed(x).unwrap())).collect::<Vec<_>>();
+        assert_eq!(out, expected);
+
+        let (errors, _) = mock_run(10, 8, |ctx, range| {
+            let a = load_poly(ctx, &[1, 2]);
+            [(&[][..], &a[..], n), (&a[..], &[][..], n), (&a[..], &a[..], 0)]
+                .map(|(p, q, n)| poly_compose(ctx, &range.gate, p, q, n).unwrap_err())
+        });
+        assert!(errors.iter().all(|e| *e == CircuitError::EmptyInput));
+    }
+
+    #[test]
+    fn test_apply_automorphism_invalid() {
+        let (errors, _) = mock_run(10, 8, |ctx, range| {
+            let a = load_poly(ctx, &[1, 2, 3, 4]);
+            [(2, 4), (12, 4), (3, 3), (3, 0)]
+                .map(|(k, n)| apply_automorphism(ctx, &range.gate, &a, k, n).unwrap_err())
+        });
+        // k = 2 sends both x^0 and x^2 to +-1, so the map is not a permutation
+        assert_eq!(errors[0], CircuitError::InvalidGaloisElement { k: 2, n: 4 });
+        assert_eq!(errors[1], CircuitError::InvalidGaloisElement { k: 12, n: 4 });
+        assert_eq!(errors[2], CircuitError::WrongDegree { expected: 2, found: 3 });
+        assert_eq!(errors[3], CircuitError::EmptyInput);
+    }
+
+    #[test]
+    fn test_assert_congruent_mod_cyclo() {
+        let n = 4;
+        // the unreduced product b has 8 coefficients, and a is its reduction mod x^4 + 1
+        let b = poly_mul(&[3, 2, 5, 7], &[0, 5, 1, 1, 9]);
+        let a = reduce_mod_cyclo(&b, n);
+        // b = b_low + x^4 * b_high = (b_low - b_high) + b_high * (x^4 + 1), so a = b - b_high * (x^4 + 1)
+        let k = b[n..].iter().map(|x| -x).collect::<Vec<_>>();
+
+        let run = |a: &[i128], k: &[i128]| {
+            mock_run(10, 8, |ctx, range| {
+                let [a, b, k] = [a, &b, k].map(|p| {
+                    p.iter()
+                        .map(|x| ctx.load_witness(fe_from_i64::<Fr>(*x as i64)))
+                        .collect::<Vec<_>>()
+                });
+                assert_congruent_mod_cyclo(ctx, &range.gate, &a, &b, &k, n);
+            })
+            .1
+        };
+        assert!(run(&a, &k));
+        // the quotient and the reduced polynomial are both determined by b
+        let mut wrong_k = k.clone();
+        wrong_k[1] += 1;
+        assert!(!run(&a, &wrong_k));
+        let mut wrong_a = a.clone();
+        wrong_a[3] += 1;
+        assert!(!run(&wrong_a, &k));
+        // adding x^4 + 1 to a is compensated by the quotient
+        let mut shifted_a = a.clone();
+        shifted_a[0] += 1;
+        shifted_a.push(1);
+        let mut shifted_k = k.clone();
+        shifted_k[0] += 1;
+        assert!(!run(&shifted_a, &k));
+        assert!(run(&shifted_a, &shifted_k));
+    }
+
+    #[test]
+    fn test_assert_congruent_scalar() {
+        let num_bits = 20;
+        let run = |a: &[u64], b: &[u64], t: u64| {
+            mock_run(10, 8, |ctx, range| {
+                let [a, b] = [a, b].map(|p| load_poly(ctx, p));
+                assert_congruent_scalar(ctx, range, &a, &b, t, num_bits)
+            })
+        };
+        // the differences -8, 16, 1000, -(2^20 - 1 - 7) and 0 are multiples of t = 8, including negative ones and the largest ones
+        let (a, b) = ([3, 19, 1000, 7, 5], [11, 3, 0, (1 << 20) - 1, 5]);
+        assert_eq!(run(&a, &b, 8), (Ok(()), true));
+        // 1 - 0 and 2 - 3 are not
+        for (a, b) in [([1, 19], [0, 3]), ([3, 2], [11, 3])] {
+            assert_eq!(run(&a, &b, 8), (Ok(()), false));
+        }
+        // t does not need to be a power of two, and every integer is congruent to its reduction
+        let (a, b) = ([1048572, 700000, 6], [1048572 % 7, 700000 % 7, 13]);
+        assert_eq!(run(&a, &b, 7), (Ok(()), true));
+        assert_eq!(run(&[1048572], &[1], 7), (Ok(()), false));
+
+        assert_eq!(
+            run(&[1, 2], &[1], 8).0,
+            Err(CircuitError::LengthMismatch { left: 2, right: 1 })
+        );
+        assert_eq!(run(&[1], &[2], 1).0, Err(CircuitError::OutOfRange { value: 1, bound: 2 }));
+    }
+
+    #[test]
+    fn test_poly_neg() {
+        let q = 1048573;
+        let a = [0, 1, 12345, q - 1];
+        let ([neg, twice], ok) = mock_run(10, 8, |ctx, range| {
+            let a = load_poly(ctx, &a);
+            let neg = poly_neg(ctx, range, &a, q);
+            let twice = poly_neg(ctx, range, &neg, q);
+            for (x, y) in a.iter().zip(twice.iter()) {
+                ctx.constrain_equal(x, y);
+            }
+            // c + neg(c) is 0 for c = 0 and q otherwise, so it reduces to 0 mod q
+            let sum = poly_add_assigned(ctx, &range.gate, &a, &neg).unwrap();
+            let reduced = poly_reduce_mod_assigned(ctx, range, &sum, q, 21).unwrap();
+            assert_poly_zero(ctx, &range.gate, &reduced);
+            [neg, twice].map(|p| p.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>())
+        });
+        assert!(ok);
+        assert_eq!(neg, vec![0, q - 1, q - 12345, 1]);
+        assert_eq!(neg, ring_neg(&a, q));
+        assert_eq!(twice, a);
+    }
+
+    #[test]
+    fn test_poly_neg_unreduced() {
+        // q and q + 1 are not reduced, so q - c would be 0 and p - 1 in the field
+        let q = 1048573;
+        for c in [q, q + 1, 2 * q] {
+            let (_, ok) = mock_run(10, 8, |ctx, range| {
+                let a = load_poly(ctx, &[1, c, 3, 4]);
+                poly_neg(ctx, range, &a, q);
+            });
+            assert!(!ok, "{c} is accepted");
+        }
+    }
+}
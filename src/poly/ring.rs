@@ -0,0 +1,226 @@
+use halo2_base::gates::{RangeChip, RangeInstructions};
+use halo2_base::utils::ScalarField;
+use halo2_base::{AssignedValue, Context};
+
+use crate::error::CircuitError;
+use crate::poly::operations::{
+    assert_reduced, load_poly, poly_add_assigned, poly_mul_assigned, poly_neg,
+    poly_reduce_mod_assigned, poly_reduce_ring_assigned, poly_scalar_mul_assigned,
+};
+use crate::poly::utils::bit_length;
+
+/// The ring `R_q = Z_q[x]/(x^n + 1)`, which ties the ring dimension `n` and the modulus `q` to the in-circuit operations on its elements.
+///
+/// An element of the ring is a slice of `n` assigned coefficients in `[0, q)`, lowest degree first, and every operation returns an element in this form.
+/// The operations check the number of coefficients of their inputs and return a `LengthMismatch` error for a polynomial of another ring, instead of taking `n` and `q` as arguments.
+/// They do not range check their inputs: load the elements with `load`, or check them with `range_check`, first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CyclotomicRing {
+    pub n: usize,
+    pub q: u64,
+}
+
+impl CyclotomicRing {
+    /// Returns the ring after checking that `n` is a power of two, so that `x^n + 1` is cyclotomic, and that `q` is at least `2`.
+    pub fn new(n: usize, q: u64) -> Result<Self, CircuitError> {
+        if !n.is_power_of_two() {
+            return Err(CircuitError::InvalidRingDegree { n });
+        }
+        if q < 2 {
+            return Err(CircuitError::OutOfRange { value: q, bound: 2 });
+        }
+        Ok(Self { n, q })
+    }
+
+    // checks that every polynomial has n coefficients
+    fn check(&self, polys: &[&[AssignedValue<impl ScalarField>]]) -> Result<(), CircuitError> {
+        match polys.iter().find(|p| p.len() != self.n) {
+            Some(p) => Err(CircuitError::LengthMismatch { left: self.n, right: p.len() }),
+            None => Ok(()),
+        }
+    }
+
+    /// Assigns the coefficients as witnesses and constrains them to be in `[0, q)`.
+    pub fn load<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        range: &RangeChip<F>,
+        coeffs: &[u64],
+    ) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+        let a = load_poly(ctx, coeffs);
+        self.range_check(ctx, range, &a)?;
+        Ok(a)
+    }
+
+    /// Constrains every coefficient of `a` to be in `[0, q)`, see `assert_reduced`.
+    pub fn range_check<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        range: &RangeChip<F>,
+        a: &[AssignedValue<F>],
+    ) -> Result<(), CircuitError> {
+        self.check(&[a])?;
+        assert_reduced(ctx, range, a, self.q);
+        Ok(())
+    }
+
+    /// Returns `a + b` in the ring. The sum of two coefficients is below `2q`, so it is reduced with a single `div_mod`.
+    pub fn add<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        range: &RangeChip<F>,
+        a: &[AssignedValue<F>],
+        b: &[AssignedValue<F>],
+    ) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+        self.check(&[a, b])?;
+        let sum = poly_add_assigned(ctx, range.gate(), a, b)?;
+        poly_reduce_mod_assigned(ctx, range, &sum, self.q, bit_length(2 * self.q as u128))
+    }
+
+    /// Returns `-a` in the ring, see `poly_neg`, which also constrains the coefficients of `a` to be in `[0, q)`.
+    pub fn neg<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        range: &RangeChip<F>,
+        a: &[AssignedValue<F>],
+    ) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+        self.check(&[a])?;
+        Ok(poly_neg(ctx, range, a, self.q))
+    }
+
+    /// Returns `a - b` in the ring, computed as `a + (-b)`.
+    pub fn sub<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        range: &RangeChip<F>,
+        a: &[AssignedValue<F>],
+        b: &[AssignedValue<F>],
+    ) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+        let neg_b = self.neg(ctx, range, b)?;
+        self.add(ctx, range, a, &neg_b)
+    }
+
+    /// Returns `a * b` in the ring.
+    ///
+    /// The product is computed with `poly_mul_assigned`, folded mod `x^n + 1` and reduced mod `q`, which requires its coefficients, bounded by `n * (q - 1)^2`, to fit in the field.
+    /// Returns a `FieldOverflow` error when they do not.
+    pub fn mul<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        range: &RangeChip<F>,
+        a: &[AssignedValue<F>],
+        b: &[AssignedValue<F>],
+    ) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+        self.check(&[a, b])?;
+        let q = self.q as u128 - 1;
+        // the reduction shifts the coefficients by up to twice their bound in a u128
+        let capacity = (F::CAPACITY as usize - 1).min(127);
+        let max_abs = q.checked_mul(q).and_then(|x| x.checked_mul(self.n as u128));
+        let num_bits = match max_abs {
+            Some(max_abs) => bit_length(max_abs) + 1,
+            None => 2 * bit_length(q) + bit_length(self.n as u128) + 1,
+        };
+        if num_bits > capacity {
+            return Err(CircuitError::FieldOverflow { num_bits, capacity });
+        }
+        let prod = poly_mul_assigned(ctx, range.gate(), a, b)?;
+        self.reduce(ctx, range, &prod, max_abs.unwrap())
+    }
+
+    /// Returns `k * a` in the ring for a scalar `k` that the caller has range checked to `[0, q)`, so that every product is at most `(q - 1)^2` before it is reduced mod `q`.
+    pub fn scalar_mul<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        range: &RangeChip<F>,
+        a: &[AssignedValue<F>],
+        k: AssignedValue<F>,
+    ) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+        self.check(&[a])?;
+        let scaled = poly_scalar_mul_assigned(ctx, range.gate(), a, k);
+        let num_bits = bit_length((self.q as u128 - 1).pow(2)).max(bit_length(self.q as u128));
+        poly_reduce_mod_assigned(ctx, range, &scaled, self.q, num_bits)
+    }
+
+    /// Reduces a polynomial of any length to an element of the ring, where its coefficients are signed integers with absolute value at most `max_abs` after the negacyclic folding,
+    /// see `poly_reduce_ring_assigned`, whose errors are returned.
+    pub fn reduce<F: ScalarField>(
+        &self,
+        ctx: &mut Context<F>,
+        range: &RangeChip<F>,
+        coeffs: &[AssignedValue<F>],
+        max_abs: u128,
+    ) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+        poly_reduce_ring_assigned(ctx, range, coeffs, self.n, self.q, max_abs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CyclotomicRing;
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_base::utils::ScalarField;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::error::CircuitError;
+    use crate::poly::operations::load_poly;
+    use crate::poly::utils::{ring_add, ring_mul, ring_neg};
+    use crate::test_utils::mock_run;
+
+    const N: usize = 4;
+    const Q: u64 = 1048573;
+
+    #[test]
+    fn test_ring_operations() {
+        let ring = CyclotomicRing::new(N, Q).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let [a, b] = [(); 2].map(|_| (0..N).map(|_| rng.gen_range(0..Q)).collect::<Vec<_>>());
+
+        // 3 * (a * b - (a + b) + (-a)), computed with the ring methods and on the host
+        let (out, ok) = mock_run(11, 8, |ctx, range| {
+            let [x, y] = [&a, &b].map(|p| ring.load(ctx, range, p).unwrap());
+            let prod = ring.mul(ctx, range, &x, &y).unwrap();
+            let sum = ring.add(ctx, range, &x, &y).unwrap();
+            let diff = ring.sub(ctx, range, &prod, &sum).unwrap();
+            let neg = ring.neg(ctx, range, &x).unwrap();
+            let out = ring.add(ctx, range, &diff, &neg).unwrap();
+            let three = ctx.load_witness(Fr::from(3));
+            let out = ring.scalar_mul(ctx, range, &out, three).unwrap();
+            out.iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        let diff = ring_add(&ring_mul(&a, &b, Q), &ring_neg(&ring_add(&a, &b, Q), Q), Q);
+        assert_eq!(out, ring_mul(&ring_add(&diff, &ring_neg(&a, Q), Q), &[3, 0, 0, 0], Q));
+    }
+
+    #[test]
+    fn test_ring_rejects_other_rings() {
+        assert_eq!(CyclotomicRing::new(6, Q), Err(CircuitError::InvalidRingDegree { n: 6 }));
+        assert_eq!(CyclotomicRing::new(N, 1), Err(CircuitError::OutOfRange { value: 1, bound: 2 }));
+
+        let ring = CyclotomicRing::new(N, Q).unwrap();
+        let (errors, _) = mock_run(11, 8, |ctx, range| {
+            let a = load_poly(ctx, &[1, 2, 3, 4]);
+            let b = load_poly(ctx, &[1, 2, 3, 4, 5, 6, 7, 8]);
+            [
+                ring.add(ctx, range, &a, &b),
+                ring.mul(ctx, range, &b, &a),
+                ring.neg(ctx, range, &b),
+                ring.scalar_mul(ctx, range, &b, a[0]),
+            ]
+            .map(|r| r.unwrap_err())
+        });
+        assert!(errors.iter().all(|e| *e == CircuitError::LengthMismatch { left: N, right: 8 }));
+
+        // a coefficient that is not reduced mod q is rejected by the range check of `load`
+        let (_, ok) = mock_run(11, 8, |ctx, range| ring.load(ctx, range, &[1, 2, 3, Q]).unwrap());
+        assert!(!ok);
+
+        // the coefficients of the unreduced product must fit in the field and in a u128
+        let ring = CyclotomicRing::new(N, u64::MAX).unwrap();
+        let (err, _) = mock_run(11, 8, |ctx, range| {
+            let a = load_poly(ctx, &[0; N]);
+            ring.mul(ctx, range, &a, &a).unwrap_err()
+        });
+        assert!(matches!(err, CircuitError::FieldOverflow { capacity: 127, .. }));
+    }
+}
@@ -0,0 +1,303 @@
+use halo2_base::gates::{GateInstructions, RangeChip, RangeInstructions};
+use halo2_base::utils::ScalarField;
+use halo2_base::{AssignedValue, Context, QuantumCell::Constant};
+
+use crate::error::CircuitError;
+use crate::poly::ntt::negacyclic_ntt_assigned;
+use crate::poly::operations::assert_reduced;
+use crate::poly::utils::{bit_length, inv_mod, mul_mod_wide};
+
+/// Returns the RNS modulus `Q`, i.e. the product of the pairwise coprime `primes`.
+///
+/// Returns a `ZeroModulus` error if one of the primes is `0`, and a `FieldOverflow` error when the product does not fit in 128 bits.
+pub fn rns_modulus(primes: &[u64]) -> Result<u128, CircuitError> {
+    if primes.contains(&0) {
+        return Err(CircuitError::ZeroModulus);
+    }
+    primes.iter().try_fold(1u128, |acc, q| acc.checked_mul(*q as u128)).ok_or_else(|| {
+        // the product of the primes has at most the sum of their bit lengths, which exceeds 128 when it overflows
+        let num_bits = primes.iter().map(|q| bit_length(*q as u128)).sum();
+        CircuitError::FieldOverflow { num_bits, capacity: 128 }
+    })
+}
+
+// the CRT coefficients `(Q / q_i) * ((Q / q_i)^-1 mod q_i)` mod `Q`, such that `x = sum_i x_i * c_i mod Q` for the residues `x_i = x mod q_i`
+fn crt_coefficients(primes: &[u64]) -> Result<Vec<u128>, CircuitError> {
+    let modulus = rns_modulus(primes)?;
+    Ok(primes
+        .iter()
+        .map(|q| {
+            let q_hat = modulus / *q as u128;
+            mul_mod_wide(q_hat, inv_mod((q_hat % *q as u128) as u64, *q) as u128, modulus)
+        })
+        .collect())
+}
+
+/// Decomposes a polynomial with coefficients in `[0, Q)` into its RNS representation, i.e. one polynomial of residues in `[0, q_i)` per prime `q_i`.
+pub fn rns_decompose(coeffs: &[u128], primes: &[u64]) -> Vec<Vec<u64>> {
+    primes.iter().map(|q| coeffs.iter().map(|c| (c % *q as u128) as u64).collect()).collect()
+}
+
+/// Recomposes a polynomial with coefficients in `[0, Q)` from its RNS representation with the CRT. This is the inverse of `rns_decompose`.
+/// Returns the errors of `rns_modulus`.
+pub fn rns_compose(residues: &[Vec<u64>], primes: &[u64]) -> Result<Vec<u128>, CircuitError> {
+    let modulus = rns_modulus(primes)?;
+    let crt = crt_coefficients(primes)?;
+    Ok((0..residues[0].len())
+        .map(|j| {
+            residues
+                .iter()
+                .zip(crt.iter())
+                .fold(0, |acc, (r, c)| (acc + mul_mod_wide(r[j] as u128, *c, modulus)) % modulus)
+        })
+        .collect())
+}
+
+// checks that there is one non-empty polynomial per prime, all with the same number of coefficients `n`, and returns `n`
+fn check_rns_shape<F: ScalarField>(
+    polys: &[Vec<AssignedValue<F>>],
+    primes: &[u64],
+) -> Result<usize, CircuitError> {
+    if polys.len() != primes.len() {
+        return Err(CircuitError::LengthMismatch { left: primes.len(), right: polys.len() });
+    }
+    let n = polys.first().map_or(0, |poly| poly.len());
+    if n == 0 {
+        return Err(CircuitError::EmptyInput);
+    }
+    match polys.iter().find(|poly| poly.len() != n) {
+        Some(poly) => Err(CircuitError::LengthMismatch { left: n, right: poly.len() }),
+        None => Ok(n),
+    }
+}
+
+/// Recomposes a polynomial from its RNS representation in the circuit, and returns its coefficients in `[0, Q)`.
+///
+/// The residues mod `primes[i]` are `residues[i]`, and are constrained to be in `[0, primes[i])`. Every coefficient is then the inner product of its residues with the constant CRT coefficients, reduced mod `Q`.
+/// As the residues of a value in `[0, Q)` are unique, constraining the output to equal a given polynomial proves that `residues` is its RNS decomposition.
+/// Returns the errors of `rns_modulus`, and a `FieldOverflow` error when the inner products do not fit in the field.
+pub fn rns_compose_assigned<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    residues: &[Vec<AssignedValue<F>>],
+    primes: &[u64],
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let n = check_rns_shape(residues, primes)?;
+    let modulus = rns_modulus(primes)?;
+    // the inner products are sums of k terms below q_max * Q
+    let q_max = *primes.iter().max().unwrap() as u128;
+    let num_bits = bit_length(modulus - 1) + bit_length(primes.len() as u128 * (q_max - 1));
+    let capacity = F::CAPACITY as usize - 1;
+    if num_bits > capacity {
+        return Err(CircuitError::FieldOverflow { num_bits, capacity });
+    }
+
+    for (poly, q) in residues.iter().zip(primes.iter()) {
+        assert_reduced(ctx, range, poly, *q);
+    }
+    let crt = crt_coefficients(primes)?;
+    Ok((0..n)
+        .map(|j| {
+            let sum = range.gate().inner_product(
+                ctx,
+                residues.iter().map(|poly| poly[j]),
+                crt.iter().map(|c| Constant(F::from_u128(*c))),
+            );
+            range.div_mod(ctx, sum, modulus, num_bits).1
+        })
+        .collect())
+}
+
+/// Constrains that the residues in the channels `primes` are consistent, i.e. that they are the residues of the same values, and returns these values.
+///
+/// The values are reconstructed with `rns_compose_assigned` and range checked to `value_bits` bits. Any residues in `[0, q_i)` are the residues of exactly one value in `[0, Q)`,
+/// which is the one the composition outputs, so reducing it mod every prime again would only give back the residues: the `value_bits` bound is the only soundness this adds.
+/// `value_bits` is a public bound on the reconstructed values, so a representation of a value of `value_bits` or more bits is rejected even though its residues are consistent.
+/// Returns an `InvalidBitWidth` error when `value_bits` is zero or does not fit in the field.
+pub fn assert_crt_consistent<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    residues: &[Vec<AssignedValue<F>>],
+    primes: &[u64],
+    value_bits: usize,
+) -> Result<Vec<AssignedValue<F>>, CircuitError> {
+    let max = F::CAPACITY as usize - 1;
+    if value_bits == 0 || value_bits > max {
+        return Err(CircuitError::InvalidBitWidth { num_bits: value_bits, min: 1, max });
+    }
+    let values = rns_compose_assigned(ctx, range, residues, primes)?;
+    // the values are in [0, Q), so the range check only restricts them further when value_bits < bit_length(Q - 1)
+    let num_bits = value_bits.min(bit_length(rns_modulus(primes)? - 1));
+    for x in &values {
+        range.range_check(ctx, *x, num_bits);
+    }
+    Ok(values)
+}
+
+/// Constrains that `residues` and `evals` are the RNS and NTT representation of the polynomial `coeffs`, as stored by RNS based FHE libraries.
+///
+/// `residues[i]` must be the RNS decomposition of `coeffs` mod `primes[i]` (see `rns_compose_assigned`), and `evals[i]` must be the `negacyclic_ntt` of `residues[i]` with the primitive `2n`-th root of unity `psis[i]`, in natural order.
+/// Representations stored in bit-reversed order should be reordered with `bit_reverse_permute` first.
+/// The coefficients of `coeffs` are constrained to be in `[0, Q)`.
+pub fn assert_rns_ntt_representation<F: ScalarField>(
+    ctx: &mut Context<F>,
+    range: &RangeChip<F>,
+    coeffs: &[AssignedValue<F>],
+    residues: &[Vec<AssignedValue<F>>],
+    evals: &[Vec<AssignedValue<F>>],
+    primes: &[u64],
+    psis: &[u64],
+) -> Result<(), CircuitError> {
+    let n = check_rns_shape(residues, primes)?;
+    if check_rns_shape(evals, primes)? != n {
+        return Err(CircuitError::LengthMismatch { left: n, right: evals[0].len() });
+    }
+    if coeffs.len() != n {
+        return Err(CircuitError::LengthMismatch { left: n, right: coeffs.len() });
+    }
+    if psis.len() != primes.len() {
+        return Err(CircuitError::LengthMismatch { left: primes.len(), right: psis.len() });
+    }
+
+    // CRT recomposition
+    let composed = rns_compose_assigned(ctx, range, residues, primes)?;
+    for (x, y) in composed.iter().zip(coeffs.iter()) {
+        ctx.constrain_equal(x, y);
+    }
+
+    // NTT consistency, for each prime
+    for (((poly, claimed), q), psi) in residues.iter().zip(evals).zip(primes).zip(psis) {
+        let computed = negacyclic_ntt_assigned(ctx, range, poly, *psi, *q)?;
+        for (x, y) in computed.iter().zip(claimed.iter()) {
+            ctx.constrain_equal(x, y);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        assert_crt_consistent, assert_rns_ntt_representation, rns_compose, rns_compose_assigned,
+        rns_decompose, rns_modulus,
+    };
+    use halo2_base::utils::ScalarField;
+
+    use crate::error::CircuitError;
+    use crate::poly::ntt::{negacyclic_ntt, primitive_root_of_unity};
+    use crate::poly::operations::load_poly;
+    use crate::test_utils::mock_run;
+
+    // two NTT-friendly primes for n = 4, i.e. with 2n | q - 1
+    const PRIMES: [u64; 2] = [7681, 12289];
+    const COEFFS: [u64; 4] = [94391808, 12345678, 0, 50000001];
+    // RNS and NTT representation of COEFFS, computed independently of this crate
+    const RESIDUES: [[u64; 4]; 2] = [[7680, 2311, 0, 4372], [12288, 7522, 0, 8349]];
+    const EVALS: [[u64; 4]; 2] = [[1953, 1969, 5726, 5710], [5438, 737, 6849, 11550]];
+    const PSIS: [u64; 2] = [1213, 8246];
+
+    #[test]
+    fn test_rns_host_reference() {
+        assert_eq!(rns_modulus(&PRIMES), Ok(94391809));
+        let coeffs = COEFFS.map(|c| c as u128);
+        let residues = rns_decompose(&coeffs, &PRIMES);
+        assert_eq!(residues, RESIDUES.map(|r| r.to_vec()));
+        assert_eq!(rns_compose(&residues, &PRIMES), Ok(coeffs.to_vec()));
+        for i in 0..2 {
+            assert_eq!(primitive_root_of_unity(8, PRIMES[i]), Ok(PSIS[i]));
+            assert_eq!(negacyclic_ntt(&residues[i], PSIS[i], PRIMES[i]), EVALS[i]);
+        }
+    }
+
+    // runs `assert_rns_ntt_representation` on the given representation of COEFFS
+    fn check_representation(residues: [[u64; 4]; 2], evals: [[u64; 4]; 2]) -> bool {
+        let (res, ok) = mock_run(12, 11, |ctx, range| {
+            let coeffs = load_poly(ctx, &COEFFS);
+            let residues = residues.map(|r| load_poly(ctx, &r));
+            let evals = evals.map(|e| load_poly(ctx, &e));
+            assert_rns_ntt_representation(ctx, range, &coeffs, &residues, &evals, &PRIMES, &PSIS)
+        });
+        res.unwrap();
+        ok
+    }
+
+    #[test]
+    fn test_rns_ntt_representation() {
+        assert!(check_representation(RESIDUES, EVALS));
+    }
+
+    #[test]
+    fn test_rns_ntt_representation_corrupted() {
+        // the NTT of one residue polynomial is off in a single evaluation
+        let mut evals = EVALS;
+        evals[1][2] += 1;
+        assert!(!check_representation(RESIDUES, evals));
+
+        // a residue is shifted by its prime, so it still has the right value mod q but is out of range
+        let mut residues = RESIDUES;
+        residues[0][1] += PRIMES[0];
+        assert!(!check_representation(residues, EVALS));
+    }
+
+    #[test]
+    fn test_rns_modulus_invalid() {
+        // three 60-bit primes do not fit in a u128
+        let primes = [(1 << 60) - 93, (1 << 60) - 173, (1 << 60) - 257];
+        assert_eq!(
+            rns_modulus(&primes),
+            Err(CircuitError::FieldOverflow { num_bits: 180, capacity: 128 })
+        );
+        assert_eq!(rns_modulus(&[7681, 0]), Err(CircuitError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_rns_compose_wrong_shape() {
+        let (err, _) = mock_run(12, 11, |ctx, range| {
+            let residues = [load_poly(ctx, &RESIDUES[0])];
+            rns_compose_assigned(ctx, range, &residues, &PRIMES).err()
+        });
+        assert_eq!(err, Some(CircuitError::LengthMismatch { left: 2, right: 1 }));
+    }
+
+    // runs `assert_crt_consistent` on residues mod [97, 193] and returns the reconstructed values
+    fn check_crt(residues: [[u64; 3]; 2], value_bits: usize) -> (Vec<u64>, bool) {
+        let (values, ok) = mock_run(11, 8, |ctx, range| {
+            let residues = residues.map(|r| load_poly(ctx, &r));
+            let values = assert_crt_consistent(ctx, range, &residues, &[97, 193], value_bits);
+            values.unwrap().iter().map(|x| x.value().get_lower_64()).collect::<Vec<_>>()
+        });
+        (values, ok)
+    }
+
+    #[test]
+    fn test_crt_consistent() {
+        let values = [1234, 0, 1500];
+        let residues = [[70, 0, 45], [76, 0, 149]];
+        assert_eq!(
+            rns_decompose(&values.map(|x| x as u128), &[97, 193]),
+            residues.map(|r| r.to_vec())
+        );
+
+        // 1500 has 11 bits
+        assert_eq!(check_crt(residues, 11), (values.to_vec(), true));
+        assert!(!check_crt(residues, 10).1);
+
+        // the residues 1 mod 97 and 0 mod 193 are those of 18528, which only fits in 15 bits
+        let mut tampered = residues;
+        tampered[0][1] = 1;
+        assert!(!check_crt(tampered, 11).1);
+        assert_eq!(check_crt(tampered, 15), (vec![1234, 18528, 1500], true));
+    }
+
+    #[test]
+    fn test_crt_consistent_out_of_range() {
+        // a residue shifted by its prime has the right value mod 97, but is not a residue
+        assert!(!check_crt([[70 + 97, 0, 45], [76, 0, 149]], 15).1);
+
+        let (err, _) = mock_run(11, 8, |ctx, range| {
+            let residues = [[70], [76]].map(|r| load_poly(ctx, &r));
+            assert_crt_consistent(ctx, range, &residues, &[97, 193], 0).err()
+        });
+        assert_eq!(err, Some(CircuitError::InvalidBitWidth { num_bits: 0, min: 1, max: 252 }));
+    }
+}
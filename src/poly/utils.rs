@@ -0,0 +1,496 @@
+use halo2_base::utils::ScalarField;
+
+use crate::error::CircuitError;
+
+// the pure integer and polynomial arithmetic lives in `poly::math`, which does not depend on `std`
+pub use crate::poly::math::{
+    apply_automorphism, automorphism_index, barrett_reduce, bit_length, conjugate, div_euclid,
+    from_centered, gcd, infinity_norm, inv_mod, montgomery_bits, montgomery_reduce, mul_mod_wide,
+    norm_squared, poly_inverse_mod, poly_mul, poly_trace, pow_mod, reduce_mod_cyclo, reduce_mod_q,
+    ring_add, ring_mul, ring_mul_wide, ring_neg, to_centered,
+};
+
+/// Checks that a polynomial given by its coefficients has exactly the given degree, i.e. `degree + 1` coefficients.
+///
+/// This is meant to be called on the inputs at the top of every circuit: unlike comparing `coeffs.len() - 1` with the degree, it returns a clean `EmptyInput` error for an empty vector instead of underflowing.
+pub fn check_degree<T>(coeffs: &[T], degree: usize) -> Result<(), CircuitError> {
+    match coeffs.len() {
+        0 => Err(CircuitError::EmptyInput),
+        len if len != degree + 1 => {
+            Err(CircuitError::WrongDegree { expected: degree, found: len - 1 })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks that a polynomial has degree at most `degree`, i.e. at most `degree + 1` coefficients. An empty vector is the zero polynomial and passes.
+///
+/// This is the check to run on a polynomial of variable length, e.g. a trimmed remainder, before padding it with `pad_coeffs` or `poly::operations::pad_to_len`,
+/// which return the same error for a polynomial that is too long.
+pub fn check_max_degree<T>(coeffs: &[T], degree: usize) -> Result<(), CircuitError> {
+    match coeffs.len() {
+        len if len > degree + 1 => {
+            Err(CircuitError::DegreeTooLarge { max: degree, found: len - 1 })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Pads a little endian polynomial with zero high degree coefficients to exactly `len` coefficients.
+/// Returns a `DegreeTooLarge` error when it already has more than `len` coefficients.
+pub fn pad_coeffs<T: Clone + Default>(coeffs: &[T], len: usize) -> Result<Vec<T>, CircuitError> {
+    if coeffs.len() > len {
+        return Err(CircuitError::DegreeTooLarge {
+            max: len.saturating_sub(1),
+            found: coeffs.len() - 1,
+        });
+    }
+    let mut out = coeffs.to_vec();
+    out.resize(len, T::default());
+    Ok(out)
+}
+
+/// Checks that every coefficient is in `[0, bound)`.
+pub fn check_coeffs_bound(coeffs: &[u64], bound: u64) -> Result<(), CircuitError> {
+    match coeffs.iter().find(|&&x| x >= bound) {
+        Some(&value) => Err(CircuitError::OutOfRange { value, bound }),
+        None => Ok(()),
+    }
+}
+
+/// Returns the subgroup of the Galois group `(Z/2nZ)^*` of `Z[x]/(x^n + 1)` generated by `generators`, as the sorted exponents `k` in `[1, 2n)` of its automorphisms `x -> x^k`.
+///
+/// The subgroup is enumerated by closing `{1}` under multiplication by the generators mod `2n`, so an empty list of generators gives the trivial subgroup `{1}`.
+/// Returns an `InvalidGaloisElement` error when a generator is not coprime to `2n`, and an `EmptyInput` error when `n` is zero.
+pub fn galois_subgroup(generators: &[usize], n: usize) -> Result<Vec<usize>, CircuitError> {
+    if n == 0 {
+        return Err(CircuitError::EmptyInput);
+    }
+    if let Some(&k) = generators.iter().find(|&&k| gcd(k as u64, 2 * n as u64) != 1) {
+        return Err(CircuitError::InvalidGaloisElement { k, n });
+    }
+    let mut subgroup = vec![1];
+    let mut i = 0;
+    while i < subgroup.len() {
+        for g in generators {
+            let k = subgroup[i] * (g % (2 * n)) % (2 * n);
+            if !subgroup.contains(&k) {
+                subgroup.push(k);
+            }
+        }
+        i += 1;
+    }
+    subgroup.sort_unstable();
+    Ok(subgroup)
+}
+
+/// Converts a signed integer into a field element, mapping negative values `-x` to `p - x` where `p` is the field modulus.
+pub fn fe_from_i64<F: ScalarField>(x: i64) -> F {
+    if x >= 0 {
+        F::from(x as u64)
+    } else {
+        -F::from(x.unsigned_abs())
+    }
+}
+
+// the little endian bytes of the number written with `digits` in base `radix`, or `None` if a digit is invalid
+fn digits_to_bytes_le(digits: &str, radix: u32) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    for c in digits.chars() {
+        let mut carry = c.to_digit(radix)?;
+        for byte in bytes.iter_mut() {
+            let x = *byte as u32 * radix + carry;
+            *byte = x as u8;
+            carry = x >> 8;
+        }
+        if carry > 0 {
+            bytes.push(carry as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// Parses a coefficient written as a decimal string, or as a hexadecimal string with a `0x` prefix, into a field element.
+///
+/// This reads coefficients that do not fit in a `u64`, up to the field modulus. Unlike `F::from_str_vartime`, a value that is not less than the modulus is rejected instead of being silently reduced,
+/// so the circuit proves a statement about exactly the coefficient in the input.
+pub fn parse_coeff<F: ScalarField>(s: &str) -> Result<F, CircuitError> {
+    let (digits, radix) = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => (hex, 16),
+        None => (s, 10),
+    };
+    let bytes = Some(digits)
+        .filter(|digits| !digits.is_empty())
+        .and_then(|digits| digits_to_bytes_le(digits, radix))
+        .ok_or_else(|| {
+            CircuitError::InvalidInput(format!(
+                "{s:?} is not a decimal or 0x-prefixed hexadecimal coefficient"
+            ))
+        })?;
+    let x =
+        bytes.iter().rev().fold(F::zero(), |acc, byte| acc * F::from(256) + F::from(*byte as u64));
+    // a value that wrapped around the modulus has different bytes
+    let mut canonical = x.to_bytes_le();
+    while canonical.last() == Some(&0) {
+        canonical.pop();
+    }
+    if canonical != bytes {
+        return Err(CircuitError::InvalidInput(format!(
+            "coefficient {s} is not less than the field modulus"
+        )));
+    }
+    Ok(x)
+}
+
+// returns `x` as an integer if it is less than 2^127
+fn fe_to_i128<F: ScalarField>(x: F) -> Option<i128> {
+    let limbs = x.to_u64_limbs(4, 64);
+    if limbs[2..].iter().any(|limb| *limb != 0) || limbs[1] >> 63 != 0 {
+        return None;
+    }
+    Some(((limbs[1] as i128) << 64) | limbs[0] as i128)
+}
+
+/// Decodes a field element into a signed integer, mapping `p - x` back to `-x` where `p` is the field modulus. This is the inverse of `fe_from_i64`.
+///
+/// Returns `None` when neither the element nor its negation is less than `2^127`, i.e. when it does not encode a small signed integer.
+pub fn decode_signed<F: ScalarField>(x: &F) -> Option<i128> {
+    fe_to_i128(*x).or_else(|| fe_to_i128(-*x).map(|x| -x))
+}
+
+/// Reads a field element holding a coefficient mod `q` back as its centered representative in `(-q/2, q/2]`, see `to_centered`.
+///
+/// This is meant for comparing the values of the circuit with the host-side computations: unlike `get_lower_32` or `get_lower_64`, which silently truncate the element,
+/// it returns an `OutOfRange` error, with the lower 64 bits of the element as its value, when the element is not in `[0, q)`, e.g. a coefficient that was not reduced mod `q`.
+pub fn field_to_i64<F: ScalarField>(v: &F, q: u64) -> Result<i64, CircuitError> {
+    match fe_to_i128(*v) {
+        Some(x) if x < q as i128 => Ok(to_centered(x as u64, q)),
+        _ => Err(CircuitError::OutOfRange { value: v.get_lower_64(), bound: q }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        apply_automorphism, barrett_reduce, check_coeffs_bound, check_degree, check_max_degree,
+        decode_signed, div_euclid, fe_from_i64, field_to_i64, from_centered, galois_subgroup,
+        inv_mod, montgomery_bits, montgomery_reduce, pad_coeffs, parse_coeff, poly_inverse_mod,
+        pow_mod, ring_mul, to_centered,
+    };
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_base::utils::ScalarField;
+    use serde::Deserialize;
+
+    use crate::error::CircuitError;
+
+    // multiplies two big endian polynomials over the integers
+    fn mul(a: &[i64], b: &[i64]) -> Vec<i64> {
+        if a.is_empty() || b.is_empty() {
+            return vec![];
+        }
+        let mut c = vec![0; a.len() + b.len() - 1];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                c[i + j] += x * y;
+            }
+        }
+        c
+    }
+
+    // checks that quot * g + rem == f, aligning the polynomials at the constant term
+    fn assert_division(f: &[i64], g: &[i64], quot: &[i64], rem: &[i64]) {
+        let mut lhs = mul(quot, g);
+        let len = lhs.len().max(rem.len()).max(f.len());
+        lhs.splice(0..0, vec![0; len - lhs.len()]);
+        for (i, r) in rem.iter().enumerate() {
+            lhs[len - rem.len() + i] += r;
+        }
+        let mut f = f.to_vec();
+        f.splice(0..0, vec![0; len - f.len()]);
+        assert_eq!(lhs, f);
+    }
+
+    #[test]
+    fn test_barrett_reduce() {
+        let q = 1073741789;
+        let num_bits = 64;
+        for x in [0, 1, q as u128 - 1, q as u128, 4 * (q as u128 - 1).pow(2), (1 << 64) - 1] {
+            assert_eq!(barrett_reduce(x, q, num_bits), Ok((x / q as u128, (x % q as u128) as u64)));
+        }
+        assert_eq!(barrett_reduce(5, 0, num_bits), Err(CircuitError::ZeroModulus));
+        // x * mu must fit in a u128, which allows (127 + 30) / 2 = 78 bits for a 30-bit q
+        assert_eq!(
+            barrett_reduce(5, q, 79),
+            Err(CircuitError::FieldOverflow { num_bits: 79, capacity: 78 })
+        );
+        assert_eq!(
+            barrett_reduce(1 << 64, q, num_bits),
+            Err(CircuitError::FieldOverflow { num_bits: 65, capacity: 64 })
+        );
+    }
+
+    #[test]
+    fn test_montgomery_reduce() {
+        let q = 1073741789;
+        let num_bits = 64;
+        let r_bits = montgomery_bits(q, num_bits);
+        assert_eq!(r_bits, 35);
+        let r_inv = inv_mod(pow_mod(2, r_bits as u64, q), q) as u128;
+        for x in [0, 1, q as u128 - 1, q as u128, 4 * (q as u128 - 1).pow(2), (1 << 64) - 1] {
+            let (m, t) = montgomery_reduce(x, q, r_bits).unwrap();
+            assert!(m < 1 << r_bits && t < 2 * q as u128);
+            assert_eq!(x + m * q as u128, t << r_bits);
+            assert_eq!(t % q as u128, x % q as u128 * r_inv % q as u128);
+        }
+        assert_eq!(montgomery_reduce(5, 0, r_bits), Err(CircuitError::ZeroModulus));
+        assert_eq!(montgomery_reduce(5, 1 << 20, r_bits), Err(CircuitError::NotInvertible));
+        // x + m * q must fit in a u128, which allows 127 - 30 = 97 bits of r for a 30-bit q
+        assert_eq!(
+            montgomery_reduce(5, q, 98),
+            Err(CircuitError::FieldOverflow { num_bits: 98, capacity: 97 })
+        );
+        assert_eq!(
+            montgomery_reduce((q as u128) << r_bits, q, r_bits),
+            Err(CircuitError::FieldOverflow { num_bits: 65, capacity: 64 })
+        );
+    }
+
+    #[test]
+    fn test_check_degree() {
+        assert_eq!(check_degree(&[1, 2, 3], 2), Ok(()));
+        assert_eq!(check_degree::<u64>(&[], 2), Err(CircuitError::EmptyInput));
+        assert_eq!(
+            check_degree(&[1, 2], 2),
+            Err(CircuitError::WrongDegree { expected: 2, found: 1 })
+        );
+    }
+
+    #[test]
+    fn test_check_max_degree() {
+        assert_eq!(check_max_degree(&[1, 2, 3], 2), Ok(()));
+        assert_eq!(check_max_degree(&[1], 2), Ok(()));
+        assert_eq!(check_max_degree::<i64>(&[], 2), Ok(()));
+        // a remainder longer than the dividend cannot be padded to its length
+        assert_eq!(
+            check_max_degree(&[1, 2, 3, 4], 2),
+            Err(CircuitError::DegreeTooLarge { max: 2, found: 3 })
+        );
+    }
+
+    #[test]
+    fn test_check_degree_empty_json_input() {
+        #[derive(Deserialize)]
+        struct CircuitInput {
+            a: Vec<u8>,
+            b: Vec<u8>,
+        }
+        let input: CircuitInput = serde_json::from_str(r#"{"a": [], "b": []}"#).unwrap();
+        assert_eq!(check_degree(&input.a, 3), Err(CircuitError::EmptyInput));
+        assert_eq!(check_degree(&input.b, 3), Err(CircuitError::EmptyInput));
+    }
+
+    #[test]
+    fn test_check_coeffs_bound() {
+        assert_eq!(check_coeffs_bound(&[0, 10, 256], 257), Ok(()));
+        assert_eq!(
+            check_coeffs_bound(&[0, 257, 300], 257),
+            Err(CircuitError::OutOfRange { value: 257, bound: 257 })
+        );
+    }
+
+    #[test]
+    fn test_pad_coeffs() {
+        assert_eq!(pad_coeffs(&[3u64, 2, 5], 5), Ok(vec![3, 2, 5, 0, 0]));
+        assert_eq!(pad_coeffs(&[-1i64, 4], 2), Ok(vec![-1, 4]));
+        assert_eq!(pad_coeffs::<u64>(&[], 3), Ok(vec![0, 0, 0]));
+        assert_eq!(
+            pad_coeffs(&[3u64, 2, 5], 2),
+            Err(CircuitError::DegreeTooLarge { max: 1, found: 2 })
+        );
+    }
+
+    #[test]
+    fn test_centered_boundary() {
+        // for odd q the centered range is [-(q-1)/2, (q-1)/2]
+        let q = 1048573;
+        let cases = [(0, 0), (1, 1), (q / 2, 524286), (q / 2 + 1, -524286), (q - 1, -1)];
+        for (x, centered) in cases {
+            assert_eq!(to_centered(x, q), centered);
+            assert_eq!(from_centered(centered, q), x);
+        }
+        // for even q the value q/2 stays positive
+        assert_eq!(to_centered(4, 8), 4);
+        assert_eq!(to_centered(5, 8), -3);
+        assert_eq!(from_centered(-4, 8), 4);
+    }
+
+    #[test]
+    fn test_decode_signed() {
+        for x in [0, 1, -1, 1048573, -1048573, i64::MAX, i64::MIN] {
+            assert_eq!(decode_signed(&fe_from_i64::<Fr>(x)), Some(x as i128));
+        }
+        assert_eq!(decode_signed(&Fr::from_u128(i128::MAX as u128)), Some(i128::MAX));
+        // 2^127 and 2^200 are too large to be small signed integers
+        assert_eq!(decode_signed(&Fr::from_u128(1 << 127)), None);
+        assert_eq!(decode_signed(&(Fr::from_u128(1 << 100) * Fr::from_u128(1 << 100))), None);
+    }
+
+    #[test]
+    fn test_field_to_i64() {
+        let q = 1048573;
+        assert_eq!(field_to_i64(&Fr::from(q - 1), q), Ok(-1));
+        assert_eq!(field_to_i64(&Fr::from(0), q), Ok(0));
+        // q/2 is the largest positive representative, and q/2 + 1 the smallest negative one
+        assert_eq!(field_to_i64(&Fr::from(q / 2), q), Ok(q as i64 / 2));
+        assert_eq!(field_to_i64(&Fr::from(q / 2 + 1), q), Ok(-(q as i64 / 2)));
+        // q itself, a value that only fits in the lower 64 bits after truncation, and -1 encoded as p - 1 are not reduced mod q
+        assert_eq!(
+            field_to_i64(&Fr::from(q), q),
+            Err(CircuitError::OutOfRange { value: q, bound: q })
+        );
+        let large = Fr::from_u128((1 << 64) + 5);
+        assert_eq!(field_to_i64(&large, q), Err(CircuitError::OutOfRange { value: 5, bound: q }));
+        assert!(field_to_i64(&-Fr::from(1), q).is_err());
+    }
+
+    #[test]
+    fn test_parse_coeff() {
+        // the modulus of Fr, which does not fit in a u64
+        let p = "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+        let p_hex = "0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001";
+        let p_minus_one =
+            "21888242871839275222246405745257275088548364400416034343698204186575808495616";
+        assert_eq!(parse_coeff::<Fr>(p_minus_one), Ok(-Fr::from(1)));
+        let p_minus_one_hex = "0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000000";
+        assert_eq!(parse_coeff::<Fr>(p_minus_one_hex), Ok(-Fr::from(1)));
+        assert_eq!(parse_coeff::<Fr>("18446744073709551616"), Ok(Fr::from_u128(1 << 64)));
+        assert_eq!(parse_coeff::<Fr>("0X00ff"), Ok(Fr::from(255)));
+        assert_eq!(parse_coeff::<Fr>("0"), Ok(Fr::from(0)));
+
+        // the modulus and larger values would wrap around
+        for s in [p, p_hex, "0x1000000000000000000000000000000000000000000000000000000000000000"] {
+            assert!(matches!(parse_coeff::<Fr>(s), Err(CircuitError::InvalidInput(_))), "{s}");
+        }
+        for s in ["", "0x", "12a", "-1", "0xfg"] {
+            assert!(matches!(parse_coeff::<Fr>(s), Err(CircuitError::InvalidInput(_))), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn test_div_euclid_cyclo() {
+        // (x^4 + 6x^3 + 9x^2 + 6x + 1) / (x^2 + 1)
+        let f = [1, 6, 9, 6, 1];
+        let g = [1, 0, 1];
+        let (quot, rem) = div_euclid(&f, &g);
+        assert_eq!(quot, vec![1, 6, 8]);
+        assert_eq!(rem, vec![-7]);
+        assert_division(&f, &g, &quot, &rem);
+    }
+
+    #[test]
+    fn test_div_euclid_negative_coefficients() {
+        // these coefficients overflow `i8` in the intermediate `dividend[i] - ratio * coeff` step
+        let f = [-100, 120, -128, 127];
+        let g = [1, 100, -1];
+        let (quot, rem) = div_euclid(&f, &g);
+        assert_eq!(quot, vec![-100, 10120]);
+        assert_division(&f, &g, &quot, &rem);
+    }
+
+    #[test]
+    fn test_div_euclid_negative_leading_coefficient() {
+        // -2x^3 + 4x^2 - 6x + 8 divided by -2x + 2 is exact over the integers
+        let f = [-2, 4, -6, 8];
+        let g = [-2, 2];
+        let (quot, rem) = div_euclid(&f, &g);
+        assert_eq!(quot, vec![1, -1, 2]);
+        assert_eq!(rem, vec![4]);
+        assert_division(&f, &g, &quot, &rem);
+    }
+
+    #[test]
+    fn test_div_euclid_low_degree_dividend() {
+        // a dividend of lower degree than the divisor is its own remainder, with a zero quotient
+        let g = [1, 0, 1];
+        // the leading zeros of the dividend are trimmed from the remainder
+        for f in [&[2, 3][..], &[0, 0, 0, 2, 3], &[5]] {
+            let (quot, rem) = div_euclid(f, &g);
+            assert!(quot.is_empty(), "{f:?}");
+            assert_eq!(rem, f.iter().copied().skip_while(|x| *x == 0).collect::<Vec<_>>());
+            assert_division(f, &g, &quot, &rem);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "not divisible")]
+    fn test_div_euclid_inexact() {
+        div_euclid(&[3, 1], &[2, 1]);
+    }
+
+    #[test]
+    fn test_poly_inverse_mod() {
+        let (n, q) = (4, 1048573);
+        for a in [vec![1, 1, 0, 0], vec![3, 0, 5, q - 1], vec![7], vec![123456, 654321, 0, 999999]]
+        {
+            let inv = poly_inverse_mod(&a, n, q).unwrap();
+            assert_eq!(inv.len(), n);
+            assert_eq!(ring_mul(&pad_coeffs(&a, n).unwrap(), &inv, q), vec![1, 0, 0, 0]);
+        }
+        // the inverse of x is -x^3, as x^4 = -1
+        assert_eq!(poly_inverse_mod(&[0, 1, 0, 0], n, q), Ok(vec![0, 0, 0, q - 1]));
+        // 683314^2 = -1 mod q, so x^2 - 683314 divides x^4 + 1 (see data/poly_inverse_not_invertible.in)
+        assert_eq!(poly_inverse_mod(&[365259, 0, 1, 0], n, q), Err(CircuitError::NotInvertible));
+    }
+
+    #[test]
+    fn test_poly_inverse_mod_not_invertible() {
+        // 2^4 = -1 mod 17, so x - 2 divides x^4 + 1 mod 17
+        for a in [&[15, 1, 0, 0][..], &[0, 0, 0, 0], &[]] {
+            assert_eq!(poly_inverse_mod(a, 4, 17), Err(CircuitError::NotInvertible));
+        }
+        // x^2 - 4 = (x - 2) * (x + 2) shares the factor as well, while x + 1 does not
+        assert_eq!(poly_inverse_mod(&[13, 0, 1, 0], 4, 17), Err(CircuitError::NotInvertible));
+        assert!(poly_inverse_mod(&[1, 1, 0, 0], 4, 17).is_ok());
+
+        // the inputs that are not polynomials of R_q are errors rather than panics
+        assert_eq!(
+            poly_inverse_mod(&[1, 1, 0, 0, 1], 4, 17),
+            Err(CircuitError::DegreeTooLarge { max: 3, found: 4 })
+        );
+        assert_eq!(poly_inverse_mod(&[1], 0, 17), Err(CircuitError::EmptyInput));
+        assert_eq!(poly_inverse_mod(&[1, 1], 4, 0), Err(CircuitError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_apply_automorphism() {
+        let q = 1048573;
+        // x -> x^3 maps 1 + 2x + 3x^2 + 4x^3 to 1 + 2x^3 + 3x^6 + 4x^9 = 1 + 4x - 3x^2 + 2x^3, as x^4 = -1
+        let a = [1, 2, 3, 4];
+        assert_eq!(apply_automorphism(&a, 3, q), vec![1, 4, q - 3, 2]);
+        // 3 * 3 = 1 mod 8, so applying it twice gives back a
+        assert_eq!(apply_automorphism(&apply_automorphism(&a, 3, q), 3, q), a);
+        // the automorphism is a ring homomorphism
+        let b = [5, 0, q - 1, 7];
+        for k in [3, 5, 7] {
+            assert_eq!(
+                apply_automorphism(&ring_mul(&a, &b, q), k, q),
+                ring_mul(&apply_automorphism(&a, k, q), &apply_automorphism(&b, k, q), q)
+            );
+        }
+    }
+
+    #[test]
+    fn test_galois_subgroup() {
+        // 5 has order 4 mod 16, and 15 = -1 is not a power of 5, so both generate the whole group
+        assert_eq!(galois_subgroup(&[5], 8), Ok(vec![1, 5, 9, 13]));
+        assert_eq!(galois_subgroup(&[5, 15], 8), Ok(vec![1, 3, 5, 7, 9, 11, 13, 15]));
+        // generators are reduced mod 2n, and repeated ones do not repeat the elements
+        assert_eq!(galois_subgroup(&[11, 3], 4), Ok(vec![1, 3]));
+        assert_eq!(galois_subgroup(&[], 4), Ok(vec![1]));
+        assert_eq!(
+            galois_subgroup(&[3, 6], 4),
+            Err(CircuitError::InvalidGaloisElement { k: 6, n: 4 })
+        );
+        assert_eq!(galois_subgroup(&[1], 0), Err(CircuitError::EmptyInput));
+    }
+}
@@ -1,4 +1,5 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use serde_json::Value;
 use std::path::PathBuf;
 
 #[derive(Clone, Copy, Debug, Subcommand)]
@@ -24,6 +25,25 @@ impl std::fmt::Display for SnarkCmd {
     }
 }
 
+/// Scalar field the circuit is built over, together with the matching arkworks field used to cross-check it (see `scaffold::field`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum FieldChoice {
+    /// Scalar field of BN254, which the KZG proving backend uses
+    #[default]
+    Bn254,
+    /// Scalar field of secp256k1, only supported by the mock prover. BLS12-381 is blocked on the `halo2curves` of `halo2-base` (see `scaffold::field`)
+    Secp256k1,
+}
+
+impl std::fmt::Display for FieldChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bn254 => write!(f, "bn254"),
+            Self::Secp256k1 => write!(f, "secp256k1"),
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 /// Command-line helper for various steps in ZK proving.
@@ -32,14 +52,162 @@ pub struct Cli {
     pub command: SnarkCmd,
     #[arg(short, long = "name")]
     pub name: String,
+    /// Degree of the circuit, which has `2^k` rows. Without it, or `--auto-params`, the examples run with `scaffold::run` pick the smallest degree that fits the advice cells of the circuit (see `params::degree_for_cells`)
     #[arg(short = 'k', long = "degree")]
-    pub degree: u32,
+    pub degree: Option<u32>,
+    /// Derive the degree and `LOOKUP_BITS` from the parameters of the circuit (see `params::recommended_params`)
+    #[arg(long = "auto-params")]
+    pub auto_params: bool,
+    /// Scalar field of the circuit. Fields other than bn254 only support the `mock` command
+    #[arg(long = "field", value_enum, default_value_t = FieldChoice::Bn254)]
+    pub field: FieldChoice,
     #[arg(short, long = "input")]
     pub input_path: Option<PathBuf>,
+    /// Read the input JSON from stdin instead of a file in `data/`, so it cannot be combined with `--input`. Can also be given after the command, e.g. `mock --stdin`
+    #[arg(long = "stdin", alias = "input-stdin", global = true)]
+    pub stdin: bool,
+    /// Log the intermediate coefficient vectors of the circuit in signed form, shown with `RUST_LOG=coeffs=debug`
+    #[arg(long = "debug-coeffs")]
+    pub debug_coeffs: bool,
+    /// Fail during witness generation once the circuit assigns more advice cells than this (see `poly::budget::Budgeted`)
+    #[arg(long = "max-rows")]
+    pub max_rows: Option<usize>,
+    /// The `N` of the examples that read it at runtime with `params::circuit_n`, instead of deriving it from the length of the input polynomials
+    #[arg(long = "n")]
+    pub n: Option<usize>,
+    /// Write the values of all advice cells to this file after the circuit is built, as a JSON array of decimal strings
+    #[arg(long = "dump-witness")]
+    pub dump_witness: Option<PathBuf>,
+    /// Render the layout of the circuit to this file with the `mock` command, as an SVG image if the path ends in `.svg` and a PNG image otherwise. Only meant for small circuits
+    #[cfg(feature = "dev-graph")]
+    #[arg(long = "plot")]
+    pub plot: Option<PathBuf>,
     #[arg(long = "create-contract")]
     pub create_contract: bool,
     #[arg(short, long = "config-path")]
     pub config_path: Option<PathBuf>,
     #[arg(short, long = "data-path")]
     pub data_path: Option<PathBuf>,
+    /// Polynomials of the input given inline as `(field, coefficients)`, see `Cli::parse_with_inline_inputs`
+    #[arg(skip)]
+    pub inline_input: Vec<(String, Vec<u64>)>,
+    /// The input read ahead of the circuit with `scaffold::peek_inputs`, which the circuit reads again instead of the file or stdin
+    #[arg(skip)]
+    pub peeked_input: Option<Value>,
+}
+
+impl Cli {
+    /// Parses the command line arguments as `Cli::parse` does, and also accepts the polynomials of the input inline, e.g. `--a 1,2,3,4 --b 4,3,2,1` for an input with the fields `a` and `b`.
+    ///
+    /// The inline polynomials replace the input file, so they cannot be combined with `--input` or `--stdin`. Their lengths are checked by the circuit against the degree it was built for, as for an input file.
+    /// This is meant for quick experiments with the small `N` of the examples: inputs with other kinds of fields, or large polynomials, are given as JSON.
+    pub fn parse_with_inline_inputs() -> Self {
+        Self::parse_from_with_inline_inputs(std::env::args())
+    }
+
+    /// Same as `parse_with_inline_inputs` for the given arguments, the first of which is the name of the binary.
+    pub fn parse_from_with_inline_inputs(args: impl IntoIterator<Item = String>) -> Self {
+        let (args, inline_input) = split_inline_input(args);
+        let mut cli = Self::parse_from(args);
+        cli.inline_input = inline_input;
+        cli
+    }
+
+    /// Returns the inline polynomials as the JSON object an input file would hold, or `None` when there are none.
+    pub fn inline_json(&self) -> Option<Value> {
+        if self.inline_input.is_empty() {
+            return None;
+        }
+        let fields =
+            self.inline_input.iter().map(|(field, coeffs)| (field.clone(), coeffs.clone().into()));
+        Some(Value::Object(fields.collect()))
+    }
+}
+
+/// Takes the inline polynomials out of the command line arguments, and returns the other arguments together with the polynomials as `(field, coefficients)`.
+///
+/// An argument `--<field>` that is not an option of `Cli` and is followed by a comma-separated list of coefficients, e.g. `--a 1,2,3,4`, is an inline polynomial.
+/// Every other argument is left to clap, which rejects unknown options as before.
+pub fn split_inline_input(
+    args: impl IntoIterator<Item = String>,
+) -> (Vec<String>, Vec<(String, Vec<u64>)>) {
+    let command = Cli::command();
+    let is_option = |name: &str| {
+        command.get_arguments().any(|arg| {
+            arg.get_long() == Some(name)
+                || arg.get_all_aliases().unwrap_or_default().contains(&name)
+        })
+    };
+    let (mut rest, mut inline) = (vec![], vec![]);
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        let field = arg.strip_prefix("--").filter(|name| !name.is_empty() && !is_option(name));
+        match (field, args.peek().and_then(|value| parse_coeff_list(value))) {
+            (Some(field), Some(coeffs)) => {
+                inline.push((field.to_string(), coeffs));
+                args.next();
+            }
+            _ => rest.push(arg),
+        }
+    }
+    (rest, inline)
+}
+
+// parses a comma-separated list of coefficients, e.g. `1,2,3,4`
+fn parse_coeff_list(value: &str) -> Option<Vec<u64>> {
+    value.split(',').map(|c| c.trim().parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use clap::Parser;
+    use serde_json::Value;
+
+    use super::{split_inline_input, Cli, SnarkCmd};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_inline_input_matches_json() {
+        let cli = Cli::parse_from_with_inline_inputs(args(&[
+            "poly_add", "--name", "poly_add", "-k", "11", "--a", "3,2,5,7", "--b", "0,5,1,1",
+            "mock",
+        ]));
+        assert_eq!((cli.name.as_str(), cli.degree), ("poly_add", Some(11)));
+        assert!(matches!(cli.command, SnarkCmd::Mock));
+
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/poly_add.in");
+        let json: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(cli.inline_json(), Some(json));
+    }
+
+    #[test]
+    fn test_split_inline_input() {
+        // options of the scaffold and unknown options without a list of coefficients are left to clap
+        let (rest, inline) = split_inline_input(args(&[
+            "poly_add",
+            "--max-rows",
+            "100",
+            "--a",
+            "1, 2,3",
+            "--b",
+            "x",
+            "--c",
+            "4",
+            "mock",
+        ]));
+        assert_eq!(rest, args(&["poly_add", "--max-rows", "100", "--b", "x", "mock"]));
+        assert_eq!(inline, vec![("a".to_string(), vec![1, 2, 3]), ("c".to_string(), vec![4])]);
+        assert!(Cli::try_parse_from(rest).is_err());
+
+        let cli = Cli::parse_from_with_inline_inputs(args(&[
+            "poly_add", "--name", "x", "-k", "11", "mock",
+        ]));
+        assert_eq!(cli.inline_json(), None);
+    }
 }
@@ -0,0 +1,69 @@
+//! The examples of this crate, with the degree and `LOOKUP_BITS` they run with, as in the commands of the README.
+//!
+//! The `smoke` binary and the `example_inputs` test both run every example of [`EXAMPLES`], so a new example, or a new degree for an existing one, only has to be added here.
+
+/// An example, run with a circuit of degree `k` and `LOOKUP_BITS = lookup_bits` on its input `data/<name>.in`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Example {
+    pub name: &'static str,
+    pub k: u32,
+    pub lookup_bits: usize,
+}
+
+const fn example(name: &'static str, k: u32, lookup_bits: usize) -> Example {
+    Example { name, k, lookup_bits }
+}
+
+/// Every example, except `standard_plonk`, which is not run with the scaffold CLI. `builder` generates its input itself and has none in `data/`.
+pub const EXAMPLES: &[Example] = &[
+    // halo2-lib tutorials
+    example("halo2_lib", 10, 8),
+    example("range", 10, 8),
+    example("poseidon", 10, 8),
+    example("builder", 10, 8),
+    example("fixed_len_keccak", 10, 8),
+    example("var_len_keccak", 10, 8),
+    // polynomial operations
+    example("poly_mul", 11, 8),
+    example("poly_add", 11, 8),
+    example("poly_add_hex", 11, 8),
+    example("poly_scalarmul", 11, 8),
+    example("poly_scalar_div", 11, 8),
+    example("poly_reduce", 12, 8),
+    example("reduce_ring", 11, 8),
+    example("check_poly_from_distribution_chi_error", 11, 9),
+    example("check_poly_from_distribution_chi_key", 11, 8),
+    example("check_secret_key", 11, 8),
+    example("poly_divide_by_cyclo", 12, 8),
+    example("infinity_norm", 10, 8),
+    example("apply_galois", 11, 8),
+    example("conjugate", 11, 8),
+    example("poly_trace", 11, 8),
+    example("poly_inverse", 11, 8),
+    example("base_decompose", 10, 8),
+    // NTT and RNS
+    example("check_ntt", 12, 11),
+    example("coeff_to_eval", 12, 11),
+    example("mul_consistency", 14, 13),
+    example("rns_ntt", 12, 11),
+    // BFV
+    example("bfv_mul", 13, 8),
+    example("bfv_mul_plain", 11, 8),
+    example("ct_equality", 13, 8),
+    example("extract_plaintext", 11, 8),
+    example("scale_message", 10, 8),
+    example("ct_add", 11, 8),
+    example("ct_mul", 13, 8),
+    example("verifiable_encrypt", 12, 8),
+    example("verify_pubkey", 12, 8),
+    example("check_public_key", 12, 8),
+    example("decrypt_committed", 13, 8),
+    example("decrypt_to_zero", 13, 8),
+    example("verify_rlwe", 12, 8),
+    example("noise_bound", 13, 8),
+];
+
+/// Returns the example of [`EXAMPLES`] with the given name.
+pub fn find_example(name: &str) -> Option<&'static Example> {
+    EXAMPLES.iter().find(|example| example.name == name)
+}
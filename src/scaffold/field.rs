@@ -0,0 +1,176 @@
+//! Scalar fields the examples can be run over (see the `--field` argument), and their matching arkworks fields.
+//!
+//! The examples cross-check the output of their circuit against arkworks outside the circuit. `ArkField` maps each halo2 scalar field to the arkworks field with the same modulus,
+//! so that these checks can be written once, generically over the field of the circuit.
+//! BLS12-381 is blocked on a `halo2-base` built on a `halo2curves` with a `bls12_381` module, as the version used by `halo2-base` has none, see the README.
+use ark_ff::{BigInteger, PrimeField};
+pub use halo2_base::halo2_proofs::halo2curves::{
+    bn256::Fr as Bn254Scalar, secp256k1::Fq as Secp256k1Scalar,
+};
+use halo2_base::utils::ScalarField;
+
+/// A halo2 scalar field together with the arkworks field with the same modulus.
+pub trait ArkField: ScalarField {
+    /// The same prime field, in arkworks
+    type Ark: PrimeField;
+
+    /// Converts an element of the arkworks field into this field, through its decimal representation.
+    fn from_ark(x: &Self::Ark) -> Self {
+        Self::from_str_vartime(&x.into_bigint().to_string())
+            .expect("the two fields should have the same modulus")
+    }
+
+    /// Converts an element of this field into the arkworks field.
+    fn to_ark(&self) -> Self::Ark {
+        let bytes =
+            self.to_u64_limbs(4, 64).iter().flat_map(|limb| limb.to_le_bytes()).collect::<Vec<_>>();
+        Self::Ark::from_le_bytes_mod_order(&bytes)
+    }
+}
+
+impl ArkField for Bn254Scalar {
+    type Ark = ark_bn254::Fr;
+}
+
+impl ArkField for Secp256k1Scalar {
+    type Ark = ark_secp256k1::Fr;
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ff::{One, PrimeField};
+    use ark_poly::univariate::{DenseOrSparsePolynomial, DensePolynomial};
+    use ark_poly::DenseUVPolynomial;
+    use halo2_base::{gates::GateChip, utils::ScalarField, AssignedValue};
+
+    use super::{ArkField, Bn254Scalar, Secp256k1Scalar};
+    use crate::poly::operations::{
+        load_poly, poly_add_assigned, poly_mul_assigned, poly_neg, poly_reduce_cyclo_assigned,
+        poly_reduce_mod_assigned,
+    };
+    use crate::test_utils::mock_run_on_field;
+
+    // adds two polynomials in the circuit over `F` and compares the sum with the one computed by arkworks over `F::Ark`
+    fn check_poly_add<F: ArkField>() {
+        // the constant coefficient of a is -1, so the sum wraps around the modulus of the field
+        let minus_one = F::from_ark(&-F::Ark::one());
+        let (a, b) = ([0, 2, 5, 7], [2, 5, 1, 9]);
+        let (sum, ok) = mock_run_on_field::<F, _>(9, 8, |ctx, range| {
+            let mut a = load_poly(ctx, &a);
+            a[0] = ctx.load_witness(minus_one);
+            let b = load_poly(ctx, &b);
+            let sum = poly_add_assigned(ctx, &range.gate, &a, &b).unwrap();
+            sum.iter().map(|x| *x.value()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+
+        let a = DensePolynomial::from_coefficients_vec(
+            [-F::Ark::one()].into_iter().chain(a[1..].iter().map(|x| F::Ark::from(*x))).collect(),
+        );
+        let b =
+            DensePolynomial::from_coefficients_vec(b.iter().map(|x| F::Ark::from(*x)).collect());
+        let expected = (&a + &b).coeffs.iter().map(F::from_ark).collect::<Vec<_>>();
+        assert_eq!(sum, expected);
+        assert_eq!(sum[0], F::one());
+        assert_eq!(sum.iter().map(F::to_ark).collect::<Vec<_>>(), (&a + &b).coeffs);
+    }
+
+    #[test]
+    fn test_poly_add_over_two_fields() {
+        check_poly_add::<Bn254Scalar>();
+        check_poly_add::<Secp256k1Scalar>();
+    }
+
+    // multiplies two polynomials in the circuit over `F` and folds the product mod x^4 + 1, then compares it with the
+    // remainder of the product computed by arkworks over `F::Ark`, as in the reduced mode of the `poly_mul` example
+    fn check_poly_mul_reduced<F: ArkField>() {
+        let (a, b) = ([3, 2, 5, 7], [0, 5, 1, 255]);
+        let (out, ok) = mock_run_on_field::<F, _>(10, 8, |ctx, range| {
+            let (a, b) = (load_poly(ctx, &a), load_poly(ctx, &b));
+            let prod = poly_mul_assigned(ctx, &range.gate, &a, &b).unwrap();
+            let out = poly_reduce_cyclo_assigned(ctx, &range.gate, &prod, 4);
+            out.iter().map(|x| *x.value()).collect::<Vec<_>>()
+        });
+        assert!(ok);
+        assert_eq!(out.len(), 4);
+
+        let [a, b] = [a, b].map(|p| {
+            DensePolynomial::from_coefficients_vec(p.iter().map(|x| F::Ark::from(*x)).collect())
+        });
+        let cyclo = DensePolynomial::from_coefficients_vec(
+            [1, 0, 0, 0, 1].iter().map(|x| F::Ark::from(*x as u64)).collect(),
+        );
+        let (_, rem) = DenseOrSparsePolynomial::from(&a * &b)
+            .divide_with_q_and_r(&DenseOrSparsePolynomial::from(cyclo))
+            .unwrap();
+        assert_eq!(out, rem.coeffs.iter().map(F::from_ark).collect::<Vec<_>>());
+        // 7 * 255 x^6 folds to -1785 x^2, so the coefficient 3 + 10 - 1785 wraps around the modulus of the field
+        assert_eq!(out[2], -F::from(1772));
+    }
+
+    #[test]
+    fn test_poly_mul_reduced_over_two_fields() {
+        check_poly_mul_reduced::<Bn254Scalar>();
+        check_poly_mul_reduced::<Secp256k1Scalar>();
+    }
+
+    // negates a polynomial in R_q in the circuit over `F` and compares it with the negation computed by arkworks over
+    // `F::Ark`, shifted by q back into [0, q), then subtracts two polynomials as a + (-b) reduced mod q
+    fn check_poly_neg<F: ArkField>() {
+        const Q: u64 = 1048573;
+        let (a, b) = ([0, 1, 5, Q - 1], [7, 0, 9, 3]);
+        let ((neg, neg_neg, diff), ok) = mock_run_on_field::<F, _>(10, 8, |ctx, range| {
+            let (a, b) = (load_poly(ctx, &a), load_poly(ctx, &b));
+            let neg = poly_neg(ctx, range, &a, Q);
+            let neg_neg = poly_neg(ctx, range, &neg, Q);
+            let neg_b = poly_neg(ctx, range, &b, Q);
+            let sum = poly_add_assigned(ctx, &range.gate, &a, &neg_b).unwrap();
+            let diff = poly_reduce_mod_assigned(ctx, range, &sum, Q, 21).unwrap();
+            let values = |p: &[AssignedValue<F>]| p.iter().map(|x| *x.value()).collect::<Vec<_>>();
+            (values(&neg), values(&neg_neg), values(&diff))
+        });
+        assert!(ok);
+        assert_eq!(neg_neg, a.iter().map(|x| F::from(*x)).collect::<Vec<_>>());
+
+        let to_ark = |p: &[u64]| {
+            DensePolynomial::from_coefficients_vec(p.iter().map(|x| F::Ark::from(*x)).collect())
+        };
+        // arkworks negates and subtracts in the field, so every coefficient that went negative is shifted by q into [0, q)
+        let shift = |c: &F::Ark, negative: bool| {
+            F::from_ark(&if negative { *c + F::Ark::from(Q) } else { *c })
+        };
+        let expected = (-to_ark(&a))
+            .coeffs
+            .iter()
+            .zip(a.iter())
+            .map(|(c, x)| shift(c, *x != 0))
+            .collect::<Vec<_>>();
+        assert_eq!(neg, expected);
+        let expected = (&to_ark(&a) - &to_ark(&b))
+            .coeffs
+            .iter()
+            .zip(a.iter().zip(b.iter()))
+            .map(|(c, (x, y))| shift(c, x < y))
+            .collect::<Vec<_>>();
+        assert_eq!(diff, expected);
+        assert_eq!(diff, [Q - 7, 1, Q - 4, Q - 4].map(F::from).to_vec());
+    }
+
+    #[test]
+    fn test_poly_neg_over_two_fields() {
+        check_poly_neg::<Bn254Scalar>();
+        check_poly_neg::<Secp256k1Scalar>();
+    }
+
+    #[test]
+    fn test_ark_field_modulus() {
+        // both conversions agree on -1 only if the moduli are the same
+        fn check<F: ArkField>() {
+            assert_eq!(F::from_ark(&-F::Ark::one()), -F::one());
+            assert_eq!((-F::one()).to_ark(), -F::Ark::one());
+            assert_eq!(F::Ark::MODULUS_BIT_SIZE as usize, F::NUM_BITS as usize);
+        }
+        check::<Bn254Scalar>();
+        check::<Secp256k1Scalar>();
+    }
+}
@@ -0,0 +1,143 @@
+//! Versioning of the JSON inputs of the circuits.
+//!
+//! An input struct that changes over time, e.g. by gaining a parameter or widening its coefficient type, implements [`VersionedInput`], and its JSON files carry a `version` field.
+//! Files without the field are version 1, i.e. they were written before the struct was versioned. Older inputs are upgraded one version at a time with
+//! [`VersionedInput::migrate`] before they are parsed, and inputs from a newer version than the struct are rejected instead of being silently misread.
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+use crate::error::CircuitError;
+
+/// The name of the field holding the schema version of an input.
+pub const VERSION_KEY: &str = "version";
+
+/// A circuit input whose JSON schema is versioned.
+pub trait VersionedInput: DeserializeOwned {
+    /// The current version of the schema. Bump it, and handle the previous version in `migrate`, whenever the meaning of an input file changes.
+    const VERSION: u64;
+
+    /// Upgrades the fields of an input from version `from` to version `from + 1` in place, e.g. by inserting the value a new parameter implicitly had before.
+    /// Returns an `InvalidInput` error when the old input cannot be upgraded.
+    fn migrate(fields: &mut Map<String, Value>, from: u64) -> Result<(), CircuitError>;
+
+    /// Parses an input from a JSON value after upgrading it to the current version.
+    ///
+    /// Returns an `UnsupportedInputVersion` error for an input from a newer version, and an `InvalidInput` error for a malformed one.
+    fn from_value(value: Value) -> Result<Self, CircuitError> {
+        let mut fields = match value {
+            Value::Object(fields) => fields,
+            other => {
+                return Err(CircuitError::InvalidInput(format!(
+                    "expected an object, found {other}"
+                )))
+            }
+        };
+        let version = match fields.get(VERSION_KEY) {
+            None => 1,
+            Some(version) => version.as_u64().filter(|v| *v >= 1).ok_or_else(|| {
+                CircuitError::InvalidInput(format!(
+                    "version should be a positive integer, found {version}"
+                ))
+            })?,
+        };
+        if version > Self::VERSION {
+            return Err(CircuitError::UnsupportedInputVersion {
+                found: version,
+                supported: Self::VERSION,
+            });
+        }
+        for from in version..Self::VERSION {
+            Self::migrate(&mut fields, from)?;
+        }
+        fields.insert(VERSION_KEY.to_string(), Self::VERSION.into());
+        serde_json::from_value(Value::Object(fields))
+            .map_err(|e| CircuitError::InvalidInput(e.to_string()))
+    }
+
+    /// Parses an input from a JSON string, see `from_value`.
+    fn from_json(json: &str) -> Result<Self, CircuitError> {
+        let value =
+            serde_json::from_str(json).map_err(|e| CircuitError::InvalidInput(e.to_string()))?;
+        Self::from_value(value)
+    }
+}
+
+/// Wraps a [`VersionedInput`] so that the scaffold reads it with `VersionedInput::from_value`, by taking `Versioned(input)` as the input of the circuit function passed to `run`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Versioned<T>(pub T);
+
+impl<'de, T: VersionedInput> Deserialize<'de> for Versioned<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        T::from_value(value).map(Versioned).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::{Map, Value};
+
+    use super::{Versioned, VersionedInput};
+    use crate::error::CircuitError;
+
+    // the input of `poly_mul`: version 2 added `reduce`, which was implicitly false before
+    #[derive(Debug, PartialEq, Eq, Deserialize)]
+    struct Input {
+        a: Vec<u64>,
+        b: Vec<u64>,
+        reduce: bool,
+    }
+
+    impl VersionedInput for Input {
+        const VERSION: u64 = 2;
+
+        fn migrate(fields: &mut Map<String, Value>, from: u64) -> Result<(), CircuitError> {
+            if from == 1 {
+                fields.entry("reduce").or_insert(Value::Bool(false));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_versioned_input() {
+        // a file from before the versioning is version 1, and gets the default of the new field
+        let v1 = Input::from_json(r#"{"a": [3, 2], "b": [0, 5]}"#).unwrap();
+        assert_eq!(v1, Input { a: vec![3, 2], b: vec![0, 5], reduce: false });
+        let v1 = Input::from_json(r#"{"version": 1, "a": [3, 2], "b": [0, 5]}"#).unwrap();
+        assert!(!v1.reduce);
+
+        let v2 = r#"{"version": 2, "a": [3, 2], "b": [0, 5], "reduce": true}"#;
+        assert_eq!(
+            Input::from_json(v2).unwrap(),
+            Input { a: vec![3, 2], b: vec![0, 5], reduce: true }
+        );
+        // the scaffold reads the input through `Versioned`
+        let Versioned(input) = serde_json::from_str::<Versioned<Input>>(v2).unwrap();
+        assert!(input.reduce);
+    }
+
+    #[test]
+    fn test_versioned_input_rejected() {
+        assert_eq!(
+            Input::from_json(r#"{"version": 3, "a": [3, 2], "b": [0, 5], "reduce": true}"#),
+            Err(CircuitError::UnsupportedInputVersion { found: 3, supported: 2 })
+        );
+        for json in [
+            r#"{"version": 0, "a": [], "b": []}"#,
+            r#"{"version": "2", "a": [], "b": []}"#,
+            "[1, 2]",
+        ] {
+            assert!(matches!(Input::from_json(json), Err(CircuitError::InvalidInput(_))), "{json}");
+        }
+        // the current version is not migrated, so it needs every field
+        assert!(matches!(
+            Input::from_json(r#"{"version": 2, "a": [3, 2], "b": [0, 5]}"#),
+            Err(CircuitError::InvalidInput(_))
+        ));
+        let err = serde_json::from_str::<Versioned<Input>>(r#"{"version": 3}"#).unwrap_err();
+        assert!(err.to_string().contains("version 3"), "{err}");
+    }
+}
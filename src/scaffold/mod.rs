@@ -29,51 +29,344 @@ use halo2_base::{
         },
         SerdeFormat,
     },
-    utils::{fs::gen_srs, ScalarField},
+    utils::{fe_to_biguint, fs::gen_srs, ScalarField},
     AssignedValue, Context,
 };
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use snark_verifier_sdk::{
     halo2::{gen_snark_shplonk, read_snark, PoseidonTranscript},
-    read_pk, CircuitExt, NativeLoader,
+    read_pk, CircuitExt, NativeLoader, Snark,
 };
 use std::{
-    env::{set_var, var},
+    env::{current_exe, set_var, var},
     fs::{self, File},
-    io::{BufReader, BufWriter},
+    io::{self, BufReader, BufWriter},
     path::{Path, PathBuf},
+    sync::Mutex,
+    time::Instant,
 };
 
 use self::cmd::{Cli, SnarkCmd};
+use crate::error::CircuitError;
+use crate::params::{degree_for_cells, set_circuit_n, set_debug_coeffs, set_max_rows};
+use crate::poly::utils::decode_signed;
 
 pub mod cmd;
+pub mod examples;
+pub mod field;
+pub mod input;
 ///! The functions below are generic scaffolding functions to create circuits with 'halo2-lib'
 
 pub struct CircuitScaffold<T, Fn> {
     f: Fn,
     private_inputs: T,
+    // the circuit already built from `private_inputs` by `read_inputs_with_degree`, which `create_circuit` uses instead of running `f` again
+    prebuilt: Option<Prebuilt<Fr>>,
+}
+
+// a circuit built for the mock prover or key generation, with the cells it made public
+type Prebuilt<F> = (GateThreadBuilder<F>, Vec<AssignedValue<F>>);
+
+// the number of advice cells and of cells looked up in the range table of the last circuit built by the scaffold, reported by the `mock` command
+static CELL_COUNTS: Mutex<Option<(usize, usize)>> = Mutex::new(None);
+
+fn cell_counts<F: ScalarField>(builder: &GateThreadBuilder<F>) -> (usize, usize) {
+    builder.threads.iter().flatten().fold((0, 0), |(advice, lookup), ctx| {
+        (advice + ctx.advice.len(), lookup + ctx.cells_to_lookup.len())
+    })
+}
+
+// the path given with `--dump-witness`, where the scaffold writes the advice values of the circuit once it is built
+static WITNESS_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// writes the values of all advice cells assigned by the circuit, thread by thread in assignment order, as a JSON array of decimal strings
+fn dump_witness<F: ScalarField>(builder: &GateThreadBuilder<F>, path: &Path) {
+    let values = builder
+        .threads
+        .iter()
+        .flatten()
+        .flat_map(|ctx| ctx.advice.iter())
+        .map(|x| fe_to_biguint(&x.evaluate()).to_string())
+        .collect::<Vec<_>>();
+    let file = File::create(path)
+        .unwrap_or_else(|e| panic!("Cannot create witness file at {path:?}. {e:?}"));
+    serde_json::to_writer(BufWriter::new(file), &values).unwrap();
+    println!("{} advice values written to: {path:?}", values.len());
+}
+
+// renders which cells of the advice, fixed and lookup columns the circuit assigns, to an SVG image if the path ends in `.svg` and a PNG image otherwise
+#[cfg(feature = "dev-graph")]
+fn plot_layout<F: ScalarField>(k: u32, circuit: &impl Circuit<F>, path: &Path) {
+    use halo2_base::halo2_proofs::dev::CircuitLayout;
+    use plotters::{coord::Shift, prelude::*};
+
+    fn render<F: ScalarField, DB: DrawingBackend>(
+        k: u32,
+        circuit: &impl Circuit<F>,
+        root: DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        root.fill(&WHITE)?;
+        CircuitLayout::default().render(k, circuit, &root)?;
+        root.present()
+    }
+
+    const SIZE: (u32, u32) = (1024, 768);
+    // the two backends have different error types, so the error is formatted in each branch
+    let result = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("svg") => render(k, circuit, SVGBackend::new(path, SIZE).into_drawing_area())
+            .map_err(|e| format!("{e:?}")),
+        _ => render(k, circuit, BitMapBackend::new(path, SIZE).into_drawing_area())
+            .map_err(|e| format!("{e:?}")),
+    };
+    result.unwrap_or_else(|e| panic!("Cannot plot the circuit layout to {path:?}. {e}"));
+    println!("Circuit layout written to: {path:?}");
+}
+
+// the mock prover only checks the constraints, so we say so explicitly to avoid mistaking it for a proof
+fn print_mock_success(k: u32, counts: Option<(usize, usize)>) {
+    match counts {
+        Some((advice, lookup)) => println!(
+            "Mock proving succeeded: {advice} advice cells and {lookup} lookup cells satisfy the constraints in 2^{k} rows"
+        ),
+        None => println!("Mock proving succeeded: the witness satisfies the constraints in 2^{k} rows"),
+    }
+    println!("No proof was generated, run `keygen` and `prove` for a real proof");
 }
 
 pub fn run<T: DeserializeOwned>(
-    f: impl FnOnce(&mut Context<Fr>, T, &mut Vec<AssignedValue<Fr>>),
+    f: impl FnOnce(&mut Context<Fr>, T, &mut Vec<AssignedValue<Fr>>) + Clone,
     cli: Cli,
 ) {
     run_builder(|builder, inp, public| f(builder.main(0), inp, public), cli)
 }
 
+/// Runs the command of `cli` on the circuit built by `f` from the input of `cli`.
+///
+/// Without `-k` or `--auto-params`, the `mock` and `keygen` commands build the circuit to count its advice cells, which is why `f` must be `Clone`, set the degree to `params::degree_for_cells` of them,
+/// and then use this circuit instead of building it again. The `prove` and `verify` commands read the degree of the key from the pinning of the circuit, `configs/<name>.json`.
 pub fn run_builder<T: DeserializeOwned>(
-    f: impl FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
-    cli: Cli,
+    f: impl FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>) + Clone,
+    mut cli: Cli,
 ) {
-    let name = &cli.name;
+    let (private_inputs, prebuilt) = read_inputs_with_degree(&mut cli, f.clone());
+    run_cli(CircuitScaffold { f, private_inputs, prebuilt }, cli);
+}
+
+// reads the input of the circuit, and sets the degree of `cli` when it is not given: to the one keygen used for `prove` and `verify`,
+// and otherwise to the smallest one that fits the advice cells assigned by `f`. The cells are counted by building the circuit with the flags of `cli`,
+// in the mode of the mock prover and key generation, and this circuit is returned so that the command does not build it a second time.
+// The input is read as JSON, so that it can also be parsed for the command even when it comes from stdin
+fn read_inputs_with_degree<F: ScalarField, T: DeserializeOwned>(
+    cli: &mut Cli,
+    f: impl FnOnce(&mut GateThreadBuilder<F>, T, &mut Vec<AssignedValue<F>>),
+) -> (T, Option<Prebuilt<F>>) {
+    if cli.degree.is_some() {
+        return (read_inputs(cli), None);
+    }
+    if matches!(cli.command, SnarkCmd::Prove | SnarkCmd::Verify) {
+        let config_path = cli.config_path.clone().unwrap_or_else(|| PathBuf::from("configs"));
+        let pinning_path = config_path.join(format!("{}.json", cli.name));
+        cli.degree = Some(AggregationConfigPinning::from_path(pinning_path).degree());
+        return (read_inputs(cli), None);
+    }
+    let input: serde_json::Value = read_inputs(cli);
+    let parse = || -> T {
+        serde_json::from_value(input.clone()).expect("Input should have the fields of the circuit")
+    };
+    set_debug_coeffs(cli.debug_coeffs);
+    set_max_rows(cli.max_rows);
+    set_circuit_n(cli.n);
+    let mut builder = GateThreadBuilder::<F>::mock();
+    let mut assigned_instances = vec![];
+    f(&mut builder, parse(), &mut assigned_instances);
+    let (advice, _) = cell_counts(&builder);
+
+    // the circuit has set `LOOKUP_BITS` to its default with `params::lookup_bits` if it was not set
+    let lookup_bits = var("LOOKUP_BITS").ok().and_then(|bits| bits.parse().ok()).unwrap_or(0);
+    let minimum_rows = var("MINIMUM_ROWS").unwrap_or_else(|_| "9".to_string()).parse().unwrap();
+    let k = degree_for_cells(advice, minimum_rows, lookup_bits);
+    println!("Circuit degree k = {k} selected for {advice} advice cells, pass -k to override it");
+    cli.degree = Some(k as u32);
+    (parse(), Some((builder, assigned_instances)))
+}
+
+/// Runs the mock prover on the circuit over an arbitrary scalar field `F`.
+///
+/// Key generation, proving and verification use KZG commitments on BN254, so they are only available over its scalar field through [`run`].
+/// Use [`run_on_field!`](crate::run_on_field) to dispatch on the `--field` argument.
+pub fn run_mock_on_field<F: ScalarField, T: DeserializeOwned>(
+    f: impl FnOnce(&mut Context<F>, T, &mut Vec<AssignedValue<F>>) + Clone,
+    mut cli: Cli,
+) {
+    assert!(
+        matches!(cli.command, SnarkCmd::Mock),
+        "only the mock prover is available over the {} scalar field",
+        cli.field
+    );
+    let dry_run = f.clone();
+    let (private_inputs, prebuilt) = read_inputs_with_degree(&mut cli, |builder, inp, public| {
+        dry_run(builder.main(0), inp, public)
+    });
+    let k = cli.degree.unwrap();
+    set_debug_coeffs(cli.debug_coeffs);
+    set_max_rows(cli.max_rows);
+    set_circuit_n(cli.n);
+
+    // same circuit construction as `create_circuit` in mock mode, see there for the details
+    let lookup_bits: usize = var("LOOKUP_BITS").map(|str| str.parse().unwrap()).unwrap_or(0);
+    assert!(lookup_bits < k as usize, "LOOKUP_BITS needs to be less than DEGREE");
+    set_var("LOOKUP_BITS", lookup_bits.to_string());
+    let (mut builder, assigned_instances) = prebuilt.unwrap_or_else(|| {
+        let mut builder = GateThreadBuilder::<F>::mock();
+        let mut assigned_instances = vec![];
+        f(builder.main(0), private_inputs, &mut assigned_instances);
+        (builder, assigned_instances)
+    });
+    let counts = cell_counts(&builder);
+    if let Some(path) = &cli.dump_witness {
+        dump_witness(&builder, path);
+    }
+
+    let minimum_rows = var("MINIMUM_ROWS").unwrap_or_else(|_| "9".to_string()).parse().unwrap();
+    builder.config(k as usize, Some(minimum_rows));
+    let circuit = ScaffoldCircuitBuilder(RangeWithInstanceCircuitBuilder::new(
+        RangeCircuitBuilder::mock(builder),
+        assigned_instances,
+    ));
+    MockProver::run(k, &circuit, circuit.instances()).unwrap().assert_satisfied();
+    #[cfg(feature = "dev-graph")]
+    if let Some(path) = &cli.plot {
+        plot_layout(k, &circuit, path);
+    }
+    print_mock_success(k, Some(counts));
+}
+
+/// Runs a circuit written generically over `F: ArkField` over the scalar field selected by the `--field` argument of `cli`.
+///
+/// The function is expanded once per field, so it can be a closure calling a generic function.
+#[macro_export]
+macro_rules! run_on_field {
+    ($f:expr, $cli:expr $(,)?) => {{
+        let cli: $crate::scaffold::cmd::Cli = $cli;
+        match cli.field {
+            $crate::scaffold::cmd::FieldChoice::Bn254 => $crate::scaffold::run($f, cli),
+            $crate::scaffold::cmd::FieldChoice::Secp256k1 => $crate::scaffold::run_mock_on_field::<
+                $crate::scaffold::field::Secp256k1Scalar,
+                _,
+            >($f, cli),
+        }
+    }};
+}
+
+/// Constrains the computed coefficients to equal the `claimed` public values of the input, and makes them public.
+///
+/// Pushing a cell to `make_public` only exposes it: the instance column is filled from the witness, so the proof holds for whatever the circuit computed, and only a verifier that compares the instances with its own values notices a wrong result.
+/// Here the claimed values are loaded and constrained against the computed cells before they are exposed, so no proof exists for an input whose claim is wrong, and the instances of any proof are the claimed values.
+/// Returns a `LengthMismatch` error when `computed` and `claimed` have different lengths.
+pub fn constrain_equals_public<F: ScalarField>(
+    ctx: &mut Context<F>,
+    computed: &[AssignedValue<F>],
+    claimed: &[u64],
+    make_public: &mut Vec<AssignedValue<F>>,
+) -> Result<(), CircuitError> {
+    if computed.len() != claimed.len() {
+        return Err(CircuitError::LengthMismatch { left: computed.len(), right: claimed.len() });
+    }
+    for (x, y) in computed.iter().zip(claimed) {
+        let y = ctx.load_witness(F::from(*y));
+        ctx.constrain_equal(x, &y);
+        make_public.push(y);
+    }
+    Ok(())
+}
+
+/// How [`run_and_collect`] decodes the public outputs of a circuit into integers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputRepr {
+    /// The outputs are already reduced, and each is returned as its integer representative.
+    #[default]
+    Reduced,
+    /// The outputs are in centered representation, i.e. `p - x` stands for `-x` where `p` is the field modulus, and are mapped to their representative in `[0, q)`.
+    Centered(u64),
+}
+
+/// Runs the circuit `f` on `input` in witness-only mode, without checking any constraint, and returns its public outputs decoded into integers as described by `repr`.
+///
+/// This is meant for computing with a circuit without proving it, e.g. in tests. Panics if an output does not fit the representation, such as an output of at least `2^64` with `OutputRepr::Reduced`.
+pub fn run_and_collect<F: ScalarField, T>(
+    f: impl FnOnce(&mut Context<F>, T, &mut Vec<AssignedValue<F>>),
+    input: T,
+    repr: OutputRepr,
+) -> Vec<u64> {
+    let mut builder = GateThreadBuilder::<F>::new(true);
+    let mut make_public = vec![];
+    f(builder.main(0), input, &mut make_public);
+    make_public
+        .iter()
+        .map(|x| {
+            let decoded = match repr {
+                OutputRepr::Reduced => decode_signed(x.value()).filter(|x| *x >= 0),
+                OutputRepr::Centered(q) => {
+                    decode_signed(x.value()).map(|x| x.rem_euclid(q as i128))
+                }
+            };
+            decoded
+                .and_then(|x| u64::try_from(x).ok())
+                .unwrap_or_else(|| panic!("public output {:?} does not fit {repr:?}", x.value()))
+        })
+        .collect()
+}
+
+// the default input of a circuit: `<name>.in`, or the input shipped with the running example, `<example>.in`, when there is no input for the name
+fn default_input_file(name: &str) -> PathBuf {
+    let file = PathBuf::from(format!("{name}.in"));
+    if Path::new("data").join(&file).exists() {
+        return file;
+    }
+    current_exe()
+        .ok()
+        .and_then(|exe| exe.file_stem().map(|example| PathBuf::from(example).with_extension("in")))
+        .unwrap_or(file)
+}
+
+/// Reads the input of `cli` before the circuit is built, e.g. to derive the parameters of the circuit from the lengths of its polynomials.
+///
+/// The input is kept in `cli`, so that the circuit reads the same input afterwards, even when it comes from stdin.
+pub fn peek_inputs<T: DeserializeOwned>(cli: &mut Cli) -> T {
+    if cli.peeked_input.is_none() {
+        cli.peeked_input = Some(read_inputs(cli));
+    }
+    read_inputs(cli)
+}
+
+// reads the input from the inline polynomials (see `Cli::parse_with_inline_inputs`), from stdin with `--stdin`, and from `data/<input>` (by default `data/<name>.in`, see `default_input_file`) otherwise.
+// An input read ahead with `peek_inputs` is read again from `cli`
+fn read_inputs<T: DeserializeOwned>(cli: &Cli) -> T {
+    if let Some(input) = &cli.peeked_input {
+        return serde_json::from_value(input.clone())
+            .expect("Input should have the fields of the circuit");
+    }
+    if let Some(json) = cli.inline_json() {
+        assert!(
+            !cli.stdin && cli.input_path.is_none(),
+            "inline polynomials cannot be used with --input or --stdin"
+        );
+        return serde_json::from_value(json)
+            .expect("Inline polynomials should be the fields of the input");
+    }
+    if cli.stdin {
+        // clap cannot check this conflict when `--stdin` is given after the command
+        assert!(cli.input_path.is_none(), "--stdin cannot be used with --input");
+        return serde_json::from_reader(io::stdin().lock())
+            .expect("Input from stdin should be a valid JSON");
+    }
     let input_path = PathBuf::from("data")
-        .join(cli.input_path.clone().unwrap_or_else(|| PathBuf::from(format!("{name}.in"))));
-    let private_inputs: T = serde_json::from_reader(
+        .join(cli.input_path.clone().unwrap_or_else(|| default_input_file(&cli.name)));
+    serde_json::from_reader(
         File::open(&input_path)
             .unwrap_or_else(|e| panic!("Input file not found at {input_path:?}. {e:?}")),
     )
-    .expect("Input file should be a valid JSON file");
-    run_builder_on_inputs(f, cli, private_inputs)
+    .expect("Input file should be a valid JSON file")
 }
 
 pub fn run_builder_on_inputs<T: DeserializeOwned>(
@@ -89,7 +382,7 @@ pub fn pre_run_builder_on_inputs<T>(
     f: impl FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>),
     private_inputs: T,
 ) -> CircuitScaffold<T, impl FnOnce(&mut GateThreadBuilder<Fr>, T, &mut Vec<AssignedValue<Fr>>)> {
-    CircuitScaffold { f, private_inputs }
+    CircuitScaffold { f, private_inputs, prebuilt: None }
 }
 
 pub use eth::*;
@@ -97,9 +390,7 @@ mod eth {
     use std::{
         cell::RefCell,
         env::{set_var, var},
-        fs::File,
         marker::PhantomData,
-        path::PathBuf,
     };
 
     use axiom_eth::{
@@ -122,7 +413,7 @@ mod eth {
     };
     use serde::de::DeserializeOwned;
 
-    use super::{cmd::Cli, run_cli};
+    use super::{cmd::Cli, read_inputs, run_cli};
 
     pub struct EthScaffold<T, FN, F1> {
         f: FN,
@@ -220,14 +511,7 @@ mod eth {
         ) -> F1,
         F1: FnOnce(&mut Context<Fr>, &mut Context<Fr>, &EthChip<Fr>) + Clone,
     {
-        let name = &cli.name;
-        let input_path = PathBuf::from("data")
-            .join(cli.input_path.clone().unwrap_or_else(|| PathBuf::from(format!("{name}.in"))));
-        let private_inputs: T = serde_json::from_reader(
-            File::open(&input_path)
-                .unwrap_or_else(|e| panic!("Input file not found at {input_path:?}. {e:?}")),
-        )
-        .expect("Input file should be a valid JSON file");
+        let private_inputs: T = read_inputs(&cli);
         run_eth_builder_on_inputs(f, cli, private_inputs)
     }
 
@@ -250,7 +534,14 @@ mod eth {
 
 pub fn run_cli<P: PreCircuit>(precircuit: P, cli: Cli) {
     let name = cli.name;
-    let k = cli.degree;
+    let k = cli.degree.expect("circuit degree should be set with -k or --auto-params");
+    // the circuit is only built below, so the examples see the flags when they call `params::log_coeffs` or `Budgeted::from_env`
+    set_debug_coeffs(cli.debug_coeffs);
+    set_max_rows(cli.max_rows);
+    set_circuit_n(cli.n);
+    *WITNESS_PATH.lock().unwrap() = cli.dump_witness;
+    #[cfg(feature = "dev-graph")]
+    let plot_path = cli.plot;
 
     let config_path = cli.config_path.unwrap_or_else(|| PathBuf::from("configs"));
     let data_path = cli.data_path.unwrap_or_else(|| PathBuf::from("data"));
@@ -263,6 +554,11 @@ pub fn run_cli<P: PreCircuit>(precircuit: P, cli: Cli) {
         SnarkCmd::Mock => {
             let circuit = precircuit.create_circuit(CircuitBuilderStage::Mock, None, &params);
             MockProver::run(k, &circuit, circuit.instances()).unwrap().assert_satisfied();
+            #[cfg(feature = "dev-graph")]
+            if let Some(path) = &plot_path {
+                plot_layout(k, &circuit, path);
+            }
+            print_mock_success(k, CELL_COUNTS.lock().unwrap().take());
         }
         SnarkCmd::Keygen => {
             let pk_path = data_path.join(PathBuf::from(format!("{name}.pk")));
@@ -270,76 +566,178 @@ pub fn run_cli<P: PreCircuit>(precircuit: P, cli: Cli) {
                 fs::remove_file(&pk_path).unwrap();
             }
             let pinning_path = config_path.join(PathBuf::from(format!("{name}.json")));
-            let pk = precircuit.create_pk(&params, &pk_path, pinning_path);
+            let start = Instant::now();
+            let pk = precircuit.create_pk(&params, &pk_path, &pinning_path);
+            println!("Key generation took {:?}", start.elapsed());
+            // the proving key is written by `create_pk`, so only its configuration is left to write
+            let key_config = KeyConfig::new(k, &P::Pinning::from_path(&pinning_path));
+            save_key_config(&pk_path, &key_config).unwrap_or_else(|e| panic!("{e}"));
             println!("Proving key written to: {pk_path:?}");
 
             let vk_path = data_path.join(PathBuf::from(format!("{name}.vk")));
-            let f = File::create(&vk_path).unwrap();
-            let mut writer = BufWriter::new(f);
-            pk.get_vk()
-                .write(&mut writer, SerdeFormat::RawBytes)
-                .expect("writing vkey should not fail");
+            save_vk(pk.get_vk(), &key_config, &vk_path).unwrap_or_else(|e| panic!("{e}"));
             println!("Verifying key written to: {vk_path:?}");
         }
         SnarkCmd::Prove => {
             let pinning_path = config_path.join(PathBuf::from(format!("{name}.json")));
             let pinning = P::Pinning::from_path(pinning_path);
             pinning.set_var();
+            let key_config = KeyConfig::new(k, &pinning);
             let circuit =
                 precircuit.create_circuit(CircuitBuilderStage::Prover, Some(pinning), &params);
             let pk_path = data_path.join(PathBuf::from(format!("{name}.pk")));
-            let pk = custom_read_pk(pk_path, &circuit);
+            let pk = load_pk(pk_path, &circuit, &key_config).unwrap_or_else(|e| panic!("{e}"));
             let snark_path = data_path.join(PathBuf::from(format!("{name}.snark")));
             if snark_path.exists() {
                 fs::remove_file(&snark_path).unwrap();
             }
+            let start = Instant::now();
             gen_snark_shplonk(&params, &pk, circuit, Some(&snark_path));
+            println!("Proving took {:?}", start.elapsed());
             println!("Snark written to: {snark_path:?}");
         }
         SnarkCmd::Verify => {
+            // the break points of a keygen circuit are only known once it is synthesized, so they are read from the pinning, as for proving
+            let pinning_path = config_path.join(PathBuf::from(format!("{name}.json")));
+            let pinning = P::Pinning::from_path(pinning_path);
+            pinning.set_var();
+            let key_config = KeyConfig::new(k, &pinning);
             let vk_path = data_path.join(PathBuf::from(format!("{name}.vk")));
             let circuit = precircuit.create_circuit(CircuitBuilderStage::Keygen, None, &params);
-            let vk = custom_read_vk(vk_path, &circuit);
+            let vk = load_vk(vk_path, &circuit, &key_config).unwrap_or_else(|e| panic!("{e}"));
             let snark_path = data_path.join(PathBuf::from(format!("{name}.snark")));
             let snark = read_snark(&snark_path)
                 .unwrap_or_else(|e| panic!("Snark not found at {snark_path:?}. {e:?}"));
 
-            let verifier_params = params.verifier_params();
-            let strategy = SingleStrategy::new(&params);
-            let mut transcript =
-                PoseidonTranscript::<NativeLoader, &[u8]>::new::<0>(&snark.proof[..]);
-            let instance = &snark.instances[0][..];
-            verify_proof::<
-                KZGCommitmentScheme<Bn256>,
-                VerifierSHPLONK<'_, Bn256>,
-                _,
-                _,
-                SingleStrategy<'_, Bn256>,
-            >(verifier_params, &vk, strategy, &[&[instance]], &mut transcript)
-            .unwrap();
+            assert!(verify_snark(&params, &vk, &snark), "Snark verification failed");
             println!("Snark verified successfully!");
         }
     }
 }
 
-fn custom_read_pk<C, P>(fname: P, _: &C) -> ProvingKey<G1Affine>
-where
-    C: Circuit<Fr>,
-    P: AsRef<Path>,
-{
-    read_pk::<C>(fname.as_ref())
-        .unwrap_or_else(|e| panic!("Failed to open file: {:?}: {e:?}", fname.as_ref()))
+/// Checks the proof of a snark against the verifying key `vk`.
+pub fn verify_snark(params: &ParamsKZG<Bn256>, vk: &VerifyingKey<G1Affine>, snark: &Snark) -> bool {
+    let verifier_params = params.verifier_params();
+    let strategy = SingleStrategy::new(params);
+    let mut transcript = PoseidonTranscript::<NativeLoader, &[u8]>::new::<0>(&snark.proof[..]);
+    let instance = &snark.instances[0][..];
+    verify_proof::<
+        KZGCommitmentScheme<Bn256>,
+        VerifierSHPLONK<'_, Bn256>,
+        _,
+        _,
+        SingleStrategy<'_, Bn256>,
+    >(verifier_params, vk, strategy, &[&[instance]], &mut transcript)
+    .is_ok()
 }
 
-fn custom_read_vk<C, P>(fname: P, _: &C) -> VerifyingKey<G1Affine>
-where
-    C: Circuit<Fr>,
-    P: AsRef<Path>,
-{
-    let f = File::open(&fname)
-        .unwrap_or_else(|e| panic!("Failed to open file: {:?}: {e:?}", fname.as_ref()));
-    let mut bufreader = BufReader::new(f);
-    VerifyingKey::read::<_, C>(&mut bufreader, SerdeFormat::RawBytes).expect("Could not read vkey")
+fn key_io_error(path: &Path, e: impl std::fmt::Debug) -> CircuitError {
+    CircuitError::KeyIo { path: path.display().to_string(), reason: format!("{e:?}") }
+}
+
+/// The configuration a proving or verifying key was generated for, which `save_pk` and `save_vk` write next to the key and `load_pk` and `load_vk` compare with the circuit.
+///
+/// The key itself only records the degree `k`, while a circuit built with another `LOOKUP_BITS` or other break points has other columns or another layout, so the key does not fit it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyConfig {
+    pub k: u32,
+    pub lookup_bits: usize,
+    /// The break points of the pinning of the circuit, which are a `MultiPhaseThreadBreakPoints` for the scaffold circuits and differ for the `EthScaffold` circuits.
+    pub break_points: serde_json::Value,
+}
+
+impl KeyConfig {
+    /// Returns the configuration of a circuit of degree `k` pinned by `pinning`, i.e. its `LOOKUP_BITS` and break points, or `0` and no break points if the pinning does not have them.
+    pub fn new(k: u32, pinning: &impl Serialize) -> Self {
+        let pinning = serde_json::to_value(pinning).unwrap_or_default();
+        let lookup_bits = pinning["params"]["lookup_bits"].as_u64().unwrap_or(0) as usize;
+        Self { k, lookup_bits, break_points: pinning["break_points"].clone() }
+    }
+}
+
+// the configuration of the key at `path` is written to `path` with `.json` appended, e.g. `data/poly_add.pk.json`
+fn key_config_path(path: &Path) -> PathBuf {
+    let mut config_path = path.as_os_str().to_owned();
+    config_path.push(".json");
+    PathBuf::from(config_path)
+}
+
+fn save_key_config(path: &Path, config: &KeyConfig) -> Result<(), CircuitError> {
+    let config_path = key_config_path(path);
+    let writer = File::create(&config_path).map_err(|e| key_io_error(&config_path, e))?;
+    serde_json::to_writer_pretty(writer, config).map_err(|e| key_io_error(&config_path, e))
+}
+
+// checks that a key read from disk was generated for a circuit of degree `config.k`, then that the configuration written next to it is `config`
+fn check_key_config(
+    path: &Path,
+    vk: &VerifyingKey<G1Affine>,
+    config: &KeyConfig,
+) -> Result<(), CircuitError> {
+    let found = vk.get_domain().k();
+    if found != config.k {
+        return Err(CircuitError::KeyDegreeMismatch { expected: config.k, found });
+    }
+    let config_path = key_config_path(path);
+    let f = File::open(&config_path).map_err(|e| key_io_error(&config_path, e))?;
+    let saved: KeyConfig =
+        serde_json::from_reader(BufReader::new(f)).map_err(|e| key_io_error(&config_path, e))?;
+    if saved != *config {
+        return Err(CircuitError::KeyConfigMismatch { path: path.display().to_string() });
+    }
+    Ok(())
+}
+
+/// Writes a proving key to `path` and its configuration next to it, so that later runs of the same circuit can skip the key generation with `load_pk`.
+pub fn save_pk(
+    pk: &ProvingKey<G1Affine>,
+    config: &KeyConfig,
+    path: impl AsRef<Path>,
+) -> Result<(), CircuitError> {
+    let path = path.as_ref();
+    let mut writer = BufWriter::new(File::create(path).map_err(|e| key_io_error(path, e))?);
+    pk.write(&mut writer, SerdeFormat::RawBytes).map_err(|e| key_io_error(path, e))?;
+    save_key_config(path, config)
+}
+
+/// Writes a verifying key to `path` and its configuration next to it, to be read back with `load_vk`.
+pub fn save_vk(
+    vk: &VerifyingKey<G1Affine>,
+    config: &KeyConfig,
+    path: impl AsRef<Path>,
+) -> Result<(), CircuitError> {
+    let path = path.as_ref();
+    let mut writer = BufWriter::new(File::create(path).map_err(|e| key_io_error(path, e))?);
+    vk.write(&mut writer, SerdeFormat::RawBytes).map_err(|e| key_io_error(path, e))?;
+    save_key_config(path, config)
+}
+
+/// Reads a proving key written by `save_pk` for the circuit type of `circuit`, and checks that it was generated for the configuration `config`.
+///
+/// Returns a `KeyDegreeMismatch` error for a key of another degree, and a `KeyConfigMismatch` error for a key generated with another `LOOKUP_BITS` or other break points.
+pub fn load_pk<C: Circuit<Fr>>(
+    path: impl AsRef<Path>,
+    _: &C,
+    config: &KeyConfig,
+) -> Result<ProvingKey<G1Affine>, CircuitError> {
+    let path = path.as_ref();
+    let pk = read_pk::<C>(path).map_err(|e| key_io_error(path, e))?;
+    check_key_config(path, pk.get_vk(), config)?;
+    Ok(pk)
+}
+
+/// Reads a verifying key written by `save_vk` for the circuit type of `circuit`, and checks that it was generated for the configuration `config`, with the errors of `load_pk`.
+pub fn load_vk<C: Circuit<Fr>>(
+    path: impl AsRef<Path>,
+    _: &C,
+    config: &KeyConfig,
+) -> Result<VerifyingKey<G1Affine>, CircuitError> {
+    let path = path.as_ref();
+    let f = File::open(path).map_err(|e| key_io_error(path, e))?;
+    let vk = VerifyingKey::read::<_, C>(&mut BufReader::new(f), SerdeFormat::RawBytes)
+        .map_err(|e| key_io_error(path, e))?;
+    check_key_config(path, &vk, config)?;
+    Ok(vk)
 }
 
 impl<T, Fn> PreCircuit for CircuitScaffold<T, Fn>
@@ -355,11 +753,6 @@ where
         pinning: Option<Self::Pinning>,
         params: &ParamsKZG<Bn256>,
     ) -> impl PinnableCircuit<Fr> {
-        // we initiate a "thread builder". This is what keeps track of the execution trace of our program. If not in proving mode, it also keeps track of the ZK constraints.
-        let mut builder = match stage {
-            CircuitBuilderStage::Prover => GateThreadBuilder::new(true),
-            _ => GateThreadBuilder::new(false),
-        };
         let k = params.k() as usize;
         // we use env var `LOOKUP_BITS` to determine whether to use `GateThreadBuilder` or `RangeCircuitBuilder`. The difference is that the latter creates a lookup table with 2^LOOKUP_BITS rows, while the former does not.
         let lookup_bits: usize = var("LOOKUP_BITS")
@@ -372,11 +765,22 @@ where
             .unwrap_or(0);
         // set `LOOKUP_BITS` to '0' if it is not set; this is just a technicality to re-use some other code
         set_var("LOOKUP_BITS", lookup_bits.to_string());
-        // builder.main(phase) gets a default "main" thread for the given phase. For most purposes we only need to think about phase 0
-        // we need a 64-bit number as input in this case
-        // while `some_algorithm_in_zk` was written generically for any field `F`, in practice we use the scalar field of the BN254 curve because that's what the proving system backend uses
-        let mut assigned_instances = vec![];
-        (self.f)(&mut builder, self.private_inputs, &mut assigned_instances);
+        // a circuit built ahead to count its cells is in the mode of the mock prover and key generation, and is used as is for those stages
+        let prebuilt = self.prebuilt.filter(|_| stage != CircuitBuilderStage::Prover);
+        let (mut builder, assigned_instances) = prebuilt.unwrap_or_else(|| {
+            // we initiate a "thread builder". This is what keeps track of the execution trace of our program. If not in proving mode, it also keeps track of the ZK constraints.
+            let mut builder = GateThreadBuilder::new(stage == CircuitBuilderStage::Prover);
+            // builder.main(phase) gets a default "main" thread for the given phase. For most purposes we only need to think about phase 0
+            // we need a 64-bit number as input in this case
+            // while `some_algorithm_in_zk` was written generically for any field `F`, in practice we use the scalar field of the BN254 curve because that's what the proving system backend uses
+            let mut assigned_instances = vec![];
+            (self.f)(&mut builder, self.private_inputs, &mut assigned_instances);
+            (builder, assigned_instances)
+        });
+        *CELL_COUNTS.lock().unwrap() = Some(cell_counts(&builder));
+        if let Some(path) = WITNESS_PATH.lock().unwrap().as_deref() {
+            dump_witness(&builder, path);
+        }
 
         // now `builder` contains the execution trace, and we are ready to actually create the circuit
         // minimum rows is the number of rows used for blinding factors. This depends on the circuit itself, but we can guess the number and change it if something breaks (default 9 usually works)
@@ -437,3 +841,164 @@ impl<F: ScalarField> PinnableCircuit<F> for ScaffoldCircuitBuilder<F> {
         self.0.circuit.0.break_points.borrow().clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+
+    use axiom_eth::util::circuit::PreCircuit;
+    use halo2_base::{
+        gates::{builder::CircuitBuilderStage, GateChip, GateInstructions},
+        halo2_proofs::{
+            dev::MockProver,
+            halo2curves::bn256::{Bn256, Fr},
+            poly::kzg::commitment::ParamsKZG,
+        },
+        Context,
+        QuantumCell::Constant,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_json::json;
+    use snark_verifier_sdk::{gen_pk, halo2::gen_snark_shplonk, CircuitExt};
+
+    use super::{
+        constrain_equals_public, load_pk, load_vk, pre_run_builder_on_inputs, run_and_collect,
+        save_pk, save_vk, verify_snark, KeyConfig, OutputRepr,
+    };
+    use crate::error::CircuitError;
+    use crate::poly::operations::{load_poly, poly_add_assigned};
+    use crate::test_utils::MOCK_LOCK;
+
+    // a circuit proving the square of its input
+    fn square_circuit(x: u64, params: &ParamsKZG<Bn256>) -> impl CircuitExt<Fr> {
+        let precircuit = pre_run_builder_on_inputs(
+            |builder, x: u64, make_public| {
+                let ctx = builder.main(0);
+                let x = ctx.load_witness(Fr::from(x));
+                let square = GateChip::default().mul(ctx, x, x);
+                make_public.push(square);
+            },
+            x,
+        );
+        precircuit.create_circuit(CircuitBuilderStage::Keygen, None, params)
+    }
+
+    #[test]
+    fn test_saved_keys_prove_and_verify() {
+        let _guard = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        std::env::remove_var("LOOKUP_BITS");
+        let k = 8;
+        let params = ParamsKZG::<Bn256>::setup(k, StdRng::seed_from_u64(0));
+        let (pk_path, vk_path) =
+            (temp_dir().join("scaffold_square.pk"), temp_dir().join("scaffold_square.vk"));
+
+        let circuit = square_circuit(3, &params);
+        let pk = gen_pk(&params, &circuit, None);
+        let config = KeyConfig { k, lookup_bits: 0, break_points: json!([[]]) };
+        save_pk(&pk, &config, &pk_path).unwrap();
+        save_vk(pk.get_vk(), &config, &vk_path).unwrap();
+        let loaded_pk = load_pk(&pk_path, &circuit, &config).unwrap();
+        let loaded_vk = load_vk(&vk_path, &circuit, &config).unwrap();
+
+        // a proof with the loaded proving key verifies exactly like a proof with the fresh one, against both verifying keys
+        let fresh = gen_snark_shplonk(&params, &pk, square_circuit(3, &params), None::<&str>);
+        let loaded =
+            gen_snark_shplonk(&params, &loaded_pk, square_circuit(3, &params), None::<&str>);
+        assert_eq!(fresh.instances, loaded.instances);
+        for vk in [pk.get_vk(), &loaded_vk] {
+            assert!(verify_snark(&params, vk, &fresh));
+            assert!(verify_snark(&params, vk, &loaded));
+        }
+
+        // the keys do not fit a circuit of another degree, or with another configuration
+        assert_eq!(
+            load_vk(&vk_path, &circuit, &KeyConfig { k: k + 1, ..config.clone() }).err(),
+            Some(CircuitError::KeyDegreeMismatch { expected: k + 1, found: k })
+        );
+        let mismatch = CircuitError::KeyConfigMismatch { path: pk_path.display().to_string() };
+        let other_configs = [
+            KeyConfig { lookup_bits: 7, ..config.clone() },
+            KeyConfig { break_points: json!([[42]]), ..config.clone() },
+        ];
+        for other in &other_configs {
+            assert_eq!(load_pk(&pk_path, &circuit, other).err(), Some(mismatch.clone()));
+        }
+        assert!(matches!(
+            load_pk(temp_dir().join("scaffold_missing.pk"), &circuit, &config),
+            Err(CircuitError::KeyIo { .. })
+        ));
+    }
+
+    #[test]
+    fn test_run_and_collect() {
+        let poly_add =
+            |ctx: &mut Context<Fr>, (a, b): ([u64; 4], [u64; 4]), make_public: &mut Vec<_>| {
+                let (a, b) = (load_poly(ctx, &a), load_poly(ctx, &b));
+                make_public.extend(poly_add_assigned(ctx, &GateChip::default(), &a, &b).unwrap());
+            };
+        let input = ([1, 2, 3, 4], [1, 1, 1, 1]);
+        assert_eq!(run_and_collect(poly_add, input, OutputRepr::Reduced), vec![2, 3, 4, 5]);
+
+        // a centered output is mapped back into [0, q)
+        let sub_one = |ctx: &mut Context<Fr>, a: u64, make_public: &mut Vec<_>| {
+            let a = ctx.load_witness(Fr::from(a));
+            make_public.push(GateChip::default().sub(ctx, a, Constant(Fr::from(1))));
+        };
+        assert_eq!(run_and_collect(sub_one, 0, OutputRepr::Centered(7)), vec![6]);
+        assert_eq!(run_and_collect(sub_one, 3, OutputRepr::Centered(7)), vec![2]);
+        assert_eq!(run_and_collect(sub_one, 3, OutputRepr::Reduced), vec![2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit")]
+    fn test_run_and_collect_negative_output() {
+        let neg = |ctx: &mut Context<Fr>, a: u64, make_public: &mut Vec<_>| {
+            let a = ctx.load_witness(Fr::from(a));
+            make_public.push(GateChip::default().neg(ctx, a));
+        };
+        run_and_collect(neg, 1, OutputRepr::Reduced);
+    }
+    // a circuit proving that `a + b` is `claimed`, with `claimed` as public input
+    fn sum_circuit(
+        (a, b): ([u64; 4], [u64; 4]),
+        claimed: [u64; 4],
+        params: &ParamsKZG<Bn256>,
+    ) -> impl CircuitExt<Fr> {
+        let precircuit = pre_run_builder_on_inputs(
+            move |builder, claimed: [u64; 4], make_public| {
+                let ctx = builder.main(0);
+                let (a, b) = (load_poly(ctx, &a), load_poly(ctx, &b));
+                let sum = poly_add_assigned(ctx, &GateChip::default(), &a, &b).unwrap();
+                constrain_equals_public(ctx, &sum, &claimed, make_public).unwrap();
+            },
+            claimed,
+        );
+        precircuit.create_circuit(CircuitBuilderStage::Mock, None, params)
+    }
+
+    // checks the circuit with the mock prover against the instances a verifier expects
+    fn verify(k: u32, circuit: &impl CircuitExt<Fr>, instances: Vec<Vec<Fr>>) -> bool {
+        MockProver::run(k, circuit, instances).unwrap().verify().is_ok()
+    }
+
+    #[test]
+    fn test_constrain_equals_public() {
+        let _guard = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        std::env::remove_var("LOOKUP_BITS");
+        let k = 8;
+        let params = ParamsKZG::<Bn256>::setup(k, StdRng::seed_from_u64(0));
+        let input = ([1, 2, 3, 4], [1, 1, 1, 1]);
+
+        let circuit = sum_circuit(input, [2, 3, 4, 5], &params);
+        let instances = circuit.instances();
+        assert_eq!(instances, vec![[2, 3, 4, 5].map(Fr::from).to_vec()]);
+        assert!(verify(k, &circuit, instances));
+        // a verifier expecting another result rejects the proof
+        assert!(!verify(k, &circuit, vec![[2, 3, 4, 6].map(Fr::from).to_vec()]));
+
+        // a wrong claim in the input cannot be proven, even though its instances match it
+        let circuit = sum_circuit(input, [2, 3, 4, 6], &params);
+        let instances = circuit.instances();
+        assert!(!verify(k, &circuit, instances));
+    }
+}
@@ -0,0 +1,46 @@
+//! Helpers shared by the unit tests of the halo2-lib gadgets in this crate.
+use std::{env::set_var, sync::Mutex};
+
+use halo2_base::{
+    gates::{
+        builder::{GateThreadBuilder, RangeCircuitBuilder},
+        RangeChip,
+    },
+    halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr},
+    utils::ScalarField,
+    Context,
+};
+
+// `LOOKUP_BITS` and the auto-tuned circuit configuration are passed around through environment variables, which are global to the process.
+// Tests run in parallel by default, so we serialize the mock runs (and any other test touching these variables) to stop them from overwriting each other's configuration.
+pub static MOCK_LOCK: Mutex<()> = Mutex::new(());
+
+/// Runs `f` inside a fresh `Context` and checks the resulting circuit with the `MockProver`.
+///
+/// Returns the output of `f` together with whether all constraints were satisfied.
+pub fn mock_run<R>(
+    k: usize,
+    lookup_bits: usize,
+    f: impl FnOnce(&mut Context<Fr>, &RangeChip<Fr>) -> R,
+) -> (R, bool) {
+    mock_run_on_field(k, lookup_bits, f)
+}
+
+/// Same as `mock_run`, over an arbitrary scalar field `F`.
+pub fn mock_run_on_field<F: ScalarField, R>(
+    k: usize,
+    lookup_bits: usize,
+    f: impl FnOnce(&mut Context<F>, &RangeChip<F>) -> R,
+) -> (R, bool) {
+    let _guard = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    set_var("LOOKUP_BITS", lookup_bits.to_string());
+
+    let mut builder = GateThreadBuilder::mock();
+    let range = RangeChip::default(lookup_bits);
+    let out = f(builder.main(0), &range);
+
+    builder.config(k, Some(9));
+    let circuit = RangeCircuitBuilder::mock(builder);
+    let satisfied = MockProver::run(k as u32, &circuit, vec![]).unwrap().verify().is_ok();
+    (out, satisfied)
+}
@@ -0,0 +1,147 @@
+//! Differential tests of the BFV circuits against precomputed test vectors, for encryption, addition and multiplication.
+//!
+//! The vectors live in `tests/fixtures/bfv_<operation>.json`, together with the parameters `n`, `q`, `t` and the error bound `b` they were generated with.
+//! Every polynomial is a list of `n` coefficients in `[0, q)`, lowest degree first, and multiplication is the tensor product before relinearization.
+//! The vectors are computed at toy parameters by a standalone Python implementation of textbook BFV (`tests/fixtures/gen_bfv_vectors.py`), not exported
+//! from an FHE library, so they check the circuits against a second implementation of the same formulas rather than interoperability with one.
+//! The interoperability test against vectors exported from SEAL or OpenFHE is still blocked on generating them with one of those libraries.
+//!
+//! Every vector is checked against the host-side reference implementation in `fhe::bfv`, and against the circuit, which must accept it under the mock prover
+//! and, for addition and multiplication, output the expected ciphertext.
+use std::env::set_var;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+
+use halo2_base::gates::builder::{GateThreadBuilder, RangeCircuitBuilder};
+use halo2_base::gates::RangeChip;
+use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+use halo2_base::utils::ScalarField;
+use halo2_base::{AssignedValue, Context};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use halo2_scaffold::fhe::bfv::{add, encrypt_with_randomness, tensor};
+use halo2_scaffold::fhe::gadgets::{bfv_add, bfv_tensor, bfv_verify_encryption};
+use halo2_scaffold::poly::operations::load_poly;
+
+const K: usize = 13;
+const LOOKUP_BITS: usize = 8;
+const MIN_VECTORS: usize = 3;
+
+// `LOOKUP_BITS` is passed to the circuit builder through the environment, so the mock runs are serialized
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Deserialize)]
+struct Fixture<V> {
+    n: usize,
+    q: u64,
+    t: u64,
+    b: u64,
+    vectors: Vec<V>,
+}
+
+/// The encryption `ct` of the plaintext `m` under the public key `pk`, with the ephemeral key `u` and the errors `e1`, `e2`.
+#[derive(Deserialize)]
+struct EncryptVector {
+    pk: [Vec<u64>; 2],
+    m: Vec<u64>,
+    u: Vec<u64>,
+    e1: Vec<u64>,
+    e2: Vec<u64>,
+    ct: [Vec<u64>; 2],
+}
+
+/// The ciphertext `out` computed from the ciphertexts `ct_a` and `ct_b`.
+#[derive(Deserialize)]
+struct BinaryVector {
+    ct_a: [Vec<u64>; 2],
+    ct_b: [Vec<u64>; 2],
+    out: Vec<Vec<u64>>,
+}
+
+fn load<V: DeserializeOwned>(name: &str) -> Fixture<V> {
+    let path =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(format!("{name}.json"));
+    let file = File::open(&path).unwrap_or_else(|e| panic!("cannot open {path:?}: {e}"));
+    let fixture: Fixture<V> =
+        serde_json::from_reader(file).unwrap_or_else(|e| panic!("invalid fixture {path:?}: {e}"));
+    assert!(fixture.vectors.len() >= MIN_VECTORS, "{path:?} has less than {MIN_VECTORS} vectors");
+    fixture
+}
+
+// loads the polynomials, which must all have n coefficients, builds the constraints on them and returns the values of
+// the output polynomials and whether the mock prover accepts the circuit
+fn run(
+    n: usize,
+    polys: &[&[u64]],
+    build: impl FnOnce(
+        &mut Context<Fr>,
+        &RangeChip<Fr>,
+        &[Vec<AssignedValue<Fr>>],
+    ) -> Vec<Vec<AssignedValue<Fr>>>,
+) -> (Vec<Vec<u64>>, bool) {
+    assert!(polys.iter().all(|p| p.len() == n), "every polynomial must have {n} coefficients");
+    let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    set_var("LOOKUP_BITS", LOOKUP_BITS.to_string());
+
+    let mut builder = GateThreadBuilder::mock();
+    let range = RangeChip::default(LOOKUP_BITS);
+    let ctx = builder.main(0);
+    let assigned = polys.iter().map(|p| load_poly(ctx, p)).collect::<Vec<_>>();
+    let out = build(ctx, &range, &assigned)
+        .iter()
+        .map(|p| p.iter().map(|x| x.value().get_lower_64()).collect())
+        .collect();
+
+    builder.config(K, Some(9));
+    let circuit = RangeCircuitBuilder::mock(builder);
+    (out, MockProver::run(K as u32, &circuit, vec![]).unwrap().verify().is_ok())
+}
+
+#[test]
+fn test_bfv_encrypt_vectors() {
+    let Fixture { n, q, t, b, vectors } = load::<EncryptVector>("bfv_encrypt");
+    for (i, v) in vectors.iter().enumerate() {
+        let ct = encrypt_with_randomness(&v.pk, &v.m, &v.u, &v.e1, &v.e2, q, t);
+        assert_eq!(ct, v.ct, "the host encryption of vector {i} differs");
+
+        let polys = [&v.pk[0], &v.pk[1], &v.ct[0], &v.ct[1], &v.m, &v.u, &v.e1, &v.e2];
+        let (_, ok) = run(n, &polys.map(|p| &p[..]), |ctx, range, w| {
+            let (pk, ct, e) = ([&w[0][..], &w[1]], [&w[2][..], &w[3]], [&w[6][..], &w[7]]);
+            bfv_verify_encryption(ctx, range, pk, ct, &w[4], &w[5], e, q, t, b).unwrap();
+            vec![]
+        });
+        assert!(ok, "the circuit rejects the encryption of vector {i}");
+    }
+}
+
+#[test]
+fn test_bfv_add_vectors() {
+    let Fixture { n, q, vectors, .. } = load::<BinaryVector>("bfv_add");
+    for (i, v) in vectors.iter().enumerate() {
+        assert_eq!(add(&v.ct_a, &v.ct_b, q).to_vec(), v.out, "the host sum of vector {i} differs");
+
+        let polys = [&v.ct_a[0], &v.ct_a[1], &v.ct_b[0], &v.ct_b[1]];
+        let (out, ok) = run(n, &polys.map(|p| &p[..]), |ctx, range, w| {
+            bfv_add(ctx, range, [&w[0], &w[1]], [&w[2], &w[3]], q).unwrap().to_vec()
+        });
+        assert!(ok, "the circuit rejects the sum of vector {i}");
+        assert_eq!(out, v.out, "the circuit sum of vector {i} differs");
+    }
+}
+
+#[test]
+fn test_bfv_mul_vectors() {
+    let Fixture { n, q, t, vectors, .. } = load::<BinaryVector>("bfv_mul");
+    for (i, v) in vectors.iter().enumerate() {
+        let out = tensor(&v.ct_a, &v.ct_b, q, t);
+        assert_eq!(out.to_vec(), v.out, "the host product of vector {i} differs");
+
+        let polys = [&v.ct_a[0], &v.ct_a[1], &v.ct_b[0], &v.ct_b[1]];
+        let (out, ok) = run(n, &polys.map(|p| &p[..]), |ctx, range, w| {
+            bfv_tensor(ctx, range, [&w[0], &w[1]], [&w[2], &w[3]], q, t).unwrap().to_vec()
+        });
+        assert!(ok, "the circuit rejects the product of vector {i}");
+        assert_eq!(out, v.out, "the circuit product of vector {i} differs");
+    }
+}
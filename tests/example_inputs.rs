@@ -0,0 +1,233 @@
+//! Checks that every example ships an input in `data/` named after it, and that the example accepts it under the mock prover.
+//!
+//! The examples are run with a circuit name that has no input of its own, so they read the input shipped with them (`data/<example>.in`).
+//! As in `stdin_input`, `cargo test` builds the examples next to the test binaries, so they are run directly from the target directory.
+use std::env::current_exe;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use halo2_scaffold::params::degree_for_cells;
+use halo2_scaffold::scaffold::examples::EXAMPLES;
+
+// the examples that generate their inputs themselves
+const WITHOUT_INPUT: [&str; 2] = ["builder", "standard_plonk"];
+
+fn manifest_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+// the path of an example built by `cargo test`, see `stdin_input`
+fn example_path(example: &str) -> PathBuf {
+    let exe = current_exe().unwrap();
+    exe.parent().unwrap().parent().unwrap().join("examples").join(example)
+}
+
+#[test]
+fn test_every_example_ships_an_input() {
+    let mut examples = vec![];
+    for entry in fs::read_dir(manifest_dir().join("examples")).unwrap() {
+        let path = entry.unwrap().path();
+        let example = path.file_stem().unwrap().to_str().unwrap().to_string();
+        if WITHOUT_INPUT.contains(&example.as_str()) {
+            continue;
+        }
+        let input = manifest_dir().join("data").join(format!("{example}.in"));
+        let json = fs::read_to_string(&input).unwrap_or_else(|e| panic!("{input:?}: {e}"));
+        serde_json::from_str::<serde_json::Value>(&json)
+            .unwrap_or_else(|e| panic!("{input:?} is not a valid JSON: {e}"));
+        examples.push(example);
+    }
+    // every example is also run below
+    examples.sort();
+    let mut listed = EXAMPLES
+        .iter()
+        .filter(|example| !WITHOUT_INPUT.contains(&example.name))
+        .map(|example| example.name.to_string())
+        .collect::<Vec<_>>();
+    listed.sort();
+    assert_eq!(examples, listed);
+}
+
+#[test]
+fn test_example_inputs_mock() {
+    let mut failures = vec![];
+    for example in EXAMPLES.iter().filter(|example| !WITHOUT_INPUT.contains(&example.name)) {
+        let path = example_path(example.name);
+        assert!(path.exists(), "the example should have been built by `cargo test` at {path:?}");
+        let output = Command::new(path)
+            .current_dir(manifest_dir())
+            .env("LOOKUP_BITS", example.lookup_bits.to_string())
+            .args(["--name", "example_inputs", "-k", &example.k.to_string(), "mock"])
+            .output()
+            .unwrap();
+        if !output.status.success() {
+            failures.push(format!("{}: {}", example.name, String::from_utf8_lossy(&output.stderr)));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_mock_exit_status() {
+    // the bad input satisfies the host-side check but not the constraints of the circuit
+    for (input, valid) in [("verify_pubkey.in", true), ("verify_pubkey_bad_error.in", false)] {
+        let output = Command::new(example_path("verify_pubkey"))
+            .current_dir(manifest_dir())
+            .env("LOOKUP_BITS", "8")
+            .args(["--name", "verify_pubkey", "-k", "12", "--input", input, "mock"])
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(output.status.success(), valid, "{input}: {stdout}");
+        assert_eq!(stdout.contains("Mock proving succeeded"), valid, "{input}: {stdout}");
+    }
+}
+
+#[test]
+fn test_divide_by_cyclo_degrees() {
+    let run = |args: &[&str], nominator: &str, denominator: &str| {
+        Command::new(example_path("poly_divide_by_cyclo"))
+            .current_dir(manifest_dir())
+            .env("LOOKUP_BITS", "8")
+            .args(["--name", "poly_divide_by_cyclo", "-k", "12"])
+            .args(args)
+            .args(["--nominator", nominator, "--denominator", denominator, "mock"])
+            .output()
+            .unwrap()
+    };
+
+    // with --n, a nominator of another degree is rejected with an error rather than an assertion
+    let output = run(&["--n", "4"], "1,2,3", "1,0,1");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("WrongDegree { expected: 4, found: 2 }"), "{stderr}");
+
+    // so is a denominator of a larger degree than the nominator
+    let output = run(&[], "1,2", "1,0,1");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("DegreeTooLarge { max: 1, found: 2 }"), "{stderr}");
+
+    // without --n, the degrees are read from the input, e.g. x^6 + 1 divided by x^2 + 1 or by x^4 + 1
+    for (nominator, denominator) in [("1,0,0,0,0,0,1", "1,0,1"), ("1,0,0,0,0,0,1", "1,0,0,0,1")] {
+        let output = run(&[], nominator, denominator);
+        assert!(
+            output.status.success(),
+            "{nominator} / {denominator}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    // with leading zeros, the nominator can have a lower actual degree than the denominator: the quotient is zero and
+    // the remainder is the nominator
+    for nominator in ["0,0,0,2,3", "0,0,0,0,0", "0,2,0,3,1"] {
+        let output = run(&[], nominator, "1,0,1");
+        assert!(
+            output.status.success(),
+            "{nominator}: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn test_poly_add_runtime_degree() {
+    let run = |args: &[&str]| {
+        Command::new(example_path("poly_add"))
+            .current_dir(manifest_dir())
+            .env("LOOKUP_BITS", "8")
+            .args(["--name", "poly_add", "-k", "11"])
+            .args(args)
+            .arg("mock")
+            .output()
+            .unwrap()
+    };
+
+    // the degree is read from the length of the input, or given with --n, so the same binary runs at N = 3 and N = 7
+    for args in [
+        &["--a", "3,2,5,7", "--b", "0,5,1,1"][..],
+        &["--a", "3,2,5,7,1,0,4,9", "--b", "0,5,1,1,2,8,6,3"],
+        &["--n", "3", "--a", "3,2,5,7", "--b", "0,5,1,1"],
+        &["--n", "7", "--a", "3,2,5,7,1,0,4,9", "--b", "0,5,1,1,2,8,6,3"],
+    ] {
+        let output = run(args);
+        assert!(output.status.success(), "{args:?}: {}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    // with --n, an input of another degree is rejected
+    let output = run(&["--n", "7", "--a", "3,2,5,7", "--b", "0,5,1,1"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("WrongDegree { expected: 7, found: 3 }"), "{stderr}");
+}
+
+#[test]
+fn test_poly_mul_auto_degree() {
+    // without -k, the degree is selected from the advice cells of the circuit, here the product of two polynomials of degree N = 16
+    let a = (1..=17).map(|x| x.to_string()).collect::<Vec<_>>().join(",");
+    let b = (1..=17).rev().map(|x| x.to_string()).collect::<Vec<_>>().join(",");
+    let output = Command::new(example_path("poly_mul"))
+        .current_dir(manifest_dir())
+        .env("LOOKUP_BITS", "8")
+        .args(["--name", "poly_mul", "--n", "16", "--a", &a, "--b", &b, "mock"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let number_after = |prefix: &str| {
+        stdout
+            .split(prefix)
+            .nth(1)
+            .and_then(|s| s.split(' ').next())
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or_else(|| panic!("no {prefix:?} in {stdout}"))
+    };
+    let (k, advice) = (number_after("Circuit degree k = "), number_after(" selected for "));
+    assert_eq!(k, degree_for_cells(advice, 9, 8));
+    assert!(stdout.contains(&format!("in 2^{k} rows")), "{stdout}");
+}
+
+#[test]
+fn test_divide_by_cyclo_not_monic() {
+    let run = |denominator: &str| {
+        Command::new(example_path("poly_divide_by_cyclo"))
+            .current_dir(manifest_dir())
+            .env("LOOKUP_BITS", "8")
+            .args(["--name", "poly_divide_by_cyclo", "-k", "12"])
+            .args(["--nominator", "2,0,4,0,2", "--denominator", denominator, "mock"])
+            .output()
+            .unwrap()
+    };
+
+    // 2x^2 + 2 divides the nominator exactly, but only monic denominators are accepted
+    for (denominator, leading) in [("2,0,2", 2), ("0,1,1", 0), ("3,0,1", 3)] {
+        let output = run(denominator);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success());
+        assert!(stderr.contains(&format!("NotMonic {{ leading: {leading} }}")), "{stderr}");
+    }
+    let output = run("1,0,1");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn test_poly_scalar_div_not_invertible() {
+    let run = |input: &str| {
+        Command::new(example_path("poly_scalar_div"))
+            .current_dir(manifest_dir())
+            .env("LOOKUP_BITS", "8")
+            .args(["--name", "poly_scalar_div", "-k", "11", "--input", input, "mock"])
+            .output()
+            .unwrap()
+    };
+    let output = run("poly_scalar_div.in");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // k = 0 has no inverse mod Q, so the example fails before building the circuit
+    let output = run("poly_scalar_div_not_invertible.in");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("NotInvertible"), "{stderr}");
+}
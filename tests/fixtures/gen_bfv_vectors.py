@@ -0,0 +1,109 @@
+"""Generates the BFV test vectors in this directory with a standalone Python implementation of textbook BFV.
+
+The vectors are not exported from an FHE library such as SEAL or OpenFHE: they are computed from the formulas below,
+written independently of the Rust code, at the toy parameters of the tests:
+- polynomials are lists of coefficients in [0, q), lowest degree first
+- the public key is (-(a*s + e), a), and the secret key and the ephemeral key u are ternary
+- encryption is (p0*u + e1 + delta*m, p1*u + e2) with delta = floor(q/t)
+- multiplication lifts the coefficients to (-q/2, q/2], computes the tensor product over the integers mod x^n + 1,
+  and scales it by t/q with rounding half up (q is odd, so there are no ties)
+
+Run with `python3 tests/fixtures/gen_bfv_vectors.py` from the root of the repository. The seed is fixed, so running
+it again gives the same files.
+"""
+import json
+import random
+import re
+
+N, Q, T, B = 4, 1048573, 8, 2
+NUM_VECTORS = 3
+
+
+def ring_mul(a, b, q=None):
+    out = [0] * N
+    for i, x in enumerate(a):
+        for j, y in enumerate(b):
+            if i + j < N:
+                out[i + j] += x * y
+            else:
+                out[i + j - N] -= x * y
+    return out if q is None else [c % q for c in out]
+
+
+def ring_add(*polys):
+    return [sum(cs) % Q for cs in zip(*polys)]
+
+
+def centered(x):
+    return x if x <= Q // 2 else x - Q
+
+
+def ternary(rng):
+    return [rng.choice([Q - 1, 0, 1]) for _ in range(N)]
+
+
+def error(rng):
+    return [rng.randint(-B, B) % Q for _ in range(N)]
+
+
+def uniform(rng):
+    return [rng.randrange(Q) for _ in range(N)]
+
+
+def keygen(rng):
+    s, a, e = ternary(rng), uniform(rng), error(rng)
+    return s, [[-c % Q for c in ring_add(ring_mul(a, s, Q), e)], a]
+
+
+def encrypt(rng, pk, m):
+    u, e1, e2 = ternary(rng), error(rng), error(rng)
+    delta = Q // T
+    ct = [ring_add(ring_mul(pk[0], u, Q), e1, [delta * x for x in m]), ring_add(ring_mul(pk[1], u, Q), e2)]
+    return {"pk": pk, "m": m, "u": u, "e1": e1, "e2": e2, "ct": ct}
+
+
+def multiply(ct_a, ct_b):
+    a0, a1, b0, b1 = ([centered(x) for x in p] for p in ct_a + ct_b)
+    e1 = [x + y for x, y in zip(ring_mul(a0, b1), ring_mul(a1, b0))]
+    # floor(t*x/q + 1/2), with Python's floor division rounding toward negative infinity
+    return [[(T * x + Q // 2) // Q % Q for x in e] for e in (ring_mul(a0, b0), e1, ring_mul(a1, b1))]
+
+
+def fresh_ciphertext(rng):
+    _, pk = keygen(rng)
+    return encrypt(rng, pk, [rng.randrange(T) for _ in range(N)])["ct"]
+
+
+def write(name, vectors):
+    fixture = {"n": N, "q": Q, "t": T, "b": B, "vectors": vectors}
+    with open(f"tests/fixtures/{name}.json", "w") as f:
+        # one polynomial per line
+        text = json.dumps(fixture, indent=2)
+        poly = re.compile(r"\[\s+([-\d,\s]+?)\s+\]")
+        f.write(poly.sub(lambda p: "[" + " ".join(p.group(1).split()) + "]", text))
+        f.write("\n")
+
+
+def main():
+    rng = random.Random(0)
+    encryptions = []
+    for _ in range(NUM_VECTORS):
+        _, pk = keygen(rng)
+        encryptions.append(encrypt(rng, pk, [rng.randrange(T) for _ in range(N)]))
+    write("bfv_encrypt", encryptions)
+
+    additions = []
+    for _ in range(NUM_VECTORS):
+        ct_a, ct_b = fresh_ciphertext(rng), fresh_ciphertext(rng)
+        additions.append({"ct_a": ct_a, "ct_b": ct_b, "out": [ring_add(x, y) for x, y in zip(ct_a, ct_b)]})
+    write("bfv_add", additions)
+
+    products = []
+    for _ in range(NUM_VECTORS):
+        ct_a, ct_b = fresh_ciphertext(rng), fresh_ciphertext(rng)
+        products.append({"ct_a": ct_a, "ct_b": ct_b, "out": multiply(ct_a, ct_b)})
+    write("bfv_mul", products)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,67 @@
+//! Checks that the host-side arithmetic of `poly::math` builds and works without `std`.
+//!
+//! The module is compiled directly into this `#![no_std]` test crate, together with the `error` module it returns errors from, so any use of `std` in them is a compile error here.
+#![no_std]
+#![feature(error_in_core)]
+
+extern crate alloc;
+
+// only the errors returned by `math` are constructed in this crate
+#[allow(dead_code)]
+#[path = "../src/error.rs"]
+mod error;
+#[path = "../src/poly/math.rs"]
+mod math;
+
+use alloc::{vec, vec::Vec};
+
+use math::{
+    apply_automorphism, barrett_reduce, conjugate, div_euclid, from_centered, gcd, infinity_norm,
+    inv_mod, montgomery_bits, montgomery_reduce, norm_squared, poly_inverse_mod, poly_mul,
+    poly_trace, pow_mod, reduce_mod_cyclo, reduce_mod_q, ring_add, ring_mul, ring_mul_wide,
+    ring_neg, to_centered,
+};
+
+#[test]
+fn test_no_std_modular_arithmetic() {
+    let q = 1048573;
+    assert_eq!(pow_mod(3, 10, 257), 196);
+    assert_eq!(inv_mod(5, q) * 5 % q, 1);
+    assert_eq!(
+        barrett_reduce(1 << 40, q, 41),
+        Ok(((1 << 40) / q as u128, ((1 << 40) % q as u128) as u64))
+    );
+    // r = 42 - 20 = 22 bits for the 20-bit q, and 2^40 * 2^(-22) = 2^18 mod q
+    let (_, t) = montgomery_reduce(1 << 40, q, montgomery_bits(q, 41)).unwrap();
+    assert_eq!(t % q as u128, 1 << 18);
+    assert_eq!(to_centered(q - 1, q), -1);
+    assert_eq!(from_centered(-1, q), q - 1);
+    assert_eq!(infinity_norm(&[3, q - 5, 4], q), 5);
+    assert_eq!(norm_squared(&[3, q - 5, 4], q), 50);
+    assert_eq!(gcd(12, 18), 6);
+}
+
+#[test]
+fn test_no_std_ring_arithmetic() {
+    let q = 1048573;
+    let (a, b) = (vec![3, 0, 5, q - 1], vec![1, 2, 0, 7]);
+    let expected = reduce_mod_q(&reduce_mod_cyclo(&poly_mul(&[3, 0, 5, -1], &[1, 2, 0, 7]), 4), q);
+    assert_eq!(ring_mul(&a, &b, q), expected);
+    assert_eq!(
+        ring_mul_wide(
+            &a.iter().map(|x| *x as u128).collect::<Vec<_>>(),
+            &b.iter().map(|x| *x as u128).collect::<Vec<_>>(),
+            q as u128
+        ),
+        expected.iter().map(|x| *x as u128).collect::<Vec<_>>()
+    );
+    assert_eq!(ring_add(&a, &b, q), vec![4, 2, 5, 6]);
+    assert_eq!(ring_add(&a, &ring_neg(&a, q), q), vec![0; 4]);
+    assert_eq!(ring_mul(&a, &poly_inverse_mod(&a, 4, q).unwrap(), q), vec![1, 0, 0, 0]);
+    assert_eq!(apply_automorphism(&a, 3, q), vec![3, q - 1, q - 5, 0]);
+    assert_eq!(conjugate(&a, q), apply_automorphism(&a, 7, q));
+    assert_eq!(poly_trace(&a, &[1, 7], q), ring_add(&a, &conjugate(&a, q), q));
+
+    // x^4 + 1 divided by x + 1 is x^3 - x^2 + x - 1 with remainder 2
+    assert_eq!(div_euclid(&[1, 0, 0, 0, 1], &[1, 1]), (vec![1, -1, 1, -1], vec![2]));
+}
@@ -0,0 +1,109 @@
+//! Property-based tests checking the in-circuit polynomial operations against arkworks.
+//!
+//! Every case runs the `MockProver`, so the number of cases is kept small. Run with `PROPTEST_CASES=<n>` to try more.
+//! The reference computation is generic over `ArkField`, so the same properties can be checked over every field the examples support.
+use std::{env::set_var, sync::Mutex};
+
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
+use halo2_base::{
+    gates::{
+        builder::{GateThreadBuilder, RangeCircuitBuilder},
+        GateChip,
+    },
+    halo2_proofs::dev::MockProver,
+    Context,
+};
+use halo2_scaffold::poly::operations::{
+    load_poly, poly_add_assigned, poly_mul_assigned, poly_scalar_mul_assigned,
+};
+use halo2_scaffold::scaffold::field::{ArkField, Bn254Scalar as Fr, Secp256k1Scalar};
+use proptest::{collection::vec, prelude::*};
+
+// upper bound (exclusive) on the sampled coefficients
+const BOUND: u64 = 1 << 16;
+// maximum number of coefficients of the sampled polynomials
+const MAX_LEN: usize = 8;
+
+// the circuit configuration is passed through environment variables, so mock runs must not interleave
+static MOCK_LOCK: Mutex<()> = Mutex::new(());
+
+// runs `f` in a fresh context over `F`, checks the constraints with the `MockProver` and returns the values of the output cells
+fn mock_values<F: ArkField>(f: impl FnOnce(&mut Context<F>, &GateChip<F>) -> Vec<F>) -> Vec<F> {
+    let _guard = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let k = 9;
+    set_var("LOOKUP_BITS", "8");
+    let mut builder = GateThreadBuilder::mock();
+    let out = f(builder.main(0), &GateChip::default());
+    builder.config(k, Some(9));
+    let circuit = RangeCircuitBuilder::mock(builder);
+    MockProver::run(k as u32, &circuit, vec![]).unwrap().assert_satisfied();
+    out
+}
+
+fn to_ark<F: ArkField>(coeffs: &[u64]) -> DensePolynomial<F::Ark> {
+    DensePolynomial::from_coefficients_vec(coeffs.iter().map(|x| F::Ark::from(*x)).collect())
+}
+
+// arkworks trims leading zero coefficients, so we pad the result back to the expected length
+fn from_ark<F: ArkField>(poly: &DensePolynomial<F::Ark>, len: usize) -> Vec<F> {
+    let mut out = poly.coeffs.iter().map(F::from_ark).collect::<Vec<_>>();
+    out.resize(len, F::zero());
+    out
+}
+
+// the sum of a and b, computed in the circuit over `F`, and by arkworks over `F::Ark`
+fn poly_add_values<F: ArkField>(a: &[u64], b: &[u64]) -> (Vec<F>, Vec<F>) {
+    let out = mock_values(|ctx, gate| {
+        let (a, b) = (load_poly(ctx, a), load_poly(ctx, b));
+        poly_add_assigned(ctx, gate, &a, &b).unwrap().iter().map(|x| *x.value()).collect()
+    });
+    (out, from_ark(&(&to_ark::<F>(a) + &to_ark::<F>(b)), a.len()))
+}
+
+// the product of a and b, computed in the circuit over `F`, and by arkworks over `F::Ark`
+fn poly_mul_values<F: ArkField>(a: &[u64], b: &[u64]) -> (Vec<F>, Vec<F>) {
+    let out = mock_values(|ctx, gate| {
+        let (a, b) = (load_poly(ctx, a), load_poly(ctx, b));
+        poly_mul_assigned(ctx, gate, &a, &b).unwrap().iter().map(|x| *x.value()).collect()
+    });
+    let len = a.len() + b.len() - 1;
+    (out, from_ark(&(&to_ark::<F>(a) * &to_ark::<F>(b)), len))
+}
+
+fn poly_pair() -> impl Strategy<Value = (Vec<u64>, Vec<u64>)> {
+    (1..=MAX_LEN).prop_flat_map(|n| (vec(0..BOUND, n), vec(0..BOUND, n)))
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn prop_poly_add((a, b) in poly_pair()) {
+        let (out, expected) = poly_add_values::<Fr>(&a, &b);
+        prop_assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn prop_poly_mul(a in vec(0..BOUND, 1..=MAX_LEN), b in vec(0..BOUND, 1..=MAX_LEN)) {
+        let (out, expected) = poly_mul_values::<Fr>(&a, &b);
+        prop_assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn prop_poly_add_mul_secp256k1((a, b) in poly_pair()) {
+        let (out, expected) = poly_add_values::<Secp256k1Scalar>(&a, &b);
+        prop_assert_eq!(out, expected);
+        let (out, expected) = poly_mul_values::<Secp256k1Scalar>(&a, &b);
+        prop_assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn prop_poly_scalar_mul(a in vec(0..BOUND, 1..=MAX_LEN), k in 0..BOUND) {
+        let out = mock_values(|ctx, gate| {
+            let a = load_poly(ctx, &a);
+            let k = ctx.load_witness(Fr::from(k));
+            poly_scalar_mul_assigned(ctx, gate, &a, k).iter().map(|x| *x.value()).collect()
+        });
+        prop_assert_eq!(out, from_ark(&(&to_ark::<Fr>(&a) * <Fr as ArkField>::Ark::from(k)), a.len()));
+    }
+}
@@ -0,0 +1,456 @@
+//! Soundness checks for the circuits of the examples: every circuit accepts a valid witness, and rejects it once a single witness or claimed output value is changed.
+//!
+//! The examples compare their outputs with the host-side reference implementation, which only shows that honest witnesses give the right values.
+//! Here the claimed outputs (and the auxiliary witnesses, e.g. the encryption randomness) are loaded as witnesses and constrained against the circuit,
+//! so a value that is not constrained by the circuit shows up as a tampered witness that the mock prover still accepts.
+use std::env::set_var;
+use std::sync::Mutex;
+
+use halo2_base::gates::builder::{GateThreadBuilder, RangeCircuitBuilder};
+use halo2_base::gates::{GateInstructions, RangeChip};
+use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+use halo2_base::{AssignedValue, Context};
+use rand::{rngs::StdRng, SeedableRng};
+
+use halo2_scaffold::fhe::bfv::{
+    add, decrypt, encrypt_with_randomness, extract_plaintext, keygen, mul_plain, noise,
+    rlwe_sample, sample_error, sample_ternary, sample_uniform, tensor,
+};
+use halo2_scaffold::fhe::gadgets::{
+    bfv_add, bfv_decrypt, bfv_extract_plaintext, bfv_mul_plain, bfv_noise, bfv_tensor,
+    bfv_verify_encryption, bfv_verify_public_key, rlwe_verify_sample,
+};
+use halo2_scaffold::poly::distribution::{
+    check_poly_from_distribution_chi_error, check_poly_from_distribution_chi_key,
+};
+use halo2_scaffold::poly::ntt::{
+    negacyclic_ntt, negacyclic_ntt_assigned, ntt_mul, primitive_root_of_unity,
+};
+use halo2_scaffold::poly::operations::{
+    apply_automorphism as apply_automorphism_assigned, assert_base_decomposition,
+    assert_inf_norm_bound, assert_poly_zero, assert_reduced, infinity_norm_assigned, load_poly,
+    poly_mul_assigned, poly_reduce_mod_assigned, poly_reduce_ring_assigned,
+};
+use halo2_scaffold::poly::ring::CyclotomicRing;
+use halo2_scaffold::poly::rns::{assert_rns_ntt_representation, rns_decompose};
+use halo2_scaffold::poly::utils::{
+    apply_automorphism, infinity_norm, poly_inverse_mod, poly_mul, ring_add, ring_mul,
+};
+
+const K: usize = 12;
+const LOOKUP_BITS: usize = 8;
+
+const N: usize = 4;
+const Q: u64 = 1048573;
+const T: u64 = 8;
+const B: u64 = 2;
+const NOISE_BOUND: u64 = Q / (2 * T) - T;
+
+// `LOOKUP_BITS` is passed to the circuit builder through the environment, so the mock runs are serialized
+static LOCK: Mutex<()> = Mutex::new(());
+
+type Constraints = fn(&mut Context<Fr>, &RangeChip<Fr>, &[Vec<AssignedValue<Fr>>]);
+
+/// A circuit under test, given by its witnesses and the constraints built on them.
+struct Circuit {
+    witnesses: Vec<Vec<u64>>,
+    /// the values to change one at a time, as `(witness, coefficient, modulus)`: the coefficient is replaced by `coefficient + 1 mod modulus`
+    tampered: Vec<(usize, usize, u64)>,
+    build: Constraints,
+}
+
+impl Circuit {
+    // loads the witnesses, builds the constraints and returns whether the mock prover accepts them
+    fn satisfied(&self, witnesses: &[Vec<u64>]) -> bool {
+        let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_var("LOOKUP_BITS", LOOKUP_BITS.to_string());
+
+        let mut builder = GateThreadBuilder::mock();
+        let range = RangeChip::default(LOOKUP_BITS);
+        let ctx = builder.main(0);
+        let assigned = witnesses.iter().map(|w| load_poly(ctx, w)).collect::<Vec<_>>();
+        (self.build)(ctx, &range, &assigned);
+
+        builder.config(K, Some(9));
+        let circuit = RangeCircuitBuilder::mock(builder);
+        MockProver::run(K as u32, &circuit, vec![]).unwrap().verify().is_ok()
+    }
+
+    fn check_soundness(&self) {
+        assert!(self.satisfied(&self.witnesses), "the valid witness is rejected");
+        for &(i, j, modulus) in &self.tampered {
+            let mut witnesses = self.witnesses.clone();
+            witnesses[i][j] = (witnesses[i][j] + 1) % modulus;
+            assert!(
+                !self.satisfied(&witnesses),
+                "tampering with coefficient {j} of witness {i} is not detected"
+            );
+        }
+    }
+}
+
+fn constrain_poly_equal(ctx: &mut Context<Fr>, a: &[AssignedValue<Fr>], b: &[AssignedValue<Fr>]) {
+    assert_eq!(a.len(), b.len());
+    for (x, y) in a.iter().zip(b.iter()) {
+        ctx.constrain_equal(x, y);
+    }
+}
+
+// a ciphertext of the plaintext `m` under a fresh key pair, returned as (s, pk, ct, u, e1, e2)
+#[allow(clippy::type_complexity)]
+fn encryption(
+    seed: u64,
+    m: &[u64],
+) -> (Vec<u64>, [Vec<u64>; 2], [Vec<u64>; 2], Vec<u64>, Vec<u64>, Vec<u64>) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (s, pk) = keygen(&mut rng, N, Q, B);
+    let u = sample_ternary(&mut rng, N, Q);
+    let (e1, e2) = (sample_error(&mut rng, N, B, Q), sample_error(&mut rng, N, B, Q));
+    let ct = encrypt_with_randomness(&pk, m, &u, &e1, &e2, Q, T);
+    (s, pk, ct, u, e1, e2)
+}
+
+#[test]
+fn test_poly_mul_soundness() {
+    let (a, b) = (vec![3, 255, 0, 17], vec![255, 1, 2, 254]);
+    let [a_int, b_int] = [&a, &b].map(|p| p.iter().map(|x| *x as i128).collect::<Vec<_>>());
+    let prod = poly_mul(&a_int, &b_int).iter().map(|x| *x as u64).collect();
+    Circuit {
+        witnesses: vec![a, b, prod],
+        tampered: vec![(2, 3, Q)],
+        build: |ctx, range, w| {
+            let prod = poly_mul_assigned(ctx, &range.gate, &w[0], &w[1]).unwrap();
+            constrain_poly_equal(ctx, &prod, &w[2]);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_poly_reduce_soundness() {
+    let poly = vec![1000, 37, 2047, 5];
+    let out = poly.iter().map(|x| x % 11).collect();
+    Circuit {
+        witnesses: vec![poly, out],
+        tampered: vec![(1, 0, 11), (1, 2, 11)],
+        build: |ctx, range, w| {
+            let rem = poly_reduce_mod_assigned(ctx, range, &w[0], 11, 11).unwrap();
+            constrain_poly_equal(ctx, &rem, &w[1]);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_reduce_ring_soundness() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let (a, b) = (sample_uniform(&mut rng, N, Q), sample_uniform(&mut rng, N, Q));
+    let out = ring_mul(&a, &b, Q);
+    Circuit {
+        witnesses: vec![a, b, out],
+        tampered: vec![(2, 1, Q)],
+        build: |ctx, range, w| {
+            assert_reduced(ctx, range, &w[0], Q);
+            assert_reduced(ctx, range, &w[1], Q);
+            let prod = poly_mul_assigned(ctx, &range.gate, &w[0], &w[1]).unwrap();
+            let max_abs = N as u128 * (Q as u128 - 1).pow(2);
+            let out = poly_reduce_ring_assigned(ctx, range, &prod, N, Q, max_abs).unwrap();
+            constrain_poly_equal(ctx, &out, &w[2]);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_poly_inverse_soundness() {
+    let a = vec![3, 0, 5, Q - 1];
+    let a_inv = poly_inverse_mod(&a, N, Q).unwrap();
+    Circuit {
+        witnesses: vec![a, a_inv],
+        tampered: vec![(0, 1, Q), (1, 0, Q), (1, 3, Q)],
+        build: |ctx, range, w| {
+            assert_reduced(ctx, range, &w[0], Q);
+            assert_reduced(ctx, range, &w[1], Q);
+            let prod = poly_mul_assigned(ctx, &range.gate, &w[0], &w[1]).unwrap();
+            let max_abs = N as u128 * (Q as u128 - 1).pow(2);
+            let out = poly_reduce_ring_assigned(ctx, range, &prod, N, Q, max_abs).unwrap();
+            range.gate.assert_is_const(ctx, &out[0], &Fr::from(1));
+            assert_poly_zero(ctx, &range.gate, &out[1..]);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_apply_galois_soundness() {
+    let a = vec![1, 2, 3, 4];
+    let out = apply_automorphism(&a, 3, Q);
+    Circuit {
+        witnesses: vec![a, out],
+        tampered: vec![(0, 2, Q), (1, 1, Q), (1, 2, Q)],
+        build: |ctx, range, w| {
+            assert_reduced(ctx, range, &w[0], Q);
+            let rotated = apply_automorphism_assigned(ctx, &range.gate, &w[0], 3, N).unwrap();
+            let rotated =
+                poly_reduce_ring_assigned(ctx, range, &rotated, N, Q, Q as u128 - 1).unwrap();
+            constrain_poly_equal(ctx, &rotated, &w[1]);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_base_decompose_soundness() {
+    // 65519 = 15 + 14 * 16 + 15 * 16^2 + 15 * 16^3
+    Circuit {
+        witnesses: vec![vec![65519], vec![15, 14, 15, 15]],
+        tampered: vec![(0, 0, Q), (1, 0, 16), (1, 1, 16), (1, 3, 16)],
+        build: |ctx, range, w| assert_base_decomposition(ctx, range, w[0][0], &w[1], 16),
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_distributions_soundness() {
+    // incrementing the first coefficient moves it out of {-1, 0, 1}, respectively out of [-B, B]
+    Circuit {
+        witnesses: vec![vec![1, 0, Q - 1, 1], vec![B, Q - B, 0, 1]],
+        tampered: vec![(0, 0, Q), (1, 0, Q)],
+        build: |ctx, range, w| {
+            check_poly_from_distribution_chi_key(ctx, &range.gate, &w[0], Q);
+            check_poly_from_distribution_chi_error(ctx, range, &w[1], Q, B);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_ct_add_soundness() {
+    let (_, _, ct1, ..) = encryption(1, &[1, 2, 3, 4]);
+    let (_, _, ct2, ..) = encryption(2, &[5, 0, 7, 1]);
+    let sum = add(&ct1, &ct2, Q);
+    Circuit {
+        witnesses: [ct1, ct2, sum].into_iter().flatten().collect(),
+        tampered: vec![(4, 0, Q), (5, 3, Q)],
+        build: |ctx, range, w| {
+            let sum = bfv_add(ctx, range, [&w[0], &w[1]], [&w[2], &w[3]], Q).unwrap();
+            constrain_poly_equal(ctx, &sum[0], &w[4]);
+            constrain_poly_equal(ctx, &sum[1], &w[5]);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_bfv_mul_soundness() {
+    let (_, _, ct1, ..) = encryption(3, &[1, 2, 3, 4]);
+    let (_, _, ct2, ..) = encryption(4, &[5, 0, 7, 1]);
+    let out = tensor(&ct1, &ct2, Q, T);
+    Circuit {
+        witnesses: [ct1.to_vec(), ct2.to_vec(), out.to_vec()].concat(),
+        tampered: vec![(4, 0, Q), (5, 1, Q), (6, 3, Q)],
+        build: |ctx, range, w| {
+            let out = bfv_tensor(ctx, range, [&w[0], &w[1]], [&w[2], &w[3]], Q, T).unwrap();
+            for (poly, claimed) in out.iter().zip(&w[4..]) {
+                constrain_poly_equal(ctx, poly, claimed);
+            }
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_bfv_mul_plain_soundness() {
+    // the plaintext is a constant of the circuit, so only the ciphertexts can be tampered with
+    const PT: [u64; N] = [2, 7, 0, 1];
+    let (_, _, ct, ..) = encryption(7, &[3, 0, 5, 7]);
+    let out = mul_plain(&ct, &PT, Q);
+    Circuit {
+        witnesses: [ct, out].into_iter().flatten().collect(),
+        tampered: vec![(0, 1, Q), (2, 0, Q), (3, 3, Q)],
+        build: |ctx, range, w| {
+            let out = bfv_mul_plain(ctx, range, [&w[0], &w[1]], &PT, Q).unwrap();
+            constrain_poly_equal(ctx, &out[0], &w[2]);
+            constrain_poly_equal(ctx, &out[1], &w[3]);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_verifiable_encrypt_soundness() {
+    let m = vec![5, 1, 0, 3];
+    let (_, pk, ct, u, e1, e2) = encryption(5, &m);
+    Circuit {
+        witnesses: vec![pk[0].clone(), pk[1].clone(), ct[0].clone(), ct[1].clone(), m, u, e1, e2],
+        tampered: vec![(2, 0, Q), (3, 1, Q), (4, 2, T), (5, 3, Q), (6, 0, Q), (7, 1, Q)],
+        build: |ctx, range, w| {
+            let (pk, ct, e) = ([&w[0][..], &w[1]], [&w[2][..], &w[3]], [&w[6][..], &w[7]]);
+            bfv_verify_encryption(ctx, range, pk, ct, &w[4], &w[5], e, Q, T, B).unwrap();
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_verify_pubkey_soundness() {
+    let (s, pk, ..) = encryption(6, &[0; N]);
+    // recover the error of the public key, e = -(p0 + p1 * s)
+    let e = ring_add(&pk[0], &ring_mul(&pk[1], &s, Q), Q).iter().map(|x| (Q - x) % Q).collect();
+    Circuit {
+        witnesses: vec![pk[0].clone(), pk[1].clone(), s, e],
+        tampered: vec![(0, 0, Q), (1, 1, Q), (2, 2, Q), (3, 3, Q)],
+        build: |ctx, range, w| {
+            bfv_verify_public_key(ctx, range, [&w[0], &w[1]], &w[2], &w[3], Q, B).unwrap();
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_check_public_key_soundness() {
+    let (s, pk, ..) = encryption(6, &[0; N]);
+    // recover the error of the public key, e = -(p0 + p1 * s)
+    let e = ring_add(&pk[0], &ring_mul(&pk[1], &s, Q), Q).iter().map(|x| (Q - x) % Q).collect();
+    Circuit {
+        witnesses: vec![pk[0].clone(), pk[1].clone(), s, e],
+        tampered: vec![(0, 0, Q), (1, 1, Q), (2, 2, Q), (3, 3, Q)],
+        build: |ctx, range, w| {
+            check_poly_from_distribution_chi_key(ctx, &range.gate, &w[2], Q);
+            check_poly_from_distribution_chi_error(ctx, range, &w[3], Q, B);
+            // p0 = -(a * s + e) in R_Q, as in the check_public_key example
+            let ring = CyclotomicRing::new(N, Q).unwrap();
+            ring.range_check(ctx, range, &w[1]).unwrap();
+            let prod = ring.mul(ctx, range, &w[1], &w[2]).unwrap();
+            let sum = ring.add(ctx, range, &prod, &w[3]).unwrap();
+            let p0 = ring.neg(ctx, range, &sum).unwrap();
+            constrain_poly_equal(ctx, &p0, &w[0]);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_verify_rlwe_soundness() {
+    let sample = rlwe_sample(&mut StdRng::seed_from_u64(13), N, Q, B);
+    Circuit {
+        witnesses: vec![sample.a, sample.b, sample.s, sample.e],
+        tampered: vec![(0, 0, Q), (1, 1, Q), (2, 2, Q), (3, 3, Q)],
+        build: |ctx, range, w| {
+            rlwe_verify_sample(ctx, range, [&w[0], &w[1]], &w[2], &w[3], Q, B).unwrap();
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_decrypt_soundness() {
+    let m = vec![3, 0, 5, 1];
+    let (s, _, ct, ..) = encryption(7, &m);
+    assert_eq!(decrypt(&s, &ct, Q, T), m);
+    Circuit {
+        witnesses: vec![s, ct[0].clone(), ct[1].clone(), m],
+        tampered: vec![(3, 0, T), (3, 3, T)],
+        build: |ctx, range, w| {
+            let m = bfv_decrypt(ctx, range, &w[0], [&w[1], &w[2]], Q, T, NOISE_BOUND).unwrap();
+            constrain_poly_equal(ctx, &m, &w[3]);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_extract_plaintext_soundness() {
+    let v = vec![0, Q / 2, 65536, Q - 1];
+    let m = extract_plaintext(&v, Q, T);
+    Circuit {
+        witnesses: vec![v, m],
+        tampered: vec![(1, 1, T), (1, 2, T)],
+        build: |ctx, range, w| {
+            let m = bfv_extract_plaintext(ctx, range, &w[0], Q, T).unwrap();
+            constrain_poly_equal(ctx, &m, &w[1]);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_noise_bound_soundness() {
+    let m = vec![6, 1, 0, 4];
+    let (s, _, ct, ..) = encryption(8, &m);
+    let e = noise(&s, &ct, &m, Q, T);
+    // the bound is the actual norm of the noise, so it is tight
+    let bound = vec![infinity_norm(&e, Q)];
+    Circuit {
+        witnesses: vec![s, ct[0].clone(), ct[1].clone(), m, e, bound],
+        tampered: vec![(3, 0, T), (4, 2, Q), (5, 0, Q)],
+        build: |ctx, range, w| {
+            let e = bfv_noise(ctx, range, &w[0], [&w[1], &w[2]], &w[3], Q, T).unwrap();
+            constrain_poly_equal(ctx, &e, &w[4]);
+            assert_inf_norm_bound(ctx, range, &e, Q, w[5][0]);
+            // the claimed norm must be exact, so that decreasing the bound below it is also caught
+            let norm = infinity_norm_assigned(ctx, range, &e, Q);
+            ctx.constrain_equal(&norm, &w[5][0]);
+        },
+    }
+    .check_soundness();
+}
+
+// NTT-friendly parameters for the NTT circuits
+const NTT_N: usize = 8;
+const NTT_Q: u64 = 7681;
+
+#[test]
+fn test_check_ntt_soundness() {
+    let coeffs = vec![1, 2, 3, 4, 5, 6, 7, 7680];
+    let evals =
+        negacyclic_ntt(&coeffs, primitive_root_of_unity(2 * NTT_N as u64, NTT_Q).unwrap(), NTT_Q);
+    Circuit {
+        witnesses: vec![coeffs, evals],
+        tampered: vec![(1, 0, NTT_Q), (1, 5, NTT_Q)],
+        build: |ctx, range, w| {
+            let psi = primitive_root_of_unity(2 * NTT_N as u64, NTT_Q).unwrap();
+            let evals = negacyclic_ntt_assigned(ctx, range, &w[0], psi, NTT_Q).unwrap();
+            constrain_poly_equal(ctx, &evals, &w[1]);
+        },
+    }
+    .check_soundness();
+}
+
+#[test]
+fn test_mul_consistency_soundness() {
+    let mut rng = StdRng::seed_from_u64(9);
+    let (a, b) = (sample_uniform(&mut rng, NTT_N, NTT_Q), sample_uniform(&mut rng, NTT_N, NTT_Q));
+    let prod = ring_mul(&a, &b, NTT_Q);
+    Circuit {
+        witnesses: vec![a, b, prod],
+        tampered: vec![(2, 0, NTT_Q), (2, 7, NTT_Q)],
+        build: |ctx, range, w| {
+            let psi = primitive_root_of_unity(2 * NTT_N as u64, NTT_Q).unwrap();
+            let prod = ntt_mul(ctx, range, &w[0], &w[1], psi, NTT_Q).unwrap();
+            constrain_poly_equal(ctx, &prod, &w[2]);
+        },
+    }
+    .check_soundness();
+}
+
+const PRIMES: [u64; 2] = [7681, 12289];
+const PSIS: [u64; 2] = [1213, 8246];
+
+#[test]
+fn test_rns_ntt_soundness() {
+    let coeffs = vec![94391808, 12345678, 0, 50000001];
+    let residues = rns_decompose(&coeffs.iter().map(|c| *c as u128).collect::<Vec<_>>(), &PRIMES);
+    let evals =
+        (0..2).map(|i| negacyclic_ntt(&residues[i], PSIS[i], PRIMES[i])).collect::<Vec<_>>();
+    Circuit {
+        witnesses: [vec![coeffs], residues, evals].concat(),
+        tampered: vec![(1, 1, PRIMES[0]), (2, 3, PRIMES[1]), (4, 2, PRIMES[1])],
+        build: |ctx, range, w| {
+            let (residues, evals) = (w[1..3].to_vec(), w[3..5].to_vec());
+            assert_rns_ntt_representation(ctx, range, &w[0], &residues, &evals, &PRIMES, &PSIS)
+                .unwrap();
+        },
+    }
+    .check_soundness();
+}
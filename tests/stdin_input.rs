@@ -0,0 +1,137 @@
+//! Runs an example with its input piped through stdin (`--stdin`), or given inline on the command line (e.g. `--a 1,2,3,4`), instead of read from a file in `data/`.
+//!
+//! It also checks the witness written with `--dump-witness`, and the layout plotted with `--plot` when the `dev-graph` feature is enabled.
+//!
+//! `cargo test` builds the examples next to the test binaries, so the example is run directly from the target directory.
+use std::env::current_exe;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// the path of the `poly_add` example built by `cargo test`
+fn example_path() -> PathBuf {
+    // the test binary is at target/<profile>/deps/<test>, and the examples at target/<profile>/examples/<example>
+    let exe = current_exe().unwrap();
+    let path = exe.parent().unwrap().parent().unwrap().join("examples").join("poly_add");
+    assert!(path.exists(), "the example should have been built by `cargo test` at {path:?}");
+    path
+}
+
+// runs the example with the given arguments and stdin, and returns whether it succeeded together with its stderr
+fn run_with_stdin(args: &[&str], input: &[u8]) -> (bool, String) {
+    let mut child = Command::new(example_path())
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")))
+        .env("LOOKUP_BITS", "8")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (output.status.success(), String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+#[test]
+fn test_piped_input() {
+    let input = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("data/poly_input.in")).unwrap();
+    // the flag is accepted both before and after the command
+    for args in [
+        ["--name", "poly_input", "-k", "11", "mock", "--stdin"],
+        ["--name", "poly_input", "-k", "11", "--stdin", "mock"],
+    ] {
+        let (ok, stderr) = run_with_stdin(&args, &input);
+        assert!(ok, "{args:?} failed: {stderr}");
+    }
+}
+
+#[test]
+fn test_piped_input_invalid() {
+    let (ok, stderr) =
+        run_with_stdin(&["--name", "poly_input", "-k", "11", "mock", "--stdin"], b"{\"a\": [1, 2");
+    assert!(!ok);
+    assert!(stderr.contains("Input from stdin should be a valid JSON"), "{stderr}");
+
+    // the input is either piped or read from a file, not both
+    let (ok, stderr) = run_with_stdin(
+        &["--name", "poly_input", "-k", "11", "--input", "poly_input.in", "mock", "--stdin"],
+        b"",
+    );
+    assert!(!ok);
+    assert!(stderr.contains("cannot be used with"), "{stderr}");
+}
+
+#[test]
+fn test_inline_input() {
+    // the same polynomials as data/poly_add.in
+    let (ok, stderr) = run_with_stdin(
+        &["--name", "poly_add", "-k", "11", "--a", "3,2,5,7", "--b", "0,5,1,1", "mock"],
+        b"",
+    );
+    assert!(ok, "{stderr}");
+
+    // the lengths are checked against the degree of the circuit
+    let (ok, stderr) = run_with_stdin(
+        &["--name", "poly_add", "-k", "11", "--a", "3,2,5", "--b", "0,5,1,1", "mock"],
+        b"",
+    );
+    assert!(!ok);
+    assert!(stderr.contains("WrongDegree { expected: 3, found: 2 }"), "{stderr}");
+
+    // the inline polynomials replace the input file
+    let (ok, stderr) = run_with_stdin(
+        &["--name", "poly_add", "-k", "11", "--a", "3,2,5,7", "--b", "0,5,1,1", "mock", "--stdin"],
+        b"",
+    );
+    assert!(!ok);
+    assert!(stderr.contains("cannot be used with"), "{stderr}");
+}
+
+#[test]
+fn test_dump_witness() {
+    let path = std::env::temp_dir().join(format!("poly_add_witness_{}.json", std::process::id()));
+    let output = Command::new(example_path())
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")))
+        .env("LOOKUP_BITS", "8")
+        .args(["--name", "poly_add", "-k", "11", "--dump-witness", path.to_str().unwrap(), "mock"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    // one entry per advice cell, as counted by the mock prover
+    let advice = stdout
+        .split("Mock proving succeeded: ")
+        .nth(1)
+        .and_then(|s| s.split(' ').next())
+        .and_then(|n| n.parse::<usize>().ok())
+        .unwrap_or_else(|| panic!("no advice cell count in {stdout}"));
+    let values: Vec<String> = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(values.len(), advice);
+    assert!(values.iter().all(|x| x.parse::<u128>().is_ok()), "{values:?}");
+    // the witness starts with the coefficients of `a` from data/poly_add.in
+    assert_eq!(values[..4], ["3", "2", "5", "7"]);
+}
+
+#[cfg(feature = "dev-graph")]
+#[test]
+fn test_plot_layout() {
+    for ext in ["png", "svg"] {
+        let path =
+            std::env::temp_dir().join(format!("poly_add_layout_{}.{ext}", std::process::id()));
+        let output = Command::new(example_path())
+            .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")))
+            .env("LOOKUP_BITS", "8")
+            .args(["--name", "poly_add", "-k", "11", "--n", "3", "--plot", path.to_str().unwrap()])
+            .arg("mock")
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let size = fs::metadata(&path).unwrap().len();
+        fs::remove_file(&path).unwrap();
+        assert!(size > 0, "empty layout at {path:?}");
+    }
+}